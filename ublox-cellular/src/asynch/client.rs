@@ -0,0 +1,625 @@
+use atat::{asynch::AtatClient, AtatUrcChannel, UrcSubscription};
+use embassy_time::{Duration, Timer};
+use heapless::Vec;
+use ublox_sockets::SocketSet;
+
+use super::network::{AtTx, Network};
+use crate::{
+    client::{State, URC_CAPACITY, URC_SUBSCRIBERS},
+    command::{
+        control::types::BaudRate,
+        device_lock::{
+            types::{Facility, FacilityLockMode},
+            ChangePassword, ChangePin, GetPinStatus, SetFacilityLock, SetPin,
+        },
+        fibocom,
+        general::{GetCCID, GetFirmwareVersion, GetModelId},
+        ip_transport_layer,
+        network_service::{
+            responses::{OperatorInfo, OperatorSelection, SignalQuality},
+            GetAvailableOperators, GetBandMask, GetLpwaRadioAccessTechnology, GetOperatorSelection,
+            GetSignalQuality, GetSignalStrength,
+        },
+        Urc,
+    },
+    config::CellularConfig,
+    error::{Error, GenericError},
+    event::{self, Event, ReportedState},
+    module::{detect_module, Vendor},
+    power::PowerState,
+    power_saving::PowerSavingClient,
+    radio,
+    registration::{ConnectionState, RegistrationUrcs},
+    services::data::usage::DataBudget,
+    setup::{self, InterfaceSetup, ModuleSetup, EXTENDED_ERRORS, FULL_FUNCTIONALITY},
+    signal::SignalMetrics,
+    sim::{self, PinStep, PinUnlock, SimSlot},
+    status::NetworkStatus,
+    UbloxCellularBuffers, UbloxCellularIngress, UbloxCellularUrcChannel, UbloxDigester,
+};
+
+/// Async u-blox device
+///
+/// Mirrors [`GsmClient`](crate::GsmClient), but is driven by an
+/// [`atat::asynch::AtatClient`] and yields to the executor whenever the
+/// blocking driver would busy-wait.
+pub struct Device<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+where
+    Config: CellularConfig,
+{
+    pub(crate) config: Config,
+    pub(crate) network: Network<'sub, PowerSavingClient<AtCl, Config::DtrPin>>,
+    urc_channel: &'buf AtUrcCh,
+    urc_subscription: UrcSubscription<'sub, Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
+    /// State last reported to [`CellularConfig::on_event`]
+    reported: ReportedState,
+    /// The configured [`CellularConfig::SIM_PIN`] was rejected, and is not
+    /// given again until the SIM is unlocked by the application
+    pub(crate) sim_pin_rejected: bool,
+    /// SIM slot selected with [`CellularConfig::SIM_SELECT`]
+    pub(crate) sim_slot: SimSlot,
+
+    pub(crate) state: State,
+    pub(crate) power_state: PowerState,
+    /// Rate of the serial port, once detected or switched to
+    pub(crate) baud_rate: Option<BaudRate>,
+    // Ublox devices can hold a maximum of 6 active sockets
+    pub(crate) sockets: Option<&'static mut SocketSet<N, L>>,
+}
+
+impl<'buf, 'sub, W, Config, const INGRESS_BUF_SIZE: usize, const N: usize, const L: usize>
+    Device<
+        'buf,
+        'sub,
+        atat::asynch::Client<'buf, W, INGRESS_BUF_SIZE>,
+        UbloxCellularUrcChannel,
+        Config,
+        N,
+        L,
+    >
+where
+    'buf: 'sub,
+    W: embedded_io_async::Write,
+    Config: CellularConfig,
+{
+    /// Create new async u-blox device
+    ///
+    /// Look for [`data_service`](Device::data_service) how to handle data connection automatically.
+    ///
+    pub fn from_buffers(
+        buffers: &'buf UbloxCellularBuffers<INGRESS_BUF_SIZE>,
+        tx: W,
+        config: Config,
+    ) -> (UbloxCellularIngress<INGRESS_BUF_SIZE>, Self) {
+        let (ingress, client) =
+            buffers.split(tx, UbloxDigester::default(), atat::Config::default());
+
+        (ingress, Device::new(client, &buffers.urc_channel, config))
+    }
+}
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    AtUrcCh: AtatUrcChannel<Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
+    Config: CellularConfig,
+{
+    pub fn new(client: AtCl, urc_channel: &'buf AtUrcCh, mut config: Config) -> Self {
+        let network_urc_subscription = urc_channel.subscribe().unwrap();
+        let client =
+            PowerSavingClient::new(client, config.take_dtr_pin(), Config::POWER_SAVING_IDLE);
+        let mut network = Network::new(AtTx::new(client, network_urc_subscription));
+        network.status.operators = Config::OPERATORS;
+        network.contexts.data_budget = Config::DATA_BUDGET.map(DataBudget::new);
+        Self {
+            config,
+            reported: ReportedState::new(network.status.conn_state, &network.contexts),
+            sim_pin_rejected: false,
+            sim_slot: SimSlot::Primary,
+            network,
+            state: State::Off,
+            power_state: PowerState::Off,
+            baud_rate: None,
+            sockets: None,
+            urc_channel,
+            urc_subscription: urc_channel.subscribe().unwrap(),
+        }
+    }
+}
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Set storage for TCP/UDP sockets
+    ///
+    /// See [`GsmClient::set_socket_storage`](crate::GsmClient::set_socket_storage).
+    pub fn set_socket_storage(&mut self, socket_set: &'static mut SocketSet<N, L>) {
+        socket_set.prune();
+        self.sockets.replace(socket_set);
+    }
+
+    pub fn take_socket_storage(&mut self) -> Option<&'static mut SocketSet<N, L>> {
+        self.sockets.take()
+    }
+
+    pub async fn signal_strength(&mut self) -> Result<SignalQuality, Error> {
+        self.send_at(&GetSignalQuality).await
+    }
+
+    /// Scan the network for the available operators. This can take up to a
+    /// few minutes, during which the module does not respond to other
+    /// commands.
+    pub async fn scan_operators(&mut self) -> Result<Vec<OperatorInfo, 8>, Error> {
+        Ok(self.send_at(&GetAvailableOperators).await?.operators)
+    }
+
+    /// Signal quality of the serving cell in physical units, from `+CESQ`,
+    /// or from `+CSQ` if the module does not support `+CESQ`.
+    pub async fn signal_metrics(&mut self) -> Result<SignalMetrics, Error> {
+        match self.send_at(&GetSignalQuality).await {
+            Ok(quality) => Ok(SignalMetrics::from(&quality)),
+            Err(Error::Network(crate::network::Error::AT(atat::Error::Error))) => {
+                let strength = self.send_at(&GetSignalStrength).await?;
+                Ok(SignalMetrics::from(&strength))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// SIM slot currently selected, see [`CellularConfig::SIM_SELECT`]
+    pub fn sim_slot(&self) -> SimSlot {
+        self.sim_slot
+    }
+
+    /// Snapshot of the network state, as last seen by the driver. No AT
+    /// command is sent.
+    pub fn network_status(&self) -> NetworkStatus {
+        NetworkStatus::new(&self.network.status, &self.network.contexts)
+    }
+
+    /// Unlock the SIM with `pin`, when it is waiting for the SIM PIN
+    pub async fn enter_pin(&mut self, pin: &str) -> Result<(), Error> {
+        self.network.send_internal(&SetPin { pin }, true).await?;
+        self.sim_pin_rejected = false;
+        Ok(())
+    }
+
+    /// Unblock the SIM with `puk`, when it is waiting for the SIM PUK, and
+    /// set `new_pin` as its PIN
+    pub async fn unblock_pin(&mut self, puk: &str, new_pin: &str) -> Result<(), Error> {
+        self.network
+            .send_internal(
+                &ChangePin {
+                    puk,
+                    newpin: new_pin,
+                },
+                true,
+            )
+            .await?;
+        self.sim_pin_rejected = false;
+        Ok(())
+    }
+
+    /// Change the SIM PIN from `old_pin` to `new_pin`. The PIN request must
+    /// be enabled.
+    pub async fn change_pin(&mut self, old_pin: &str, new_pin: &str) -> Result<(), Error> {
+        self.network
+            .send_internal(
+                &ChangePassword {
+                    fac: Facility::SimPin,
+                    oldpwd: old_pin,
+                    newpwd: new_pin,
+                },
+                true,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Enable the SIM PIN request at power on
+    pub async fn enable_pin(&mut self, pin: &str) -> Result<(), Error> {
+        self.set_pin_lock(FacilityLockMode::Lock, pin).await
+    }
+
+    /// Disable the SIM PIN request at power on
+    pub async fn disable_pin(&mut self, pin: &str) -> Result<(), Error> {
+        self.set_pin_lock(FacilityLockMode::Unlock, pin).await
+    }
+
+    async fn set_pin_lock(&mut self, mode: FacilityLockMode, pin: &str) -> Result<(), Error> {
+        self.network
+            .send_internal(
+                &SetFacilityLock {
+                    fac: Facility::SimPin,
+                    mode,
+                    passwd: Some(pin),
+                },
+                true,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Run modem state machine
+    ///
+    /// Turns on modem if needed and processes URCs.
+    /// Returns `nb::Error::WouldBlock` until the modem is fully initialized
+    /// and registered to the network.
+    ///
+    /// This must be called periodically in a loop.
+    pub async fn spin(&mut self) -> nb::Result<(), Error> {
+        // Let the module's UART sleep if nothing has been sent for a while
+        self.network.at_tx.client.sleep_if_idle();
+
+        let res = self.initialize().await;
+
+        self.process_events().await.map_err(Error::from)?;
+
+        res?;
+
+        if self.network.is_connected().map_err(Error::from)?
+            && self.state == State::FullyInitialized
+        {
+            Ok(())
+        } else {
+            // Reset context state if data connection is lost (This will act as a safeguard if a URC is missed)
+            self.network.contexts.deactivate_all();
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Setup only essential settings to use AT commands
+    ///
+    /// See [`GsmClient::setup_at_commands`](crate::GsmClient::setup_at_commands).
+    pub async fn setup_at_commands(&mut self) -> Result<(), Error> {
+        // Always re-configure the PDP contexts if we reconfigure the module
+        self.network.contexts.reset();
+
+        self.clear_buffers()?;
+
+        self.power_on().await?;
+
+        // At this point, if is_alive fails, the configured Baud rate is probably
+        // wrong, so probe the others
+        if self.is_alive(5).await.is_err() {
+            if let Err(e) = self.detect_baud_rate().await {
+                if self.hard_reset().await.is_err() {
+                    self.hard_power_off().await?;
+                    Timer::after(Duration::from_secs(1)).await;
+                }
+                return Err(e);
+            }
+        }
+
+        self.switch_baud_rate().await?;
+
+        self.network.send_internal(&EXTENDED_ERRORS, false).await?;
+
+        // Identify the module, selecting the module specific behaviour
+        let model = self.network.send_internal(&GetModelId, false).await?;
+        let firmware = self
+            .network
+            .send_internal(&GetFirmwareVersion, false)
+            .await?;
+        self.network.module = detect_module(&model.model, &firmware.version);
+
+        self.select_sim_slot().await?;
+
+        self.select_sim_card().await?;
+
+        self.network.send_internal(&GetCCID, false).await?;
+
+        let interface = InterfaceSetup::new::<Config>(self.network.module);
+        self.network.send_internal(&interface.dcd, false).await?;
+        self.network.send_internal(&interface.dtr, false).await?;
+        if let Some(hex_mode) = &interface.hex_mode {
+            self.network.send_internal(hex_mode, false).await?;
+        }
+        self.network
+            .send_internal(&interface.flow_control, false)
+            .await?;
+
+        // u-blox specific: UART power saving, once hardware flow control is
+        // settled
+        if self.network.module.vendor() == Vendor::Ublox {
+            self.set_power_saving().await?;
+        }
+
+        self.state = State::AtInitialized;
+        Ok(())
+    }
+
+    /// Send AT commands and wait responses from modem
+    ///
+    /// Modem must be initialized before this works.
+    /// For example use [`setup_at_commands`](Device::setup_at_commands) to only initialize AT commands support and nothing else.
+    pub async fn send_at<A, const LEN: usize>(&mut self, cmd: &A) -> Result<A::Response, Error>
+    where
+        A: atat::AtatCmd<LEN>,
+    {
+        match self.state {
+            State::Off => {
+                error!("Device not initialized!");
+                return Err(Error::Uninitialized);
+            }
+            State::AtInitialized | State::FullyInitialized => {}
+        }
+
+        Ok(self.network.send_internal(cmd, true).await?)
+    }
+
+    /// Select the active SIM slot, as configured with
+    /// [`CellularConfig::SIM_SELECT`]
+    async fn select_sim_slot(&mut self) -> Result<(), Error> {
+        for cmd in Config::SIM_SELECT.gpio_configuration(self.network.module, self.sim_slot) {
+            self.network.send_internal(&cmd, false).await?;
+        }
+        Ok(())
+    }
+
+    /// Switch to the other SIM slot if the active SIM fails to register,
+    /// see [`CellularConfig::SIM_FAILOVER_TIMEOUT`]. The module is left at
+    /// minimum functionality, to be initialized again with the new SIM.
+    async fn check_sim_failover(&mut self) -> Result<(), Error> {
+        if !sim::failover_needed::<Config>(self.state, &self.network.status) {
+            return Ok(());
+        }
+
+        self.sim_slot = self.sim_slot.other();
+        warn!(
+            "SIM failed to register, switching to {:?} slot",
+            self.sim_slot
+        );
+
+        self.network
+            .send_internal(&setup::minimum_functionality(self.network.module), false)
+            .await?;
+        self.select_sim_slot().await?;
+
+        self.state = State::Off;
+        self.config.on_event(Event::SimSlotSwitched(self.sim_slot));
+        Ok(())
+    }
+
+    pub(crate) async fn select_sim_card(&mut self) -> Result<(), Error> {
+        let mut unlock = PinUnlock::default();
+        for _ in 0..2 {
+            let status = self.network.send_internal(&GetPinStatus, true).await;
+            match unlock.step::<Config>(status, self.sim_pin_rejected)? {
+                PinStep::Ready => return Ok(()),
+                PinStep::EnterPin(pin) => {
+                    let res = self.network.send_internal(&SetPin { pin }, true).await;
+                    sim::check_pin(res, &mut self.sim_pin_rejected)?;
+                }
+                PinStep::Wait => Timer::after(Duration::from_secs(1)).await,
+            }
+        }
+
+        // There was an error initializing the SIM
+        // We've seen issues on uBlox-based devices, as a precation, we'll cycle
+        // the modem here through minimal/full functional state.
+        self.network
+            .send_internal(&setup::minimum_functionality(self.network.module), true)
+            .await?;
+        self.network
+            .send_internal(&FULL_FUNCTIONALITY, true)
+            .await?;
+
+        Err(Error::Busy)
+    }
+
+    /// Apply the RAT and band configuration, deregistering the module with
+    /// `AT+CFUN=0` if anything needs to be changed. `initialize` registers
+    /// it again right after.
+    async fn configure_radio(&mut self) -> Result<(), Error> {
+        if !radio::configured::<Config>(self.network.module) {
+            return Ok(());
+        }
+
+        let rat_update = if Config::RADIO_ACCESS_TECHNOLOGIES.is_empty() {
+            None
+        } else {
+            let current = self
+                .network
+                .send_internal(&GetLpwaRadioAccessTechnology, false)
+                .await?;
+            radio::rat_update(Config::RADIO_ACCESS_TECHNOLOGIES, &current)
+        };
+
+        let current_bands = if Config::BAND_MASKS.is_empty() {
+            None
+        } else {
+            Some(self.network.send_internal(&GetBandMask, false).await?)
+        };
+        let band_updates = || radio::band_updates(Config::BAND_MASKS, current_bands.as_ref());
+
+        if rat_update.is_none() && band_updates().next().is_none() {
+            return Ok(());
+        }
+
+        debug!("Applying the RAT and band configuration");
+        self.network
+            .send_internal(&setup::minimum_functionality(self.network.module), false)
+            .await?;
+
+        if let Some(cmd) = rat_update {
+            self.network.send_internal(&cmd, false).await?;
+        }
+        for cmd in band_updates() {
+            self.network.send_internal(&cmd, false).await?;
+        }
+        Ok(())
+    }
+
+    /// Initialize modem fully
+    ///
+    /// See [`GsmClient::initialize`](crate::GsmClient::initialize).
+    pub async fn initialize(&mut self) -> Result<(), Error> {
+        if self.power_state != PowerState::On {
+            // Always re-configure the module when power has been off
+            self.state = State::Off;
+
+            // Catch states where we have no vint sense, and the module is already in powered mode,
+            // but for some reason doesn't answer to AT commands.
+            // This usually happens on programming after modem power on.
+            if self.power_on().await.is_err() {
+                self.hard_reset().await?;
+            }
+
+            self.power_state = PowerState::On;
+        } else if matches!(self.state, State::FullyInitialized) {
+            return Ok(());
+        }
+
+        self.setup_at_commands().await?;
+        self.select_sim_card().await?;
+
+        let settings = ModuleSetup::new(self.network.module);
+        if let Some(message_waiting) = &settings.message_waiting {
+            self.network.send_internal(message_waiting, false).await?;
+        }
+        self.network
+            .send_internal(&settings.timezone, false)
+            .await?;
+
+        self.configure_radio().await?;
+
+        self.network
+            .send_internal(&settings.functionality, true)
+            .await?;
+
+        self.network.status.reset();
+        self.network
+            .status
+            .set_connection_state(ConnectionState::Connecting);
+
+        self.enable_registration_urcs().await?;
+
+        // Start from the first preferred operator, else set automatic operator
+        // selection, if not already set
+        let OperatorSelection { mode, .. } = self
+            .network
+            .send_internal(&GetOperatorSelection, true)
+            .await?;
+        if !self.network.status.operator_selected(mode) {
+            self.network.select_operator(true).await?;
+        }
+
+        self.network.update_registration().await?;
+
+        self.network.reset_reg_time()?;
+
+        self.state = State::FullyInitialized;
+        Ok(())
+    }
+
+    pub(crate) fn clear_buffers(&mut self) -> Result<(), Error> {
+        if let Some(ref mut sockets) = self.sockets.as_deref_mut() {
+            sockets.prune();
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn enable_registration_urcs(&mut self) -> Result<(), Error> {
+        let urcs = RegistrationUrcs::new(&self.network.status);
+        if self
+            .network
+            .send_internal(&urcs.events, true)
+            .await
+            .is_err()
+        {
+            warn!("Packet domain event reporting set failed");
+        }
+        self.network.send_internal(&urcs.creg, true).await?;
+        self.network.send_internal(&urcs.cgreg, true).await?;
+        self.network.send_internal(&urcs.cereg, true).await?;
+        Ok(())
+    }
+
+    fn handle_urc_internal(&mut self) -> Result<(), Error> {
+        let Some(urc) = self.urc_subscription.try_next_message_pure() else {
+            return Ok(());
+        };
+
+        if let Some(sockets) = self.sockets.as_deref_mut() {
+            match &urc {
+                Urc::SocketClosed(ip_transport_layer::urc::SocketClosed { socket })
+                | Urc::MipSocketStatus(fibocom::urc::SocketStatus { socket, status: 1 }) => {
+                    info!("[URC] SocketClosed {}", socket.0);
+                    if let Some((_, mut sock)) =
+                        sockets.iter_mut().find(|(handle, _)| handle == socket)
+                    {
+                        sock.closed_by_remote();
+                    }
+                }
+                Urc::SocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable {
+                    socket,
+                    length,
+                })
+                | Urc::SocketDataAvailableUDP(ip_transport_layer::urc::SocketDataAvailable {
+                    socket,
+                    length,
+                })
+                | Urc::MipSocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable {
+                    socket,
+                    length,
+                }) => {
+                    trace!("[Socket({})] {} bytes available", socket.0, *length as u16);
+                    if let Some((_, mut sock)) =
+                        sockets.iter_mut().find(|(handle, _)| handle == socket)
+                    {
+                        sock.set_available_data(*length);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(event) = event::urc_event(&urc) {
+            self.config.on_event(event);
+        }
+        Ok(())
+    }
+
+    /// Report the registration and data connection changes since the last
+    /// call
+    fn report_state_events(&mut self) {
+        let config = &mut self.config;
+        self.reported.update(
+            self.network.status.conn_state,
+            &self.network.contexts,
+            |event| config.on_event(event),
+        );
+    }
+
+    pub(crate) async fn process_events(&mut self) -> Result<(), Error> {
+        if self.power_state != PowerState::On {
+            return Err(Error::Uninitialized);
+        }
+
+        self.handle_urc_internal()?;
+
+        let res = self.network.process_events().await;
+        self.report_state_events();
+
+        match res {
+            // Catch "Resetting the modem due to the network registration timeout"
+            // as well as consecutive AT timeouts and do a hard reset.
+            Err(crate::network::Error::Generic(GenericError::Timeout)) => {
+                self.hard_reset().await?;
+                Err(Error::Generic(GenericError::Timeout))
+            }
+            result => {
+                result?;
+                self.check_sim_failover().await
+            }
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use atat::asynch::AtatClient;
+use embassy_time::{Duration, Timer};
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketSet};
+
+use super::{
+    dialect::{self, Dialect, ModuleDialect},
+    network::Network,
+    Device,
+};
+use crate::{
+    command::{
+        ip_transport_layer::responses::SocketData,
+        psn::{responses::GPRSAttached, types::GPRSAttachedState, GetGPRSAttached},
+        Urc,
+    },
+    config::CellularConfig,
+    error::{Error as DeviceError, GenericError},
+    network::ContextId,
+    power_saving::PowerSavingClient,
+    services::data::{
+        apn::APNInfo,
+        context::PdpContext,
+        dialect::SecurityDataKind,
+        hex,
+        ssl::SecurityProfileId,
+        usage::{self, ContextUsage, DataBudget, DataUsage},
+        ContextState, Error, INGRESS_CHUNK_SIZE,
+    },
+};
+use embedded_nal::IpAddr;
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Define a PDP context
+    async fn define_context(&mut self, cid: ContextId, apn_info: &APNInfo) -> Result<(), Error> {
+        dialect::for_module(self.network.module)
+            .define_context(&mut self.network, cid, apn_info)
+            .await
+    }
+
+    /// Handle modem data connection
+    ///
+    /// Async counterpart of [`GsmClient::data_service`](crate::GsmClient::data_service).
+    /// Instead of returning `WouldBlock`, this keeps spinning the device,
+    /// yielding to the executor between attempts, until the modem is
+    /// registered and a data context is active.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut data_service = modem.data_service(&APNInfo::new("myapn")).await?;
+    /// // at this point modem is registered to network and data connection is active
+    /// ```
+    pub async fn data_service<'a>(
+        &'a mut self,
+        apn_info: &APNInfo,
+    ) -> Result<DataService<'a, 'sub, PowerSavingClient<AtCl, Config::DtrPin>, N, L>, DeviceError>
+    {
+        self.data_service_on(PdpContext::DEFAULT, apn_info).await
+    }
+
+    /// Handle the data connection over `context`
+    ///
+    /// Async counterpart of
+    /// [`GsmClient::data_service_on`](crate::GsmClient::data_service_on).
+    pub async fn data_service_on<'a>(
+        &'a mut self,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> Result<DataService<'a, 'sub, PowerSavingClient<AtCl, Config::DtrPin>, N, L>, DeviceError>
+    {
+        self.network.contexts.register(context)?;
+
+        loop {
+            // Spin [`Device`], handling [`Network`] related URC changes and
+            // propagting the FSM
+            let spin = self.spin().await;
+
+            // Resolved once the IMSI has been read
+            let apn_info = &apn_info.resolve(
+                self.network.status.imsi,
+                self.network.contexts.failed_activations(context.cid),
+            );
+
+            match spin {
+                Ok(()) => {
+                    self.define_context(context.cid, apn_info).await?;
+
+                    // At this point we WILL be registered on the network!
+                    match DataService::connect_network(&mut self.network, context, apn_info).await {
+                        Ok(()) => break,
+                        Err(nb::Error::Other(e)) => return Err(e.into()),
+                        Err(nb::Error::WouldBlock) => {}
+                    }
+                }
+                // Define the context ahead of activation, if the dialect
+                // needs to, e.g. through AT+CGDCONT
+                Err(nb::Error::WouldBlock) => {
+                    self.define_context(context.cid, apn_info).await?;
+                }
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+
+            Timer::after(Duration::from_secs(1)).await;
+        }
+
+        let mut data_service = DataService {
+            network: &mut self.network,
+            context,
+            sockets: self.sockets.as_deref_mut(),
+        };
+
+        // Attempt to ingress data from every open socket, into it's internal rx
+        // buffer
+        if data_service.sockets.is_some() {
+            data_service.socket_ingress_all().await?;
+        }
+
+        Ok(data_service)
+    }
+}
+
+/// Async counterpart of [`crate::DataService`], opening sockets over the
+/// default context only as well
+pub struct DataService<'a, 'sub, AtCl, const N: usize, const L: usize>
+where
+    AtCl: AtatClient,
+{
+    pub(crate) network: &'a mut Network<'sub, AtCl>,
+    /// Context of the data connection
+    pub(crate) context: PdpContext,
+    pub(crate) sockets: Option<&'a mut SocketSet<N, L>>,
+}
+
+impl<'a, 'sub, AtCl, const N: usize, const L: usize> DataService<'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    /// Make sure the data context is active, returning `WouldBlock` while
+    /// the module is still attaching or activating it.
+    async fn connect_network(
+        network: &mut Network<'sub, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error> {
+        match network.contexts.state(context.cid) {
+            ContextState::Active => return Ok(()),
+            ContextState::Setup | ContextState::Activating => {}
+        }
+
+        // See `crate::DataService::connect` for why attaching is checked
+        // before activating the context.
+        Self::attach_network(network).await?;
+
+        // Activate the context, going through the next APN candidate on
+        // failure
+        let result = dialect::for_module(network.module)
+            .activate_context(network, context, apn_info)
+            .await;
+        if let Err(nb::Error::Other(_)) = result {
+            network.contexts.activation_failed(context.cid);
+        }
+        result
+    }
+
+    /// Context of the data connection
+    pub fn context(&self) -> PdpContext {
+        self.context
+    }
+
+    /// IP address of the data connection
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.network.contexts.ip_addr(self.context.cid)
+    }
+
+    /// Bytes sent and received over `socket` since it was created
+    pub async fn socket_usage(&mut self, socket: SocketHandle) -> Result<DataUsage, Error> {
+        self.dialect().socket_usage(self.network, socket).await
+    }
+
+    /// Bytes sent and received over the context of the data connection
+    pub async fn context_usage(&mut self) -> Result<ContextUsage, Error> {
+        let counters = self.dialect().data_counters(self.network).await?;
+        Ok(usage::context_usage(&counters, self.context.cid))
+    }
+
+    /// Reset the total data usage of the context of the data connection,
+    /// persisted by the module. The data budget is reset as well, including
+    /// on the modules not counting the data.
+    pub async fn reset_data_usage(&mut self) -> Result<(), Error> {
+        match self
+            .dialect()
+            .reset_data_counters(self.network, self.context.cid)
+            .await
+        {
+            Ok(()) => {}
+            Err(Error::Generic(GenericError::Unsupported))
+                if self.network.contexts.data_budget.is_some() => {}
+            Err(e) => return Err(e),
+        }
+        if let Some(budget) = self.network.contexts.data_budget.as_mut() {
+            budget.reset();
+        }
+        Ok(())
+    }
+
+    /// Import a client certificate under `name`, and assign it to `profile_id`
+    pub async fn import_certificate(
+        &mut self,
+        profile_id: SecurityProfileId,
+        name: &str,
+        certificate: &[u8],
+    ) -> Result<(), Error> {
+        self.dialect()
+            .import_security_data(
+                self.network,
+                profile_id,
+                SecurityDataKind::ClientCertificate,
+                name,
+                certificate,
+                None,
+            )
+            .await
+    }
+
+    /// Import a trusted root CA under `name`, and assign it to `profile_id`
+    pub async fn import_root_ca(
+        &mut self,
+        profile_id: SecurityProfileId,
+        name: &str,
+        root_ca: &[u8],
+    ) -> Result<(), Error> {
+        self.dialect()
+            .import_security_data(
+                self.network,
+                profile_id,
+                SecurityDataKind::TrustedRootCa,
+                name,
+                root_ca,
+                None,
+            )
+            .await
+    }
+
+    /// Import a client private key under `name`, and assign it to `profile_id`
+    pub async fn import_private_key(
+        &mut self,
+        profile_id: SecurityProfileId,
+        name: &str,
+        private_key: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error> {
+        self.dialect()
+            .import_security_data(
+                self.network,
+                profile_id,
+                SecurityDataKind::ClientPrivateKey,
+                name,
+                private_key,
+                password,
+            )
+            .await
+    }
+
+    /// Set up `profile_id` for TLS connections to `server_hostname`
+    pub async fn enable_ssl(
+        &mut self,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error> {
+        self.dialect()
+            .enable_ssl(self.network, profile_id, server_hostname, use_sni)
+            .await
+    }
+
+    /// Refuse to send once the data budget is used up, reading the data
+    /// counters again if they are due to.
+    pub(crate) async fn check_data_budget(&mut self) -> Result<(), Error> {
+        let Some(budget) = self.network.contexts.data_budget.as_ref() else {
+            return Ok(());
+        };
+
+        if budget.needs_refresh() {
+            let counters = match self.dialect().data_counters(self.network).await {
+                Ok(counters) => Some(counters),
+                Err(Error::Generic(GenericError::Unsupported)) => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(budget) = self.network.contexts.data_budget.as_mut() {
+                budget.refresh(counters.as_deref());
+            }
+        }
+
+        self.network
+            .contexts
+            .data_budget
+            .as_ref()
+            .map_or(Ok(()), DataBudget::check)
+    }
+
+    // Make sure we are attached to the cellular network.
+    async fn attach_network(network: &mut Network<'sub, AtCl>) -> nb::Result<(), Error> {
+        // Wait for AT+CGATT to return 1
+        for _ in 0..10 {
+            let GPRSAttached { state } = network
+                .send_internal(&GetGPRSAttached, true)
+                .await
+                .map_err(Error::from)?;
+
+            if state == GPRSAttachedState::Attached {
+                return Ok(());
+            }
+
+            Timer::after(Duration::from_secs(1)).await;
+        }
+
+        Err(nb::Error::WouldBlock)
+    }
+
+    /// Dialect spoken by the attached module
+    pub(crate) fn dialect(&self) -> ModuleDialect {
+        dialect::for_module(self.network.module)
+    }
+
+    pub async fn send_at<A, const LEN: usize>(&mut self, cmd: &A) -> Result<A::Response, Error>
+    where
+        A: atat::AtatCmd<LEN>,
+    {
+        Ok(self.network.send_internal(cmd, true).await?)
+    }
+
+    pub fn handle_urc<F: FnOnce(Urc) -> bool>(&mut self, f: F) -> Result<(), Error> {
+        self.network.at_tx.handle_urc(f).map_err(Error::Network)
+    }
+
+    pub(crate) async fn socket_ingress_all(&mut self) -> Result<(), Error> {
+        let dialect = self.dialect();
+        let sockets = self.sockets.as_deref_mut().ok_or(Error::SocketMemory)?;
+        let network = &mut self.network;
+
+        for (handle, mut socket) in sockets.iter_mut() {
+            let available_data = socket.available_data();
+
+            if available_data == 0 {
+                // Check for new socket data available at regular
+                // intervals, just in case a URC is missed
+                if socket.should_update_available_data() {
+                    match dialect
+                        .read_socket(network, handle, socket.get_type(), 0)
+                        .await
+                    {
+                        Ok(SocketData { length, .. }) => socket.set_available_data(length),
+                        Err(_) => socket.closed_by_remote(),
+                    }
+                }
+
+                continue;
+            }
+
+            if !socket.can_recv() {
+                continue;
+            }
+
+            // Request [`INGRESS_CHUNK_SIZE`] if it is available, otherwise
+            // request maximum available data, capped by the room left in
+            // the socket buffer
+            let wanted_len = core::cmp::min(available_data, INGRESS_CHUNK_SIZE);
+            let requested_len = core::cmp::min(wanted_len, socket.rx_window());
+
+            let (socket_handle, data, len) = match dialect
+                .read_socket(network, handle, socket.get_type(), requested_len)
+                .await
+            {
+                Ok(SocketData {
+                    socket,
+                    data,
+                    length,
+                }) => (socket, data, length),
+                Err(_) => continue,
+            };
+
+            if socket_handle != handle {
+                error!("WrongSocketType {:?} != {:?}", socket_handle, handle);
+                continue;
+            }
+
+            if len == 0 {
+                socket.set_available_data(0);
+            }
+
+            let Some(mut data) = data else {
+                error!("{:?}", SocketError::Exhausted);
+                continue;
+            };
+
+            let data_len = data.len() / 2;
+            if len > 0 && data_len != len {
+                error!("BadLength {} != {}, {}", len, data_len, data.as_str());
+                continue;
+            }
+
+            let Ok(demangled) = hex::from_hex(unsafe { data.as_bytes_mut() }) else {
+                continue;
+            };
+
+            network.contexts.record_data(demangled.len());
+            let enqueued = socket.rx_enqueue_slice(demangled);
+            if enqueued != demangled.len() {
+                // This should never happen, due to the `requested_len` check
+                // above
+                error!(
+                    "Failed to enqueue full slice of data! {} != {}",
+                    enqueued,
+                    demangled.len()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
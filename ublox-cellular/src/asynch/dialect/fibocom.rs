@@ -0,0 +1,298 @@
+//! Async Fibocom dialect: `+MIPxxx` commands
+use super::{Dialect, Fibocom};
+use crate::{
+    asynch::network::Network,
+    command::{
+        fibocom::{
+            responses::{InternetConnection, OpenSocketResponse},
+            types::{ConnectionOperation, ConnectionStatus, MipProtocol},
+            CloseSocket, GetInternetConnection, OpenSocket, PushSocketData, ReadSocketData,
+            ResolveHost, SendSocketData, SetInternetConnection,
+        },
+        ip_transport_layer::responses::SocketData,
+        psn::responses::DataCounters,
+    },
+    error::GenericError,
+    network::ContextId,
+    services::data::{
+        apn::{APNInfo, Apn, PdpType},
+        context::PdpContext,
+        dialect::{
+            fibocom::{ssl_config, SecurityDataImport, SEND_CHUNK_SIZE},
+            SecurityDataKind,
+        },
+        hex,
+        ssl::SecurityProfileId,
+        usage::DataUsage,
+        ContextState, Error,
+    },
+};
+use atat::asynch::AtatClient;
+use embedded_nal::{IpAddr, SocketAddr};
+use heapless::{String, Vec};
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketType};
+
+async fn open_socket<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    socket: SocketHandle,
+    remote: SocketAddr,
+    protocol: MipProtocol,
+) -> Result<(), Error> {
+    let OpenSocketResponse { state, .. } = network
+        .send_internal(
+            &OpenSocket {
+                socket,
+                local_port: None,
+                remote_addr: remote.ip(),
+                remote_port: remote.port(),
+                protocol,
+            },
+            false,
+        )
+        .await?;
+
+    if state != 1 {
+        return Err(Error::Socket(SocketError::Unaddressable));
+    }
+
+    Ok(())
+}
+
+async fn send<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    socket: SocketHandle,
+    data: &[u8],
+) -> Result<(), Error> {
+    for chunk in data.chunks(SEND_CHUNK_SIZE) {
+        trace!("Sending: {} bytes", chunk.len());
+        let hex = hex::to_hex::<{ SEND_CHUNK_SIZE * 2 }>(chunk).map_err(|_| Error::BadLength)?;
+
+        let response = network
+            .send_internal(&SendSocketData { socket, data: &hex }, false)
+            .await?;
+        if response.socket != socket {
+            return Err(Error::Socket(SocketError::InvalidSocket));
+        }
+
+        network
+            .send_internal(&PushSocketData { socket }, false)
+            .await?;
+    }
+
+    Ok(())
+}
+
+impl<AtCl: AtatClient> Dialect<AtCl> for Fibocom {
+    async fn define_context(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _cid: ContextId,
+        _apn_info: &APNInfo,
+    ) -> Result<(), Error> {
+        // The APN is given along `+MIPCALL` when activating
+        Ok(())
+    }
+
+    async fn activate_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error> {
+        // `+MIPCALL` brings up a single IPv4 connection
+        if context != PdpContext::DEFAULT || apn_info.pdp_type != PdpType::Ip {
+            return Err(nb::Error::Other(Error::Generic(GenericError::Unsupported)));
+        }
+
+        let InternetConnection { status, ip_addr } = network
+            .send_internal(&GetInternetConnection, true)
+            .await
+            .map_err(Error::from)?;
+
+        if status == ConnectionStatus::Connected {
+            network.contexts.set_active(context.cid, ip_addr);
+            return Ok(());
+        }
+
+        let apn = match apn_info.apn {
+            Apn::Given(apn) => Some(apn),
+            Apn::Automatic => None,
+        };
+
+        network
+            .send_internal(
+                &SetInternetConnection {
+                    operation: ConnectionOperation::Connect,
+                    apn,
+                    username: apn_info.user_name,
+                    password: apn_info.password,
+                },
+                true,
+            )
+            .await
+            .map_err(Error::from)?;
+
+        network
+            .contexts
+            .set_state(context.cid, ContextState::Activating);
+        Err(nb::Error::WouldBlock)
+    }
+
+    async fn create_socket(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        socket_type: SocketType,
+        candidate: SocketHandle,
+    ) -> Result<SocketHandle, Error> {
+        // `+MIPCALL` only brings up the default context
+        if context != PdpContext::DEFAULT {
+            return Err(Error::Generic(GenericError::Unsupported));
+        }
+
+        // Sockets are only created on the module by `+MIPOPEN`, with an id
+        // chosen by the host
+        match socket_type {
+            SocketType::Tcp | SocketType::Udp => Ok(candidate),
+            _ => Err(Error::WrongSocketType),
+        }
+    }
+
+    async fn connect_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        open_socket(network, socket, remote, MipProtocol::Tls).await
+    }
+
+    async fn connect_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        open_socket(network, socket, remote, MipProtocol::Udp).await
+    }
+
+    async fn send_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        send(network, socket, data).await
+    }
+
+    async fn send_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        _remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        // The remote endpoint is fixed by `+MIPOPEN`
+        send(network, socket, data).await
+    }
+
+    async fn read_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        _socket_type: SocketType,
+        length: usize,
+    ) -> Result<SocketData, Error> {
+        let data = network
+            .send_internal(&ReadSocketData { socket, length }, false)
+            .await?;
+
+        Ok(SocketData {
+            socket: data.socket,
+            length: data.length,
+            data: data.data,
+        })
+    }
+
+    async fn close_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<(), Error> {
+        network
+            .send_internal(&CloseSocket { socket }, false)
+            .await?;
+        Ok(())
+    }
+
+    async fn socket_usage(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _socket: SocketHandle,
+    ) -> Result<DataUsage, Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    async fn data_counters(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+    ) -> Result<Vec<DataCounters, 8>, Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    async fn reset_data_counters(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _cid: ContextId,
+    ) -> Result<(), Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    async fn resolve_host(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        host: &str,
+    ) -> Result<IpAddr, Error> {
+        let resp = network.send_internal(&ResolveHost { host }, true).await?;
+        resp.ip_addr.parse().map_err(|_| Error::Dns)
+    }
+
+    async fn resolve_address(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _ip_addr: IpAddr,
+    ) -> Result<String<256>, Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    async fn import_security_data(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &str,
+        data: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error> {
+        let commands = SecurityDataImport::new(profile_id, kind, name, data, password)?;
+        network.send_internal(&commands.prepare, true).await?;
+        network.send_internal(&commands.data, true).await?;
+        network.send_internal(&commands.assign, true).await?;
+
+        Ok(())
+    }
+
+    async fn enable_ssl(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error> {
+        for cmd in &ssl_config(profile_id, server_hostname, use_sni)? {
+            network.send_internal(cmd, true).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,416 @@
+//! Async counterpart of the data service dialects
+//!
+//! Mirrors [`crate::services::data::dialect`], the command sequences shared
+//! by both drivers being built there. The async [`DataService`] and the
+//! `embedded-nal-async` stacks only talk to the [`Dialect`] returned by
+//! [`for_module`].
+//!
+//! [`DataService`]: super::DataService
+
+mod fibocom;
+mod ublox;
+
+pub use crate::services::data::dialect::{Fibocom, Ublox};
+
+use super::network::Network;
+use crate::{
+    command::{ip_transport_layer::responses::SocketData, psn::responses::DataCounters},
+    module::{ModuleKind, Vendor},
+    network::ContextId,
+    services::data::{
+        apn::APNInfo,
+        context::PdpContext,
+        dialect::{ContextDefinition, SecurityDataKind},
+        ssl::SecurityProfileId,
+        usage::DataUsage,
+        ContextState, Error,
+    },
+};
+use atat::asynch::AtatClient;
+use embedded_nal::{IpAddr, SocketAddr};
+use heapless::{String, Vec};
+use ublox_sockets::{SocketHandle, SocketType};
+
+/// Async counterpart of [`crate::services::data::dialect::Dialect`]
+pub trait Dialect<AtCl: AtatClient> {
+    /// Define the PDP context `cid` while the module is registering. Called
+    /// on every `data_service` attempt until the context is activating.
+    async fn define_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+        apn_info: &APNInfo,
+    ) -> Result<(), Error>;
+
+    /// Activate the data connection over `context`, once attached to the
+    /// network. Returns `WouldBlock` while the activation is still pending.
+    async fn activate_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error>;
+
+    /// Create a socket of `socket_type`, bound to the connection of
+    /// `context`. Modules letting the host choose the socket id use
+    /// `candidate`, the lowest id not in use.
+    async fn create_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        socket_type: SocketType,
+        candidate: SocketHandle,
+    ) -> Result<SocketHandle, Error>;
+
+    /// Connect a TCP socket, using TLS with security profile 0.
+    async fn connect_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error>;
+
+    /// Bind a UDP socket to its remote endpoint.
+    async fn connect_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error>;
+
+    async fn send_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error>;
+
+    async fn send_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error>;
+
+    /// Read up to `length` hex encoded bytes from `socket`. With `length` 0
+    /// only the number of available bytes is returned.
+    async fn read_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        socket_type: SocketType,
+        length: usize,
+    ) -> Result<SocketData, Error>;
+
+    async fn close_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<(), Error>;
+
+    /// Bytes sent and received over `socket` since it was created
+    async fn socket_usage(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<DataUsage, Error>;
+
+    /// Data counters of the active contexts
+    async fn data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+    ) -> Result<Vec<DataCounters, 8>, Error>;
+
+    /// Reset the total data counters of `cid`
+    async fn reset_data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+    ) -> Result<(), Error>;
+
+    async fn resolve_host(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        host: &str,
+    ) -> Result<IpAddr, Error>;
+
+    async fn resolve_address(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        ip_addr: IpAddr,
+    ) -> Result<String<256>, Error>;
+
+    /// Import security data under `name`, and assign it to `profile_id`.
+    /// Fails with [`Error::BadLength`] when `name`, `data` or `password` do
+    /// not fit in the import commands.
+    async fn import_security_data(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &str,
+        data: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error>;
+
+    async fn enable_ssl(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error>;
+}
+
+/// Dialect spoken by a module, dispatching to [`Ublox`] or [`Fibocom`]
+#[derive(Debug, Clone, Copy)]
+pub enum ModuleDialect {
+    Ublox(Ublox),
+    Fibocom(Fibocom),
+}
+
+/// Select the dialect spoken by `module`
+pub fn for_module(module: ModuleKind) -> ModuleDialect {
+    match module.vendor() {
+        Vendor::Ublox => ModuleDialect::Ublox(Ublox),
+        Vendor::Fibocom => ModuleDialect::Fibocom(Fibocom),
+    }
+}
+
+impl<AtCl: AtatClient> Dialect<AtCl> for ModuleDialect {
+    async fn define_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+        apn_info: &APNInfo,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.define_context(network, cid, apn_info).await,
+            Self::Fibocom(d) => d.define_context(network, cid, apn_info).await,
+        }
+    }
+
+    async fn activate_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.activate_context(network, context, apn_info).await,
+            Self::Fibocom(d) => d.activate_context(network, context, apn_info).await,
+        }
+    }
+
+    async fn create_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        socket_type: SocketType,
+        candidate: SocketHandle,
+    ) -> Result<SocketHandle, Error> {
+        match self {
+            Self::Ublox(d) => {
+                d.create_socket(network, context, socket_type, candidate)
+                    .await
+            }
+            Self::Fibocom(d) => {
+                d.create_socket(network, context, socket_type, candidate)
+                    .await
+            }
+        }
+    }
+
+    async fn connect_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.connect_tcp(network, socket, remote).await,
+            Self::Fibocom(d) => d.connect_tcp(network, socket, remote).await,
+        }
+    }
+
+    async fn connect_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.connect_udp(network, socket, remote).await,
+            Self::Fibocom(d) => d.connect_udp(network, socket, remote).await,
+        }
+    }
+
+    async fn send_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.send_tcp(network, socket, data).await,
+            Self::Fibocom(d) => d.send_tcp(network, socket, data).await,
+        }
+    }
+
+    async fn send_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.send_udp(network, socket, remote, data).await,
+            Self::Fibocom(d) => d.send_udp(network, socket, remote, data).await,
+        }
+    }
+
+    async fn read_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        socket_type: SocketType,
+        length: usize,
+    ) -> Result<SocketData, Error> {
+        match self {
+            Self::Ublox(d) => d.read_socket(network, socket, socket_type, length).await,
+            Self::Fibocom(d) => d.read_socket(network, socket, socket_type, length).await,
+        }
+    }
+
+    async fn close_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.close_socket(network, socket).await,
+            Self::Fibocom(d) => d.close_socket(network, socket).await,
+        }
+    }
+
+    async fn socket_usage(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<DataUsage, Error> {
+        match self {
+            Self::Ublox(d) => d.socket_usage(network, socket).await,
+            Self::Fibocom(d) => d.socket_usage(network, socket).await,
+        }
+    }
+
+    async fn data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+    ) -> Result<Vec<DataCounters, 8>, Error> {
+        match self {
+            Self::Ublox(d) => d.data_counters(network).await,
+            Self::Fibocom(d) => d.data_counters(network).await,
+        }
+    }
+
+    async fn reset_data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => d.reset_data_counters(network, cid).await,
+            Self::Fibocom(d) => d.reset_data_counters(network, cid).await,
+        }
+    }
+
+    async fn resolve_host(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        host: &str,
+    ) -> Result<IpAddr, Error> {
+        match self {
+            Self::Ublox(d) => d.resolve_host(network, host).await,
+            Self::Fibocom(d) => d.resolve_host(network, host).await,
+        }
+    }
+
+    async fn resolve_address(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        ip_addr: IpAddr,
+    ) -> Result<String<256>, Error> {
+        match self {
+            Self::Ublox(d) => d.resolve_address(network, ip_addr).await,
+            Self::Fibocom(d) => d.resolve_address(network, ip_addr).await,
+        }
+    }
+
+    async fn import_security_data(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &str,
+        data: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => {
+                d.import_security_data(network, profile_id, kind, name, data, password)
+                    .await
+            }
+            Self::Fibocom(d) => {
+                d.import_security_data(network, profile_id, kind, name, data, password)
+                    .await
+            }
+        }
+    }
+
+    async fn enable_ssl(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Ublox(d) => {
+                d.enable_ssl(network, profile_id, server_hostname, use_sni)
+                    .await
+            }
+            Self::Fibocom(d) => {
+                d.enable_ssl(network, profile_id, server_hostname, use_sni)
+                    .await
+            }
+        }
+    }
+}
+
+/// Async counterpart of
+/// [`define_pdp_context`](crate::services::data::dialect::define_pdp_context)
+pub(crate) async fn define_pdp_context<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    cid: ContextId,
+    apn_info: &APNInfo<'_>,
+) -> Result<(), Error> {
+    let commands = ContextDefinition::new(&network.contexts, network.module, cid, apn_info);
+    if let Some(cmd) = &commands.minimum_functionality {
+        network.send_internal(cmd, true).await?;
+    }
+    if let Some(cmd) = &commands.definition {
+        network.send_internal(cmd, true).await?;
+    }
+    if let Some(cmd) = &commands.credentials {
+        network.send_internal(cmd, true).await?;
+    }
+    if let Some(cmd) = &commands.full_functionality {
+        network.send_internal(cmd, true).await?;
+    }
+
+    network.contexts.set_state(cid, ContextState::Activating);
+    Ok(())
+}
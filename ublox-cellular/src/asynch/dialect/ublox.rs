@@ -0,0 +1,560 @@
+//! Async u-blox dialect: `+UPSD`, `+USOxx` and `+UDNSRN` commands
+use super::{define_pdp_context, Dialect, Ublox};
+use crate::{
+    asynch::network::Network,
+    command::{
+        dns::{types::ResolutionType, ResolveNameIp},
+        ip_transport_layer::{
+            responses::{SocketData, UDPSocketData},
+            types::{SocketControlParam, SocketProtocol, SslTlsStatus},
+            CloseSocket, ConnectSocket, CreateSocket, PrepareUDPSendToDataBinary,
+            PrepareWriteSocketDataBinary, ReadSocketData, ReadUDPSocketData, SetSocketSslState,
+            SocketControl, UDPSendToDataBinary, WriteSocketDataBinary,
+        },
+        psn::{
+            self,
+            responses::{DataCounters, PacketSwitchedNetworkData},
+            types::{PDPContextStatus, PacketSwitchedAction, PacketSwitchedNetworkDataParam},
+            GetDataCounters, GetPDPContextState, GetPacketSwitchedNetworkData, SetDataCounters,
+            SetPDPContextState, SetPacketSwitchedAction,
+        },
+    },
+    error::GenericError,
+    module::ModuleKind,
+    network::ContextId,
+    services::data::{
+        apn::{APNInfo, PdpType},
+        context::PdpContext,
+        dialect::{
+            ublox::{
+                context_activated, security_profile_config, upsd_profile_config, SecurityDataImport,
+            },
+            SecurityDataKind,
+        },
+        ssl::SecurityProfileId,
+        usage::DataUsage,
+        ContextState, Error, EGRESS_CHUNK_SIZE,
+    },
+};
+use atat::asynch::AtatClient;
+use core::fmt::Write;
+use embedded_nal::{IpAddr, SocketAddr};
+use heapless::{String, Vec};
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketType};
+
+impl<AtCl: AtatClient> Dialect<AtCl> for Ublox {
+    async fn define_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+        apn_info: &APNInfo,
+    ) -> Result<(), Error> {
+        // With AT+UPSD-based context activation, everything is set up when
+        // activating
+        if network.module.upsd_context_activation()
+            || network.contexts.state(cid) != ContextState::Setup
+        {
+            return Ok(());
+        }
+
+        define_pdp_context(network, cid, apn_info).await
+    }
+
+    async fn activate_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error> {
+        if network.module.upsd_context_activation() {
+            activate_context_upsd(network, context, apn_info).await
+        } else {
+            activate_context_3gpp(network, context, apn_info).await
+        }
+    }
+
+    async fn create_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        socket_type: SocketType,
+        _candidate: SocketHandle,
+    ) -> Result<SocketHandle, Error> {
+        // `+USOCR` sockets go over the default context, with no way of
+        // selecting another one
+        if context != PdpContext::DEFAULT {
+            return Err(Error::Generic(GenericError::Unsupported));
+        }
+
+        let (protocol, check_urc) = match socket_type {
+            SocketType::Tcp => (SocketProtocol::TCP, true),
+            SocketType::Udp => (SocketProtocol::UDP, false),
+            _ => return Err(Error::WrongSocketType),
+        };
+
+        let socket_resp = network
+            .send_internal(
+                &CreateSocket {
+                    protocol,
+                    local_port: None,
+                },
+                check_urc,
+            )
+            .await?;
+
+        Ok(socket_resp.socket)
+    }
+
+    async fn connect_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        network
+            .send_internal(
+                &SetSocketSslState {
+                    socket,
+                    ssl_tls_status: SslTlsStatus::Enabled(SecurityProfileId(0)),
+                },
+                true,
+            )
+            .await?;
+
+        network
+            .send_internal(
+                &ConnectSocket {
+                    socket,
+                    remote_addr: remote.ip(),
+                    remote_port: remote.port(),
+                },
+                false,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn connect_udp(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _socket: SocketHandle,
+        _remote: SocketAddr,
+    ) -> Result<(), Error> {
+        // The remote endpoint is given along every `+USOST`
+        Ok(())
+    }
+
+    async fn send_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        for chunk in data.chunks(EGRESS_CHUNK_SIZE) {
+            trace!("Sending: {} bytes", chunk.len());
+            network
+                .send_internal(
+                    &PrepareWriteSocketDataBinary {
+                        socket,
+                        length: chunk.len(),
+                    },
+                    false,
+                )
+                .await?;
+
+            let response = network
+                .send_internal(
+                    &WriteSocketDataBinary {
+                        data: atat::serde_bytes::Bytes::new(chunk),
+                    },
+                    false,
+                )
+                .await?;
+
+            if response.length != chunk.len() {
+                return Err(Error::BadLength);
+            }
+            if response.socket != socket {
+                return Err(Error::Socket(SocketError::InvalidSocket));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        for chunk in data.chunks(EGRESS_CHUNK_SIZE) {
+            trace!("Sending: {} bytes", chunk.len());
+            network
+                .send_internal(
+                    &PrepareUDPSendToDataBinary {
+                        socket,
+                        remote_addr: remote.ip(),
+                        remote_port: remote.port(),
+                        length: chunk.len(),
+                    },
+                    false,
+                )
+                .await?;
+
+            let response = network
+                .send_internal(
+                    &UDPSendToDataBinary {
+                        data: atat::serde_bytes::Bytes::new(chunk),
+                    },
+                    false,
+                )
+                .await?;
+
+            if response.length != chunk.len() {
+                return Err(Error::BadLength);
+            }
+            if response.socket != socket {
+                return Err(Error::Socket(SocketError::InvalidSocket));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn read_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        socket_type: SocketType,
+        length: usize,
+    ) -> Result<SocketData, Error> {
+        match socket_type {
+            SocketType::Tcp => Ok(network
+                .send_internal(&ReadSocketData { socket, length }, false)
+                .await?),
+            SocketType::Udp => {
+                let UDPSocketData {
+                    socket,
+                    data,
+                    length,
+                    ..
+                } = network
+                    .send_internal(&ReadUDPSocketData { socket, length }, false)
+                    .await?;
+
+                Ok(SocketData {
+                    socket,
+                    length,
+                    data,
+                })
+            }
+            _ => Err(Error::WrongSocketType),
+        }
+    }
+
+    async fn close_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<(), Error> {
+        network
+            .send_internal(&CloseSocket { socket }, false)
+            .await?;
+        Ok(())
+    }
+
+    async fn socket_usage(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<DataUsage, Error> {
+        let sent = network
+            .send_internal(
+                &SocketControl {
+                    socket,
+                    param_id: SocketControlParam::BytesSent,
+                },
+                false,
+            )
+            .await?;
+        let received = network
+            .send_internal(
+                &SocketControl {
+                    socket,
+                    param_id: SocketControlParam::BytesReceived,
+                },
+                false,
+            )
+            .await?;
+
+        Ok(DataUsage {
+            sent: sent.param_val.into(),
+            received: received.param_val.into(),
+        })
+    }
+
+    async fn data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+    ) -> Result<Vec<DataCounters, 8>, Error> {
+        Ok(network.send_internal(&GetDataCounters, true).await?)
+    }
+
+    async fn reset_data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+    ) -> Result<(), Error> {
+        network
+            .send_internal(
+                &SetDataCounters {
+                    cid,
+                    total_bytes_sent: 0,
+                    total_bytes_received: 0,
+                },
+                true,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn resolve_host(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        host: &str,
+    ) -> Result<IpAddr, Error> {
+        let resp = network
+            .send_internal(
+                &ResolveNameIp {
+                    resolution_type: ResolutionType::DomainNameToIp,
+                    ip_domain_string: host,
+                },
+                true,
+            )
+            .await?;
+
+        resp.ip_domain_string.parse().map_err(|_| Error::Dns)
+    }
+
+    async fn resolve_address(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        ip_addr: IpAddr,
+    ) -> Result<String<256>, Error> {
+        let mut ip_str = String::<256>::new();
+        write!(&mut ip_str, "{ip_addr}").map_err(|_| Error::BadLength)?;
+
+        let resp = network
+            .send_internal(
+                &ResolveNameIp {
+                    resolution_type: ResolutionType::IpToDomainName,
+                    ip_domain_string: &ip_str,
+                },
+                true,
+            )
+            .await?;
+
+        Ok(String::from(resp.ip_domain_string.as_str()))
+    }
+
+    async fn import_security_data(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &str,
+        data: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error> {
+        let commands = SecurityDataImport::new(profile_id, kind, name, data, password)?;
+        network.send_internal(&commands.prepare, true).await?;
+        network.send_internal(&commands.data, true).await?;
+        network.send_internal(&commands.assign, true).await?;
+
+        Ok(())
+    }
+
+    async fn enable_ssl(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error> {
+        for cmd in &security_profile_config(profile_id, server_hostname, use_sni)? {
+            network.send_internal(cmd, true).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Activate context using AT+UPSD commands
+/// Required for SARA-G3, SARA-U2 SARA-R5 modules.
+async fn activate_context_upsd<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    context: PdpContext,
+    apn_info: &APNInfo<'_>,
+) -> nb::Result<(), Error> {
+    let PdpContext { cid, profile_id } = context;
+    if network.contexts.state(cid) == ContextState::Active {
+        return Ok(());
+    }
+
+    // Only the SARA-R5 PSD profiles have a protocol type, the others being
+    // IPv4 only
+    if apn_info.pdp_type != PdpType::Ip && network.module != ModuleKind::SaraR5 {
+        return Err(nb::Error::Other(Error::Generic(GenericError::Unsupported)));
+    }
+
+    // Check if the PSD profile is activated (param_tag = 1)
+    let PacketSwitchedNetworkData { param_tag, .. } = network
+        .send_internal(
+            &GetPacketSwitchedNetworkData {
+                profile_id,
+                param: PacketSwitchedNetworkDataParam::PsdProfileStatus,
+            },
+            true,
+        )
+        .await
+        .map_err(Error::from)?;
+
+    if param_tag == 0 {
+        network.contexts.set_state(cid, ContextState::Activating);
+
+        for cmd in upsd_profile_config(network.module, context, apn_info) {
+            network
+                .send_internal(&cmd, true)
+                .await
+                .map_err(Error::from)?;
+        }
+
+        network.contexts.activating_profile = Some(profile_id);
+        network
+            .send_internal(
+                &SetPacketSwitchedAction {
+                    profile_id,
+                    action: PacketSwitchedAction::Activate,
+                },
+                true,
+            )
+            .await
+            .map_err(Error::from)?;
+    }
+
+    network.contexts.set_state(cid, ContextState::Active);
+    Ok(())
+}
+
+/// Activate context using 3GPP commands
+/// Required for SARA-R4 and TOBY modules.
+async fn activate_context_3gpp<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    context: PdpContext,
+    apn_info: &APNInfo<'_>,
+) -> nb::Result<(), Error> {
+    let PdpContext { cid, profile_id } = context;
+    if network.contexts.state(cid) == ContextState::Active {
+        return Ok(());
+    }
+
+    let context_states = network
+        .send_internal(&GetPDPContextState, true)
+        .await
+        .map_err(Error::from)?;
+
+    if !context_activated(&context_states, cid) {
+        network
+            .send_internal(
+                &SetPDPContextState {
+                    status: PDPContextStatus::Activated,
+                    cid: Some(cid),
+                },
+                true,
+            )
+            .await
+            .map_err(Error::from)?;
+
+        return Err(nb::Error::WouldBlock);
+    }
+
+    // Note: SARA-R4 only supports a single context at any one time and so
+    // doesn't require/support AT+UPSD.
+    if network.module.maps_psd_profile() {
+        if let psn::responses::PacketSwitchedConfig {
+            param: psn::types::PacketSwitchedParam::MapProfile(context),
+            ..
+        } = network
+            .send_internal(
+                &psn::GetPacketSwitchedConfig {
+                    profile_id,
+                    param: psn::types::PacketSwitchedParamReq::MapProfile,
+                },
+                true,
+            )
+            .await
+            .map_err(Error::from)?
+        {
+            if context != cid {
+                network
+                    .send_internal(
+                        &psn::SetPacketSwitchedConfig {
+                            profile_id,
+                            param: psn::types::PacketSwitchedParam::MapProfile(cid),
+                        },
+                        true,
+                    )
+                    .await
+                    .map_err(Error::from)?;
+
+                network
+                    .send_internal(
+                        &GetPacketSwitchedNetworkData {
+                            profile_id,
+                            param: PacketSwitchedNetworkDataParam::PsdProfileStatus,
+                        },
+                        true,
+                    )
+                    .await
+                    .map_err(Error::from)?;
+            }
+        }
+
+        let PacketSwitchedNetworkData { param_tag, .. } = network
+            .send_internal(
+                &GetPacketSwitchedNetworkData {
+                    profile_id,
+                    param: PacketSwitchedNetworkDataParam::PsdProfileStatus,
+                },
+                true,
+            )
+            .await
+            .map_err(Error::from)?;
+
+        if param_tag == 0 {
+            network
+                .send_internal(
+                    &SetPacketSwitchedAction {
+                        profile_id,
+                        action: PacketSwitchedAction::Activate,
+                    },
+                    true,
+                )
+                .await
+                .map_err(Error::from)?;
+        }
+    }
+
+    // Only `+UPSD` activations report the IP address, in `+UUPSDA`
+    let ip_addr = network
+        .send_internal(&psn::GetPDPAddress { cid }, true)
+        .await
+        .ok()
+        .and_then(|addr| apn_info.pdp_type.select_addr(addr.ip_addrs()));
+
+    network.contexts.set_active(cid, ip_addr);
+    Ok(())
+}
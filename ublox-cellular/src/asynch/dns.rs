@@ -0,0 +1,57 @@
+use atat::asynch::AtatClient;
+use embedded_nal_async::{AddrType, Dns, IpAddr};
+
+use super::{dialect::Dialect, stack::DataStack};
+use crate::services::data::Error;
+
+impl<'a, 'sub, AtCl, const N: usize, const L: usize> Dns for DataStack<'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    type Error = Error;
+
+    async fn get_host_by_name(
+        &self,
+        host: &str,
+        addr_type: AddrType,
+    ) -> Result<IpAddr, Self::Error> {
+        let mut data_service = self.lock().await;
+        let dialect = data_service.dialect();
+
+        // The module resolves to the address type of the PDP context
+        let ip_addr = match dialect.resolve_host(data_service.network, host).await {
+            Ok(ip_addr) => ip_addr,
+            Err(e) => {
+                error!("get_host_by_name failed: {:?}", e);
+                return Err(Error::Dns);
+            }
+        };
+
+        match (addr_type, ip_addr) {
+            (AddrType::IPv4, IpAddr::V6(_)) | (AddrType::IPv6, IpAddr::V4(_)) => Err(Error::Dns),
+            _ => Ok(ip_addr),
+        }
+    }
+
+    async fn get_host_by_address(
+        &self,
+        addr: IpAddr,
+        result: &mut [u8],
+    ) -> Result<usize, Self::Error> {
+        let mut data_service = self.lock().await;
+        let dialect = data_service.dialect();
+
+        match dialect.resolve_address(data_service.network, addr).await {
+            Ok(name) => {
+                let name = name.as_bytes();
+                let dest = result.get_mut(..name.len()).ok_or(Error::BadLength)?;
+                dest.copy_from_slice(name);
+                Ok(name.len())
+            }
+            Err(e) => {
+                error!("get_host_by_address failed: {:?}", e);
+                Err(Error::Dns)
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use atat::asynch::AtatClient;
+
+use super::Device;
+use crate::{
+    command::{
+        edrx::{
+            types::{EDRXAccessTechnology, EDRXMode},
+            GetEDRXDynamicParameters, SetEDRXSettings,
+        },
+        network_service::{GetLpwaRadioAccessTechnology, GetRadioAccessTechnology},
+    },
+    config::CellularConfig,
+    edrx::{
+        decode_parameters, encode_parameters, lpwa_rat_selected, rat_selected, EdrxParameters,
+        RatCheck,
+    },
+    error::Error,
+};
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Request eDRX with the given parameters
+    ///
+    /// See [`GsmClient::set_edrx`](crate::GsmClient::set_edrx).
+    pub async fn set_edrx(&mut self, params: &EdrxParameters) -> Result<(), Error> {
+        let selected = match RatCheck::for_module(self.network.module) {
+            RatCheck::Lpwa => {
+                let rats = self.send_at(&GetLpwaRadioAccessTechnology).await?;
+                lpwa_rat_selected(&rats, params.act)
+            }
+            RatCheck::Selected => {
+                let rat = self.send_at(&GetRadioAccessTechnology).await?;
+                rat_selected(&rat.act, params.act)
+            }
+            RatCheck::Unchecked => true,
+        };
+        if !selected {
+            return Err(Error::InvalidEdrx);
+        }
+
+        let (cycle, paging_time_window) = encode_parameters(params)?;
+        self.send_at(&SetEDRXSettings {
+            mode: EDRXMode::EnabledWithUrc,
+            act_type: params.act,
+            requested_edrx_value: Some(&cycle),
+            requested_paging_time_window: paging_time_window.as_deref(),
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Disable eDRX on `act`
+    pub async fn disable_edrx(&mut self, act: EDRXAccessTechnology) -> Result<(), Error> {
+        self.send_at(&SetEDRXSettings {
+            mode: EDRXMode::Disabled,
+            act_type: act,
+            requested_edrx_value: None,
+            requested_paging_time_window: None,
+        })
+        .await?;
+
+        let status = &mut self.network.status;
+        if status.edrx.map_or(false, |edrx| edrx.act == act) {
+            status.edrx = None;
+        }
+        Ok(())
+    }
+
+    /// Read the eDRX parameters provided by the network for the serving
+    /// cell, if eDRX is in use.
+    pub async fn read_edrx(&mut self) -> Result<Option<EdrxParameters>, Error> {
+        let params = self.send_at(&GetEDRXDynamicParameters).await?;
+        let edrx = decode_parameters(
+            params.act_type,
+            params.nw_provided_edrx_value.as_deref(),
+            params.paging_time_window.as_deref(),
+        );
+
+        self.network.status.edrx = edrx;
+        Ok(edrx)
+    }
+
+    /// eDRX parameters last provided by the network, as reported by the
+    /// `+CEDRXP` URC
+    pub fn edrx(&self) -> Option<EdrxParameters> {
+        self.network.status.edrx
+    }
+}
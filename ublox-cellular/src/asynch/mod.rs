@@ -0,0 +1,33 @@
+//! Async driver
+//!
+//! Mirrors the blocking [`GsmClient`](crate::GsmClient) and
+//! [`DataService`](crate::DataService), but is built on
+//! [`atat::asynch::AtatClient`] and `embassy_time::Timer`, yielding to the
+//! executor wherever the blocking driver would busy-wait.
+//!
+//! Wrapping a [`DataService`] in a [`DataStack`] gives access to the
+//! `embedded-nal-async` traits (`TcpConnect`, `UdpStack` and `Dns`).
+
+mod client;
+mod data;
+mod dialect;
+mod dns;
+mod edrx;
+mod network;
+mod power;
+mod psm;
+mod stack;
+
+#[cfg(feature = "socket-tcp")]
+mod tcp_stack;
+
+#[cfg(feature = "socket-udp")]
+mod udp_stack;
+
+pub use client::Device;
+pub use data::DataService;
+pub use stack::DataStack;
+#[cfg(feature = "socket-tcp")]
+pub use tcp_stack::TcpConnection;
+#[cfg(feature = "socket-udp")]
+pub use udp_stack::{UdpConnection, UnboundUdp};
@@ -0,0 +1,412 @@
+use crate::{
+    client::{URC_CAPACITY, URC_SUBSCRIBERS},
+    command::{
+        general::GetCIMI,
+        mobile_control::{
+            types::{Functionality, ResetMode},
+            GetExtendedErrorReport, SetModuleFunctionality,
+        },
+        network_service::{
+            types::OperatorSelectionMode, GetNetworkRegistrationStatus, SetManualOperatorSelection,
+            SetOperatorSelection,
+        },
+        psn::{
+            types::PDPContextStatus, GetEPSNetworkRegistrationStatus,
+            GetGPRSNetworkRegistrationStatus, GetPDPContextState, SetPDPContextState,
+        },
+        Urc, AT,
+    },
+    error::GenericError,
+    module::ModuleKind,
+    network::{handle_network_urc, Error},
+    registration::{self, ConnectionState, RegistrationState},
+    services::data::context::Contexts,
+};
+use atat::{asynch::AtatClient, UrcSubscription};
+use embassy_time::{Duration, Instant};
+
+const REGISTRATION_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const REGISTRATION_TIMEOUT: Duration = Duration::from_secs(3 * 60);
+const CHECK_IMSI_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct AtTx<'sub, AtCl> {
+    pub(crate) consecutive_timeouts: u8,
+    urc_subscription: UrcSubscription<'sub, Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
+    pub(crate) client: AtCl,
+}
+
+impl<'sub, AtCl: AtatClient> AtTx<'sub, AtCl> {
+    pub fn new(
+        client: AtCl,
+        urc_subscription: UrcSubscription<'sub, Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
+    ) -> Self {
+        Self {
+            consecutive_timeouts: 0,
+            urc_subscription,
+            client,
+        }
+    }
+
+    pub async fn send<A, const LEN: usize>(&mut self, req: &A) -> Result<A::Response, Error>
+    where
+        A: atat::AtatCmd<LEN>,
+    {
+        match self.client.send_retry(req).await {
+            Ok(res) => {
+                self.consecutive_timeouts = 0;
+                Ok(res)
+            }
+            Err(atat::Error::Timeout) => {
+                self.consecutive_timeouts = self.consecutive_timeouts.saturating_add(A::ATTEMPTS);
+                Err(Error::AT(atat::Error::Timeout))
+            }
+            Err(atat::Error::Read) => Err(Error::AT(atat::Error::Read)),
+            Err(atat::Error::Write) => Err(Error::AT(atat::Error::Write)),
+            Err(atat::Error::InvalidResponse) => Err(Error::AT(atat::Error::InvalidResponse)),
+            Err(atat::Error::Aborted) => Err(Error::AT(atat::Error::Aborted)),
+            Err(atat::Error::Parse) => Err(Error::AT(atat::Error::Parse)),
+            Err(_) => Err(Error::AT(atat::Error::Error)),
+        }
+    }
+
+    pub fn handle_urc<F: FnOnce(Urc) -> bool>(&mut self, f: F) -> Result<(), Error> {
+        if let Some(urc) = self.urc_subscription.try_next_message_pure() {
+            f(urc);
+        }
+        Ok(())
+    }
+}
+
+/// Async counterpart of [`crate::network::Network`]
+pub struct Network<'sub, AtCl> {
+    pub(crate) status: RegistrationState,
+    /// State of the PDP contexts of the data connections
+    pub(crate) contexts: Contexts,
+    pub(crate) module: ModuleKind,
+    pub(crate) at_tx: AtTx<'sub, AtCl>,
+}
+
+impl<'sub, AtCl> Network<'sub, AtCl>
+where
+    AtCl: AtatClient,
+{
+    pub(crate) fn new(at_tx: AtTx<'sub, AtCl>) -> Self {
+        Self {
+            status: RegistrationState::new(),
+            contexts: Contexts::new(),
+            module: ModuleKind::from_features(),
+            at_tx,
+        }
+    }
+
+    /// Module kind the driver is operating with
+    pub fn module(&self) -> ModuleKind {
+        self.module
+    }
+
+    pub fn is_connected(&self) -> Result<bool, Error> {
+        Ok(matches!(self.status.conn_state, ConnectionState::Connected))
+    }
+
+    pub fn reset_reg_time(&mut self) -> Result<(), Error> {
+        self.status.reg_start_time.replace(Instant::now());
+        self.status.reg_check_time = self.status.reg_start_time;
+        Ok(())
+    }
+
+    pub async fn process_events(&mut self) -> Result<(), Error> {
+        if self.at_tx.consecutive_timeouts > 10 {
+            self.at_tx.consecutive_timeouts = 0;
+            warn!("Resetting the modem due to consecutive AT timeouts");
+            return Err(Error::Generic(GenericError::Timeout));
+        }
+
+        self.handle_urc().ok(); // Ignore errors
+
+        // The module is unreachable while sleeping in PSM, but stays
+        // registered. Resume polling once it wakes up.
+        if self.status.psm_sleep {
+            return Ok(());
+        }
+
+        self.status.update_connection_state();
+        self.intervene_registration().await?;
+        self.check_running_imsi().await.ok(); // Ignore errors
+
+        let now = Instant::now();
+        let should_check = self
+            .status
+            .reg_check_time
+            .and_then(|reg_check_time| {
+                now.checked_duration_since(reg_check_time)
+                    .map(|dur| dur >= REGISTRATION_CHECK_INTERVAL)
+            })
+            .unwrap_or(true);
+
+        if !should_check {
+            return Ok(());
+        }
+
+        self.status.reg_check_time.replace(now);
+
+        self.update_registration().await?;
+
+        let now = Instant::now();
+        let is_timeout = self
+            .status
+            .reg_start_time
+            .and_then(|reg_start_time| {
+                now.checked_duration_since(reg_start_time)
+                    .map(|dur| dur >= REGISTRATION_TIMEOUT)
+            })
+            .unwrap_or(false);
+
+        if self.status.conn_state == ConnectionState::Connecting && is_timeout {
+            warn!("Resetting the modem due to the network registration timeout");
+
+            return Err(Error::Generic(GenericError::Timeout));
+        }
+        Ok(())
+    }
+
+    /// Select the next preferred operator, or let the module select one
+    /// automatically once they have all been tried
+    pub(crate) async fn select_operator(&mut self, check_urc: bool) -> Result<(), Error> {
+        match self.status.next_operator() {
+            Some(oper) => {
+                debug!("Selecting operator {}", oper);
+                self.send_internal(
+                    &SetManualOperatorSelection {
+                        mode: OperatorSelectionMode::ManualAutomatic,
+                        format: 2,
+                        oper,
+                        act: None,
+                    },
+                    check_urc,
+                )
+                .await?;
+            }
+            None => {
+                self.send_internal(
+                    &SetOperatorSelection {
+                        mode: OperatorSelectionMode::Automatic,
+                        format: Some(2),
+                    },
+                    check_urc,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn check_running_imsi(&mut self) -> Result<(), Error> {
+        let now = Instant::now();
+        let check_imsi = self
+            .status
+            .imsi_check_time
+            .and_then(|imsi_check_time| {
+                now.checked_duration_since(imsi_check_time)
+                    .map(|dur| dur >= CHECK_IMSI_TIMEOUT)
+            })
+            .unwrap_or(true);
+
+        if check_imsi {
+            // See `Network::check_running_imsi` for why a failing CIMI is
+            // followed up by a plain AT
+            match self.send_internal(&GetCIMI, false).await {
+                Ok(cimi) => self.status.imsi = Some(cimi.imsi),
+                Err(_) => {
+                    self.send_internal(&AT, false).await?;
+                }
+            }
+
+            self.status.imsi_check_time.replace(now);
+        }
+        Ok(())
+    }
+
+    /// Cycle the module through minimum and full functionality, to force an
+    /// RF reset
+    async fn rf_reset(&mut self) -> Result<(), Error> {
+        self.send_internal(
+            &SetModuleFunctionality {
+                fun: Functionality::Minimum,
+                rst: self.module.cfun_reset_mode(&Functionality::Minimum),
+            },
+            false,
+        )
+        .await?;
+        self.send_internal(
+            &SetModuleFunctionality {
+                fun: Functionality::Full,
+                rst: Some(ResetMode::DontReset),
+            },
+            false,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn intervene_registration(&mut self) -> Result<(), Error> {
+        if self.status.conn_state != ConnectionState::Connecting {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+
+        // If EPS has been sticky for longer than `timeout`
+        let timeout = Duration::from_secs(self.status.registration_interventions as u64 * 15);
+        if self.status.eps.sticky() && self.status.eps.duration(now) >= timeout {
+            // If (EPS + CSD) is not attempting registration
+            if self.status.eps.get_status() == registration::Status::NotRegistering
+                && self.status.csd.get_status() == registration::Status::NotRegistering
+            {
+                debug!(
+                    "Sticky not registering state for {}, PLMN reselection",
+                    self.status.eps.duration(now)
+                );
+
+                self.status.csd.reset();
+                self.status.psd.reset();
+                self.status.eps.reset();
+                self.status.registration_interventions =
+                    self.status.registration_interventions.saturating_add(1);
+
+                self.select_operator(false).await.ok(); // Ignore result
+                return Ok(());
+
+            // If (EPS + CSD) is denied registration
+            } else if self.status.eps.get_status() == registration::Status::Denied
+                && self.status.csd.get_status() == registration::Status::Denied
+            {
+                debug!(
+                    "Sticky denied state for {}, RF reset",
+                    self.status.eps.duration(now)
+                );
+                self.status.csd.reset();
+                self.status.psd.reset();
+                self.status.eps.reset();
+                self.status.registration_interventions =
+                    self.status.registration_interventions.saturating_add(1);
+                self.rf_reset().await?;
+                return Ok(());
+            }
+        }
+
+        // If CSD has been sticky for longer than `timeout`,
+        // and (CSD + PSD) is denied registration.
+        if self.status.csd.sticky()
+            && self.status.csd.duration(now) >= timeout
+            && matches!(
+                self.status.csd.get_status(),
+                registration::Status::Denied | registration::Status::Roaming
+            )
+            && self.status.psd.get_status() == registration::Status::Denied
+        {
+            debug!(
+                "Sticky CSD and PSD denied state for {}, RF reset",
+                self.status.csd.duration(now)
+            );
+            self.status.csd.reset();
+            self.status.psd.reset();
+            self.status.eps.reset();
+            self.status.registration_interventions =
+                self.status.registration_interventions.saturating_add(1);
+            self.rf_reset().await?;
+            return Ok(());
+        }
+
+        // If CSD is registered, but PSD has been sticky for longer than `timeout`,
+        // and (PSD + EPS) is not attempting registration.
+        if self.status.csd.registered()
+            && self.status.psd.sticky()
+            && self.status.psd.duration(now) >= timeout
+            && self.status.psd.get_status() == registration::Status::NotRegistering
+            && self.status.eps.get_status() == registration::Status::NotRegistering
+        {
+            debug!(
+                "Sticky not registering PSD state for {}, force GPRS attach",
+                self.status.psd.duration(now)
+            );
+            self.status.psd.reset();
+            self.status.registration_interventions =
+                self.status.registration_interventions.saturating_add(1);
+            self.send_internal(&GetPDPContextState, true).await?;
+
+            if self
+                .send_internal(
+                    &SetPDPContextState {
+                        status: PDPContextStatus::Activated,
+                        cid: None,
+                    },
+                    true,
+                )
+                .await
+                .is_err()
+            {
+                self.status.csd.reset();
+                self.status.psd.reset();
+                self.status.eps.reset();
+                warn!("GPRS attach failed, try PLMN reselection");
+                self.select_operator(true).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn update_registration(&mut self) -> Result<(), Error> {
+        self.send_internal(&GetExtendedErrorReport, false)
+            .await
+            .ok();
+
+        if let Ok(reg) = self
+            .send_internal(&GetNetworkRegistrationStatus, false)
+            .await
+        {
+            self.status.compare_and_set(reg.into());
+        }
+
+        if let Ok(reg) = self
+            .send_internal(&GetGPRSNetworkRegistrationStatus, false)
+            .await
+        {
+            self.status.compare_and_set(reg.into());
+        }
+
+        if let Ok(reg) = self
+            .send_internal(&GetEPSNetworkRegistrationStatus, false)
+            .await
+        {
+            self.status.compare_and_set(reg.into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn handle_urc(&mut self) -> Result<(), Error> {
+        let status = &mut self.status;
+        let contexts = &mut self.contexts;
+        self.at_tx
+            .handle_urc(|urc| handle_network_urc(urc, status, contexts))?;
+        Ok(())
+    }
+
+    pub(crate) async fn send_internal<A, const LEN: usize>(
+        &mut self,
+        req: &A,
+        check_urc: bool,
+    ) -> Result<A::Response, Error>
+    where
+        A: atat::AtatCmd<LEN>,
+    {
+        if check_urc {
+            if let Err(e) = self.handle_urc() {
+                error!("Failed handle URC {:?}", &e);
+            }
+        }
+
+        self.at_tx.send(req).await
+    }
+}
@@ -0,0 +1,316 @@
+use atat::asynch::AtatClient;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_hal::digital::{InputPin, OutputPin};
+
+use super::Device;
+use crate::{
+    command::{
+        control::SetDataRate,
+        mobile_control::{types::Functionality, ModuleSwitchOff, SetModuleFunctionality},
+        system_features::{
+            types::{FSFactoryRestoreType, NVMFactoryRestoreType, PowerSavingMode, Seconds},
+            SetFactoryConfiguration, SetPowerSavingControl,
+        },
+        AT,
+    },
+    config::CellularConfig,
+    error::{Error, GenericError},
+    module_timing::{baud_rate_switch_time, pwr_off_time, pwr_on_time, reset_time, GSM_FRAME},
+    power::PowerState,
+};
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Check that the cellular module is alive.
+    ///
+    /// See if the cellular module is responding at the AT interface by poking
+    /// it with "AT" up to `attempts` times, waiting 1 second for an "OK"
+    /// response each time
+    pub(crate) async fn is_alive(&mut self, attempts: u8) -> Result<(), Error> {
+        let mut error = Error::BaudDetection;
+        for _ in 0..attempts {
+            match self.network.at_tx.send(&AT).await {
+                Ok(_) => {
+                    return Ok(());
+                }
+                Err(e) => error = e.into(),
+            };
+        }
+        Err(error)
+    }
+
+    /// Find the rate the module responds at, probing
+    /// [`CellularConfig::BAUD_RATES`].
+    pub(crate) async fn detect_baud_rate(&mut self) -> Result<(), Error> {
+        for &baud_rate in Config::BAUD_RATES {
+            if !self.config.set_baud_rate(baud_rate) {
+                break;
+            }
+
+            if self.is_alive(2).await.is_ok() {
+                info!("Module responding at {:?}", baud_rate);
+                self.baud_rate = Some(baud_rate);
+                return Ok(());
+            }
+        }
+        Err(Error::BaudDetection)
+    }
+
+    /// Switch the module, and then the host, to
+    /// [`CellularConfig::BAUD_RATE`], if not running at it already.
+    pub(crate) async fn switch_baud_rate(&mut self) -> Result<(), Error> {
+        let Some(baud_rate) = Config::BAUD_RATE else {
+            return Ok(());
+        };
+        if self.baud_rate == Some(baud_rate) {
+            return Ok(());
+        }
+
+        debug!("Switching to {:?}", baud_rate);
+        self.network
+            .send_internal(&SetDataRate { rate: baud_rate }, false)
+            .await?;
+
+        // The module switches rate after sending the final result code
+        Timer::after(baud_rate_switch_time()).await;
+        if !self.config.set_baud_rate(baud_rate) {
+            return Err(Error::BaudDetection);
+        }
+        self.is_alive(2).await.map_err(|_| Error::BaudDetection)?;
+
+        self.baud_rate = Some(baud_rate);
+        Ok(())
+    }
+
+    /// Apply [`CellularConfig::POWER_SAVING`] with `AT+UPSV`. Fails with
+    /// [`Error::InvalidPowerSaving`], without sending anything, when the mode
+    /// can't be used with this configuration.
+    pub(crate) async fn set_power_saving(&mut self) -> Result<(), Error> {
+        let mode = Config::POWER_SAVING;
+        self.network
+            .at_tx
+            .client
+            .check_mode(mode, Config::FLOW_CONTROL)?;
+
+        // Drive the DTR/RTS line from now on, so it is ON by the time the
+        // module starts observing it
+        self.network.at_tx.client.set_mode(mode);
+
+        // The UART idle timeout, in GSM frames, only applies to cyclic power
+        // saving
+        let timeout = (mode == PowerSavingMode::Enabled).then(|| {
+            let frames = Config::POWER_SAVING_IDLE.as_micros() / GSM_FRAME.as_micros();
+            Seconds(frames.clamp(40, 65000) as u32)
+        });
+        self.network
+            .send_internal(&SetPowerSavingControl { mode, timeout }, false)
+            .await?;
+        Ok(())
+    }
+
+    /// Perform at full factory reset of the module, clearing all NVM sectors in the process
+    pub async fn factory_reset(&mut self) -> Result<(), Error> {
+        self.network
+            .send_internal(
+                &SetFactoryConfiguration {
+                    fs_op: FSFactoryRestoreType::AllFiles,
+                    nvm_op: NVMFactoryRestoreType::NVMFlashSectors,
+                },
+                false,
+            )
+            .await?;
+
+        info!("Successfully factory reset modem!");
+
+        if self.soft_reset(true).await.is_err() {
+            self.hard_reset().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reset the module by sending AT CFUN command
+    pub(crate) async fn soft_reset(&mut self, sim_reset: bool) -> Result<(), Error> {
+        trace!(
+            "Attempting to soft reset of the modem with sim reset: {}.",
+            sim_reset
+        );
+
+        let fun = if sim_reset {
+            Functionality::SilentResetWithSimReset
+        } else {
+            Functionality::SilentReset
+        };
+        let rst = self.network.module.cfun_reset_mode(&fun);
+
+        self.network
+            .send_internal(&SetModuleFunctionality { fun, rst }, false)
+            .await?;
+
+        self.wait_power_state(PowerState::On, Duration::from_secs(30))
+            .await
+            .map_err(|_| Error::Generic(GenericError::Timeout))?;
+
+        Ok(())
+    }
+
+    /// Drive the `RESET_N` pin low for the module specific reset time
+    async fn pulse_reset_pin(&mut self) {
+        if let Some(rst) = self.config.reset_pin() {
+            rst.set_low().ok();
+
+            Timer::after(reset_time(self.network.module)).await;
+
+            rst.set_high().ok();
+
+            Timer::after(Duration::from_secs(5)).await;
+        }
+
+        self.power_state = PowerState::Off;
+    }
+
+    /// Reset the module by driving it's `RESET_N` pin low for 50 ms
+    ///
+    /// **NOTE** This function will reset NVM settings!
+    pub async fn hard_reset(&mut self) -> Result<(), Error> {
+        trace!("Attempting to hard reset of the modem.");
+        self.pulse_reset_pin().await;
+
+        self.power_on().await?;
+
+        Ok(())
+    }
+
+    pub async fn power_on(&mut self) -> Result<(), Error> {
+        info!(
+            "Attempting to power on the modem with PWR_ON pin: {} and VInt pin: {}.",
+            self.config.power_pin().is_some(),
+            self.config.vint_pin().is_some(),
+        );
+
+        if self.power_state().await? != PowerState::On {
+            trace!("Powering modem on.");
+            match self.config.power_pin() {
+                // Apply Low pulse on PWR_ON for 50 microseconds to power on
+                Some(pwr) => {
+                    pwr.set_low().ok();
+                    Timer::after(pwr_on_time(self.network.module)).await;
+
+                    pwr.set_high().ok();
+
+                    if let Err(e) = self
+                        .wait_power_state(PowerState::On, Duration::from_secs(10))
+                        .await
+                    {
+                        error!("Failed to power on modem");
+                        return Err(e);
+                    } else {
+                        trace!("Modem powered on");
+                    }
+
+                    Timer::after(Duration::from_secs(3)).await;
+                }
+                _ => {
+                    // Software restart. Unlike the blocking driver, a failed
+                    // soft reset only pulses `RESET_N` here, as `hard_reset`
+                    // would recurse back into `power_on`.
+                    if self.soft_reset(false).await.is_err() {
+                        self.pulse_reset_pin().await;
+                        self.wait_power_state(PowerState::On, Duration::from_secs(10))
+                            .await?;
+                    }
+                }
+            }
+        } else {
+            debug!("module is already on");
+        }
+        Ok(())
+    }
+
+    pub async fn soft_power_off(&mut self) -> Result<(), Error> {
+        trace!("Attempting to soft power off the modem.");
+
+        self.network.send_internal(&ModuleSwitchOff, false).await?;
+
+        self.power_state = PowerState::Off;
+        trace!("Modem powered off");
+
+        Timer::after(Duration::from_secs(10)).await;
+
+        Ok(())
+    }
+
+    pub async fn hard_power_off(&mut self) -> Result<(), Error> {
+        trace!("Attempting to hard power off the modem.");
+
+        if self.power_state().await? == PowerState::On {
+            match self.config.power_pin() {
+                Some(pwr) => {
+                    // Apply Low pulse on PWR_ON >= 1 second to power off
+                    pwr.set_low().ok();
+                    Timer::after(pwr_off_time(self.network.module)).await;
+
+                    pwr.set_high().ok();
+                    self.power_state = PowerState::Off;
+                    trace!("Modem powered off");
+                }
+                _ => {
+                    return Err(Error::Generic(GenericError::Unsupported));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the power state of the module, by probing `Vint` pin if available,
+    /// fallbacking to checking for AT responses through `is_alive`
+    pub async fn power_state(&mut self) -> Result<PowerState, Error> {
+        match self.config.vint_pin() {
+            Some(vint) => {
+                if vint
+                    .is_high()
+                    .map_err(|_| Error::Generic(GenericError::Unsupported))?
+                {
+                    Ok(PowerState::On)
+                } else {
+                    Ok(PowerState::Off)
+                }
+            }
+            _ => Ok(self
+                .is_alive(2)
+                .await
+                .map_or(PowerState::Off, |_| PowerState::On)),
+        }
+    }
+
+    /// Wait for the power state to change into `expected`, with a timeout
+    async fn wait_power_state(
+        &mut self,
+        expected: PowerState,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+
+        trace!("Waiting for the modem to reach {:?}.", expected);
+
+        while Instant::now()
+            .checked_duration_since(start)
+            .map_or(false, |dur| dur < timeout)
+        {
+            if self.power_state().await? == expected {
+                trace!("Success.");
+                return Ok(());
+            }
+
+            Timer::after(Duration::from_millis(5)).await;
+        }
+
+        error!("Modem never reach {:?}.", expected);
+        Err(Error::Generic(GenericError::Timeout))
+    }
+}
@@ -0,0 +1,57 @@
+use atat::asynch::AtatClient;
+
+use super::Device;
+use crate::{
+    config::CellularConfig,
+    error::Error,
+    psm::{psm_reporting, PsmRequest, PsmTimers, PSM_DISABLED},
+    registration::eps_registration_urcs,
+};
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Request PSM with the given timers
+    ///
+    /// See [`GsmClient::request_psm`](crate::GsmClient::request_psm).
+    pub async fn request_psm(&mut self, timers: PsmTimers) -> Result<(), Error> {
+        self.send_at(&PsmRequest::new(timers).settings()).await?;
+
+        if let Some(reporting) = psm_reporting(self.network.module) {
+            if self.send_at(&reporting).await.is_err() {
+                warn!("PSM state reporting not supported");
+            }
+        }
+
+        // Report the granted timers along with the EPS registration
+        self.send_at(&eps_registration_urcs(true)).await?;
+
+        self.network.status.psm_requested = true;
+        Ok(())
+    }
+
+    /// Disable PSM
+    pub async fn disable_psm(&mut self) -> Result<(), Error> {
+        self.send_at(&PSM_DISABLED).await?;
+        self.send_at(&eps_registration_urcs(false)).await?;
+
+        self.network.status.psm_requested = false;
+        self.network.status.psm = None;
+        Ok(())
+    }
+
+    /// PSM timers granted by the network at the last EPS registration
+    /// update, if PSM has been requested and granted.
+    pub fn psm_timers(&self) -> Option<PsmTimers> {
+        self.network.status.psm
+    }
+
+    /// Whether the module is currently sleeping in PSM
+    pub fn is_psm_sleeping(&self) -> bool {
+        self.network.status.psm_sleep
+    }
+}
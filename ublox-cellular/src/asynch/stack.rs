@@ -0,0 +1,101 @@
+use core::cell::RefCell;
+
+use atat::asynch::AtatClient;
+use embassy_sync::{
+    blocking_mutex::{raw::NoopRawMutex, Mutex as BlockingMutex},
+    mutex::{Mutex, MutexGuard},
+};
+use heapless::Vec;
+use ublox_sockets::SocketHandle;
+
+use super::{dialect::Dialect, DataService};
+use crate::services::data::Error;
+
+/// Shared handle to an async [`DataService`]
+///
+/// The `embedded-nal-async` traits take `&self`, and hand out connection
+/// objects that keep using the modem after the call returns. `DataStack`
+/// serializes every access to the underlying [`DataService`] through an
+/// async mutex, so any number of connections can be driven from the same
+/// executor.
+///
+/// # Examples
+///
+/// ```ignore
+/// let data_service = modem.data_service(&APNInfo::new("myapn")).await?;
+/// let stack = DataStack::new(data_service);
+///
+/// let mut conn = stack.connect(remote).await?;
+/// conn.write_all(b"hello").await?;
+/// ```
+pub struct DataStack<'a, 'sub, AtCl, const N: usize, const L: usize>
+where
+    AtCl: AtatClient,
+{
+    inner: Mutex<NoopRawMutex, DataService<'a, 'sub, AtCl, N, L>>,
+    /// Sockets whose connection object was dropped, and that still need to
+    /// be closed on the module.
+    pending_close: BlockingMutex<NoopRawMutex, RefCell<Vec<SocketHandle, N>>>,
+}
+
+impl<'a, 'sub, AtCl, const N: usize, const L: usize> DataStack<'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    pub fn new(data_service: DataService<'a, 'sub, AtCl, N, L>) -> Self {
+        Self {
+            inner: Mutex::new(data_service),
+            pending_close: BlockingMutex::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Release the underlying [`DataService`]
+    pub fn into_inner(self) -> DataService<'a, 'sub, AtCl, N, L> {
+        self.inner.into_inner()
+    }
+
+    /// Lock the underlying [`DataService`], closing any sockets that were
+    /// dropped since the last access.
+    pub(crate) async fn lock(
+        &self,
+    ) -> MutexGuard<'_, NoopRawMutex, DataService<'a, 'sub, AtCl, N, L>> {
+        let mut data_service = self.inner.lock().await;
+
+        let pending = self
+            .pending_close
+            .lock(|pending| core::mem::take(&mut *pending.borrow_mut()));
+
+        for socket in pending {
+            data_service.close_socket(socket).await.ok();
+        }
+
+        data_service
+    }
+
+    /// Schedule `socket` to be closed on the next access to the stack.
+    ///
+    /// Used from `Drop` implementations, where the module can not be talked
+    /// to.
+    pub(crate) fn defer_close(&self, socket: SocketHandle) {
+        self.pending_close.lock(|pending| {
+            if pending.borrow_mut().push(socket).is_err() {
+                error!("Failed to schedule close of socket {:?}", socket);
+            }
+        });
+    }
+}
+
+impl<'a, 'sub, AtCl, const N: usize, const L: usize> DataService<'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    /// Close `socket` on the module, and release it from the socket set.
+    pub(crate) async fn close_socket(&mut self, socket: SocketHandle) -> Result<(), Error> {
+        let dialect = self.dialect();
+        let sockets = self.sockets.as_deref_mut().ok_or(Error::SocketMemory)?;
+
+        dialect.close_socket(self.network, socket).await.ok();
+        sockets.remove(socket)?;
+        Ok(())
+    }
+}
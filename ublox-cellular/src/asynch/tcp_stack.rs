@@ -0,0 +1,189 @@
+use atat::asynch::AtatClient;
+use embassy_time::{Duration, Timer};
+use embedded_nal_async::{SocketAddr, TcpConnect};
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketType, TcpSocket, TcpState};
+
+use super::{dialect::Dialect, stack::DataStack};
+use crate::services::data::{free_socket_id, Error};
+
+/// Interval at which a pending read polls the module for new data
+const READ_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> TcpConnect
+    for &'s DataStack<'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    type Error = Error;
+
+    type Connection<'c> = TcpConnection<'s, 'a, 'sub, AtCl, N, L> where Self: 'c;
+
+    async fn connect<'c>(&'c self, remote: SocketAddr) -> Result<Self::Connection<'c>, Self::Error>
+    where
+        Self: 'c,
+    {
+        let mut data_service = self.lock().await;
+        let data_service = &mut *data_service;
+        let dialect = data_service.dialect();
+
+        let sockets = data_service
+            .sockets
+            .as_deref_mut()
+            .ok_or(Error::SocketMemory)?;
+
+        // Check if there are any unused sockets available
+        if sockets.len() >= sockets.capacity() {
+            // Check if there are any sockets closed by remote, and close it
+            // if it has exceeded its timeout, in order to recycle it.
+            if !sockets.recycle() {
+                return Err(Error::Socket(SocketError::SocketSetFull));
+            }
+        }
+
+        let candidate = free_socket_id(sockets);
+        let socket = dialect
+            .create_socket(
+                data_service.network,
+                data_service.context,
+                SocketType::Tcp,
+                candidate,
+            )
+            .await?;
+
+        let handle = sockets.add(TcpSocket::new(socket.0))?;
+
+        let res = dialect
+            .connect_tcp(data_service.network, handle, remote)
+            .await;
+
+        if let Err(e) = res {
+            data_service.close_socket(handle).await.ok();
+            return Err(e);
+        }
+
+        let sockets = data_service
+            .sockets
+            .as_deref_mut()
+            .ok_or(Error::SocketMemory)?;
+        sockets
+            .get::<TcpSocket<L>>(handle)?
+            .set_state(TcpState::Connected(remote));
+
+        Ok(TcpConnection {
+            stack: *self,
+            handle,
+        })
+    }
+}
+
+/// TCP connection handed out by [`DataStack`]
+///
+/// The socket is closed on the module when the connection is dropped, the
+/// next time the stack is accessed. Use [`TcpConnection::close`] to close it
+/// right away.
+pub struct TcpConnection<'s, 'a, 'sub, AtCl, const N: usize, const L: usize>
+where
+    AtCl: AtatClient,
+{
+    stack: &'s DataStack<'a, 'sub, AtCl, N, L>,
+    handle: SocketHandle,
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> TcpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+
+    /// Close the socket on the module
+    pub async fn close(self) -> Result<(), Error> {
+        let res = self.stack.lock().await.close_socket(self.handle).await;
+        core::mem::forget(self);
+        res
+    }
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> Drop
+    for TcpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    fn drop(&mut self) {
+        self.stack.defer_close(self.handle);
+    }
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> embedded_io_async::ErrorType
+    for TcpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    type Error = Error;
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> embedded_io_async::Read
+    for TcpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            {
+                let mut data_service = self.stack.lock().await;
+                data_service.socket_ingress_all().await.ok();
+
+                let sockets = data_service
+                    .sockets
+                    .as_deref_mut()
+                    .ok_or(Error::SocketMemory)?;
+                let mut tcp = sockets.get::<TcpSocket<L>>(self.handle)?;
+
+                match tcp.recv_slice(buf) {
+                    Ok(0) | Err(SocketError::Exhausted) => {}
+                    Ok(len) => return Ok(len),
+                    Err(e) => return Err(e.into()),
+                }
+
+                // Nothing buffered, and nothing more will arrive
+                if !tcp.is_connected() {
+                    return Ok(0);
+                }
+            }
+
+            Timer::after(READ_POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> embedded_io_async::Write
+    for TcpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let mut data_service = self.stack.lock().await;
+        let dialect = data_service.dialect();
+
+        let sockets = data_service
+            .sockets
+            .as_deref_mut()
+            .ok_or(Error::SocketMemory)?;
+        if !sockets.get::<TcpSocket<L>>(self.handle)?.is_connected() {
+            return Err(Error::Socket(SocketError::SocketClosed));
+        }
+        data_service.check_data_budget().await?;
+
+        dialect
+            .send_tcp(data_service.network, self.handle, buf)
+            .await?;
+        data_service.network.contexts.record_data(buf.len());
+
+        Ok(buf.len())
+    }
+}
@@ -0,0 +1,224 @@
+use atat::asynch::AtatClient;
+use embassy_time::{Duration, Timer};
+use embedded_nal_async::{ConnectedUdp, SocketAddr, UdpStack, UnconnectedUdp};
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketType, UdpSocket};
+
+use super::{dialect::Dialect, stack::DataStack};
+use crate::{
+    error::GenericError,
+    services::data::{free_socket_id, Error},
+};
+
+/// Interval at which a pending receive polls the module for new data
+const RECEIVE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> UdpStack
+    for &'s DataStack<'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    type Error = Error;
+
+    type Connected = UdpConnection<'s, 'a, 'sub, AtCl, N, L>;
+
+    /// Binding to a local address is not supported by the module
+    type UniquelyBound = UnboundUdp;
+
+    /// Binding to a local address is not supported by the module
+    type MultiplyBound = UnboundUdp;
+
+    /// Open a new UDP socket to `remote`. The module picks the local port,
+    /// so `local` is only echoed back.
+    async fn connect_from(
+        &self,
+        local: SocketAddr,
+        remote: SocketAddr,
+    ) -> Result<(SocketAddr, Self::Connected), Self::Error> {
+        let mut data_service = self.lock().await;
+        let data_service = &mut *data_service;
+        let dialect = data_service.dialect();
+
+        let sockets = data_service
+            .sockets
+            .as_deref_mut()
+            .ok_or(Error::SocketMemory)?;
+
+        if sockets.len() >= sockets.capacity() {
+            // Check if there are any sockets closed by remote, and close it
+            // if it has exceeded its timeout, in order to recycle it.
+            if !sockets.recycle() {
+                return Err(Error::Socket(SocketError::SocketSetFull));
+            }
+        }
+
+        let candidate = free_socket_id(sockets);
+        let socket = dialect
+            .create_socket(
+                data_service.network,
+                data_service.context,
+                SocketType::Udp,
+                candidate,
+            )
+            .await?;
+
+        let handle = sockets.add(UdpSocket::new(socket.0))?;
+
+        if let Err(e) = dialect
+            .connect_udp(data_service.network, handle, remote)
+            .await
+        {
+            data_service.close_socket(handle).await.ok();
+            return Err(e);
+        }
+
+        let sockets = data_service
+            .sockets
+            .as_deref_mut()
+            .ok_or(Error::SocketMemory)?;
+        sockets.get::<UdpSocket<L>>(handle)?.bind(remote)?;
+
+        Ok((
+            local,
+            UdpConnection {
+                stack: *self,
+                handle,
+            },
+        ))
+    }
+
+    async fn bind_single(
+        &self,
+        _local: SocketAddr,
+    ) -> Result<(SocketAddr, Self::UniquelyBound), Self::Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    async fn bind_multiple(&self, _local: SocketAddr) -> Result<Self::MultiplyBound, Self::Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+}
+
+/// Connected UDP socket handed out by [`DataStack`]
+///
+/// The socket is closed on the module when the connection is dropped, the
+/// next time the stack is accessed. Use [`UdpConnection::close`] to close it
+/// right away.
+pub struct UdpConnection<'s, 'a, 'sub, AtCl, const N: usize, const L: usize>
+where
+    AtCl: AtatClient,
+{
+    stack: &'s DataStack<'a, 'sub, AtCl, N, L>,
+    handle: SocketHandle,
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> UdpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    pub fn handle(&self) -> SocketHandle {
+        self.handle
+    }
+
+    /// Close the socket on the module
+    pub async fn close(self) -> Result<(), Error> {
+        let res = self.stack.lock().await.close_socket(self.handle).await;
+        core::mem::forget(self);
+        res
+    }
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> Drop
+    for UdpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    fn drop(&mut self) {
+        self.stack.defer_close(self.handle);
+    }
+}
+
+impl<'s, 'a, 'sub, AtCl, const N: usize, const L: usize> ConnectedUdp
+    for UdpConnection<'s, 'a, 'sub, AtCl, N, L>
+where
+    AtCl: AtatClient,
+{
+    type Error = Error;
+
+    /// Send a datagram to the remote host.
+    async fn send(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        let mut data_service = self.stack.lock().await;
+        data_service.check_data_budget().await?;
+        let dialect = data_service.dialect();
+
+        let sockets = data_service
+            .sockets
+            .as_deref_mut()
+            .ok_or(Error::SocketMemory)?;
+        let udp = sockets.get::<UdpSocket<L>>(self.handle)?;
+
+        if !udp.is_open() {
+            return Err(Error::Socket(SocketError::SocketClosed));
+        }
+        let endpoint = udp.endpoint().ok_or(SocketError::SocketClosed)?;
+
+        dialect
+            .send_udp(data_service.network, self.handle, endpoint, data)
+            .await?;
+        data_service.network.contexts.record_data(data.len());
+
+        Ok(())
+    }
+
+    /// Wait for a datagram from the remote host, placing it in
+    /// `&buffer[0..n]` and returning `n`.
+    async fn receive_into(&mut self, buffer: &mut [u8]) -> Result<usize, Self::Error> {
+        loop {
+            {
+                let mut data_service = self.stack.lock().await;
+                data_service.socket_ingress_all().await.ok();
+
+                let sockets = data_service
+                    .sockets
+                    .as_deref_mut()
+                    .ok_or(Error::SocketMemory)?;
+                let mut udp = sockets.get::<UdpSocket<L>>(self.handle)?;
+
+                match udp.recv_slice(buffer) {
+                    Ok(0) | Err(SocketError::Exhausted) => {}
+                    Ok(len) => return Ok(len),
+                    Err(e) => return Err(e.into()),
+                }
+
+                if !udp.is_open() {
+                    return Err(Error::Socket(SocketError::SocketClosed));
+                }
+            }
+
+            Timer::after(RECEIVE_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Placeholder for the bound UDP socket types of [`UdpStack`], which the
+/// module does not support. Can not be constructed.
+pub enum UnboundUdp {}
+
+impl UnconnectedUdp for UnboundUdp {
+    type Error = Error;
+
+    async fn send(
+        &mut self,
+        _local: SocketAddr,
+        _remote: SocketAddr,
+        _data: &[u8],
+    ) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    async fn receive_into(
+        &mut self,
+        _buffer: &mut [u8],
+    ) -> Result<(usize, SocketAddr, SocketAddr), Self::Error> {
+        match *self {}
+    }
+}
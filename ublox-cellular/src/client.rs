@@ -1,50 +1,38 @@
 use atat::{blocking::AtatClient, AtatUrcChannel, UrcSubscription};
 use embassy_time::Duration;
+use heapless::Vec;
 use ublox_sockets::SocketSet;
 
 use crate::{
     blocking_timer::BlockingTimer,
-    command::device_lock::{responses::PinStatus, types::PinStatusCode, GetPinStatus},
-    command::{
-        control::{
-            types::{Circuit108Behaviour, Circuit109Behaviour, FlowControl},
-            SetCircuit108Behaviour, SetCircuit109Behaviour, SetFlowControl,
-        },
-        ip_transport_layer,
-        mobile_control::{
-            types::{AutomaticTimezone, Functionality, ResetMode, TerminationErrorMode},
-            SetAutomaticTimezoneUpdate, SetModuleFunctionality, SetReportMobileTerminationError,
-        },
-        network_service, psn,
-        system_features::{types::PowerSavingMode, SetPowerSavingControl},
-        Urc,
-    },
     command::{
+        control::types::BaudRate,
+        device_lock::{GetPinStatus, SetPin},
+        fibocom,
         general::{GetCCID, GetFirmwareVersion, GetModelId},
-        gpio::{
-            types::{GpioInPull, GpioMode, GpioOutValue},
-            SetGpioConfiguration,
-        },
+        ip_transport_layer,
         network_service::{
-            responses::{OperatorSelection, SignalQuality},
-            types::OperatorSelectionMode,
-            GetOperatorSelection, GetSignalQuality, SetOperatorSelection,
+            responses::{OperatorInfo, OperatorSelection, SignalQuality},
+            GetAvailableOperators, GetBandMask, GetLpwaRadioAccessTechnology, GetOperatorSelection,
+            GetSignalQuality, GetSignalStrength,
         },
-        psn::{types::PSEventReportingMode, SetPacketSwitchedEventReporting},
+        Urc,
     },
     config::CellularConfig,
     error::{Error, GenericError},
+    event::{self, Event, ReportedState},
+    module::{detect_module, Vendor},
     network::{AtTx, Network},
     power::PowerState,
-    registration::ConnectionState,
-    services::data::ContextState,
-    UbloxCellularBuffers, UbloxCellularIngress, UbloxCellularUrcChannel,
-};
-use ip_transport_layer::{types::HexMode, SetHexMode};
-use network_service::{types::NetworkRegistrationUrcConfig, SetNetworkRegistrationStatus};
-use psn::{
-    types::{EPSNetworkRegistrationUrcConfig, GPRSNetworkRegistrationUrcConfig},
-    SetEPSNetworkRegistrationStatus, SetGPRSNetworkRegistrationStatus,
+    power_saving::PowerSavingClient,
+    radio,
+    registration::{ConnectionState, RegistrationUrcs},
+    services::data::usage::DataBudget,
+    setup::{self, InterfaceSetup, ModuleSetup, EXTENDED_ERRORS, FULL_FUNCTIONALITY},
+    signal::SignalMetrics,
+    sim::{self, PinStep, PinUnlock, SimSlot},
+    status::NetworkStatus,
+    UbloxCellularBuffers, UbloxCellularIngress, UbloxCellularUrcChannel, UbloxDigester,
 };
 
 pub(crate) const URC_CAPACITY: usize = 3;
@@ -61,14 +49,26 @@ pub enum State {
     FullyInitialized,
 }
 
-pub struct Device<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize> {
+pub struct Device<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+where
+    Config: CellularConfig,
+{
     pub(crate) config: Config,
-    pub(crate) network: Network<'sub, AtCl>,
+    pub(crate) network: Network<'sub, PowerSavingClient<AtCl, Config::DtrPin>>,
     urc_channel: &'buf AtUrcCh,
     urc_subscription: UrcSubscription<'sub, Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
+    /// State last reported to [`CellularConfig::on_event`]
+    reported: ReportedState,
+    /// The configured [`CellularConfig::SIM_PIN`] was rejected, and is not
+    /// given again until the SIM is unlocked by the application
+    pub(crate) sim_pin_rejected: bool,
+    /// SIM slot selected with [`CellularConfig::SIM_SELECT`]
+    pub(crate) sim_slot: SimSlot,
 
     pub(crate) state: State,
     pub(crate) power_state: PowerState,
+    /// Rate of the serial port, once detected or switched to
+    pub(crate) baud_rate: Option<BaudRate>,
     // Ublox devices can hold a maximum of 6 active sockets
     pub(crate) sockets: Option<&'static mut SocketSet<N, L>>,
 }
@@ -97,11 +97,8 @@ where
         tx: W,
         config: Config,
     ) -> (UbloxCellularIngress<INGRESS_BUF_SIZE>, Self) {
-        let (ingress, client) = buffers.split_blocking(
-            tx,
-            atat::DefaultDigester::<Urc>::default(),
-            atat::Config::default(),
-        );
+        let (ingress, client) =
+            buffers.split_blocking(tx, UbloxDigester::default(), atat::Config::default());
 
         (ingress, Device::new(client, &buffers.urc_channel, config))
     }
@@ -115,13 +112,22 @@ where
     AtUrcCh: AtatUrcChannel<Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
     Config: CellularConfig,
 {
-    pub fn new(client: AtCl, urc_channel: &'buf AtUrcCh, config: Config) -> Self {
+    pub fn new(client: AtCl, urc_channel: &'buf AtUrcCh, mut config: Config) -> Self {
         let network_urc_subscription = urc_channel.subscribe().unwrap();
+        let client =
+            PowerSavingClient::new(client, config.take_dtr_pin(), Config::POWER_SAVING_IDLE);
+        let mut network = Network::new(AtTx::new(client, network_urc_subscription));
+        network.status.operators = Config::OPERATORS;
+        network.contexts.data_budget = Config::DATA_BUDGET.map(DataBudget::new);
         Self {
             config,
-            network: Network::new(AtTx::new(client, network_urc_subscription)),
+            reported: ReportedState::new(network.status.conn_state, &network.contexts),
+            sim_pin_rejected: false,
+            sim_slot: SimSlot::Primary,
+            network,
             state: State::Off,
             power_state: PowerState::Off,
+            baud_rate: None,
             sockets: None,
             urc_channel,
             urc_subscription: urc_channel.subscribe().unwrap(),
@@ -166,6 +172,37 @@ where
     pub fn signal_strength(&mut self) -> Result<SignalQuality, Error> {
         self.send_at(&GetSignalQuality)
     }
+
+    /// Scan the network for the available operators. This can take up to a
+    /// few minutes, during which the module does not respond to other
+    /// commands.
+    pub fn scan_operators(&mut self) -> Result<Vec<OperatorInfo, 8>, Error> {
+        Ok(self.send_at(&GetAvailableOperators)?.operators)
+    }
+
+    /// Signal quality of the serving cell in physical units, from `+CESQ`,
+    /// or from `+CSQ` if the module does not support `+CESQ`.
+    pub fn signal_metrics(&mut self) -> Result<SignalMetrics, Error> {
+        match self.send_at(&GetSignalQuality) {
+            Ok(quality) => Ok(SignalMetrics::from(&quality)),
+            Err(Error::Network(crate::network::Error::AT(atat::Error::Error))) => {
+                let strength = self.send_at(&GetSignalStrength)?;
+                Ok(SignalMetrics::from(&strength))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// SIM slot currently selected, see [`CellularConfig::SIM_SELECT`]
+    pub fn sim_slot(&self) -> SimSlot {
+        self.sim_slot
+    }
+
+    /// Snapshot of the network state, as last seen by the driver. No AT
+    /// command is sent.
+    pub fn network_status(&self) -> NetworkStatus {
+        NetworkStatus::new(&self.network.status, &self.network.contexts)
+    }
     /// Run modem state machine
     ///
     /// Turns on modem if needed and processes URCs.
@@ -174,6 +211,9 @@ where
     ///
     /// This must be called periodically in a loop.
     pub fn spin(&mut self) -> nb::Result<(), Error> {
+        // Let the module's UART sleep if nothing has been sent for a while
+        self.network.at_tx.client.sleep_if_idle();
+
         let res = self.initialize();
 
         self.process_events().map_err(Error::from)?;
@@ -186,9 +226,7 @@ where
             Ok(())
         } else {
             // Reset context state if data connection is lost (This will act as a safeguard if a URC is missed)
-            if self.network.context_state == ContextState::Active {
-                self.network.context_state = ContextState::Activating;
-            }
+            self.network.contexts.deactivate_all();
             Err(nb::Error::WouldBlock)
         }
     }
@@ -200,48 +238,30 @@ where
     /// After this [`send_at`](Device::send_at) can be used.
     pub fn setup_at_commands(&mut self) -> Result<(), Error> {
         // Always re-configure the PDP contexts if we reconfigure the module
-        self.network.context_state = ContextState::Setup;
+        self.network.contexts.reset();
 
         self.clear_buffers()?;
 
         self.power_on()?;
 
-        // At this point, if is_alive fails, the configured Baud rate is probably wrong
-        if let Err(e) = self.is_alive(5).map_err(|_| Error::BaudDetection) {
-            if self.hard_reset().is_err() {
-                self.hard_power_off()?;
-                BlockingTimer::after(Duration::from_secs(1)).wait();
+        // At this point, if is_alive fails, the configured Baud rate is probably
+        // wrong, so probe the others
+        if self.is_alive(5).is_err() {
+            if let Err(e) = self.detect_baud_rate() {
+                if self.hard_reset().is_err() {
+                    self.hard_power_off()?;
+                    BlockingTimer::after(Duration::from_secs(1)).wait();
+                }
+                return Err(e);
             }
-            return Err(e);
         }
 
-        // Extended errors on
-        self.network.send_internal(
-            &SetReportMobileTerminationError {
-                n: TerminationErrorMode::Enabled,
-            },
-            false,
-        )?;
-
-        // Select SIM
-        self.network.send_internal(
-            &SetGpioConfiguration {
-                gpio_id: 25,
-                gpio_mode: GpioMode::Output(GpioOutValue::High),
-            },
-            false,
-        )?;
-
-        #[cfg(any(feature = "lara-r6"))]
-        self.network.send_internal(
-            &SetGpioConfiguration {
-                gpio_id: 42,
-                gpio_mode: GpioMode::Input(GpioInPull::NoPull),
-            },
-            false,
-        )?;
-
-        self.network.send_internal(&GetModelId, false)?;
+        self.switch_baud_rate()?;
+
+        self.network.send_internal(&EXTENDED_ERRORS, false)?;
+
+        // Identify the module, selecting the module specific behaviour
+        let model = self.network.send_internal(&GetModelId, false)?;
 
         // self.network.send_internal(
         //     &IdentificationInformation {
@@ -250,69 +270,27 @@ where
         //     false,
         // )?;
 
-        self.network.send_internal(&GetFirmwareVersion, false)?;
+        let firmware = self.network.send_internal(&GetFirmwareVersion, false)?;
+        self.network.module = detect_module(&model.model, &firmware.version);
+
+        self.select_sim_slot()?;
 
         self.select_sim_card()?;
 
         self.network.send_internal(&GetCCID, false)?;
 
-        // DCD circuit (109) changes in accordance with the carrier
-        self.network.send_internal(
-            &SetCircuit109Behaviour {
-                value: Circuit109Behaviour::ChangesWithCarrier,
-            },
-            false,
-        )?;
-
-        // Ignore changes to DTR
-        self.network.send_internal(
-            &SetCircuit108Behaviour {
-                value: Circuit108Behaviour::Ignore,
-            },
-            false,
-        )?;
-
-        // Switch off UART power saving until it is integrated into this API
-        self.network.send_internal(
-            &SetPowerSavingControl {
-                mode: PowerSavingMode::Disabled,
-                timeout: None,
-            },
-            false,
-        )?;
-
-        if Config::HEX_MODE {
-            self.network.send_internal(
-                &SetHexMode {
-                    hex_mode_disable: HexMode::Enabled,
-                },
-                false,
-            )?;
-        } else {
-            self.network.send_internal(
-                &SetHexMode {
-                    hex_mode_disable: HexMode::Disabled,
-                },
-                false,
-            )?;
+        let interface = InterfaceSetup::new::<Config>(self.network.module);
+        self.network.send_internal(&interface.dcd, false)?;
+        self.network.send_internal(&interface.dtr, false)?;
+        if let Some(hex_mode) = &interface.hex_mode {
+            self.network.send_internal(hex_mode, false)?;
         }
+        self.network.send_internal(&interface.flow_control, false)?;
 
-        // Tell module whether we support flow control
-        // FIXME: Use AT+IFC=2,2 instead of AT&K here
-        if Config::FLOW_CONTROL {
-            self.network.send_internal(
-                &SetFlowControl {
-                    value: FlowControl::RtsCts,
-                },
-                false,
-            )?;
-        } else {
-            self.network.send_internal(
-                &SetFlowControl {
-                    value: FlowControl::Disabled,
-                },
-                false,
-            )?;
+        // u-blox specific: UART power saving, once hardware flow control is
+        // settled
+        if self.network.module.vendor() == Vendor::Ublox {
+            self.set_power_saving()?;
         }
 
         self.state = State::AtInitialized;
@@ -351,43 +329,103 @@ where
         Ok(self.network.send_internal(cmd, true)?)
     }
 
-    fn select_sim_card(&mut self) -> Result<(), Error> {
+    /// Select the active SIM slot, as configured with
+    /// [`CellularConfig::SIM_SELECT`]
+    fn select_sim_slot(&mut self) -> Result<(), Error> {
+        for cmd in Config::SIM_SELECT.gpio_configuration(self.network.module, self.sim_slot) {
+            self.network.send_internal(&cmd, false)?;
+        }
+        Ok(())
+    }
+
+    /// Switch to the other SIM slot if the active SIM fails to register,
+    /// see [`CellularConfig::SIM_FAILOVER_TIMEOUT`]. The module is left at
+    /// minimum functionality, to be initialized again with the new SIM.
+    fn check_sim_failover(&mut self) -> Result<(), Error> {
+        if !sim::failover_needed::<Config>(self.state, &self.network.status) {
+            return Ok(());
+        }
+
+        self.sim_slot = self.sim_slot.other();
+        warn!(
+            "SIM failed to register, switching to {:?} slot",
+            self.sim_slot
+        );
+
+        self.network
+            .send_internal(&setup::minimum_functionality(self.network.module), false)?;
+        self.select_sim_slot()?;
+
+        self.state = State::Off;
+        self.config.on_event(Event::SimSlotSwitched(self.sim_slot));
+        Ok(())
+    }
+
+    pub(crate) fn select_sim_card(&mut self) -> Result<(), Error> {
+        let mut unlock = PinUnlock::default();
         for _ in 0..2 {
-            match self.network.send_internal(&GetPinStatus, true) {
-                Ok(PinStatus { code }) if code == PinStatusCode::Ready => {
-                    return Ok(());
+            let status = self.network.send_internal(&GetPinStatus, true);
+            match unlock.step::<Config>(status, self.sim_pin_rejected)? {
+                PinStep::Ready => return Ok(()),
+                PinStep::EnterPin(pin) => {
+                    let res = self.network.send_internal(&SetPin { pin }, true);
+                    sim::check_pin(res, &mut self.sim_pin_rejected)?;
                 }
-                _ => {}
+                PinStep::Wait => BlockingTimer::after(Duration::from_secs(1)).wait(),
             }
-
-            BlockingTimer::after(Duration::from_secs(1)).wait();
         }
 
         // There was an error initializing the SIM
         // We've seen issues on uBlox-based devices, as a precation, we'll cycle
         // the modem here through minimal/full functional state.
-        self.network.send_internal(
-            &SetModuleFunctionality {
-                fun: Functionality::Minimum,
-                // SARA-R5 This parameter can be used only when <fun> is 1, 4 or 19
-                #[cfg(feature = "sara-r5")]
-                rst: None,
-                #[cfg(not(feature = "sara-r5"))]
-                rst: Some(ResetMode::DontReset),
-            },
-            true,
-        )?;
-        self.network.send_internal(
-            &SetModuleFunctionality {
-                fun: Functionality::Full,
-                rst: Some(ResetMode::DontReset),
-            },
-            true,
-        )?;
+        self.network
+            .send_internal(&setup::minimum_functionality(self.network.module), true)?;
+        self.network.send_internal(&FULL_FUNCTIONALITY, true)?;
 
         Err(Error::Busy)
     }
 
+    /// Apply the RAT and band configuration, deregistering the module with
+    /// `AT+CFUN=0` if anything needs to be changed. `initialize` registers
+    /// it again right after.
+    fn configure_radio(&mut self) -> Result<(), Error> {
+        if !radio::configured::<Config>(self.network.module) {
+            return Ok(());
+        }
+
+        let rat_update = if Config::RADIO_ACCESS_TECHNOLOGIES.is_empty() {
+            None
+        } else {
+            let current = self
+                .network
+                .send_internal(&GetLpwaRadioAccessTechnology, false)?;
+            radio::rat_update(Config::RADIO_ACCESS_TECHNOLOGIES, &current)
+        };
+
+        let current_bands = if Config::BAND_MASKS.is_empty() {
+            None
+        } else {
+            Some(self.network.send_internal(&GetBandMask, false)?)
+        };
+        let band_updates = || radio::band_updates(Config::BAND_MASKS, current_bands.as_ref());
+
+        if rat_update.is_none() && band_updates().next().is_none() {
+            return Ok(());
+        }
+
+        debug!("Applying the RAT and band configuration");
+        self.network
+            .send_internal(&setup::minimum_functionality(self.network.module), false)?;
+
+        if let Some(cmd) = rat_update {
+            self.network.send_internal(&cmd, false)?;
+        }
+        for cmd in band_updates() {
+            self.network.send_internal(&cmd, false)?;
+        }
+        Ok(())
+    }
+
     /// Initialize modem fully
     ///
     /// Turns modem on if it is off, configures it and starts registering to network.
@@ -416,29 +454,15 @@ where
         self.setup_at_commands()?;
         self.select_sim_card()?;
 
-        // Disable Message Waiting URCs (UMWI)
-        #[cfg(any(feature = "toby-r2"))]
-        self.network.send_internal(
-            &crate::command::sms::SetMessageWaitingIndication {
-                mode: crate::command::sms::types::MessageWaitingMode::Disabled,
-            },
-            false,
-        )?;
-
-        self.network.send_internal(
-            &SetAutomaticTimezoneUpdate {
-                on_off: AutomaticTimezone::EnabledLocal,
-            },
-            false,
-        )?;
-
-        self.network.send_internal(
-            &SetModuleFunctionality {
-                fun: Functionality::Full,
-                rst: None,
-            },
-            true,
-        )?;
+        let settings = ModuleSetup::new(self.network.module);
+        if let Some(message_waiting) = &settings.message_waiting {
+            self.network.send_internal(message_waiting, false)?;
+        }
+        self.network.send_internal(&settings.timezone, false)?;
+
+        self.configure_radio()?;
+
+        self.network.send_internal(&settings.functionality, true)?;
 
         self.network.status.reset();
         self.network
@@ -447,22 +471,12 @@ where
 
         self.enable_registration_urcs()?;
 
-        // Set automatic operator selection, if not already set
+        // Start from the first preferred operator, else set automatic operator
+        // selection, if not already set
         let OperatorSelection { mode, .. } =
             self.network.send_internal(&GetOperatorSelection, true)?;
-
-        // Only run AT+COPS=0 if currently de-registered, to avoid PLMN reselection
-        if !matches!(
-            mode,
-            OperatorSelectionMode::Automatic | OperatorSelectionMode::Manual
-        ) {
-            self.network.send_internal(
-                &SetOperatorSelection {
-                    mode: OperatorSelectionMode::Automatic,
-                    format: Some(2),
-                },
-                true,
-            )?;
+        if !self.network.status.operator_selected(mode) {
+            self.network.select_operator(true)?;
         }
 
         self.network.update_registration()?;
@@ -482,87 +496,70 @@ where
     }
 
     pub(crate) fn enable_registration_urcs(&mut self) -> Result<(), Error> {
-        // if packet domain event reporting is not set it's not a stopper. We
-        // might lack some events when we are dropped from the network.
-        // TODO: Re-enable this when it works, and is useful!
-        if self
-            .network
-            .send_internal(
-                &SetPacketSwitchedEventReporting {
-                    mode: PSEventReportingMode::CircularBufferUrcs,
-                    bfr: None,
-                },
-                true,
-            )
-            .is_err()
-        {
+        let urcs = RegistrationUrcs::new(&self.network.status);
+        if self.network.send_internal(&urcs.events, true).is_err() {
             warn!("Packet domain event reporting set failed");
         }
-
-        // FIXME: Currently `atat` is unable to distinguish `xREG` family of
-        // commands from URC's
-
-        // CREG URC
-        self.network.send_internal(
-            &SetNetworkRegistrationStatus {
-                n: NetworkRegistrationUrcConfig::UrcDisabled,
-            },
-            true,
-        )?;
-
-        // CGREG URC
-        self.network.send_internal(
-            &SetGPRSNetworkRegistrationStatus {
-                n: GPRSNetworkRegistrationUrcConfig::UrcDisabled,
-            },
-            true,
-        )?;
-
-        // CEREG URC
-        self.network.send_internal(
-            &SetEPSNetworkRegistrationStatus {
-                n: EPSNetworkRegistrationUrcConfig::UrcDisabled,
-            },
-            true,
-        )?;
-
+        self.network.send_internal(&urcs.creg, true)?;
+        self.network.send_internal(&urcs.cgreg, true)?;
+        self.network.send_internal(&urcs.cereg, true)?;
         Ok(())
     }
 
-    fn handle_urc_internal(&mut self) -> Result<(), Error> {
-        if let Some(ref mut sockets) = self.sockets.as_deref_mut() {
-            if let Some(urc) = self.urc_subscription.try_next_message_pure() {
-                match urc {
-                    Urc::SocketClosed(ip_transport_layer::urc::SocketClosed { socket }) => {
-                        info!("[URC] SocketClosed {}", socket.0);
-                        if let Some((_, mut sock)) =
-                            sockets.iter_mut().find(|(handle, _)| *handle == socket)
-                        {
-                            sock.closed_by_remote();
-                        }
+    pub(crate) fn handle_urc_internal(&mut self) -> Result<(), Error> {
+        let Some(urc) = self.urc_subscription.try_next_message_pure() else {
+            return Ok(());
+        };
+
+        if let Some(sockets) = self.sockets.as_deref_mut() {
+            match &urc {
+                Urc::SocketClosed(ip_transport_layer::urc::SocketClosed { socket })
+                | Urc::MipSocketStatus(fibocom::urc::SocketStatus { socket, status: 1 }) => {
+                    info!("[URC] SocketClosed {}", socket.0);
+                    if let Some((_, mut sock)) =
+                        sockets.iter_mut().find(|(handle, _)| handle == socket)
+                    {
+                        sock.closed_by_remote();
                     }
-                    Urc::SocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable {
-                        socket,
-                        length,
-                    })
-                    | Urc::SocketDataAvailableUDP(ip_transport_layer::urc::SocketDataAvailable {
-                        socket,
-                        length,
-                    }) => {
-                        trace!("[Socket({})] {} bytes available", socket.0, length as u16);
-                        if let Some((_, mut sock)) =
-                            sockets.iter_mut().find(|(handle, _)| *handle == socket)
-                        {
-                            sock.set_available_data(length);
-                        }
+                }
+                Urc::SocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable {
+                    socket,
+                    length,
+                })
+                | Urc::SocketDataAvailableUDP(ip_transport_layer::urc::SocketDataAvailable {
+                    socket,
+                    length,
+                })
+                | Urc::MipSocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable {
+                    socket,
+                    length,
+                }) => {
+                    trace!("[Socket({})] {} bytes available", socket.0, *length as u16);
+                    if let Some((_, mut sock)) =
+                        sockets.iter_mut().find(|(handle, _)| handle == socket)
+                    {
+                        sock.set_available_data(*length);
                     }
-                    _ => {}
                 }
+                _ => {}
             }
-            Ok(())
-        } else {
-            Ok(())
         }
+
+        if let Some(event) = event::urc_event(&urc) {
+            self.config.on_event(event);
+        }
+        Ok(())
+    }
+
+    /// Report the registration and data connection changes since the last
+    /// call
+    fn report_state_events(&mut self) {
+        let config = &mut self.config;
+        self.reported.update(
+            self.network.status.conn_state,
+            &self.network.contexts,
+            |event| config.on_event(event),
+        );
     }
 
     pub(crate) fn process_events(&mut self) -> Result<(), Error> {
@@ -572,14 +569,20 @@ where
 
         self.handle_urc_internal()?;
 
-        match self.network.process_events() {
+        let res = self.network.process_events();
+        self.report_state_events();
+
+        match res {
             // Catch "Resetting the modem due to the network registration timeout"
             // as well as consecutive AT timeouts and do a hard reset.
             Err(crate::network::Error::Generic(GenericError::Timeout)) => {
                 self.hard_reset()?;
                 Err(Error::Generic(GenericError::Timeout))
             }
-            result => result.map_err(Error::from),
+            result => {
+                result?;
+                self.check_sim_failover()
+            }
         }
     }
 }
@@ -0,0 +1,211 @@
+//! 3GPP TS 27.010 basic option framing
+//!
+//! ```text
+//! | Flag | Address | Control | Length (1-2) | Information | FCS | Flag |
+//! ```
+
+use embedded_io::Write;
+use heapless::Vec;
+
+use super::FRAME_SIZE;
+
+/// Opening and closing flag of every frame
+pub const FLAG: u8 = 0xF9;
+
+/// Extension bit, set on the last byte of the address and length fields
+const EA: u8 = 0x01;
+/// Command/response bit of the address field
+const CR: u8 = 0x02;
+/// Poll/final bit of the control field
+const PF: u8 = 0x10;
+
+/// Remainder of the FCS calculation over a frame including its FCS
+const GOOD_FCS: u8 = 0xCF;
+
+/// Frame types, as encoded in the control field (without the P/F bit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameType {
+    /// Set Asynchronous Balanced Mode, opening a channel
+    Sabm = 0x2F,
+    /// Unnumbered Acknowledgement
+    Ua = 0x63,
+    /// Disconnected Mode, rejecting a command
+    Dm = 0x0F,
+    /// Disconnect, closing a channel
+    Disc = 0x43,
+    /// Unnumbered Information with Header check
+    Uih = 0xEF,
+    /// Unnumbered Information
+    Ui = 0x03,
+}
+
+impl FrameType {
+    fn from_control(control: u8) -> Option<Self> {
+        match control & !PF {
+            0x2F => Some(Self::Sabm),
+            0x63 => Some(Self::Ua),
+            0x0F => Some(Self::Dm),
+            0x43 => Some(Self::Disc),
+            0xEF => Some(Self::Uih),
+            0x03 => Some(Self::Ui),
+            _ => None,
+        }
+    }
+}
+
+/// Decoded frame, borrowing its information field from the [`Decoder`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub dlci: u8,
+    pub frame_type: FrameType,
+    /// Command/response bit of the address field
+    pub command: bool,
+    pub poll_final: bool,
+    pub payload: &'a [u8],
+}
+
+fn crc(crc: u8, data: &[u8]) -> u8 {
+    data.iter().fold(crc, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x01 != 0 {
+                (crc >> 1) ^ 0xE0
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+/// Frame check sequence over the address, control and length fields, and
+/// for all but UIH frames the information field.
+pub fn fcs(frame_type: FrameType, header: &[u8], payload: &[u8]) -> u8 {
+    let header_crc = crc(0xFF, header);
+    if frame_type == FrameType::Uih {
+        0xFF - header_crc
+    } else {
+        0xFF - crc(header_crc, payload)
+    }
+}
+
+/// Write a frame sent by the TE, which always acts as the initiator.
+pub fn write_frame<W: Write>(
+    w: &mut W,
+    dlci: u8,
+    frame_type: FrameType,
+    poll_final: bool,
+    payload: &[u8],
+) -> Result<(), W::Error> {
+    debug_assert!(payload.len() <= FRAME_SIZE);
+
+    let mut header = Vec::<u8, 4>::new();
+    // Commands sent by the initiator, and data, carry C/R = 1
+    header.push((dlci << 2) | CR | EA).ok();
+    header
+        .push(frame_type as u8 | if poll_final { PF } else { 0 })
+        .ok();
+    if payload.len() < 0x80 {
+        header.push(((payload.len() as u8) << 1) | EA).ok();
+    } else {
+        header.push((payload.len() as u8) << 1).ok();
+        header.push((payload.len() >> 7) as u8).ok();
+    }
+
+    w.write_all(&[FLAG])?;
+    w.write_all(&header)?;
+    w.write_all(payload)?;
+    w.write_all(&[fcs(frame_type, &header, payload), FLAG])
+}
+
+/// Incremental frame decoder, fed one byte at a time
+pub struct Decoder {
+    buf: Vec<u8, { FRAME_SIZE + 5 }>,
+    /// The buffer holds the frame returned by the last `feed`
+    complete: bool,
+    /// Bytes are dropped until the next flag
+    discarding: bool,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            complete: false,
+            discarding: false,
+        }
+    }
+
+    /// Feed a received byte, returning the frame it completes, if any.
+    /// Frames with a bad length or FCS are dropped.
+    pub fn feed(&mut self, byte: u8) -> Option<Frame<'_>> {
+        if self.complete {
+            self.buf.clear();
+            self.complete = false;
+        }
+
+        if byte != FLAG {
+            if !self.discarding && self.buf.push(byte).is_err() {
+                warn!("[CMUX] Frame exceeds N1, dropping it");
+                self.buf.clear();
+                self.discarding = true;
+            }
+            return None;
+        }
+
+        self.discarding = false;
+        if self.buf.is_empty() {
+            // Opening flag, or a flag shared between two frames
+            return None;
+        }
+
+        self.complete = true;
+        let frame = Self::parse(&self.buf);
+        if frame.is_none() {
+            warn!("[CMUX] Dropping invalid frame");
+        }
+        frame
+    }
+
+    fn parse(buf: &[u8]) -> Option<Frame<'_>> {
+        let (&address, rest) = buf.split_first()?;
+        let (&control, rest) = rest.split_first()?;
+        let (&len_lo, rest) = rest.split_first()?;
+
+        let (len, header_len, rest) = if len_lo & EA != 0 {
+            ((len_lo >> 1) as usize, 3, rest)
+        } else {
+            let (&len_hi, rest) = rest.split_first()?;
+            ((len_lo >> 1) as usize | (len_hi as usize) << 7, 4, rest)
+        };
+
+        if rest.len() != len + 1 {
+            return None;
+        }
+
+        let frame_type = FrameType::from_control(control)?;
+        let checked = if frame_type == FrameType::Uih {
+            &buf[..header_len]
+        } else {
+            &buf[..header_len + len]
+        };
+        if crc(crc(0xFF, checked), &rest[len..]) != GOOD_FCS {
+            return None;
+        }
+
+        Some(Frame {
+            dlci: address >> 2,
+            frame_type,
+            command: address & CR != 0,
+            poll_final: control & PF != 0,
+            payload: &rest[..len],
+        })
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,440 @@
+//! 3GPP TS 27.010 multiplexing (CMUX)
+//!
+//! [`Mux`] takes over the serial port of the module, and splits it into
+//! virtual channels (DLCIs). Every [`Channel`] implements the `embedded-io`
+//! `Read`, `Write` and `ReadReady` traits, so DLCI 1 is handed to the AT
+//! client in place of the UART, leaving the other channels free for e.g. PPP
+//! or GNSS data.
+//!
+//! Until [`Mux::start`], DLCI 1 passes bytes through unframed, which lets the
+//! AT client set up the module and switch it into multiplexing mode:
+//!
+//! ```ignore
+//! static MUX: StaticCell<Mux<CriticalSectionRawMutex, Uart, 2, 1024>> = StaticCell::new();
+//! let mux: &'static Mux<_, _, 2, 1024> = MUX.init(Mux::new(uart));
+//!
+//! let (mut ingress, mut device) = GsmClient::from_buffers(&BUFFERS, mux.channel(1), config);
+//!
+//! // Meanwhile, from the ingress task: read `mux.channel(1)` into `ingress`
+//! device.setup_at_commands()?;
+//! device.enter_multiplexing()?;
+//! mux.start()?;
+//!
+//! let mut gnss = mux.channel(2);
+//! ```
+//!
+//! The serial port is only read once it reports data with `ReadReady`, so
+//! the lock is never held while blocking on the port. For the same reason,
+//! reading a [`Channel`] with no data fails with [`Error::WouldBlock`]
+//! instead of blocking: readers poll `read_ready` first, or retry.
+
+pub mod frame;
+
+use core::cell::RefCell;
+
+use atat::blocking::AtatClient;
+use embassy_sync::blocking_mutex::{raw::RawMutex, Mutex};
+use embassy_time::{Duration, Instant};
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+use heapless::{Deque, Vec};
+
+use crate::{
+    client::Device,
+    command::control::{
+        types::{MultiplexingMode, MultiplexingSubset},
+        SetMultiplexing,
+    },
+    config::CellularConfig,
+    error::Error as DeviceError,
+};
+use frame::{Decoder, FrameType};
+
+/// Maximum size of the information field of a frame (N1), configured on the
+/// module by [`Device::enter_multiplexing`].
+pub const FRAME_SIZE: usize = 127;
+
+/// Time to wait for the module to acknowledge a channel being opened or
+/// closed (T1 and retransmissions).
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Control channel message closing down the multiplexer (CLD command)
+const CLOSE_DOWN: [u8; 2] = [0xC3, 0x01];
+
+/// Command/response bit of a control channel message type
+const CONTROL_CR: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// Error of the underlying serial port
+    Transport(E),
+    /// The module did not answer the opening or closing of a channel
+    Timeout,
+    /// The module refused to open the channel
+    Rejected(u8),
+    /// The channel is not open
+    Closed,
+    /// No data is available on the channel yet
+    WouldBlock,
+}
+
+impl<E: embedded_io::Error> embedded_io::Error for Error<E> {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Transport(e) => e.kind(),
+            Self::Timeout => embedded_io::ErrorKind::TimedOut,
+            Self::Rejected(_) => embedded_io::ErrorKind::ConnectionRefused,
+            Self::Closed => embedded_io::ErrorKind::NotConnected,
+            Self::WouldBlock => embedded_io::ErrorKind::Interrupted,
+        }
+    }
+}
+
+struct Inner<S, const CHANNELS: usize, const BUF: usize> {
+    serial: S,
+    started: bool,
+    decoder: Decoder,
+    /// Open DLCIs, as a bitmask
+    open: u32,
+    /// DLCIs acknowledged (UA) and not yet consumed, as a bitmask
+    acked: u32,
+    /// DLCIs refused (DM) and not yet consumed, as a bitmask
+    refused: u32,
+    /// Received data of DLCI 1 to `CHANNELS`
+    rx: [Deque<u8, BUF>; CHANNELS],
+}
+
+impl<S, const CHANNELS: usize, const BUF: usize> Inner<S, CHANNELS, BUF>
+where
+    S: Read + Write + ReadReady,
+{
+    /// Read whatever the serial port has ready, and dispatch the received
+    /// frames.
+    fn poll(&mut self) -> Result<(), S::Error> {
+        if !self.serial.read_ready()? {
+            return Ok(());
+        }
+
+        let mut buf = [0u8; 64];
+        let len = self.serial.read(&mut buf)?;
+
+        for &byte in &buf[..len] {
+            let Some(frame) = self.decoder.feed(byte) else {
+                continue;
+            };
+            if frame.dlci as usize > CHANNELS {
+                warn!("[CMUX] Frame for unknown DLCI {}", frame.dlci);
+                continue;
+            }
+            let bit = 1 << frame.dlci;
+
+            match frame.frame_type {
+                FrameType::Ua => self.acked |= bit,
+                FrameType::Dm => {
+                    self.refused |= bit;
+                    self.open &= !bit;
+                }
+                FrameType::Disc => {
+                    warn!("[CMUX] DLCI {} closed by the module", frame.dlci);
+                    self.open &= !bit;
+                }
+                FrameType::Uih | FrameType::Ui if frame.dlci == 0 => {
+                    // Acknowledge control commands (e.g. MSC) by echoing
+                    // them back as a response
+                    match frame.payload.split_first() {
+                        Some((&kind, _)) if kind & CONTROL_CR != 0 => {
+                            let mut response = Vec::<u8, FRAME_SIZE>::new();
+                            response.extend_from_slice(frame.payload).ok();
+                            response[0] &= !CONTROL_CR;
+                            frame::write_frame(
+                                &mut self.serial,
+                                0,
+                                FrameType::Uih,
+                                false,
+                                &response,
+                            )?;
+                        }
+                        _ => {}
+                    }
+                }
+                FrameType::Uih | FrameType::Ui => {
+                    let rx = &mut self.rx[frame.dlci as usize - 1];
+                    for &b in frame.payload {
+                        if rx.push_back(b).is_err() {
+                            error!("[CMUX] DLCI {} receive buffer full", frame.dlci);
+                            break;
+                        }
+                    }
+                }
+                FrameType::Sabm => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Multiplexer over the serial port `S`, with `CHANNELS` data channels
+/// (DLCI 1 to `CHANNELS`) each buffering up to `BUF` received bytes.
+pub struct Mux<M: RawMutex, S, const CHANNELS: usize, const BUF: usize> {
+    inner: Mutex<M, RefCell<Inner<S, CHANNELS, BUF>>>,
+}
+
+impl<M: RawMutex, S, const CHANNELS: usize, const BUF: usize> Mux<M, S, CHANNELS, BUF> {
+    const EMPTY: Deque<u8, BUF> = Deque::new();
+
+    pub const fn new(serial: S) -> Self {
+        assert!(CHANNELS < 32);
+
+        Self {
+            inner: Mutex::new(RefCell::new(Inner {
+                serial,
+                started: false,
+                decoder: Decoder::new(),
+                open: 0,
+                acked: 0,
+                refused: 0,
+                rx: [Self::EMPTY; CHANNELS],
+            })),
+        }
+    }
+
+    /// Endpoint of DLCI `dlci`, from 1 to `CHANNELS`
+    pub fn channel(&self, dlci: u8) -> Channel<'_, M, S, CHANNELS, BUF> {
+        assert!(dlci >= 1 && dlci as usize <= CHANNELS);
+        Channel { mux: self, dlci }
+    }
+
+    /// Whether the multiplexer has been started
+    pub fn is_started(&self) -> bool {
+        self.with(|inner| inner.started)
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut Inner<S, CHANNELS, BUF>) -> R) -> R {
+        self.inner.lock(|inner| f(&mut inner.borrow_mut()))
+    }
+}
+
+impl<M, S, const CHANNELS: usize, const BUF: usize> Mux<M, S, CHANNELS, BUF>
+where
+    M: RawMutex,
+    S: Read + Write + ReadReady,
+{
+    /// Open the control channel and every data channel. The module has to be
+    /// in multiplexing mode already, see [`Device::enter_multiplexing`].
+    pub fn start(&self) -> Result<(), Error<S::Error>> {
+        self.with(|inner| inner.started = true);
+
+        for dlci in 0..=CHANNELS as u8 {
+            if let Err(e) = self.command(dlci, FrameType::Sabm) {
+                error!("[CMUX] Failed to open DLCI {}", dlci);
+                return Err(e);
+            }
+            self.with(|inner| inner.open |= 1 << dlci);
+        }
+
+        Ok(())
+    }
+
+    /// Close every channel, and return the module to AT command mode.
+    pub fn stop(&self) -> Result<(), Error<S::Error>> {
+        for dlci in (1..=CHANNELS as u8).rev() {
+            if self.with(|inner| inner.open & (1 << dlci) != 0) {
+                // The channel is gone either way
+                self.command(dlci, FrameType::Disc).ok();
+                self.with(|inner| inner.open &= !(1 << dlci));
+            }
+        }
+
+        self.with(|inner| {
+            let res = frame::write_frame(&mut inner.serial, 0, FrameType::Uih, false, &CLOSE_DOWN);
+            inner.open = 0;
+            inner.started = false;
+            inner.rx.iter_mut().for_each(Deque::clear);
+            res
+        })
+        .map_err(Error::Transport)
+    }
+
+    /// Send `command` to `dlci`, and wait for the module to acknowledge it.
+    fn command(&self, dlci: u8, command: FrameType) -> Result<(), Error<S::Error>> {
+        let bit = 1 << dlci;
+
+        self.with(|inner| frame::write_frame(&mut inner.serial, dlci, command, true, &[]))
+            .map_err(Error::Transport)?;
+
+        let start = Instant::now();
+        loop {
+            let (acked, refused) = self
+                .with(|inner| {
+                    inner.poll()?;
+                    let response = (inner.acked & bit != 0, inner.refused & bit != 0);
+                    inner.acked &= !bit;
+                    inner.refused &= !bit;
+                    Ok(response)
+                })
+                .map_err(Error::Transport)?;
+
+            if acked {
+                return Ok(());
+            }
+            if refused {
+                return Err(Error::Rejected(dlci));
+            }
+            if start.elapsed() > RESPONSE_TIMEOUT {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+}
+
+/// `embedded-io` endpoint of a single DLCI of a [`Mux`]
+pub struct Channel<'a, M: RawMutex, S, const CHANNELS: usize, const BUF: usize> {
+    mux: &'a Mux<M, S, CHANNELS, BUF>,
+    dlci: u8,
+}
+
+impl<'a, M: RawMutex, S, const CHANNELS: usize, const BUF: usize> Channel<'a, M, S, CHANNELS, BUF> {
+    pub fn dlci(&self) -> u8 {
+        self.dlci
+    }
+
+    /// Passthrough to the serial port, before the multiplexer is started
+    fn is_passthrough(inner: &Inner<S, CHANNELS, BUF>, dlci: u8) -> bool {
+        !inner.started && dlci == 1
+    }
+
+    fn is_open(inner: &Inner<S, CHANNELS, BUF>, dlci: u8) -> bool {
+        inner.started && inner.open & (1 << dlci) != 0
+    }
+}
+
+impl<'a, M, S, const CHANNELS: usize, const BUF: usize> ErrorType
+    for Channel<'a, M, S, CHANNELS, BUF>
+where
+    M: RawMutex,
+    S: ErrorType,
+{
+    type Error = Error<S::Error>;
+}
+
+impl<'a, M, S, const CHANNELS: usize, const BUF: usize> Read for Channel<'a, M, S, CHANNELS, BUF>
+where
+    M: RawMutex,
+    S: Read + Write + ReadReady,
+{
+    /// Read the data received on the channel, failing with
+    /// [`Error::WouldBlock`] if there is none yet.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let dlci = self.dlci;
+        self.mux.with(|inner| {
+            if Self::is_passthrough(inner, dlci) {
+                return if inner.serial.read_ready().map_err(Error::Transport)? {
+                    inner.serial.read(buf).map_err(Error::Transport)
+                } else {
+                    Err(Error::WouldBlock)
+                };
+            }
+
+            if !Self::is_open(inner, dlci) {
+                return Err(Error::Closed);
+            }
+
+            inner.poll().map_err(Error::Transport)?;
+
+            let rx = &mut inner.rx[dlci as usize - 1];
+            let mut len = 0;
+            for slot in buf.iter_mut() {
+                match rx.pop_front() {
+                    Some(b) => *slot = b,
+                    None => break,
+                }
+                len += 1;
+            }
+
+            if len == 0 {
+                return Err(Error::WouldBlock);
+            }
+            Ok(len)
+        })
+    }
+}
+
+impl<'a, M, S, const CHANNELS: usize, const BUF: usize> ReadReady
+    for Channel<'a, M, S, CHANNELS, BUF>
+where
+    M: RawMutex,
+    S: Read + Write + ReadReady,
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        let dlci = self.dlci;
+        self.mux.with(|inner| {
+            if Self::is_passthrough(inner, dlci) {
+                return inner.serial.read_ready().map_err(Error::Transport);
+            }
+
+            if !Self::is_open(inner, dlci) {
+                return Err(Error::Closed);
+            }
+
+            inner.poll().map_err(Error::Transport)?;
+            Ok(!inner.rx[dlci as usize - 1].is_empty())
+        })
+    }
+}
+
+impl<'a, M, S, const CHANNELS: usize, const BUF: usize> Write for Channel<'a, M, S, CHANNELS, BUF>
+where
+    M: RawMutex,
+    S: Read + Write + ReadReady,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let dlci = self.dlci;
+        self.mux.with(|inner| {
+            if Self::is_passthrough(inner, dlci) {
+                return inner.serial.write(buf).map_err(Error::Transport);
+            }
+
+            if !Self::is_open(inner, dlci) {
+                return Err(Error::Closed);
+            }
+
+            let len = buf.len().min(FRAME_SIZE);
+            frame::write_frame(&mut inner.serial, dlci, FrameType::Uih, false, &buf[..len])
+                .map_err(Error::Transport)?;
+            Ok(len)
+        })
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.mux
+            .with(|inner| inner.serial.flush())
+            .map_err(Error::Transport)
+    }
+}
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Switch the UART of the module into multiplexing mode, with frames of
+    /// up to [`FRAME_SIZE`] bytes. The [`Mux`] has to be started right after,
+    /// as the module stops answering plain AT commands.
+    pub fn enter_multiplexing(&mut self) -> Result<(), DeviceError> {
+        self.send_at(&SetMultiplexing {
+            mode: MultiplexingMode::Basic,
+            subset: MultiplexingSubset::Uih,
+            port_speed: None,
+            n1: Some(FRAME_SIZE as u16),
+        })?;
+
+        Ok(())
+    }
+}
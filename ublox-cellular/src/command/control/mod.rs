@@ -6,7 +6,10 @@
 pub mod types;
 
 use atat::atat_derive::AtatCmd;
-use types::{BaudRate, Circuit108Behaviour, Circuit109Behaviour, FlowControl, SoftwareFlowControl};
+use types::{
+    BaudRate, Circuit108Behaviour, Circuit109Behaviour, FlowControl, MultiplexingMode,
+    MultiplexingSubset, SoftwareFlowControl,
+};
 
 use super::NoResponse;
 
@@ -81,6 +84,25 @@ pub struct SetDataRate {
     pub rate: BaudRate,
 }
 
+/// Multiplexing mode +CMUX
+///
+/// Enables the 3GPP TS 27.010 multiplexer on the UART. After the final
+/// result code the module only accepts multiplexer frames, starting with the
+/// opening of the control channel (DLCI 0). `n1` is the maximum size of the
+/// information field of a frame.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CMUX", NoResponse)]
+pub struct SetMultiplexing {
+    #[at_arg(position = 0)]
+    pub mode: MultiplexingMode,
+    #[at_arg(position = 1)]
+    pub subset: MultiplexingSubset,
+    #[at_arg(position = 2)]
+    pub port_speed: Option<u8>,
+    #[at_arg(position = 3)]
+    pub n1: Option<u16>,
+}
+
 /// 15.25 Set to factory defined configuration &F
 ///
 /// Resets the current profile to factory-programmed setting. Other NVM
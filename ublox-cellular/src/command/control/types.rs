@@ -51,40 +51,17 @@ pub enum SoftwareFlowControl {
     Circuit105_106 = 3,
 }
 
-#[derive(Clone, PartialEq, Eq, AtatEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[at_enum(u32)]
 pub enum BaudRate {
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4"
-    ))]
+    /// Supported by TOBY-L2, MPCI-L2, SARA-U2, TOBY-R2, LARA-R2, TOBY-L4, LEON-G1, SARA-G3, SARA-G4
     B0 = 0,
-    #[cfg(any(feature = "lisa-u1", feature = "lisa-u2", feature = "sara-u2",))]
+    /// Supported by LISA-U1, LISA-U2, SARA-U2
     B1200 = 1200,
-    #[cfg(any(
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4"
-    ))]
+    /// Supported by LISA-U1, LISA-U2, SARA-U2, LEON-G1, SARA-G3, SARA-G4
     B2400 = 2400,
-    #[cfg(any(
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4"
-    ))]
+    /// Supported by LISA-U1, LISA-U2, SARA-U2, LEON-G1, SARA-G3, SARA-G4
     B4800 = 4800,
     B9600 = 9600,
     B19200 = 19200,
@@ -92,45 +69,30 @@ pub enum BaudRate {
     B57600 = 57600,
     B115200 = 115_200,
 
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "toby-l4",
-    ))]
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, TOBY-L4
     B230400 = 230_400,
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "toby-l4",
-    ))]
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, TOBY-L4
     B460800 = 460_800,
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "toby-l4",
-    ))]
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, TOBY-L4
     B921600 = 921_600,
-    #[cfg(any(feature = "toby-r2", feature = "lara-r2",))]
+    /// Supported by TOBY-R2, LARA-R2
     B3000000 = 3_000_000,
-    #[cfg(any(feature = "toby-r2", feature = "lara-r2",))]
+    /// Supported by TOBY-R2, LARA-R2
     B3250000 = 3_250_000,
-    #[cfg(any(feature = "toby-r2", feature = "lara-r2",))]
+    /// Supported by TOBY-R2, LARA-R2
     B6000000 = 6_000_000,
-    #[cfg(any(feature = "toby-r2", feature = "lara-r2",))]
+    /// Supported by TOBY-R2, LARA-R2
     B6500000 = 6_500_000,
 }
+
+#[derive(Clone, PartialEq, Eq, AtatEnum)]
+pub enum MultiplexingMode {
+    /// 0: basic option
+    Basic = 0,
+}
+
+#[derive(Clone, PartialEq, Eq, AtatEnum)]
+pub enum MultiplexingSubset {
+    /// 0: UIH frames used only
+    Uih = 0,
+}
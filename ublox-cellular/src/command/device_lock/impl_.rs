@@ -1,6 +1,18 @@
-use super::types::PinStatusCode;
+use super::types::{Facility, PinStatusCode};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
+impl Serialize for Facility {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Self::SimPin => Serializer::serialize_str(serializer, "SC"),
+            Self::SimPin2 => Serializer::serialize_str(serializer, "P2"),
+        }
+    }
+}
+
 impl Serialize for PinStatusCode {
     fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
     where
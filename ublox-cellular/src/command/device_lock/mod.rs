@@ -6,6 +6,7 @@ pub mod types;
 
 use atat::atat_derive::AtatCmd;
 use responses::PinStatus;
+use types::{Facility, FacilityLockMode};
 
 use super::NoResponse;
 
@@ -26,7 +27,7 @@ pub struct GetPinStatus;
 #[derive(Clone, AtatCmd)]
 #[at_cmd("+CPIN", NoResponse)]
 pub struct SetPin<'a> {
-    #[at_arg(position = 0, len = 4)]
+    #[at_arg(position = 0, len = 8)]
     pub pin: &'a str,
 }
 
@@ -40,6 +41,37 @@ pub struct SetPin<'a> {
 pub struct ChangePin<'a> {
     #[at_arg(position = 0, len = 8)]
     pub puk: &'a str,
-    #[at_arg(position = 1, len = 4)]
+    #[at_arg(position = 1, len = 8)]
     pub newpin: &'a str,
 }
+
+/// 9.2 Facility lock +CLCK
+///
+/// Locks, unlocks or interrogates an MT or network facility <fac>. A password
+/// is normally needed to do such actions, e.g. the PIN to enable or disable
+/// the SIM PIN request.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CLCK", NoResponse)]
+pub struct SetFacilityLock<'a> {
+    #[at_arg(position = 0)]
+    pub fac: Facility,
+    #[at_arg(position = 1)]
+    pub mode: FacilityLockMode,
+    #[at_arg(position = 2, len = 8)]
+    pub passwd: Option<&'a str>,
+}
+
+/// 9.3 Change password +CPWD
+///
+/// Sets a new password for the facility lock function defined by command
+/// +CLCK, e.g. changes the SIM PIN.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CPWD", NoResponse)]
+pub struct ChangePassword<'a> {
+    #[at_arg(position = 0)]
+    pub fac: Facility,
+    #[at_arg(position = 1, len = 8)]
+    pub oldpwd: &'a str,
+    #[at_arg(position = 2, len = 8)]
+    pub newpwd: &'a str,
+}
@@ -1,4 +1,5 @@
 //! Argument and parameter types used by Device lock Commands and Responses
+use atat::atat_derive::AtatEnum;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -25,3 +26,22 @@ pub enum PinStatusCode {
     /// • PH-SIM PIN: MT is waiting phone to SIM/UICC card password to be given
     PhSimPin,
 }
+
+/// Facility of the +CLCK and +CPWD commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Facility {
+    /// • "SC": SIM (lock SIM/UICC card, the PIN is requested at power on)
+    SimPin,
+    /// • "P2": SIM PIN2
+    SimPin2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FacilityLockMode {
+    /// • 0: unlock
+    Unlock = 0,
+    /// • 1: lock
+    Lock = 1,
+}
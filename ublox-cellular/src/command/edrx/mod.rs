@@ -0,0 +1,46 @@
+//! ### eDRX - Extended discontinuous reception
+//!
+//! Extended DRX lets the module sleep between paging occasions for up to
+//! hours on LTE-M and NB-IoT, while staying reachable for mobile terminated
+//! data once per cycle. The eDRX values are coded as half a byte bit strings,
+//! see the 3GPP TS 24.008 \[12\] Extended DRX parameters information
+//! element.
+
+pub mod responses;
+pub mod types;
+pub mod urc;
+
+use super::NoResponse;
+use atat::atat_derive::AtatCmd;
+use responses::EDRXDynamicParameters;
+use types::{EDRXAccessTechnology, EDRXMode};
+
+/// eDRX setting +CEDRXS (3GPP TS 27.007 7.40)
+///
+/// Sets the requested eDRX value and paging time window for a Radio Access
+/// Technology. With `<mode>`=2, the +CEDRXP URC reports the eDRX parameters
+/// provided by the network whenever they change.
+///
+/// **NOTES:**
+/// - The `<Requested_paging_time_window>` parameter is a u-blox extension,
+///   only supported on E-UTRAN by **SARA-R4 / SARA-R5**.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CEDRXS", NoResponse)]
+pub struct SetEDRXSettings<'a> {
+    #[at_arg(position = 0)]
+    pub mode: EDRXMode,
+    #[at_arg(position = 1)]
+    pub act_type: EDRXAccessTechnology,
+    #[at_arg(position = 2, len = 4)]
+    pub requested_edrx_value: Option<&'a str>,
+    #[at_arg(position = 3, len = 4)]
+    pub requested_paging_time_window: Option<&'a str>,
+}
+
+/// eDRX read dynamic parameters +CEDRXRDP (3GPP TS 27.007 7.41)
+///
+/// Reads the eDRX parameters provided by the network for the serving cell,
+/// along with the requested ones.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CEDRXRDP", EDRXDynamicParameters)]
+pub struct GetEDRXDynamicParameters;
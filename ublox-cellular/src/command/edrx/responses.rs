@@ -0,0 +1,17 @@
+//! Responses for eDRX Commands
+use super::types::EDRXAccessTechnology;
+use atat::atat_derive::AtatResp;
+use heapless::String;
+
+/// eDRX read dynamic parameters +CEDRXRDP
+#[derive(Debug, Clone, AtatResp)]
+pub struct EDRXDynamicParameters {
+    #[at_arg(position = 0)]
+    pub act_type: EDRXAccessTechnology,
+    #[at_arg(position = 1)]
+    pub requested_edrx_value: Option<String<4>>,
+    #[at_arg(position = 2)]
+    pub nw_provided_edrx_value: Option<String<4>>,
+    #[at_arg(position = 3)]
+    pub paging_time_window: Option<String<4>>,
+}
@@ -0,0 +1,34 @@
+//! Argument and parameter types used by eDRX Commands and Responses
+use atat::atat_derive::AtatEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EDRXMode {
+    /// • 0 (default value): disable the use of eDRX
+    Disabled = 0,
+    /// • 1: enable the use of eDRX
+    Enabled = 1,
+    /// • 2: enable the use of eDRX and the +CEDRXP URC
+    EnabledWithUrc = 2,
+    /// • 3: disable the use of eDRX and reset all the eDRX parameters to
+    /// their default values
+    DisabledAndReset = 3,
+}
+
+/// Access technology the eDRX parameters apply to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EDRXAccessTechnology {
+    /// • 0: access technology not using eDRX
+    NotUsingEdrx = 0,
+    /// • 1: EC-GSM-IoT (A/Gb mode)
+    EcGsmIot = 1,
+    /// • 2: GSM (A/Gb mode)
+    Gsm = 2,
+    /// • 3: UTRAN (Iu mode)
+    Utran = 3,
+    /// • 4: E-UTRAN (WB-S1 mode), i.e. LTE and LTE-M
+    EutranWbS1 = 4,
+    /// • 5: E-UTRAN (NB-S1 mode), i.e. NB-IoT
+    EutranNbS1 = 5,
+}
@@ -0,0 +1,167 @@
+//! ### Fibocom TCP/IP Commands
+//!
+//! Fibocom modules (L610, L716, FG621, ...) do not implement the u-blox
+//! `+USOxx`, `+UPSD` and `+USECMNG` families. Instead the internal IP stack is
+//! driven through the `+MIPxxx` commands:
+//! - `AT+MIPCALL`: activation of the data connection, including the APN;
+//! - `AT+MIPOPEN` / `AT+MIPCLOSE`: opening and closing TCP, UDP and TLS
+//!   sockets, using a socket id chosen by the host (1 to 6);
+//! - `AT+MIPSEND` / `AT+MIPPUSH`: buffering and flushing hex encoded data;
+//! - `AT+MIPREAD`: reading hex encoded data buffered by the module;
+//! - `AT+MIPDNS`: name resolution;
+//! - `AT+MIPCERT` / `AT+MIPSSLCFG`: certificates and TLS configuration.
+//!
+//! Only the subset used by the data service is covered.
+
+pub mod responses;
+pub mod types;
+pub mod urc;
+
+use atat::atat_derive::AtatCmd;
+use embedded_nal::IpAddr;
+use responses::{
+    CertificateImport, InternetConnection, OpenSocketResponse, ResolveHostResponse,
+    SendSocketDataResponse, SocketData,
+};
+use types::{CertificateType, ConnectionOperation, MipProtocol, SslParam};
+
+use super::NoResponse;
+use crate::services::data::ssl::SecurityProfileId;
+use ublox_sockets::SocketHandle;
+
+/// Data connection activation +MIPCALL
+///
+/// Activates (or deactivates) the PDP context used by the internal IP stack.
+/// The command returns right away; the result is polled with
+/// [`GetInternetConnection`].
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPCALL", NoResponse, timeout_ms = 30000)]
+pub struct SetInternetConnection<'a> {
+    #[at_arg(position = 0)]
+    pub operation: ConnectionOperation,
+    #[at_arg(position = 1, len = 99)]
+    pub apn: Option<&'a str>,
+    #[at_arg(position = 2, len = 64)]
+    pub username: Option<&'a str>,
+    #[at_arg(position = 3, len = 64)]
+    pub password: Option<&'a str>,
+}
+
+/// Data connection status +MIPCALL?
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPCALL?", InternetConnection)]
+pub struct GetInternetConnection;
+
+/// Open socket +MIPOPEN
+///
+/// Opens the socket `socket` towards `remote_addr:remote_port`. For UDP the
+/// remote endpoint is fixed for the lifetime of the socket.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPOPEN", OpenSocketResponse, timeout_ms = 120000)]
+pub struct OpenSocket {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+    #[at_arg(position = 1)]
+    pub local_port: Option<u16>,
+    #[at_arg(position = 2, len = 39)]
+    pub remote_addr: IpAddr,
+    #[at_arg(position = 3)]
+    pub remote_port: u16,
+    #[at_arg(position = 4)]
+    pub protocol: MipProtocol,
+}
+
+/// Close socket +MIPCLOSE
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPCLOSE", NoResponse, timeout_ms = 10000)]
+pub struct CloseSocket {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+}
+
+/// Buffer socket data +MIPSEND
+///
+/// Appends hex encoded `data` to the send buffer of `socket`. The data is
+/// only transmitted once flushed with [`PushSocketData`].
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPSEND", SendSocketDataResponse)]
+pub struct SendSocketData<'a> {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+    #[at_arg(position = 1, len = 1024)]
+    pub data: &'a str,
+}
+
+/// Flush socket data +MIPPUSH
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPPUSH", NoResponse, timeout_ms = 10000)]
+pub struct PushSocketData {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+}
+
+/// Read socket data +MIPREAD
+///
+/// Reads up to `length` bytes, hex encoded, from the receive buffer of
+/// `socket`. With `length` 0 only the number of buffered bytes is returned.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPREAD", SocketData)]
+pub struct ReadSocketData {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+    #[at_arg(position = 1)]
+    pub length: usize,
+}
+
+/// Resolve host name +MIPDNS
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPDNS", ResolveHostResponse, timeout_ms = 70000)]
+pub struct ResolveHost<'a> {
+    #[at_arg(position = 0, len = 128)]
+    pub host: &'a str,
+}
+
+/// Import certificate or private key +MIPCERT
+///
+/// Prepares the import of `data_size` bytes under `name`. The data itself is
+/// sent with [`SendCertificateData`] after the `>` prompt.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPCERT", NoResponse)]
+pub struct PrepareCertificateImport<'a> {
+    #[at_arg(position = 0)]
+    pub cert_type: CertificateType,
+    #[at_arg(position = 1, len = 200)]
+    pub name: &'a str,
+    #[at_arg(position = 2)]
+    pub data_size: usize,
+    #[at_arg(position = 3, len = 128)]
+    pub password: Option<&'a str>,
+}
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd(
+    "",
+    CertificateImport,
+    value_sep = false,
+    cmd_prefix = "",
+    termination = "",
+    force_receive_state = true,
+    timeout_ms = 3000
+)]
+pub struct SendCertificateData<'a> {
+    #[at_arg(position = 0, len = 2048)]
+    pub data: &'a atat::serde_bytes::Bytes,
+}
+
+/// TLS configuration +MIPSSLCFG
+///
+/// Sets one parameter of the TLS profile `profile_id`, used by sockets opened
+/// with [`MipProtocol::Tls`].
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+MIPSSLCFG", NoResponse)]
+pub struct SetSslConfig {
+    #[at_arg(position = 0)]
+    pub profile_id: SecurityProfileId,
+    #[at_arg(position = 1)]
+    pub param: SslParam,
+}
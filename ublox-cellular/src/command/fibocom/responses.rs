@@ -0,0 +1,66 @@
+//! Responses for Fibocom TCP/IP Commands
+use super::types::ConnectionStatus;
+use crate::services::data::INGRESS_CHUNK_SIZE;
+use atat::atat_derive::AtatResp;
+use embedded_nal::IpAddr;
+use heapless::String;
+use ublox_sockets::SocketHandle;
+
+/// +MIPCALL?
+#[derive(Clone, AtatResp)]
+pub struct InternetConnection {
+    #[at_arg(position = 0)]
+    pub status: ConnectionStatus,
+    #[at_arg(position = 1, len = 39)]
+    pub ip_addr: Option<IpAddr>,
+}
+
+/// +MIPOPEN
+#[derive(Clone, AtatResp)]
+pub struct OpenSocketResponse {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+    /// 1 if the socket was opened
+    #[at_arg(position = 1)]
+    pub state: u8,
+}
+
+/// +MIPSEND
+#[derive(Clone, AtatResp)]
+pub struct SendSocketDataResponse {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+    /// Free space left in the send buffer
+    #[at_arg(position = 1)]
+    pub free: usize,
+}
+
+/// +MIPREAD
+#[derive(Clone, AtatResp)]
+pub struct SocketData {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+    #[at_arg(position = 1)]
+    pub length: usize,
+    #[at_arg(position = 2)]
+    // Note: Data max length is `INGRESS_CHUNK_SIZE` * 2, due to hex encoding
+    pub data: Option<String<{ INGRESS_CHUNK_SIZE * 2 }>>,
+}
+
+/// +MIPDNS
+#[derive(Clone, AtatResp)]
+pub struct ResolveHostResponse {
+    #[at_arg(position = 0)]
+    pub host: String<128>,
+    #[at_arg(position = 1)]
+    pub ip_addr: String<39>,
+}
+
+/// +MIPCERT
+#[derive(Clone, AtatResp)]
+pub struct CertificateImport {
+    #[at_arg(position = 0)]
+    pub name: String<200>,
+    #[at_arg(position = 1)]
+    pub data_size: usize,
+}
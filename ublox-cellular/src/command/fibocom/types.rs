@@ -0,0 +1,60 @@
+//! Argument and parameter types used by Fibocom TCP/IP Commands and Responses
+use atat::atat_derive::AtatEnum;
+use heapless::String;
+
+#[derive(Clone, PartialEq, Eq, AtatEnum)]
+pub enum ConnectionOperation {
+    /// 0: Deactivate the data connection
+    Disconnect = 0,
+    /// 1: Activate the data connection
+    Connect = 1,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConnectionStatus {
+    /// 0: No data connection
+    Disconnected = 0,
+    /// 1: Data connection active
+    Connected = 1,
+}
+
+#[derive(Clone, PartialEq, Eq, AtatEnum)]
+pub enum MipProtocol {
+    /// 0: TCP
+    Tcp = 0,
+    /// 1: UDP
+    Udp = 1,
+    /// 2: TCP with TLS, using the TLS profile configured by `+MIPSSLCFG`
+    Tls = 2,
+}
+
+#[derive(Clone, PartialEq, Eq, AtatEnum)]
+pub enum CertificateType {
+    /// 0: Trusted root CA certificate
+    TrustedRootCa = 0,
+    /// 1: Client certificate
+    ClientCertificate = 1,
+    /// 2: Client private key
+    ClientPrivateKey = 2,
+}
+
+#[derive(Clone, PartialEq, Eq, AtatEnum)]
+pub enum SslParam {
+    /// 0: Server certificate verification; 0 none, 1 against the trusted root
+    /// CA, 2 against the trusted root CA including the validity date
+    #[at_arg(value = 0)]
+    VerifyLevel(u8),
+    /// 1: Name of the imported trusted root CA certificate
+    #[at_arg(value = 1)]
+    TrustedRootCa(String<200>),
+    /// 2: Name of the imported client certificate
+    #[at_arg(value = 2)]
+    ClientCertificate(String<200>),
+    /// 3: Name of the imported client private key
+    #[at_arg(value = 3)]
+    ClientPrivateKey(String<200>),
+    /// 4: Expected server host name; also sent as SNI when not empty
+    #[at_arg(value = 4)]
+    ServerName(String<128>),
+}
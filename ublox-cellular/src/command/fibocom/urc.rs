@@ -0,0 +1,14 @@
+//! Unsolicited responses for Fibocom TCP/IP Commands
+use atat::atat_derive::AtatResp;
+use ublox_sockets::SocketHandle;
+
+/// +MIPSTAT
+#[derive(Debug, Clone, AtatResp)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SocketStatus {
+    #[at_arg(position = 0)]
+    pub socket: SocketHandle,
+    /// 1 if the connection was closed by the remote, or lost
+    #[at_arg(position = 1)]
+    pub status: u8,
+}
@@ -2,7 +2,7 @@
 use atat::atat_derive::AtatResp;
 use ublox_sockets::SocketHandle;
 
-/// +UUSORD/+UUSORF
+/// +UUSORD/+UUSORF, and the Fibocom +MIPDATA
 #[derive(Debug, Clone, AtatResp)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct SocketDataAvailable {
@@ -5,169 +5,66 @@ use atat::atat_derive::AtatEnum;
 pub enum Functionality {
     /// 0: Sets the MT to minimum functionality (disable both transmit and receive RF
     /// circuits by deactivating both CS and PS services)
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "lara-r6",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, LARA-R6, TOBY-L4, LEON-G1, SARA-G3, SARA-G4, SARA-R5
     Minimum = 0,
 
     /// 1 (factory-programmed value): sets the MT to full functionality, e.g. from airplane
     /// mode or minimum functionality
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "lara-r6",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, LARA-R6, TOBY-L4, LEON-G1, SARA-G3, SARA-G4, SARA-R5
     Full = 1,
 
     /// 4: Disables both transmit and receive RF circuits by deactivating both CS and PS
     /// services and sets the MT into airplane mode. Airplane mode is persistent between
     /// power cycles triggered by +CFUN=16 or +CPWROFF (where supported)
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "lara-r6",
-        feature = "toby-l4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, LARA-R6, TOBY-L4, SARA-R5
     AirplaneMode = 4,
 
     /// 6: Enables the SIM toolkit interface in dedicated mode and fetching of proactive
     /// commands by SIM Application Toolkit from the SIM card
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "lara-r6",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, LARA-R6, TOBY-L4, LEON-G1, SARA-G3, SARA-G4, SARA-R5
     DedicatedMode = 6,
 
     /// 7: Disables the SIM toolkit interface and fetching of proactive commands by
     /// SIM Application Toolkit from the SIM card
-    #[cfg(any(
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, TOBY-L4, LEON-G1, SARA-G3, SARA-G4, SARA-R5
     DisableSimToolkit = 7,
-    #[cfg(any(
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4",
-        feature = "sara-r5",
-    ))]
+    /// Supported by LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, TOBY-L4, LEON-G1, SARA-G3, SARA-G4, SARA-R5
     DisableSimToolkit_ = 8,
 
     /// 9: Enables the SIM toolkit interface in raw mode and fetching of proactive
     /// commands by SIM Application Toolkit from the SIM card
-    #[cfg(any(
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, SARA-R5
     RawMode = 9,
 
     /// 15: MT silent reset (with detach from network and saving of NVM parameters),
     /// without reset of the SIM card
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "lara-r6",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by TOBY-L2, MPCI-L2, LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, LARA-R6, TOBY-L4, LEON-G1, SARA-G3, SARA-G4, SARA-R5
     SilentReset = 15,
 
     /// 16: MT silent reset (with detach from network and saving of NVM parameters), with
     /// reset of the SIM card
-    #[cfg(any(
-        feature = "lisa-u1",
-        feature = "lisa-u2",
-        feature = "sara-u2",
-        feature = "toby-r2",
-        feature = "lara-r2",
-        feature = "lara-r6",
-        feature = "toby-l4",
-        feature = "leon-g1",
-        feature = "sara-g3",
-        feature = "sara-g4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by LISA-U1, LISA-U2, SARA-U2, TOBY-R2, LARA-R2, LARA-R6, TOBY-L4, LEON-G1, SARA-G3, SARA-G4, SARA-R5
     SilentResetWithSimReset = 16,
 
     /// 19: Sets the MT to minimum functionality by deactivating CS and PS services and
     /// the SIM card
-    #[cfg(any(
-        feature = "toby-l2",
-        feature = "mpci-l2",
-        feature = "toby-l4",
-        feature = "sara-r5",
-    ))]
+    ///
+    /// Supported by TOBY-L2, MPCI-L2, TOBY-L4, SARA-R5
     MinimumWithoutSim = 19,
 
     /// 127: Sets the MT in a deep low power state "HALT" (with detach from the network
     /// and saving of the NVM parameters); the only way to wake up the module is a power
     /// cycle or a module reset
-    #[cfg(any(feature = "toby-l2", feature = "mpci-l2", feature = "sara-r5",))]
+    ///
+    /// Supported by TOBY-L2, MPCI-L2, SARA-R5
     Halt = 127,
 }
 
@@ -1,10 +1,13 @@
-//! AT Commands for u-blox cellular module family\
+//! AT Commands for u-blox cellular module family, and the Fibocom TCP/IP
+//! commands in [`fibocom`]\
 //! Following the [u-blox cellular modules AT commands manual](https://www.u-blox.com/sites/default/files/u-blox-CEL_ATCommands_%28UBX-13002752%29.pdf)
 
 pub mod control;
 pub mod device_data_security;
 pub mod device_lock;
 pub mod dns;
+pub mod edrx;
+pub mod fibocom;
 pub mod file_system;
 pub mod general;
 pub mod gpio;
@@ -27,18 +30,8 @@ pub struct AT;
 
 #[derive(Debug, Clone, AtatUrc)]
 pub enum Urc {
-    #[at_urc("+CGEV: NW DETACH")]
-    NetworkDetach,
-    #[at_urc("+CGEV: ME DETACH")]
-    MobileStationDetach,
-    #[at_urc("+CGEV: NW DEACT")]
-    NetworkDeactivate,
-    #[at_urc("+CGEV: ME DEACT")]
-    MobileStationDeactivate,
-    #[at_urc("+CGEV: NW PDN DEACT")]
-    NetworkPDNDeactivate,
-    #[at_urc("+CGEV: ME PDN DEACT")]
-    MobileStationPDNDeactivate,
+    #[at_urc("+CGEV")]
+    PacketDomainEvent(psn::urc::PacketDomainEvent),
 
     #[at_urc("+UUSORD")]
     SocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable),
@@ -51,16 +44,27 @@ pub enum Urc {
     #[at_urc("+UUSOCL")]
     SocketClosed(ip_transport_layer::urc::SocketClosed),
 
+    #[at_urc("+MIPDATA")]
+    MipSocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable),
+    #[at_urc("+MIPSTAT")]
+    MipSocketStatus(fibocom::urc::SocketStatus),
+
     #[at_urc("+UMWI")]
     MessageWaitingIndication(sms::urc::MessageWaitingIndication),
-    // #[at_urc("+CREG")]
-    // NetworkRegistration(network_service::urc::NetworkRegistration),
-    // #[at_urc("+CGREG")]
-    // GPRSNetworkRegistration(psn::urc::GPRSNetworkRegistration),
-    // #[at_urc("+CEREG")]
-    // EPSNetworkRegistration(psn::urc::EPSNetworkRegistration),
+    // The `xREG` URCs are told apart from the read command responses by
+    // `UbloxDigester`
+    #[at_urc("+CREG")]
+    NetworkRegistration(network_service::urc::NetworkRegistration),
+    #[at_urc("+CGREG")]
+    GPRSNetworkRegistration(psn::urc::GPRSNetworkRegistration),
+    #[at_urc("+CEREG")]
+    EPSNetworkRegistration(psn::urc::EPSNetworkRegistration),
     #[at_urc("+UREG")]
     ExtendedPSNetworkRegistration(psn::urc::ExtendedPSNetworkRegistration),
+    #[at_urc("+UUPSMR")]
+    PSMStateChanged(system_features::urc::PSMStateChanged),
+    #[at_urc("+CEDRXP")]
+    EDRXParameters(edrx::urc::EDRXParameters),
 
     #[at_urc("+UUHTTPCR")]
     HttpResponse(http::urc::HttpResponse),
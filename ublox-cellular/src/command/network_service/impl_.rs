@@ -1,5 +1,9 @@
-use super::types::NetworkRegistrationStat;
+use super::{
+    responses::{AvailableOperators, BandMasks, OperatorInfo},
+    types::{BandMaskRat, NetworkRegistrationStat, OperatorStatus, RatAct},
+};
 use crate::network::Error;
+use heapless::String;
 
 impl NetworkRegistrationStat {
     #[must_use]
@@ -14,3 +18,126 @@ impl NetworkRegistrationStat {
         }
     }
 }
+
+impl RatAct {
+    fn from_value(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::Gsm,
+            1 => Self::GsmCompact,
+            2 => Self::Utran,
+            3 => Self::GsmGprsEdge,
+            4 => Self::UtranHspda,
+            5 => Self::UtranHsupa,
+            6 => Self::UtranHspdaHsupa,
+            7 => Self::Lte,
+            8 => Self::EcGsmIot,
+            9 => Self::Eutran,
+            _ => return None,
+        })
+    }
+}
+
+impl OperatorStatus {
+    fn from_value(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::Unknown,
+            1 => Self::Available,
+            2 => Self::Current,
+            3 => Self::Forbidden,
+            _ => return None,
+        })
+    }
+}
+
+/// Split `group` on the commas outside of quotes
+fn fields(group: &str) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    group
+        .split(move |c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ',' && !quoted
+        })
+        .map(str::trim)
+}
+
+fn unquote<const N: usize>(field: Option<&str>) -> Option<String<N>> {
+    let field = field?.strip_prefix('"')?.strip_suffix('"')?;
+    // Truncate overlong names rather than dropping the operator
+    let end = (0..=field.len().min(N))
+        .rev()
+        .find(|&i| field.is_char_boundary(i))
+        .unwrap_or(0);
+    let mut name = String::new();
+    name.push_str(&field[..end]).ok()?;
+    Some(name)
+}
+
+impl OperatorInfo {
+    /// Parse the fields of one operator, e.g. `2,"Telenor","Telenor","23801",7`
+    fn parse(group: &str) -> Option<Self> {
+        let mut fields = fields(group);
+        let status = OperatorStatus::from_value(fields.next()?.parse().ok()?)?;
+        Some(Self {
+            status,
+            long_name: unquote(fields.next())?,
+            short_name: unquote(fields.next())?,
+            numeric: unquote(fields.next())?,
+            act: fields
+                .next()
+                .and_then(|act| act.parse().ok())
+                .and_then(RatAct::from_value),
+        })
+    }
+}
+
+impl AvailableOperators {
+    /// Parse the response to `AT+COPS=?`. The operator list ends at the
+    /// first empty field, followed by the supported modes and formats.
+    pub(crate) fn parse(resp: &[u8]) -> Option<Self> {
+        let resp = core::str::from_utf8(resp).ok()?.trim();
+        let mut rest = resp.strip_prefix("+COPS:").unwrap_or(resp).trim_start();
+
+        let mut list = Self::default();
+        while let Some(group) = rest.strip_prefix('(') {
+            // Names are quoted, and may contain parentheses
+            let mut quoted = false;
+            let end = group.find(|c| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c == ')' && !quoted
+            })?;
+
+            match OperatorInfo::parse(&group[..end]) {
+                Some(operator) => {
+                    list.operators.push(operator).ok();
+                }
+                // The supported modes and formats, e.g. `(0-4)`
+                None => break,
+            }
+
+            rest = group[end + 1..].strip_prefix(',').unwrap_or("");
+        }
+        Some(list)
+    }
+}
+
+impl BandMasks {
+    /// Band masks of `rat`, as bands 1 to 64 and bands 65 to 128
+    #[must_use]
+    pub fn masks(&self, rat: BandMaskRat) -> (u64, u64) {
+        let (cat_m1, cat_nb1) = match (self.field4, self.field5) {
+            // `0,<bitmask1>,<bitmask2>,1,<bitmask1>,<bitmask2>`
+            (Some(bitmask1), Some(bitmask2)) => ((self.field1, self.field2), (bitmask1, bitmask2)),
+            // `0,<bitmask1>,1,<bitmask1>`
+            _ => ((self.field1, 0), (self.field3, 0)),
+        };
+
+        match rat {
+            BandMaskRat::LteCatM1 => cat_m1,
+            BandMaskRat::LteCatNb1 => cat_nb1,
+        }
+    }
+}
@@ -6,11 +6,22 @@ pub mod types;
 pub mod urc;
 
 use super::NoResponse;
-use atat::atat_derive::AtatCmd;
+use atat::{atat_derive::AtatCmd, AtatCmd, InternalError};
+use heapless::Vec;
 use responses::{
-    NetworkRegistrationStatus, OperatorSelection, RadioAccessTechnology, SignalQuality,
+    AvailableOperators, BandMasks, LpwaRadioAccessTechnologies, NetworkRegistrationStatus,
+    OperatorSelection, RadioAccessTechnology, SignalQuality, SignalStrength,
 };
-use types::{NetworkRegistrationStat, NetworkRegistrationUrcConfig, OperatorSelectionMode};
+use types::{NetworkRegistrationStat, NetworkRegistrationUrcConfig, OperatorSelectionMode, RatAct};
+
+/// 7.3 Signal quality +CSQ
+///
+/// Returns the received signal strength indication <rssi> and the channel
+/// bit error rate <qual>, regardless of the Radio Access Technology. Prefer
+/// [`GetSignalQuality`] on the modules supporting it.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CSQ", SignalStrength)]
+pub struct GetSignalStrength;
 
 /// 7.4 Extended signal quality +CESQ
 ///
@@ -57,6 +68,49 @@ pub struct SetOperatorSelection {
 #[at_cmd("+COPS?", OperatorSelection, attempts = 1, timeout_ms = 180000)]
 pub struct GetOperatorSelection;
 
+/// 7.5 Operator selection +COPS, of the operator <oper>, in numeric format
+///
+/// With [`OperatorSelectionMode::ManualAutomatic`], the MT falls back to
+/// automatic selection if the manual selection fails.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+COPS", NoResponse, attempts = 1, timeout_ms = 180000)]
+pub struct SetManualOperatorSelection<'a> {
+    #[at_arg(position = 0)]
+    pub mode: OperatorSelectionMode,
+    #[at_arg(position = 1)]
+    pub format: u8,
+    #[at_arg(position = 2, len = 6)]
+    pub oper: &'a str,
+    #[at_arg(position = 3)]
+    pub act: Option<RatAct>,
+}
+
+/// 7.5 Operator selection +COPS, test command
+///
+/// Scans the network for the available operators, which can take up to a
+/// few minutes. The operators are listed as parenthesized groups, which are
+/// parsed by hand:
+///
+/// ```text
+/// +COPS: (2,"Telenor","Telenor","23801",7),(3,"TDC","TDC","23802",2),,(0-4),(0-2)
+/// ```
+#[derive(Clone)]
+pub struct GetAvailableOperators;
+
+impl AtatCmd<11> for GetAvailableOperators {
+    type Response = AvailableOperators;
+
+    const MAX_TIMEOUT_MS: u32 = 180_000;
+
+    fn as_bytes(&self) -> Vec<u8, 11> {
+        Vec::from_slice(b"AT+COPS=?\r\n").unwrap()
+    }
+
+    fn parse(&self, resp: Result<&[u8], InternalError>) -> Result<Self::Response, atat::Error> {
+        AvailableOperators::parse(resp?).ok_or(atat::Error::Parse)
+    }
+}
+
 /// 7.8 Radio Access Technology (RAT) selection +URAT Forces the selection of
 /// the Radio Access Technology (RAT) in the protocol stack. On the subsequent
 /// network registration (+COPS, +CGATT) the selected RAT is used.
@@ -97,19 +151,23 @@ pub struct GetOperatorSelection;
 ///   means of updating RAT related SIM files, can force RAT usage (see Notes).
 #[derive(Clone, AtatCmd)]
 #[at_cmd("+URAT", NoResponse)]
-#[cfg(not(feature = "lara-r6"))]
 pub struct SetRadioAccessTechnology {
     #[at_arg(position = 0)]
     pub selected_act: types::RadioAccessTechnologySelected,
 }
 
+/// 7.8 Radio Access Technology (RAT) selection +URAT, LARA-R6 form
+///
+/// LARA-R6 takes an ordered list of up to three RATs instead of a single
+/// mode, see [`SetRadioAccessTechnology`] for the other modules.
 #[derive(Clone, AtatCmd)]
 #[at_cmd("+URAT", NoResponse)]
-#[cfg(feature = "lara-r6")]
-pub struct SetRadioAccessTechnology {
+pub struct SetRadioAccessTechnologyList {
     #[at_arg(position = 0)]
     pub first_act: types::FirstRadioAccessTechnology,
+    #[at_arg(position = 1)]
     pub second_act: Option<types::SecondRadioAccessTechnology>,
+    #[at_arg(position = 2)]
     pub third_act: Option<types::ThirdRadioAccessTechnology>,
 }
 
@@ -117,6 +175,47 @@ pub struct SetRadioAccessTechnology {
 #[at_cmd("+URAT?", RadioAccessTechnology)]
 pub struct GetRadioAccessTechnology;
 
+/// 7.8 Radio Access Technology (RAT) selection +URAT, SARA-R4 / SARA-R5 form
+///
+/// SARA-R4 and SARA-R5 take an ordered list of up to three RATs, e.g. LTE Cat
+/// M1 with LTE Cat NB1 as fallback. The setting is stored in NVM, and takes
+/// effect at the next registration.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+URAT", NoResponse)]
+pub struct SetLpwaRadioAccessTechnology {
+    #[at_arg(position = 0)]
+    pub first_act: types::LpwaRadioAccessTechnology,
+    #[at_arg(position = 1)]
+    pub second_act: Option<types::LpwaRadioAccessTechnology>,
+    #[at_arg(position = 2)]
+    pub third_act: Option<types::LpwaRadioAccessTechnology>,
+}
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+URAT?", LpwaRadioAccessTechnologies)]
+pub struct GetLpwaRadioAccessTechnology;
+
+/// Band configuration +UBANDMASK
+///
+/// Selects the LTE bands the module may use for the given RAT, as a bit mask
+/// of bands 1 to 64 (bit 0 being band 1), and of bands 65 to 128 on the
+/// modules supporting them. The setting is stored in NVM, and takes effect
+/// at the next registration. **SARA-R4 / SARA-R5**
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+UBANDMASK", NoResponse)]
+pub struct SetBandMask {
+    #[at_arg(position = 0)]
+    pub rat: types::BandMaskRat,
+    #[at_arg(position = 1)]
+    pub bitmask1: u64,
+    #[at_arg(position = 2)]
+    pub bitmask2: Option<u64>,
+}
+
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+UBANDMASK?", BandMasks)]
+pub struct GetBandMask;
+
 /// 7.14 Network registration status +CREG
 ///
 /// Configures the network registration URC related to CS domain. Depending on the <n> parameter value, a URC
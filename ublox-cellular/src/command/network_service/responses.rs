@@ -1,10 +1,21 @@
 //! Responses for Network service Commands
 use super::types::{
-    NetworkRegistrationStat, NetworkRegistrationUrcConfig, OperatorNameFormat,
-    OperatorSelectionMode, RadioAccessTechnologySelected, RatAct,
+    LpwaRadioAccessTechnology, NetworkRegistrationStat, NetworkRegistrationUrcConfig,
+    OperatorNameFormat, OperatorSelectionMode, OperatorStatus, RadioAccessTechnologySelected,
+    RatAct,
 };
-use atat::atat_derive::AtatResp;
-use heapless::String;
+use atat::{atat_derive::AtatResp, AtatResp};
+use heapless::{String, Vec};
+
+/// 7.3 Signal quality +CSQ
+#[derive(Debug, Clone, AtatResp)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SignalStrength {
+    #[at_arg(position = 0)]
+    pub rssi: u8,
+    #[at_arg(position = 1)]
+    pub qual: u8,
+}
 
 /// 7.4 Extended signal quality +CESQ
 #[derive(Debug, Clone, AtatResp)]
@@ -35,6 +46,28 @@ pub struct OperatorSelection {
     pub act: Option<RatAct>,
 }
 
+/// Operator found by a network scan, see [`AvailableOperators`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OperatorInfo {
+    pub status: OperatorStatus,
+    pub long_name: String<24>,
+    pub short_name: String<10>,
+    /// MCC and MNC, e.g. `"23801"`
+    pub numeric: String<6>,
+    pub act: Option<RatAct>,
+}
+
+/// 7.5 Operator selection +COPS, test command
+///
+/// Operators beyond the capacity of the list are dropped.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AvailableOperators {
+    pub operators: Vec<OperatorInfo, 8>,
+}
+
+impl AtatResp for AvailableOperators {}
+
 /// 7.8 Radio Access Technology (RAT) selection +URAT
 #[derive(Clone, AtatResp)]
 pub struct RadioAccessTechnology {
@@ -42,6 +75,38 @@ pub struct RadioAccessTechnology {
     pub act: RadioAccessTechnologySelected,
 }
 
+/// 7.8 Radio Access Technology (RAT) selection +URAT, SARA-R4 / SARA-R5 form
+#[derive(Debug, Clone, AtatResp)]
+pub struct LpwaRadioAccessTechnologies {
+    #[at_arg(position = 0)]
+    pub first_act: LpwaRadioAccessTechnology,
+    #[at_arg(position = 1)]
+    pub second_act: Option<LpwaRadioAccessTechnology>,
+    #[at_arg(position = 2)]
+    pub third_act: Option<LpwaRadioAccessTechnology>,
+}
+
+/// Band configuration +UBANDMASK
+///
+/// Lists `<rat>,<bitmask1>[,<bitmask2>]` for LTE Cat M1 then LTE Cat NB1.
+/// `<bitmask2>` is only reported by the modules supporting bands above 64,
+/// see [`BandMasks::masks`].
+#[derive(Debug, Clone, AtatResp)]
+pub struct BandMasks {
+    #[at_arg(position = 0)]
+    pub field0: u64,
+    #[at_arg(position = 1)]
+    pub field1: u64,
+    #[at_arg(position = 2)]
+    pub field2: u64,
+    #[at_arg(position = 3)]
+    pub field3: u64,
+    #[at_arg(position = 4)]
+    pub field4: Option<u64>,
+    #[at_arg(position = 5)]
+    pub field5: Option<u64>,
+}
+
 /// 7.14 Network registration status +CREG
 #[derive(Clone, AtatResp)]
 pub struct NetworkRegistrationStatus {
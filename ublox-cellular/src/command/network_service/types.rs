@@ -29,6 +29,20 @@ pub enum OperatorSelectionMode {
     #[at_arg(default)]
     Unknown,
 }
+/// Availability of an operator, as listed by the `+COPS` test command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OperatorStatus {
+    /// • 0: unknown
+    Unknown = 0,
+    /// • 1: available
+    Available = 1,
+    /// • 2: current
+    Current = 2,
+    /// • 3: forbidden
+    Forbidden = 3,
+}
+
 /// Indicates the radio access technology
 #[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -134,12 +148,17 @@ pub enum RadioAccessTechnologySelected {
     /// • 6: UMTS / LTE (dual mode)
     #[at_arg(value = 6)]
     UmtsLte(RatPreferred),
+    /// • 7: LTE Cat M1 (**SARA-R4 / SARA-R5**)
+    #[at_arg(value = 7)]
+    LteCatM1,
+    /// • 8: LTE Cat NB1 (**SARA-R4**)
+    #[at_arg(value = 8)]
+    LteCatNb1,
 }
 
 /// Indicates the radio access technology
 #[derive(Debug, Clone, PartialEq, Eq, AtatEnum)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[cfg(feature = "lara-r6")]
 pub enum FirstRadioAccessTechnology {
     /// • 0: GSM / GPRS / eGPRS (single mode)
     #[at_arg(value = 0)]
@@ -155,7 +174,6 @@ pub enum FirstRadioAccessTechnology {
 /// Indicates the radio access technology
 #[derive(Debug, Clone, PartialEq, Eq, AtatEnum)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[cfg(feature = "lara-r6")]
 pub enum SecondRadioAccessTechnology {
     /// • 2: UMTS (single mode)
     #[at_arg(value = 2)]
@@ -165,13 +183,34 @@ pub enum SecondRadioAccessTechnology {
 /// Indicates the radio access technology
 #[derive(Debug, Clone, PartialEq, Eq, AtatEnum)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-#[cfg(feature = "lara-r6")]
 pub enum ThirdRadioAccessTechnology {
     /// • 0: GSM / GPRS / eGPRS (single mode)
     #[at_arg(value = 0)]
     GsmGprsEGprs,
 }
 
+/// Radio access technology of the `+URAT` list, **SARA-R4 / SARA-R5**
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LpwaRadioAccessTechnology {
+    /// • 7: LTE Cat M1
+    LteCatM1 = 7,
+    /// • 8: LTE Cat NB1
+    LteCatNb1 = 8,
+    /// • 9: GPRS / eGPRS (**SARA-R412M**)
+    GprsEgprs = 9,
+}
+
+/// Radio access technology a band mask applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BandMaskRat {
+    /// • 0: LTE Cat M1
+    LteCatM1 = 0,
+    /// • 1: LTE Cat NB1
+    LteCatNb1 = 1,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, AtatEnum)]
 pub enum OperatorNameFormat {
     #[at_arg(value = 0)]
@@ -0,0 +1,124 @@
+use super::{responses::PDPAddress, types::PacketDomainChange, urc::PacketDomainEvent};
+use crate::network::ContextId;
+use embedded_nal::{IpAddr, Ipv6Addr};
+use heapless::Vec;
+
+impl PDPAddress {
+    /// Addresses of the context, reported either in the colon notation or in
+    /// the dotted decimal one (e.g. `32.1.13.184.0.0.0.0.0.0.0.0.0.0.0.1` for
+    /// `2001:db8::1`). Addresses that do not parse are skipped.
+    pub fn ip_addrs(&self) -> impl Iterator<Item = IpAddr> + '_ {
+        [&self.ip_addr, &self.ip_addr2]
+            .into_iter()
+            .flatten()
+            .filter_map(|addr| parse_addr(addr))
+    }
+}
+
+impl PacketDomainEvent {
+    /// Effect of the event on the PDP contexts. A context is deactivated
+    /// with either of:
+    ///
+    /// ```text
+    /// +CGEV: NW PDN DEACT <cid>
+    /// +CGEV: NW DEACT <p_cid>,<cid>,<event_type>
+    /// +CGEV: NW DEACT <PDP_type>,<PDP_addr>[,<cid>]
+    /// ```
+    ///
+    /// and the same with `ME` when deactivated by the module.
+    pub fn change(&self) -> PacketDomainChange {
+        let event = &self.event[..];
+        if event == b"NW DETACH" || event == b"ME DETACH" {
+            return PacketDomainChange::Detach;
+        }
+
+        if let Some(cid) = strip_any(event, [b"NW PDN DEACT ", b"ME PDN DEACT "]) {
+            PacketDomainChange::Deactivate(parse_cid(cid))
+        } else if let Some(first) = strip_any(event, [b"NW DEACT ", b"ME DEACT "]) {
+            let cid = if parse_cid(first).is_some() {
+                &self.param1
+            } else {
+                &self.param2
+            };
+            PacketDomainChange::Deactivate(cid.as_deref().and_then(parse_cid))
+        } else {
+            PacketDomainChange::Other
+        }
+    }
+}
+
+fn strip_any<'a, const N: usize>(bytes: &'a [u8], prefixes: [&[u8]; N]) -> Option<&'a [u8]> {
+    prefixes
+        .into_iter()
+        .find_map(|prefix| bytes.strip_prefix(prefix))
+}
+
+fn parse_cid(bytes: &[u8]) -> Option<ContextId> {
+    let cid = core::str::from_utf8(bytes).ok()?.trim().parse().ok()?;
+    Some(ContextId(cid))
+}
+
+fn parse_addr(addr: &str) -> Option<IpAddr> {
+    if let Ok(addr) = addr.parse() {
+        return Some(addr);
+    }
+
+    // Dotted decimal IPv6 address, IPv4 addresses already parsing as is
+    let mut octets = Vec::<u8, 16>::new();
+    for octet in addr.split('.') {
+        octets.push(octet.parse().ok()?).ok()?;
+    }
+    let octets: [u8; 16] = octets.into_array().ok()?;
+    Some(IpAddr::V6(Ipv6Addr::from(octets)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use atat::serde_at::de::from_str;
+    use embedded_nal::Ipv4Addr;
+    use PacketDomainChange::Deactivate;
+
+    #[test]
+    fn deactivated_context_is_parsed() {
+        for (urc, change) in [
+            ("+CGEV: NW PDN DEACT 2", Deactivate(Some(ContextId(2)))),
+            ("+CGEV: ME PDN DEACT 1,0", Deactivate(Some(ContextId(1)))),
+            ("+CGEV: NW DEACT 1,3,0", Deactivate(Some(ContextId(3)))),
+            (
+                "+CGEV: ME DEACT \"IP\",\"10.0.0.2\",2",
+                Deactivate(Some(ContextId(2))),
+            ),
+            ("+CGEV: NW DEACT \"IP\",\"10.0.0.2\"", Deactivate(None)),
+            ("+CGEV: NW DETACH", PacketDomainChange::Detach),
+            ("+CGEV: ME PDN ACT 1", PacketDomainChange::Other),
+        ] {
+            let event: PacketDomainEvent = from_str(urc).unwrap();
+            assert_eq!(event.change(), change, "{urc}");
+        }
+    }
+
+    #[test]
+    fn dotted_ipv6_address() {
+        let addr: PDPAddress =
+            from_str("+CGPADDR: 1,\"10.0.0.2\",\"32.1.13.184.0.0.0.0.0.0.0.0.0.0.0.2\"").unwrap();
+
+        let mut addrs = addr.ip_addrs();
+        assert_eq!(addrs.next(), Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))));
+        assert_eq!(
+            addrs.next(),
+            Some(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2)))
+        );
+        assert_eq!(addrs.next(), None);
+    }
+
+    #[test]
+    fn colon_ipv6_address() {
+        let addr: PDPAddress = from_str("+CGPADDR: 1,\"2001:db8::2\"").unwrap();
+
+        assert_eq!(
+            addr.ip_addrs().collect::<std::vec::Vec<_>>(),
+            [IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2))]
+        );
+    }
+}
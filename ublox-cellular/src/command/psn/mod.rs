@@ -21,19 +21,20 @@
 //! instruct the GGSN to route down-link packets onto different `QoS` flows
 //! towards the TE.
 
+mod impl_;
 pub mod responses;
 pub mod types;
 pub mod urc;
 use atat::atat_derive::AtatCmd;
 use responses::{
-    EPSNetworkRegistrationStatus, ExtendedPSNetworkRegistrationStatus, GPRSAttached,
-    GPRSNetworkRegistrationStatus, PDPContextState, PacketSwitchedConfig,
+    DataCounters, EPSNetworkRegistrationStatus, ExtendedPSNetworkRegistrationStatus, GPRSAttached,
+    GPRSNetworkRegistrationStatus, PDPAddress, PDPContextState, PacketSwitchedConfig,
     PacketSwitchedNetworkData,
 };
 use types::{
     AuthenticationType, EPSNetworkRegistrationUrcConfig, ExtendedPSNetworkRegistrationUrcConfig,
     GPRSAttachedState, GPRSNetworkRegistrationUrcConfig, PDPContextStatus, PSEventReportingMode,
-    PacketSwitchedAction, PacketSwitchedNetworkDataParam, PacketSwitchedParam,
+    PSMMode, PacketSwitchedAction, PacketSwitchedNetworkDataParam, PacketSwitchedParam,
     PacketSwitchedParamReq,
 };
 
@@ -266,6 +267,16 @@ pub struct SetPDPContextState {
 #[at_cmd("+CGACT?", heapless::Vec<PDPContextState, 7>, attempts = 1, timeout_ms = 150000, abortable = true)]
 pub struct GetPDPContextState;
 
+/// 18.17 Show PDP address +CGPADDR
+///
+/// Returns the IP address assigned to the PDP context `cid`, if it is active.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CGPADDR", PDPAddress)]
+pub struct GetPDPAddress {
+    #[at_arg(position = 0)]
+    pub cid: ContextId,
+}
+
 /// 18.26 Packet switched event reporting +CGEREP
 ///
 /// Configures sending of URCs from MT to the DTE, in case of certain events
@@ -397,6 +408,32 @@ pub struct SetEPSNetworkRegistrationStatus {
 #[at_cmd("+CEREG?", EPSNetworkRegistrationStatus)]
 pub struct GetEPSNetworkRegistrationStatus;
 
+/// 18.40 Power Saving Mode setting +CPSMS
+///
+/// Controls the setting of the UEs power saving mode (PSM) parameters. The
+/// requested timers are coded as one byte octet strings, see the 3GPP TS
+/// 24.008 \[12\] GPRS Timer 3 (periodic TAU, T3412 extended) and GPRS Timer 2
+/// (active time, T3324) information elements. The values granted by the
+/// network are reported by `+CEREG` with `<n>`=4 or 5.
+///
+/// **NOTES:**
+/// - **SARA-R4 / SARA-R5** - The new setting takes effect at the next
+///   registration or tracking area update.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+CPSMS", NoResponse)]
+pub struct SetPSMSettings<'a> {
+    #[at_arg(position = 0)]
+    pub mode: PSMMode,
+    #[at_arg(position = 1, len = 8)]
+    pub requested_periodic_rau: Option<&'a str>,
+    #[at_arg(position = 2, len = 8)]
+    pub requested_gprs_ready_timer: Option<&'a str>,
+    #[at_arg(position = 3, len = 8)]
+    pub requested_periodic_tau: Option<&'a str>,
+    #[at_arg(position = 4, len = 8)]
+    pub requested_active_time: Option<&'a str>,
+}
+
 /// 18.39 Configure the authentication parameters of a PDP/EPS bearer +UAUTHREQ
 ///
 /// Configures the authentication parameters of a defined PDP/EPS bearer. The
@@ -428,3 +465,27 @@ pub struct SetAuthParameters<'a> {
     #[at_arg(position = 3, len = 64)]
     pub password: &'a str,
 }
+
+/// Read counters of sent or received PSD data +UGCNTRD
+///
+/// Reads the number of bytes sent and received over each active PDP context,
+/// in the current session and in total. The total counters are stored in NVM,
+/// until reset with [`SetDataCounters`].
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+UGCNTRD", heapless::Vec<DataCounters, 8>)]
+pub struct GetDataCounters;
+
+/// Set/reset counter of sent or received PSD data +UGCNTSET
+///
+/// Sets the total counters of bytes sent and received over `cid`, e.g. to 0
+/// to reset them.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+UGCNTSET", NoResponse)]
+pub struct SetDataCounters {
+    #[at_arg(position = 0)]
+    pub cid: ContextId,
+    #[at_arg(position = 1)]
+    pub total_bytes_sent: u64,
+    #[at_arg(position = 2)]
+    pub total_bytes_received: u64,
+}
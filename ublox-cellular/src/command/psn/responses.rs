@@ -62,6 +62,21 @@ pub struct PDPContextState {
     pub status: PDPContextStatus,
 }
 
+/// 18.17 Show PDP address +CGPADDR
+///
+/// IPv6 addresses are reported in the dotted decimal notation unless set
+/// otherwise by `AT+CGPIAF`, see [`PDPAddress::ip_addrs`].
+#[derive(Clone, AtatResp)]
+pub struct PDPAddress {
+    #[at_arg(position = 0)]
+    pub cid: ContextId,
+    #[at_arg(position = 1, len = 63)]
+    pub ip_addr: Option<String<63>>,
+    /// IPv6 address of a dual-stack context, `ip_addr` being its IPv4 one
+    #[at_arg(position = 2, len = 63)]
+    pub ip_addr2: Option<String<63>>,
+}
+
 /// 18.27 GPRS network registration status +CGREG
 #[derive(Clone, AtatResp)]
 pub struct GPRSNetworkRegistrationStatus {
@@ -101,4 +116,30 @@ pub struct EPSNetworkRegistrationStatus {
     pub ci: Option<String<8>>,
     #[at_arg(position = 4)]
     pub act: Option<RatAct>,
+    #[at_arg(position = 5)]
+    pub cause_type: Option<u8>,
+    #[at_arg(position = 6)]
+    pub reject_cause: Option<u8>,
+    /// T3324 assigned by the network, coded as GPRS Timer 2 (`<n>`=4 or 5)
+    #[at_arg(position = 7)]
+    pub active_time: Option<String<8>>,
+    /// T3412 extended assigned by the network, coded as GPRS Timer 3
+    /// (`<n>`=4 or 5)
+    #[at_arg(position = 8)]
+    pub periodic_tau: Option<String<8>>,
+}
+
+/// Read counters of sent or received PSD data +UGCNTRD
+#[derive(Clone, AtatResp)]
+pub struct DataCounters {
+    #[at_arg(position = 0)]
+    pub cid: ContextId,
+    #[at_arg(position = 1)]
+    pub sent_session_bytes: u64,
+    #[at_arg(position = 2)]
+    pub received_session_bytes: u64,
+    #[at_arg(position = 3)]
+    pub sent_total_bytes: u64,
+    #[at_arg(position = 4)]
+    pub received_total_bytes: u64,
 }
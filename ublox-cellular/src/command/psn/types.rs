@@ -379,7 +379,8 @@ pub enum ProtocolType {
     IPv4v6PreferV6Internal = 3,
 }
 
-#[derive(Clone, PartialEq, Eq, AtatEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum AuthenticationType {
     /// (factory-programmed value): none
     None = 0,
@@ -391,6 +392,12 @@ pub enum AuthenticationType {
     Auto = 3,
 }
 
+impl Default for AuthenticationType {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, AtatEnum)]
 pub enum DataCompression {
     /// (factory-programmed value): off
@@ -611,16 +618,20 @@ pub enum EPSNetworkRegistrationUrcConfig {
     /// • 2: network registration and location information URC +CEREG:
     /// <stat>[,[<tac>],[<ci>],[<AcT>]] enabled
     UrcVerbose = 2,
-    // • 3: network registration, location information and EMM cause value
-    // information URC +CEREG:
-    // <stat>[,[<tac>],[<ci>],[<AcT>][,<cause_type>,<reject_cause>]] enabled •
-    // 4: PSM, network registration and location information information URC
-    // +CEREG:
-    // <stat>[,[<tac>],[<ci>],[<AcT>][,,[,[<Assigned_Active_Time>[,<Assigned_Periodic_TAU>]]]]]
-    // enabled • 5: PSM, network registration, location information and EMM
-    // cause value information URC +CEREG:
-    // <stat>[,[<tac>],[<ci>],[<AcT>][,[<cause_type>],[<reject_cause>][,[<Assigned_Active_Time>,[<Assigned_Periodic_TAU>]]]]]
-    // enabled
+    /// • 3: network registration, location information and EMM cause value
+    /// information URC +CEREG:
+    /// <stat>[,[<tac>],[<ci>],[<AcT>][,<cause_type>,<reject_cause>]] enabled
+    UrcVerboseCause = 3,
+    /// • 4: PSM, network registration and location information information
+    /// URC +CEREG:
+    /// <stat>[,[<tac>],[<ci>],[<AcT>][,,[,[<Assigned_Active_Time>[,<Assigned_Periodic_TAU>]]]]]
+    /// enabled
+    UrcPsm = 4,
+    /// • 5: PSM, network registration, location information and EMM cause
+    /// value information URC +CEREG:
+    /// <stat>[,[<tac>],[<ci>],[<AcT>][,[<cause_type>],[<reject_cause>][,[<Assigned_Active_Time>,[<Assigned_Periodic_TAU>]]]]]
+    /// enabled
+    UrcPsmCause = 5,
 }
 
 /// EPS registration status
@@ -645,3 +656,26 @@ pub enum EPSNetworkRegistrationStat {
     /// considered as attached for emergency bearer services)
     AttachedEmergencyOnly = 8,
 }
+
+/// Power Saving Mode (PSM) setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PSMMode {
+    /// • 0 (default value): disable the use of PSM
+    Disabled = 0,
+    /// • 1: enable the use of PSM
+    Enabled = 1,
+}
+
+/// Effect of a `+CGEV` event on the PDP contexts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PacketDomainChange {
+    /// Packet domain detach, by the network or the module
+    Detach,
+    /// Context deactivated, by the network or the module, if the event tells
+    /// which one
+    Deactivate(Option<ContextId>),
+    /// Any other event, leaving the contexts as they are
+    Other,
+}
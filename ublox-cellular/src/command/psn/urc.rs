@@ -3,10 +3,26 @@ use super::types::{
     EPSNetworkRegistrationStat, ExtendedPSNetworkRegistrationState, GPRSNetworkRegistrationStat,
 };
 use crate::{command::network_service::types::RatAct, network::ProfileId};
-use atat::atat_derive::AtatResp;
+use atat::{atat_derive::AtatResp, heapless_bytes::Bytes};
 use embedded_nal::IpAddr;
 use heapless::String;
 
+/// 18.18 Packet domain event reporting +CGEV
+///
+/// The event and its first parameter are received as one field, e.g.
+/// `NW PDN DEACT 1`, see [`PacketDomainEvent::change`].
+#[derive(Debug, Clone, AtatResp)]
+pub struct PacketDomainEvent {
+    #[at_arg(position = 0)]
+    pub event: Bytes<32>,
+    #[at_arg(position = 1)]
+    pub param1: Option<Bytes<64>>,
+    #[at_arg(position = 2)]
+    pub param2: Option<Bytes<8>>,
+    #[at_arg(position = 3)]
+    pub param3: Option<Bytes<8>>,
+}
+
 /// +UUPSDA
 #[derive(Debug, Clone, AtatResp)]
 pub struct DataConnectionActivated {
@@ -60,4 +76,8 @@ pub struct EPSNetworkRegistration {
     pub cause_type: Option<u8>,
     #[at_arg(position = 6)]
     pub reject_cause: Option<u8>,
+    #[at_arg(position = 7)]
+    pub active_time: Option<String<8>>,
+    #[at_arg(position = 8)]
+    pub periodic_tau: Option<String<8>>,
 }
@@ -2,7 +2,8 @@
 use atat::atat_derive::AtatEnum;
 
 /// Indicates the basic message indication type
-#[derive(Debug, Clone, PartialEq, Eq, AtatEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MessageIndicationType {
     /// • 1: Voice Message Waiting (third level method) or Voice Message Waiting on Line 1
     /// (CPHS method)
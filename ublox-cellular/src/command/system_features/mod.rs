@@ -6,9 +6,10 @@
 
 pub mod responses;
 pub mod types;
+pub mod urc;
 use atat::atat_derive::AtatCmd;
 use responses::{FactoryConfiguration, PowerSavingControl};
-use types::{FSFactoryRestoreType, NVMFactoryRestoreType, PowerSavingMode, Seconds};
+use types::{FSFactoryRestoreType, NVMFactoryRestoreType, PSMReporting, PowerSavingMode, Seconds};
 
 use super::NoResponse;
 
@@ -39,6 +40,17 @@ pub struct SetPowerSavingControl {
 #[at_cmd("+UPSV?", PowerSavingControl)]
 pub struct GetPowerSavingControl;
 
+/// 19.31 Power saving mode indication +UPSMR
+///
+/// Enables the +UUPSMR URC, reporting when the module enters or leaves the
+/// Power Saving Mode (PSM) negotiated with +CPSMS.
+#[derive(Clone, AtatCmd)]
+#[at_cmd("+UPSMR", NoResponse)]
+pub struct SetPSMReporting {
+    #[at_arg(position = 0)]
+    pub mode: PSMReporting,
+}
+
 /// 19.25 Restore factory configuration +UFACTORY
 ///
 /// Force, at the next module boot, the restore of the factory configuration for
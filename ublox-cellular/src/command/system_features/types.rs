@@ -2,7 +2,8 @@
 use atat::atat_derive::{AtatEnum, AtatLen};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq, Eq, AtatEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PowerSavingMode {
     /// Disabled: (default and factory-programmed value)
     Disabled = 0,
@@ -57,3 +58,25 @@ pub enum NVMFactoryRestoreType {
     /// • 2: for internal use only
     InternalUseOnly = 2,
 }
+
+/// PSM state reporting mode of +UPSMR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PSMReporting {
+    /// • 0 (factory-programmed value): PSM state URC disabled
+    Disabled = 0,
+    /// • 1: PSM state URC +UUPSMR enabled
+    Enabled = 1,
+}
+
+/// PSM state reported by +UUPSMR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AtatEnum)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PSMState {
+    /// • 0: the module is not in PSM
+    NotInPsm = 0,
+    /// • 1: the module has entered PSM
+    EnteredPsm = 1,
+    /// • 2: the module could not enter PSM, as some activity is ongoing
+    Blocked = 2,
+}
@@ -0,0 +1,10 @@
+//! Unsolicited responses for System features Commands
+use super::types::PSMState;
+use atat::atat_derive::AtatResp;
+
+/// +UUPSMR
+#[derive(Debug, Clone, AtatResp)]
+pub struct PSMStateChanged {
+    #[at_arg(position = 0)]
+    pub state: PSMState,
+}
@@ -1,5 +1,16 @@
+use embassy_time::Duration;
 use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
 
+use crate::{
+    command::{
+        control::types::BaudRate, network_service::types::LpwaRadioAccessTechnology,
+        system_features::types::PowerSavingMode,
+    },
+    event::Event,
+    radio::BandMask,
+    sim::SimSelect,
+};
+
 pub struct NoPin;
 
 impl ErrorType for NoPin {
@@ -30,11 +41,112 @@ pub trait CellularConfig {
     type ResetPin: OutputPin;
     type PowerPin: OutputPin;
     type VintPin: InputPin;
+    /// Line driven with [`take_dtr_pin`]. Configurations not driving DTR or
+    /// RTS set it to [`NoPin`], with `type DtrPin = NoPin;`, and keep the
+    /// default [`take_dtr_pin`].
+    ///
+    /// [`take_dtr_pin`]: CellularConfig::take_dtr_pin
+    type DtrPin: OutputPin;
 
     const FLOW_CONTROL: bool = false;
     const HEX_MODE: bool = true;
 
+    /// Rates probed, in order, when the module does not respond at the
+    /// current rate of the serial port. Requires [`set_baud_rate`].
+    ///
+    /// [`set_baud_rate`]: CellularConfig::set_baud_rate
+    const BAUD_RATES: &'static [BaudRate] = &[
+        BaudRate::B115200,
+        BaudRate::B921600,
+        BaudRate::B460800,
+        BaudRate::B230400,
+        BaudRate::B9600,
+    ];
+    /// Rate switched to with `AT+IPR` once the module responds, e.g. when
+    /// it boots at its default rate. `None` keeps the rate it responded at.
+    const BAUD_RATE: Option<BaudRate> = None;
+
+    /// Preferred operators, in numeric format (MCC and MNC, e.g. `"23801"`),
+    /// tried in order when registering, before falling back to automatic
+    /// operator selection. Each one is selected in manual/automatic mode,
+    /// so the module may still register elsewhere, and the next one is
+    /// tried when registration is stuck.
+    const OPERATORS: &'static [&'static str] = &[];
+
+    /// PIN unlocking the SIM during `initialize`. It is given only once: if
+    /// the SIM rejects it, `initialize` fails with [`Error::SimPin`] without
+    /// retrying, so as not to use up the attempts left before the PUK.
+    ///
+    /// [`Error::SimPin`]: crate::error::Error::SimPin
+    const SIM_PIN: Option<&'static str> = None;
+    /// How the SIM slot is selected by `initialize`, the primary slot being
+    /// selected first
+    const SIM_SELECT: SimSelect = SimSelect::Default;
+    /// Switch to the other SIM slot when the active SIM is denied
+    /// registration, or is not registered within this timeout. Only used
+    /// with [`SimSelect::Gpio`]. Should be shorter than the 3 minutes after
+    /// which the module is reset, which keeps the active slot.
+    const SIM_FAILOVER_TIMEOUT: Option<Duration> = None;
+
+    /// Radio Access Technologies selected with `AT+URAT`, in order of
+    /// preference, e.g. LTE Cat M1 with LTE Cat NB1 as fallback. Empty keeps
+    /// the module's selection. **SARA-R4 / SARA-R5**
+    const RADIO_ACCESS_TECHNOLOGIES: &'static [LpwaRadioAccessTechnology] = &[];
+    /// LTE bands enabled with `AT+UBANDMASK`, per Radio Access Technology.
+    /// Empty keeps the module's bands. **SARA-R4 / SARA-R5**
+    const BAND_MASKS: &'static [BandMask] = &[];
+
+    /// Cap, in bytes, on the data sent and received over all the contexts,
+    /// as totalled by the module until reset with
+    /// [`DataService::reset_data_usage`]. Sends are refused once it is
+    /// reached, with [`Exhausted`] from the `embedded-nal` stacks.
+    ///
+    /// The totals are read at most every [`BUDGET_REFRESH_INTERVAL`], the
+    /// bytes going through the sockets being counted in between, so the
+    /// budget may be exceeded by the protocol overhead of that interval.
+    /// Modules not counting the data, e.g. the Fibocom ones, are only
+    /// budgeted the bytes going through the sockets, counted from the
+    /// creation of the driver, without the protocol overhead.
+    ///
+    /// [`DataService::reset_data_usage`]: crate::DataService::reset_data_usage
+    /// [`Exhausted`]: ublox_sockets::Error::Exhausted
+    /// [`BUDGET_REFRESH_INTERVAL`]: crate::BUDGET_REFRESH_INTERVAL
+    const DATA_BUDGET: Option<u64> = None;
+
+    /// UART power saving applied with `AT+UPSV` (u-blox modules only).
+    /// [`CtrlByDtr`] and [`CtrlByRts`] need the line from [`take_dtr_pin`];
+    /// [`CtrlByRts`] also needs [`FLOW_CONTROL`] off. `initialize` fails
+    /// with [`Error::InvalidPowerSaving`] otherwise.
+    ///
+    /// [`CtrlByDtr`]: PowerSavingMode::CtrlByDtr
+    /// [`CtrlByRts`]: PowerSavingMode::CtrlByRts
+    /// [`take_dtr_pin`]: CellularConfig::take_dtr_pin
+    /// [`FLOW_CONTROL`]: CellularConfig::FLOW_CONTROL
+    /// [`Error::InvalidPowerSaving`]: crate::error::Error::InvalidPowerSaving
+    const POWER_SAVING: PowerSavingMode = PowerSavingMode::Disabled;
+    /// Idle time of the AT interface after which the module's UART may
+    /// sleep
+    const POWER_SAVING_IDLE: Duration = Duration::from_secs(5);
+
     fn reset_pin(&mut self) -> Option<&mut Self::ResetPin>;
     fn power_pin(&mut self) -> Option<&mut Self::PowerPin>;
     fn vint_pin(&mut self) -> Option<&mut Self::VintPin>;
+    /// DTR output, or RTS output with [`PowerSavingMode::CtrlByRts`], kept
+    /// ON (low) while the module is sent commands. Taken once, when creating
+    /// the driver. None by default, for boards not wiring the line to the
+    /// host.
+    fn take_dtr_pin(&mut self) -> Option<Self::DtrPin> {
+        None
+    }
+
+    /// Reconfigure the host side of the serial port to `baud_rate`. Returns
+    /// `false` if the port can't be reconfigured, disabling baud rate
+    /// detection and switching.
+    fn set_baud_rate(&mut self, _baud_rate: BaudRate) -> bool {
+        false
+    }
+
+    /// Called with each [`Event`] of the driver, while spinning the device.
+    /// Must not block.
+    fn on_event(&mut self, _event: Event) {}
 }
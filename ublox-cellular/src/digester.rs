@@ -0,0 +1,122 @@
+//! Digester telling `+CxREG` URCs apart from read command responses
+//!
+//! The network registration URCs share their prefix with the responses to
+//! `AT+CREG?`, `AT+CGREG?` and `AT+CEREG?`. [`atat::DefaultDigester`] matches
+//! URCs first, so a response would be taken for a URC, and the read command
+//! would time out. The response however starts with two integers,
+//! `<n>,<stat>`, where the URC starts with `<stat>`, followed by a quoted
+//! location if anything:
+//!
+//! ```text
+//! +CREG: 2,1,"4E54","0141FD35",7    AT+CREG? response
+//! +CREG: 1,"4E54","0141FD35",7      URC
+//! ```
+//!
+//! The modules echo the commands by default, so the echo of the read command
+//! is skipped before telling the line apart.
+
+use atat::{digest::ParseError, AtDigester, DigestResult, Digester, Parser};
+
+use crate::command::Urc;
+
+const REGISTRATION_PREFIXES: [&[u8]; 3] = [b"+CREG:", b"+CGREG:", b"+CEREG:"];
+
+/// URC parser matching nothing, to digest the lines known to be responses
+struct NoUrc;
+
+impl Parser for NoUrc {
+    fn parse(_buf: &[u8]) -> Result<(&[u8], usize), ParseError> {
+        Err(ParseError::NoMatch)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RegistrationLine {
+    Response,
+    Urc,
+    /// The line has not been fully received yet
+    Incomplete,
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+fn is_integer(field: Option<&[u8]>) -> bool {
+    field.map_or(false, |field| {
+        let field = trim_start(field);
+        !field.is_empty() && field.iter().all(u8::is_ascii_digit)
+    })
+}
+
+/// Skip the echoed command, `AT...\r`, at the start of `buf`, if any
+fn skip_echo(buf: &[u8]) -> &[u8] {
+    let line = trim_start(buf);
+    if !line
+        .get(..2)
+        .map_or(false, |at| at.eq_ignore_ascii_case(b"AT"))
+    {
+        return line;
+    }
+    let end = line
+        .iter()
+        .position(|&b| b == b'\r' || b == b'\n')
+        .unwrap_or(line.len());
+    trim_start(&line[end..])
+}
+
+/// Classify the `+CxREG` line at the start of `buf`, if any, following the
+/// echo of the command
+fn registration_line(buf: &[u8]) -> Option<RegistrationLine> {
+    let line = skip_echo(buf);
+    let params = REGISTRATION_PREFIXES
+        .iter()
+        .find_map(|prefix| line.strip_prefix(*prefix))?;
+
+    let Some(end) = params.iter().position(|&b| b == b'\r' || b == b'\n') else {
+        return Some(RegistrationLine::Incomplete);
+    };
+
+    let mut fields = params[..end].split(|&b| b == b',');
+    if is_integer(fields.next()) && is_integer(fields.next()) {
+        Some(RegistrationLine::Response)
+    } else {
+        Some(RegistrationLine::Urc)
+    }
+}
+
+/// [`Digester`] of the u-blox AT interface, see the [module
+/// documentation](self)
+pub struct UbloxDigester {
+    inner: AtDigester<Urc>,
+    responses: AtDigester<NoUrc>,
+}
+
+impl UbloxDigester {
+    pub fn new() -> Self {
+        Self {
+            inner: AtDigester::new(),
+            responses: AtDigester::new(),
+        }
+    }
+}
+
+impl Default for UbloxDigester {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Digester for UbloxDigester {
+    fn digest<'a>(&mut self, buf: &'a [u8]) -> (DigestResult<'a>, usize) {
+        match registration_line(buf) {
+            Some(RegistrationLine::Response) => self.responses.digest(buf),
+            Some(RegistrationLine::Incomplete) => (DigestResult::None, 0),
+            Some(RegistrationLine::Urc) | None => self.inner.digest(buf),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! Extended discontinuous reception (eDRX)
+//!
+//! With eDRX, the module only listens for paging once per eDRX cycle, for the
+//! duration of the paging time window, staying reachable with much lower
+//! power consumption than regular DRX. Unlike [PSM](crate::PsmTimers), no
+//! tracking area update is needed to receive mobile terminated data.
+//!
+//! Only E-UTRAN is supported: LTE-M ([`EDRXAccessTechnology::EutranWbS1`])
+//! and NB-IoT ([`EDRXAccessTechnology::EutranNbS1`]). The network decides on
+//! the values actually used, reported by the `+CEDRXP` URC, see
+//! [`Device::edrx`].
+
+use crate::{
+    client::Device,
+    command::{
+        edrx::{
+            types::{EDRXAccessTechnology, EDRXMode},
+            GetEDRXDynamicParameters, SetEDRXSettings,
+        },
+        network_service::{
+            responses::LpwaRadioAccessTechnologies,
+            types::{LpwaRadioAccessTechnology, RadioAccessTechnologySelected},
+            GetLpwaRadioAccessTechnology, GetRadioAccessTechnology,
+        },
+    },
+    config::CellularConfig,
+    error::Error,
+    module::{ModuleKind, Vendor},
+};
+use atat::blocking::AtatClient;
+use core::fmt::Write as _;
+use embassy_time::Duration;
+use heapless::String;
+
+/// E-UTRAN eDRX cycle lengths in milliseconds, indexed by their code
+const EUTRAN_CYCLES_MS: [u64; 16] = [
+    5_120, 10_240, 20_480, 40_960, 61_440, 81_920, 102_400, 122_880, 143_360, 163_840, 327_680,
+    655_360, 1_310_720, 2_621_440, 5_242_880, 10_485_760,
+];
+
+/// eDRX parameters of an access technology, as requested to or provided by
+/// the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EdrxParameters {
+    pub act: EDRXAccessTechnology,
+    /// eDRX cycle length
+    pub cycle: Duration,
+    /// Time the module listens for paging at the start of each cycle. Left
+    /// to the network when `None`.
+    pub paging_time_window: Option<Duration>,
+}
+
+fn cycle_supported(act: EDRXAccessTechnology, code: usize) -> bool {
+    match act {
+        EDRXAccessTechnology::EutranWbS1 => code <= 0b1101,
+        EDRXAccessTechnology::EutranNbS1 => {
+            matches!(code, 0b0010 | 0b0011 | 0b0101 | 0b1001..=0b1111)
+        }
+        _ => false,
+    }
+}
+
+/// Paging time window unit in milliseconds
+fn paging_time_window_step(act: EDRXAccessTechnology) -> Option<u64> {
+    match act {
+        EDRXAccessTechnology::EutranWbS1 => Some(1_280),
+        EDRXAccessTechnology::EutranNbS1 => Some(2_560),
+        _ => None,
+    }
+}
+
+fn nibble(value: usize) -> String<4> {
+    let mut bits = String::new();
+    write!(bits, "{value:04b}").ok();
+    bits
+}
+
+/// Encode the shortest eDRX cycle supported by `act` lasting at least
+/// `cycle`
+pub(crate) fn encode_cycle(act: EDRXAccessTechnology, cycle: Duration) -> Option<String<4>> {
+    let ms = cycle.as_millis();
+    EUTRAN_CYCLES_MS
+        .iter()
+        .enumerate()
+        .find(|&(code, &len)| len >= ms && cycle_supported(act, code))
+        .map(|(code, _)| nibble(code))
+}
+
+/// Encode the shortest paging time window lasting at least `window`
+pub(crate) fn encode_paging_time_window(
+    act: EDRXAccessTechnology,
+    window: Duration,
+) -> Option<String<4>> {
+    let step = paging_time_window_step(act)?;
+    let value = ((window.as_millis() + step - 1) / step).max(1) - 1;
+    (value <= 0b1111).then(|| nibble(value as usize))
+}
+
+fn decode_nibble(bits: &str) -> Option<usize> {
+    if bits.len() != 4 {
+        return None;
+    }
+    usize::from_str_radix(bits, 2).ok()
+}
+
+/// Decode the eDRX parameters provided by the network, as reported by
+/// `+CEDRXP` and `+CEDRXRDP`. Returns `None` if eDRX is not in use.
+pub(crate) fn decode_parameters(
+    act: EDRXAccessTechnology,
+    cycle: Option<&str>,
+    paging_time_window: Option<&str>,
+) -> Option<EdrxParameters> {
+    let code = cycle.and_then(decode_nibble)?;
+    if !cycle_supported(act, code) {
+        return None;
+    }
+
+    let paging_time_window = paging_time_window
+        .and_then(decode_nibble)
+        .zip(paging_time_window_step(act))
+        .map(|(value, step)| Duration::from_millis((value as u64 + 1) * step));
+
+    Some(EdrxParameters {
+        act,
+        cycle: Duration::from_millis(EUTRAN_CYCLES_MS[code]),
+        paging_time_window,
+    })
+}
+
+/// How the access technology of an eDRX request is checked against the
+/// `+URAT` selection of the module
+pub(crate) enum RatCheck {
+    /// Ordered `+URAT` list of SARA-R4 / SARA-R5, any of them being usable
+    Lpwa,
+    /// `+URAT` selection of the other u-blox modules
+    Selected,
+    /// No `+URAT`, the module refusing the eDRX settings it does not support
+    Unchecked,
+}
+
+impl RatCheck {
+    pub(crate) fn for_module(module: ModuleKind) -> Self {
+        match module {
+            ModuleKind::SaraR4 | ModuleKind::SaraR5 => Self::Lpwa,
+            _ if module.vendor() == Vendor::Ublox => Self::Selected,
+            _ => Self::Unchecked,
+        }
+    }
+}
+
+/// Whether `act` is among the access technologies of the `+URAT` list,
+/// fallbacks included
+pub(crate) fn lpwa_rat_selected(
+    rats: &LpwaRadioAccessTechnologies,
+    act: EDRXAccessTechnology,
+) -> bool {
+    let rat = match act {
+        EDRXAccessTechnology::EutranWbS1 => LpwaRadioAccessTechnology::LteCatM1,
+        EDRXAccessTechnology::EutranNbS1 => LpwaRadioAccessTechnology::LteCatNb1,
+        _ => return false,
+    };
+    [Some(rats.first_act), rats.second_act, rats.third_act].contains(&Some(rat))
+}
+
+/// Whether `act` is among the access technologies selected with `+URAT`
+pub(crate) fn rat_selected(rat: &RadioAccessTechnologySelected, act: EDRXAccessTechnology) -> bool {
+    use RadioAccessTechnologySelected as Rat;
+
+    match act {
+        EDRXAccessTechnology::EutranWbS1 => matches!(
+            rat,
+            Rat::Lte | Rat::GsmUmtsLte(..) | Rat::GsmLte(_) | Rat::UmtsLte(_) | Rat::LteCatM1
+        ),
+        EDRXAccessTechnology::EutranNbS1 => matches!(rat, Rat::LteCatNb1),
+        _ => false,
+    }
+}
+
+/// Encode the eDRX cycle and paging time window requested by `params`
+pub(crate) fn encode_parameters(
+    params: &EdrxParameters,
+) -> Result<(String<4>, Option<String<4>>), Error> {
+    let cycle = encode_cycle(params.act, params.cycle).ok_or(Error::InvalidEdrx)?;
+    let paging_time_window = params
+        .paging_time_window
+        .map(|window| encode_paging_time_window(params.act, window).ok_or(Error::InvalidEdrx))
+        .transpose()?;
+    Ok((cycle, paging_time_window))
+}
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Request eDRX with the given parameters, which are rounded up to the
+    /// closest values supported by the access technology, and subscribe to
+    /// the eDRX parameters provided by the network.
+    ///
+    /// Fails with [`Error::InvalidEdrx`] if the access technology is not
+    /// selected (`+URAT`, as the preferred one or as a fallback), or the
+    /// values are out of its range.
+    pub fn set_edrx(&mut self, params: &EdrxParameters) -> Result<(), Error> {
+        let selected = match RatCheck::for_module(self.network.module) {
+            RatCheck::Lpwa => {
+                let rats = self.send_at(&GetLpwaRadioAccessTechnology)?;
+                lpwa_rat_selected(&rats, params.act)
+            }
+            RatCheck::Selected => {
+                let rat = self.send_at(&GetRadioAccessTechnology)?;
+                rat_selected(&rat.act, params.act)
+            }
+            RatCheck::Unchecked => true,
+        };
+        if !selected {
+            return Err(Error::InvalidEdrx);
+        }
+
+        let (cycle, paging_time_window) = encode_parameters(params)?;
+        self.send_at(&SetEDRXSettings {
+            mode: EDRXMode::EnabledWithUrc,
+            act_type: params.act,
+            requested_edrx_value: Some(&cycle),
+            requested_paging_time_window: paging_time_window.as_deref(),
+        })?;
+        Ok(())
+    }
+
+    /// Disable eDRX on `act`
+    pub fn disable_edrx(&mut self, act: EDRXAccessTechnology) -> Result<(), Error> {
+        self.send_at(&SetEDRXSettings {
+            mode: EDRXMode::Disabled,
+            act_type: act,
+            requested_edrx_value: None,
+            requested_paging_time_window: None,
+        })?;
+
+        let status = &mut self.network.status;
+        if status.edrx.map_or(false, |edrx| edrx.act == act) {
+            status.edrx = None;
+        }
+        Ok(())
+    }
+
+    /// Read the eDRX parameters provided by the network for the serving
+    /// cell, if eDRX is in use.
+    pub fn read_edrx(&mut self) -> Result<Option<EdrxParameters>, Error> {
+        let params = self.send_at(&GetEDRXDynamicParameters)?;
+        let edrx = decode_parameters(
+            params.act_type,
+            params.nw_provided_edrx_value.as_deref(),
+            params.paging_time_window.as_deref(),
+        );
+
+        self.network.status.edrx = edrx;
+        Ok(edrx)
+    }
+
+    /// eDRX parameters last provided by the network, as reported by the
+    /// `+CEDRXP` URC
+    pub fn edrx(&self) -> Option<EdrxParameters> {
+        self.network.status.edrx
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "async")]
+    use crate::test_support::{async_registered_device, block_on};
+    use crate::test_support::{registered_device, MockConfig, MockModem};
+
+    const LTE_M: EdrxParameters = EdrxParameters {
+        act: EDRXAccessTechnology::EutranWbS1,
+        cycle: Duration::from_secs(80),
+        paging_time_window: Some(Duration::from_secs(5)),
+    };
+
+    const NB_IOT: EdrxParameters = EdrxParameters {
+        act: EDRXAccessTechnology::EutranNbS1,
+        ..LTE_M
+    };
+
+    /// Values of `+CEDRXP: 4,"0101","0010","0001"`
+    const NETWORK_PROVIDED: EdrxParameters = EdrxParameters {
+        act: EDRXAccessTechnology::EutranWbS1,
+        cycle: Duration::from_millis(20_480),
+        paging_time_window: Some(Duration::from_millis(2_560)),
+    };
+
+    #[test]
+    fn edrx_is_validated_against_selected_rat() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "SARA-R510M8S", MockConfig::new());
+
+        // Rounded up to a 81.92 seconds cycle and a 5.12 seconds window
+        modem
+            .expect("AT+URAT?", "+URAT: 7,8")
+            .expect("AT+CEDRXS=2,4,\"0101\",\"0011\"", "");
+        device.set_edrx(&LTE_M).unwrap();
+
+        // NB-IoT is selected as a fallback
+        modem
+            .expect("AT+URAT?", "+URAT: 7,8")
+            .expect("AT+CEDRXS=2,5,\"0101\",\"0001\"", "");
+        device.set_edrx(&NB_IOT).unwrap();
+
+        // LTE-M is not selected on an NB-IoT only module
+        modem.expect("AT+URAT?", "+URAT: 8");
+        assert_eq!(device.set_edrx(&LTE_M), Err(Error::InvalidEdrx));
+        modem.assert_done();
+
+        // The network provided values are tracked from the URC
+        modem.urc("+CEDRXP: 4,\"0101\",\"0010\",\"0001\"");
+        modem.flush_urcs();
+        assert!(device.spin().is_ok());
+        assert_eq!(device.edrx(), Some(NETWORK_PROVIDED));
+    }
+
+    #[test]
+    fn edrx_is_not_validated_without_urat() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "L610", MockConfig::new());
+
+        modem.expect("AT+CEDRXS=2,4,\"0101\",\"0011\"", "");
+        assert_eq!(device.set_edrx(&LTE_M), Ok(()));
+        modem.assert_done();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_edrx_is_validated_against_selected_rat() {
+        let modem = MockModem::new();
+        let mut device = async_registered_device(&modem, "SARA-R510M8S", MockConfig::new());
+
+        modem
+            .expect("AT+URAT?", "+URAT: 7,8")
+            .expect("AT+CEDRXS=2,5,\"0101\",\"0001\"", "")
+            .expect("AT+URAT?", "+URAT: 8");
+        assert_eq!(block_on(device.set_edrx(&NB_IOT)), Ok(()));
+        assert_eq!(block_on(device.set_edrx(&LTE_M)), Err(Error::InvalidEdrx));
+        modem.assert_done();
+
+        modem.urc("+CEDRXP: 4,\"0101\",\"0010\",\"0001\"");
+        modem.flush_urcs();
+        assert!(block_on(device.spin()).is_ok());
+        assert_eq!(device.edrx(), Some(NETWORK_PROVIDED));
+    }
+}
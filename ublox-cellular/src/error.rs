@@ -17,6 +17,22 @@ pub enum Error {
     Busy,
     Uninitialized,
     StateTimeout,
+    /// eDRX values not supported by the requested access technology, or the
+    /// access technology is not selected
+    InvalidEdrx,
+    /// The SIM requires a PIN, and none is configured or the configured one
+    /// was rejected
+    SimPin,
+    /// The SIM is blocked and requires the PUK, see
+    /// [`unblock_pin`](crate::GsmClient::unblock_pin)
+    SimPuk,
+    /// The SIM or the module is locked by another facility than the SIM PIN
+    SimLocked,
+    /// [`CellularConfig::POWER_SAVING`] is controlled by a line the host
+    /// does not drive, or by RTS with hardware flow control on
+    ///
+    /// [`CellularConfig::POWER_SAVING`]: crate::CellularConfig::POWER_SAVING
+    InvalidPowerSaving,
 
     // Network errors
     Network(NetworkError),
@@ -38,6 +54,11 @@ impl defmt::Format for Error {
             Self::Busy => defmt::write!(f, "Busy"),
             Self::Uninitialized => defmt::write!(f, "Uninitialized"),
             Self::StateTimeout => defmt::write!(f, "StateTimeout"),
+            Self::InvalidEdrx => defmt::write!(f, "InvalidEdrx"),
+            Self::SimPin => defmt::write!(f, "SimPin"),
+            Self::SimPuk => defmt::write!(f, "SimPuk"),
+            Self::SimLocked => defmt::write!(f, "SimLocked"),
+            Self::InvalidPowerSaving => defmt::write!(f, "InvalidPowerSaving"),
             Self::Network(e) => defmt::write!(f, "Network({:?})", e),
             Self::DataService(e) => defmt::write!(f, "DataService({:?})", e),
             Self::Generic(e) => defmt::write!(f, "Generic({:?})", e),
@@ -0,0 +1,200 @@
+//! Driver events delivered to the application
+//!
+//! Events are handed to [`CellularConfig::on_event`] while spinning the
+//! device. They are derived from URCs and from changes of the state tracked
+//! by the driver, and are only informative: the driver handles them on its
+//! own, before the application is notified.
+//!
+//! [`CellularConfig::on_event`]: crate::CellularConfig::on_event
+
+use crate::{
+    command::{
+        fibocom, http, ip_transport_layer, psn::types::PacketDomainChange, sms,
+        sms::types::MessageIndicationType, Urc,
+    },
+    network::ContextId,
+    registration::ConnectionState,
+    services::data::{
+        context::{Contexts, MAX_CONTEXTS},
+        ContextState,
+    },
+    sim::SimSlot,
+};
+use heapless::Vec;
+use ublox_sockets::SocketHandle;
+
+/// Event of the driver, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// Registered to the network
+    Registered,
+    /// Registration to the network lost
+    Deregistered,
+    /// Packet domain detach, reported by `+CGEV`
+    Detached,
+    /// PDP context deactivated, reported by `+CGEV`
+    ContextDeactivated(ContextId),
+    /// Data connection over the context lost. It is reactivated by the next
+    /// [`data_service`](crate::GsmClient::data_service) call for it.
+    DataConnectionLost(ContextId),
+    /// Socket closed by the remote peer
+    SocketClosed(SocketHandle),
+    /// Data received on a socket, `length` bytes in total are available
+    DataAvailable { socket: SocketHandle, length: usize },
+    /// Result of an HTTP request, `+UUHTTPCR`
+    HttpResult {
+        profile_id: u8,
+        command: u8,
+        success: bool,
+    },
+    /// Message waiting indication, `+UMWI`
+    MessageWaiting(MessageIndicationType),
+    /// Switched to the other SIM slot, the active SIM having failed to
+    /// register. The module is initialized again with the next spin.
+    SimSlotSwitched(SimSlot),
+}
+
+/// Event reported by `urc`, if any
+pub(crate) fn urc_event(urc: &Urc) -> Option<Event> {
+    let event = match urc {
+        Urc::PacketDomainEvent(event) => match event.change() {
+            PacketDomainChange::Detach => Event::Detached,
+            PacketDomainChange::Deactivate(Some(cid)) => Event::ContextDeactivated(cid),
+            PacketDomainChange::Deactivate(None) | PacketDomainChange::Other => return None,
+        },
+        Urc::SocketClosed(ip_transport_layer::urc::SocketClosed { socket })
+        | Urc::MipSocketStatus(fibocom::urc::SocketStatus { socket, status: 1 }) => {
+            Event::SocketClosed(*socket)
+        }
+        Urc::SocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable {
+            socket,
+            length,
+        })
+        | Urc::SocketDataAvailableUDP(ip_transport_layer::urc::SocketDataAvailable {
+            socket,
+            length,
+        })
+        | Urc::MipSocketDataAvailable(ip_transport_layer::urc::SocketDataAvailable {
+            socket,
+            length,
+        }) => Event::DataAvailable {
+            socket: *socket,
+            length: *length,
+        },
+        Urc::HttpResponse(http::urc::HttpResponse {
+            profile_id,
+            http_command,
+            http_result,
+        }) => Event::HttpResult {
+            profile_id: *profile_id,
+            command: *http_command,
+            success: *http_result == 1,
+        },
+        Urc::MessageWaitingIndication(sms::urc::MessageWaitingIndication { code, .. }) => {
+            Event::MessageWaiting(*code)
+        }
+        _ => return None,
+    };
+    Some(event)
+}
+
+/// Registration and data connection state, as last reported to the
+/// application
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ReportedState {
+    connection: ConnectionState,
+    contexts: Vec<(ContextId, ContextState), MAX_CONTEXTS>,
+}
+
+impl ReportedState {
+    pub(crate) fn new(connection: ConnectionState, contexts: &Contexts) -> Self {
+        Self {
+            connection,
+            contexts: contexts.states().collect(),
+        }
+    }
+
+    /// Record the current state, and report the events leading to it
+    pub(crate) fn update(
+        &mut self,
+        connection: ConnectionState,
+        contexts: &Contexts,
+        mut report: impl FnMut(Event),
+    ) {
+        let connected = |state| state == ConnectionState::Connected;
+        match (connected(self.connection), connected(connection)) {
+            (false, true) => report(Event::Registered),
+            (true, false) => report(Event::Deregistered),
+            _ => {}
+        }
+
+        for (cid, state) in contexts.states() {
+            let was_active = self
+                .contexts
+                .iter()
+                .any(|&(c, s)| c == cid && s == ContextState::Active);
+            if was_active && state != ContextState::Active {
+                report(Event::DataConnectionLost(cid));
+            }
+        }
+
+        *self = Self::new(connection, contexts);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        test_support::{
+            connected_tcp_socket, expect_context_activation, expect_initialize, new_device,
+            MockConfig, MockModem, L, N,
+        },
+        APNInfo,
+    };
+    use ublox_sockets::SocketSet;
+
+    #[test]
+    fn events_are_reported_to_the_application() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        expect_context_activation(&modem);
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let sockets = Box::leak(Box::new(SocketSet::<N, L>::new()));
+        let handle = connected_tcp_socket(sockets);
+        device.set_socket_storage(sockets);
+
+        let apn = APNInfo::new("em");
+        assert!(device.data_service(&apn).is_ok());
+        assert_eq!(device.config.events, [Event::Registered]);
+
+        modem
+            .urc("+UUSOCL: 0")
+            .urc("+UUPSDD: 1")
+            .urc("+UUHTTPCR: 0,1,1")
+            .flush_urcs();
+
+        // One URC is handled per subscriber per spin
+        device.spin().ok();
+        device.spin().ok();
+        device.spin().ok();
+
+        assert_eq!(
+            device.config.events,
+            [
+                Event::Registered,
+                Event::SocketClosed(handle),
+                Event::DataConnectionLost(ContextId(1)),
+                Event::HttpResult {
+                    profile_id: 0,
+                    command: 1,
+                    success: true,
+                },
+            ]
+        );
+        modem.assert_done();
+    }
+}
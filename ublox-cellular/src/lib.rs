@@ -1,4 +1,9 @@
 #![cfg_attr(not(test), no_std)]
+#![cfg_attr(feature = "async", allow(incomplete_features))]
+#![cfg_attr(
+    feature = "async",
+    feature(async_fn_in_trait, impl_trait_projections)
+)]
 
 //! # U-blox cellular
 //!
@@ -7,9 +12,14 @@
 //!
 //! ## Example
 //!
-//! By default following features are enabled: `toby-r2`, `socket-udp`, `socket-tcp`.
+//! By default following features are enabled: `socket-udp`, `socket-tcp`.
 //!
-//! An example to use a different modem with only enabling TCP support:
+//! The attached module is detected at runtime from its model identification
+//! (see [`ModuleKind`]), so a single build supports every module. The module
+//! features (`sara-r5`, `lara-r6`, ...) only select the [`ModuleKind`] assumed
+//! until the module has been identified, or when its model is not recognized.
+//!
+//! An example to default to a different modem with only enabling TCP support:
 //!
 //! ```toml
 //! ublox-cellular-rs = { version = "0.4", default-features = false, features = ["sara-g3", "socket-tcp"] }
@@ -75,19 +85,43 @@
 //! [instant]: ../fugit/instant/struct.Instant.html
 //!
 
+#[cfg(feature = "test-support")]
+extern crate std;
+
 // This mod MUST go first, so that the others see its macros.
 pub(crate) mod fmt;
 
+#[cfg(feature = "async")]
+pub mod asynch;
 mod blocking_timer;
 mod client;
+#[cfg(feature = "cmux")]
+pub mod cmux;
 pub mod command;
 mod config;
+mod digester;
+mod edrx;
+mod event;
 pub mod error;
+mod module;
 mod module_timing;
 mod network;
 mod power;
+pub mod ppp;
+mod power_saving;
+mod psm;
+mod radio;
 mod registration;
 mod services;
+mod setup;
+mod signal;
+mod sim;
+mod status;
+
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+#[cfg(test)]
+mod tests;
 
 pub use atat::serde_bytes;
 use client::{URC_CAPACITY, URC_SUBSCRIBERS};
@@ -96,10 +130,23 @@ pub use ublox_sockets as sockets;
 
 pub use client::Device as GsmClient;
 pub use config::NoPin;
+pub use digester::UbloxDigester;
+pub use edrx::EdrxParameters;
+pub use event::Event;
+pub use module::{ModuleKind, Vendor};
 pub use network::{ContextId, ProfileId};
-pub use services::data::apn::{APNInfo, Apn};
+pub use power_saving::PowerSavingClient;
+pub use psm::PsmTimers;
+pub use radio::BandMask;
+pub use registration::{ConnectionState, Status as RegistrationStatus};
+pub use services::data::apn::{APNInfo, Apn, PdpType};
+pub use services::data::context::{PdpContext, MAX_CONTEXTS};
 pub use services::data::ssl::SecurityProfileId;
-pub use services::data::DataService;
+pub use services::data::{ContextState, DataService};
+pub use services::data::usage::{ContextUsage, DataUsage, BUDGET_REFRESH_INTERVAL};
+pub use signal::SignalMetrics;
+pub use sim::{SimSelect, SimSlot};
+pub use status::{DomainStatus, NetworkStatus};
 
 // Re-export atat
 pub use atat;
@@ -109,7 +156,7 @@ pub type UbloxCellularBuffers<const INGRESS_BUF_SIZE: usize> =
 
 pub type UbloxCellularIngress<'a, const INGRESS_BUF_SIZE: usize> = atat::Ingress<
     'a,
-    atat::DefaultDigester<Urc>,
+    UbloxDigester,
     Urc,
     INGRESS_BUF_SIZE,
     URC_CAPACITY,
@@ -0,0 +1,176 @@
+//! Runtime detection of the attached module
+//!
+//! The module is identified from the `+CGMM` response while setting up the
+//! AT interface. Until then, or if the model is not recognized, the kind
+//! selected through the module cargo features is used.
+
+use crate::command::mobile_control::types::{Functionality, ResetMode};
+
+/// Module family, selecting the module specific behaviour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ModuleKind {
+    LaraR2,
+    LaraR6,
+    LeonG1,
+    LisaU1,
+    LisaU2,
+    MpciL2,
+    SaraG3,
+    SaraG4,
+    SaraR4,
+    SaraR5,
+    SaraU1,
+    SaraU2,
+    TobyL2,
+    TobyL4,
+    TobyR2,
+    /// Fibocom module, driven through the `+MIPxxx` TCP/IP commands
+    Fibocom,
+    /// Module not recognized, and no module feature enabled
+    Unknown,
+}
+
+impl ModuleKind {
+    /// Identify the module from its model identification, e.g. `SARA-R510M8S`
+    pub fn from_model_id(model: &[u8]) -> Option<Self> {
+        const PREFIXES: [(&[u8], ModuleKind); 19] = [
+            (b"LARA-R2", ModuleKind::LaraR2),
+            (b"LARA-R6", ModuleKind::LaraR6),
+            (b"LEON-G1", ModuleKind::LeonG1),
+            (b"LISA-U1", ModuleKind::LisaU1),
+            (b"LISA-U2", ModuleKind::LisaU2),
+            (b"MPCI-L2", ModuleKind::MpciL2),
+            (b"SARA-G3", ModuleKind::SaraG3),
+            (b"SARA-G4", ModuleKind::SaraG4),
+            (b"SARA-R4", ModuleKind::SaraR4),
+            (b"SARA-R5", ModuleKind::SaraR5),
+            (b"SARA-U1", ModuleKind::SaraU1),
+            (b"SARA-U2", ModuleKind::SaraU2),
+            (b"TOBY-L2", ModuleKind::TobyL2),
+            (b"TOBY-L4", ModuleKind::TobyL4),
+            (b"TOBY-R2", ModuleKind::TobyR2),
+            (b"L610", ModuleKind::Fibocom),
+            (b"L716", ModuleKind::Fibocom),
+            (b"FG621", ModuleKind::Fibocom),
+            (b"FIBOCOM", ModuleKind::Fibocom),
+        ];
+
+        PREFIXES
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix))
+            .map(|(_, kind)| *kind)
+    }
+
+    /// Module kind selected through cargo features, used until the module has
+    /// been identified.
+    pub const fn from_features() -> Self {
+        if cfg!(feature = "lara-r2") {
+            Self::LaraR2
+        } else if cfg!(feature = "lara-r6") {
+            Self::LaraR6
+        } else if cfg!(feature = "leon-g1") {
+            Self::LeonG1
+        } else if cfg!(feature = "lisa-u2") {
+            Self::LisaU2
+        } else if cfg!(feature = "mpci-l2") {
+            Self::MpciL2
+        } else if cfg!(feature = "sara-g3") {
+            Self::SaraG3
+        } else if cfg!(feature = "sara-g4") {
+            Self::SaraG4
+        } else if cfg!(feature = "sara-r5") {
+            Self::SaraR5
+        } else if cfg!(feature = "sara-u1") {
+            Self::SaraU1
+        } else if cfg!(feature = "sara-u2") {
+            Self::SaraU2
+        } else if cfg!(feature = "toby-l2") {
+            Self::TobyL2
+        } else if cfg!(feature = "toby-r2") {
+            Self::TobyR2
+        } else if cfg!(feature = "toby-l4") {
+            Self::TobyL4
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Vendor of the module, selecting the AT command dialect
+    pub fn vendor(self) -> Vendor {
+        match self {
+            Self::Fibocom => Vendor::Fibocom,
+            _ => Vendor::Ublox,
+        }
+    }
+
+    /// Whether PDP contexts are activated through the `+UPSD` internal
+    /// profiles, rather than `+CGACT`.
+    ///
+    /// Can be forced for every module with the `upsd-context-activation`
+    /// feature.
+    pub fn upsd_context_activation(self) -> bool {
+        cfg!(feature = "upsd-context-activation") || matches!(self, Self::SaraR5 | Self::SaraU2)
+    }
+
+    /// Whether the `+UPSD` profile has to be mapped to a `+CGDCONT` context
+    /// when activating through `+CGACT`.
+    pub(crate) fn maps_psd_profile(self) -> bool {
+        !matches!(self, Self::SaraR4 | Self::LaraR6)
+    }
+
+    /// `<rst>` parameter to send along `+CFUN=<fun>`.
+    ///
+    /// SARA-R5 only accepts it when `<fun>` is 1, 4 or 19.
+    pub(crate) fn cfun_reset_mode(self, fun: &Functionality) -> Option<ResetMode> {
+        let supported = matches!(
+            fun,
+            Functionality::Full | Functionality::AirplaneMode | Functionality::MinimumWithoutSim
+        );
+
+        if self == Self::SaraR5 && !supported {
+            None
+        } else {
+            Some(ResetMode::DontReset)
+        }
+    }
+}
+
+/// Module vendor, selecting the AT command dialect of the data service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Vendor {
+    Ublox,
+    Fibocom,
+}
+
+impl Default for ModuleKind {
+    fn default() -> Self {
+        Self::from_features()
+    }
+}
+
+/// Select the module kind from the `+CGMM` and `+CGMR` responses, falling
+/// back to [`ModuleKind::from_features`] for unknown models.
+pub(crate) fn detect_module(model: &[u8], firmware: &[u8]) -> ModuleKind {
+    let model_str = core::str::from_utf8(model).unwrap_or("?");
+    let firmware_str = core::str::from_utf8(firmware).unwrap_or("?");
+
+    match ModuleKind::from_model_id(model) {
+        Some(module) => {
+            info!(
+                "Detected {:?} (model {}, firmware {})",
+                module, model_str, firmware_str
+            );
+            module
+        }
+        None => {
+            let module = ModuleKind::from_features();
+            warn!(
+                "Unknown module model {} (firmware {}), assuming {:?}",
+                model_str, firmware_str, module
+            );
+            module
+        }
+    }
+}
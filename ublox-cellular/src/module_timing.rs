@@ -1,47 +1,45 @@
-#![allow(clippy::if_same_then_else)]
-
 use embassy_time::Duration;
 
+use crate::module::ModuleKind;
+
+/// Duration of a GSM TDMA frame, the unit of some module timeouts
+pub const GSM_FRAME: Duration = Duration::from_micros(4615);
+
 /// Low time of `PWR_ON` pin to trigger module switch on from power off mode
-pub fn pwr_on_time() -> Duration {
-    if cfg!(feature = "lara-r6") {
-        Duration::from_millis(150)
-    } else if cfg!(feature = "toby-r2") {
-        Duration::from_micros(50)
-    } else {
-        Duration::from_micros(50)
+pub fn pwr_on_time(module: ModuleKind) -> Duration {
+    match module {
+        ModuleKind::LaraR6 => Duration::from_millis(150),
+        _ => Duration::from_micros(50),
     }
 }
 
 /// Low time of `PWR_ON` pin to trigger module graceful switch off
-pub fn pwr_off_time() -> Duration {
-    if cfg!(feature = "lara-r6") {
-        Duration::from_millis(1500)
-    } else if cfg!(feature = "toby-r2") {
-        Duration::from_secs(1)
-    } else {
-        Duration::from_secs(1)
+pub fn pwr_off_time(module: ModuleKind) -> Duration {
+    match module {
+        ModuleKind::LaraR6 => Duration::from_millis(1500),
+        _ => Duration::from_secs(1),
     }
 }
 
 /// Low time of `RESET_N` pin to trigger module reset (reboot)
-pub fn reset_time() -> Duration {
-    if cfg!(feature = "lara-r6") {
-        Duration::from_millis(10)
-    } else if cfg!(feature = "toby-r2") {
-        Duration::from_millis(50)
-    } else {
-        Duration::from_millis(50)
+pub fn reset_time(module: ModuleKind) -> Duration {
+    match module {
+        ModuleKind::LaraR6 => Duration::from_millis(10),
+        _ => Duration::from_millis(50),
     }
 }
 
 /// Low time of `RESET_N` pin to trigger module abrupt emergency switch off
 ///
 /// NOTE: Not all modules support this operation from `RESET_N`
-pub fn kill_time() -> Option<Duration> {
-    if cfg!(feature = "lara-r6") {
-        Some(Duration::from_secs(10))
-    } else {
-        None
+pub fn kill_time(module: ModuleKind) -> Option<Duration> {
+    match module {
+        ModuleKind::LaraR6 => Some(Duration::from_secs(10)),
+        _ => None,
     }
 }
+
+/// Time for the module to switch to a new rate after answering `AT+IPR`
+pub fn baud_rate_switch_time() -> Duration {
+    Duration::from_millis(100)
+}
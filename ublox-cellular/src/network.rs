@@ -1,23 +1,32 @@
 use crate::{
     client::{URC_CAPACITY, URC_SUBSCRIBERS},
     command::{
+        edrx,
         general::GetCIMI,
         mobile_control::{
             types::{Functionality, ResetMode},
             GetExtendedErrorReport, SetModuleFunctionality,
         },
         network_service::{
-            types::OperatorSelectionMode, GetNetworkRegistrationStatus, SetOperatorSelection,
+            types::OperatorSelectionMode, GetNetworkRegistrationStatus, SetManualOperatorSelection,
+            SetOperatorSelection,
         },
         psn::{
-            self, types::PDPContextStatus, GetEPSNetworkRegistrationStatus,
-            GetGPRSNetworkRegistrationStatus, GetPDPContextState, SetPDPContextState,
+            self,
+            types::{PDPContextStatus, PacketDomainChange},
+            GetEPSNetworkRegistrationStatus, GetGPRSNetworkRegistrationStatus, GetPDPContextState,
+            SetPDPContextState,
         },
+        system_features::{self, types::PSMState},
         Urc, AT,
     },
     error::GenericError,
+    module::ModuleKind,
     registration::{self, ConnectionState, RegistrationState},
-    services::data::{ContextState, PROFILE_ID},
+    services::data::{
+        context::{Contexts, PdpContext},
+        ContextState,
+    },
 };
 use atat::{atat_derive::AtatLen, blocking::AtatClient, UrcSubscription};
 use embassy_time::{Duration, Instant};
@@ -50,7 +59,7 @@ pub struct ContextId(pub u8);
 pub struct AtTx<'sub, AtCl> {
     consecutive_timeouts: u8,
     urc_subscription: UrcSubscription<'sub, Urc, URC_CAPACITY, URC_SUBSCRIBERS>,
-    client: AtCl,
+    pub(crate) client: AtCl,
 }
 
 impl<'sub, AtCl: AtatClient> AtTx<'sub, AtCl> {
@@ -128,7 +137,9 @@ impl<'sub, AtCl: AtatClient> AtTx<'sub, AtCl> {
 
 pub struct Network<'sub, AtCl> {
     pub(crate) status: RegistrationState,
-    pub(crate) context_state: ContextState,
+    /// State of the PDP contexts of the data connections
+    pub(crate) contexts: Contexts,
+    pub(crate) module: ModuleKind,
     pub(crate) at_tx: AtTx<'sub, AtCl>,
 }
 
@@ -139,11 +150,17 @@ where
     pub(crate) fn new(at_tx: AtTx<'sub, AtCl>) -> Self {
         Self {
             status: RegistrationState::new(),
-            context_state: ContextState::Setup,
+            contexts: Contexts::new(),
+            module: ModuleKind::from_features(),
             at_tx,
         }
     }
 
+    /// Module kind the driver is operating with
+    pub fn module(&self) -> ModuleKind {
+        self.module
+    }
+
     pub fn is_connected(&self) -> Result<bool, Error> {
         Ok(matches!(self.status.conn_state, ConnectionState::Connected))
     }
@@ -162,6 +179,13 @@ where
         }
 
         self.handle_urc().ok(); // Ignore errors
+
+        // The module is unreachable while sleeping in PSM, but stays
+        // registered. Resume polling once it wakes up.
+        if self.status.psm_sleep {
+            return Ok(());
+        }
+
         self.check_registration_state();
         self.intervene_registration()?;
         self.check_running_imsi().ok(); // Ignore errors
@@ -202,6 +226,35 @@ where
         Ok(())
     }
 
+    /// Select the next preferred operator, or let the module select one
+    /// automatically once they have all been tried
+    pub(crate) fn select_operator(&mut self, check_urc: bool) -> Result<(), Error> {
+        match self.status.next_operator() {
+            Some(oper) => {
+                debug!("Selecting operator {}", oper);
+                self.send_internal(
+                    &SetManualOperatorSelection {
+                        mode: OperatorSelectionMode::ManualAutomatic,
+                        format: 2,
+                        oper,
+                        act: None,
+                    },
+                    check_urc,
+                )?;
+            }
+            None => {
+                self.send_internal(
+                    &SetOperatorSelection {
+                        mode: OperatorSelectionMode::Automatic,
+                        format: Some(2),
+                    },
+                    check_urc,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn check_running_imsi(&mut self) -> Result<(), Error> {
         // Check current IMSI if registered successfully in which case
         // imsi_check_time will be `None`, else if not registered, check after
@@ -226,7 +279,7 @@ where
             // follow up the command with an AT/OK check and subsequent
             // checkParser() call to catch/address any modem parsing issues.
             match self.send_internal(&GetCIMI, false) {
-                Ok(_) => {}
+                Ok(cimi) => self.status.imsi = Some(cimi.imsi),
                 Err(_) => {
                     self.send_internal(&AT, false)?;
                 }
@@ -238,23 +291,7 @@ where
     }
 
     pub fn check_registration_state(&mut self) {
-        // Don't do anything if we are actually disconnected by choice
-        if self.status.conn_state == ConnectionState::Disconnected {
-            return;
-        }
-
-        // If both (CSD + PSD) is registered, or EPS is registered, we are connected!
-        if (self.status.csd.registered() && self.status.psd.registered())
-            || self.status.eps.registered()
-        {
-            self.status.set_connection_state(ConnectionState::Connected);
-        } else if self.status.conn_state == ConnectionState::Connected {
-            // FIXME: potentially go back into connecting state only when getting into
-            // a 'sticky' non-registered state
-            self.status.reset();
-            self.status
-                .set_connection_state(ConnectionState::Connecting);
-        }
+        self.status.update_connection_state();
     }
 
     pub fn intervene_registration(&mut self) -> Result<(), Error> {
@@ -282,14 +319,7 @@ where
                 self.status.registration_interventions =
                     self.status.registration_interventions.saturating_add(1);
 
-                self.send_internal(
-                    &SetOperatorSelection {
-                        mode: OperatorSelectionMode::Automatic,
-                        format: Some(2),
-                    },
-                    false,
-                )
-                .ok(); // Ignore result
+                self.select_operator(false).ok(); // Ignore result
                 return Ok(());
 
             // If (EPS + CSD) is denied registration
@@ -308,11 +338,7 @@ where
                 self.send_internal(
                     &SetModuleFunctionality {
                         fun: Functionality::Minimum,
-                        // SARA-R5 This parameter can be used only when <fun> is 1, 4 or 19
-                        #[cfg(feature = "sara-r5")]
-                        rst: None,
-                        #[cfg(not(feature = "sara-r5"))]
-                        rst: Some(ResetMode::DontReset),
+                        rst: self.module.cfun_reset_mode(&Functionality::Minimum),
                     },
                     false,
                 )?;
@@ -349,11 +375,7 @@ where
             self.send_internal(
                 &SetModuleFunctionality {
                     fun: Functionality::Minimum,
-                    // SARA-R5 This parameter can be used only when <fun> is 1, 4 or 19
-                    #[cfg(feature = "sara-r5")]
-                    rst: None,
-                    #[cfg(not(feature = "sara-r5"))]
-                    rst: Some(ResetMode::DontReset),
+                    rst: self.module.cfun_reset_mode(&Functionality::Minimum),
                 },
                 false,
             )?;
@@ -398,13 +420,7 @@ where
                 self.status.psd.reset();
                 self.status.eps.reset();
                 warn!("GPRS attach failed, try PLMN reselection");
-                self.send_internal(
-                    &SetOperatorSelection {
-                        mode: OperatorSelectionMode::Automatic,
-                        format: Some(2),
-                    },
-                    true,
-                )?;
+                self.select_operator(true)?;
             }
         }
 
@@ -430,79 +446,10 @@ where
     }
 
     pub(crate) fn handle_urc(&mut self) -> Result<(), Error> {
-        // TODO: How to do this cleaner?
-        let mut ctx_state = self.context_state;
-        // let mut new_reg_params: Option<RegistrationParams> = None;
-
-        self.at_tx.handle_urc(|urc| {
-            match urc {
-                Urc::NetworkDetach => {
-                    warn!("Network Detach URC!");
-                }
-                Urc::MobileStationDetach => {
-                    warn!("ME Detach URC!");
-                }
-                Urc::NetworkDeactivate => {
-                    warn!("Network Deactivate URC!");
-                }
-                Urc::MobileStationDeactivate => {
-                    warn!("ME Deactivate URC!");
-                }
-                Urc::NetworkPDNDeactivate => {
-                    warn!("Network PDN Deactivate URC!");
-                }
-                Urc::MobileStationPDNDeactivate => {
-                    warn!("ME PDN Deactivate URC!");
-                }
-                Urc::ExtendedPSNetworkRegistration(psn::urc::ExtendedPSNetworkRegistration {
-                    state,
-                }) => {
-                    info!("[URC] ExtendedPSNetworkRegistration {:?}", state);
-                }
-                // FIXME: Currently `atat` is unable to distinguish `xREG` family of
-                // commands from URC's
-
-                // Urc::GPRSNetworkRegistration(reg_params) => {
-                //     new_reg_params.replace(reg_params.into());
-                // }
-                // Urc::EPSNetworkRegistration(reg_params) => {
-                //     new_reg_params.replace(reg_params.into());
-                // }
-                // Urc::NetworkRegistration(reg_params) => {
-                //     new_reg_params.replace(reg_params.into());
-                // }
-                Urc::DataConnectionActivated(psn::urc::DataConnectionActivated {
-                    result,
-                    ip_addr: _,
-                }) => {
-                    info!("[URC] DataConnectionActivated {}", result);
-                    if result == 0 {
-                        ctx_state = ContextState::Active;
-                    } else {
-                        ctx_state = ContextState::Setup;
-                    }
-                }
-                Urc::DataConnectionDeactivated(psn::urc::DataConnectionDeactivated {
-                    profile_id,
-                }) => {
-                    info!("[URC] DataConnectionDeactivated {:?}", profile_id);
-                    if profile_id == PROFILE_ID {
-                        ctx_state = ContextState::Activating;
-                    }
-                }
-                Urc::MessageWaitingIndication(_) => {
-                    info!("[URC] MessageWaitingIndication");
-                }
-                _ => return false,
-            };
-            true
-        })?;
-
-        // if let Some(reg_params) = new_reg_params {
-        //     self.status.compare_and_set(reg_params)
-        // }
-
-        self.context_state = ctx_state;
+        let status = &mut self.status;
+        let contexts = &mut self.contexts;
+        self.at_tx
+            .handle_urc(|urc| handle_network_urc(urc, status, contexts))?;
         Ok(())
     }
 
@@ -523,3 +470,96 @@ where
         self.at_tx.send(req)
     }
 }
+
+/// Apply a network related URC to the driver state.
+///
+/// Shared between the blocking and async network handlers. Returns `false` if
+/// the URC was not handled.
+pub(crate) fn handle_network_urc(
+    urc: Urc,
+    status: &mut RegistrationState,
+    contexts: &mut Contexts,
+) -> bool {
+    match urc {
+        Urc::PacketDomainEvent(event) => match event.change() {
+            PacketDomainChange::Detach => {
+                warn!("Packet domain Detach URC!");
+                contexts.deactivate_all();
+            }
+            PacketDomainChange::Deactivate(Some(cid)) => {
+                warn!("Context {:?} Deactivate URC!", cid);
+                contexts.deactivate(cid);
+            }
+            // The event does not tell which context was deactivated: every
+            // active context is checked again
+            PacketDomainChange::Deactivate(None) => {
+                warn!("Deactivate URC!");
+                contexts.deactivate_all();
+            }
+            PacketDomainChange::Other => {}
+        },
+        Urc::ExtendedPSNetworkRegistration(psn::urc::ExtendedPSNetworkRegistration { state }) => {
+            info!("[URC] ExtendedPSNetworkRegistration {:?}", state);
+        }
+        Urc::NetworkRegistration(reg_params) => {
+            status.compare_and_set(reg_params.into());
+        }
+        Urc::GPRSNetworkRegistration(reg_params) => {
+            status.compare_and_set(reg_params.into());
+        }
+        Urc::EPSNetworkRegistration(reg_params) => {
+            status.compare_and_set(reg_params.into());
+        }
+        Urc::DataConnectionActivated(psn::urc::DataConnectionActivated {
+            result,
+            ip_addr: addr,
+        }) => {
+            info!("[URC] DataConnectionActivated {}", result);
+            // Only one `AT+UPSDA` action is pending at a time
+            let profile_id = contexts
+                .activating_profile
+                .take()
+                .unwrap_or(PdpContext::DEFAULT.profile_id);
+            if let Some(cid) = contexts.by_profile(profile_id) {
+                if result == 0 {
+                    contexts.set_active(cid, addr);
+                } else {
+                    contexts.activation_failed(cid);
+                }
+            }
+        }
+        Urc::DataConnectionDeactivated(psn::urc::DataConnectionDeactivated { profile_id }) => {
+            info!("[URC] DataConnectionDeactivated {:?}", profile_id);
+            if let Some(cid) = contexts.by_profile(profile_id) {
+                contexts.set_state(cid, ContextState::Activating);
+            }
+        }
+        Urc::MessageWaitingIndication(_) => {
+            info!("[URC] MessageWaitingIndication");
+        }
+        Urc::PSMStateChanged(system_features::urc::PSMStateChanged { state }) => {
+            info!("[URC] PSMStateChanged {:?}", state);
+            let sleeping = state == PSMState::EnteredPsm;
+            if status.psm_sleep && !sleeping {
+                // Check the registration right away after waking up
+                status.reg_check_time = None;
+            }
+            status.psm_sleep = sleeping;
+        }
+        Urc::EDRXParameters(edrx::urc::EDRXParameters {
+            act_type,
+            requested_edrx_value: _,
+            nw_provided_edrx_value,
+            paging_time_window,
+        }) => {
+            info!("[URC] EDRXParameters {:?}", act_type);
+            status.edrx = crate::edrx::decode_parameters(
+                act_type,
+                nw_provided_edrx_value.as_deref(),
+                paging_time_window.as_deref(),
+            );
+        }
+        _ => return false,
+    };
+    true
+}
@@ -6,19 +6,17 @@ use crate::{
     blocking_timer::BlockingTimer,
     client::Device,
     command::{
-        mobile_control::{
-            types::{Functionality, ResetMode},
-            ModuleSwitchOff, SetModuleFunctionality,
-        },
+        control::SetDataRate,
+        mobile_control::{types::Functionality, ModuleSwitchOff, SetModuleFunctionality},
         system_features::{
-            types::{FSFactoryRestoreType, NVMFactoryRestoreType},
-            SetFactoryConfiguration,
+            types::{FSFactoryRestoreType, NVMFactoryRestoreType, PowerSavingMode, Seconds},
+            SetFactoryConfiguration, SetPowerSavingControl,
         },
         AT,
     },
     config::CellularConfig,
     error::{Error, GenericError},
-    module_timing::{pwr_off_time, pwr_on_time, reset_time},
+    module_timing::{baud_rate_switch_time, pwr_off_time, pwr_on_time, reset_time, GSM_FRAME},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -52,6 +50,73 @@ where
         Err(error)
     }
 
+    /// Find the rate the module responds at, probing
+    /// [`CellularConfig::BAUD_RATES`].
+    pub(crate) fn detect_baud_rate(&mut self) -> Result<(), Error> {
+        for &baud_rate in Config::BAUD_RATES {
+            if !self.config.set_baud_rate(baud_rate) {
+                break;
+            }
+
+            if self.is_alive(2).is_ok() {
+                info!("Module responding at {:?}", baud_rate);
+                self.baud_rate = Some(baud_rate);
+                return Ok(());
+            }
+        }
+        Err(Error::BaudDetection)
+    }
+
+    /// Switch the module, and then the host, to
+    /// [`CellularConfig::BAUD_RATE`], if not running at it already.
+    pub(crate) fn switch_baud_rate(&mut self) -> Result<(), Error> {
+        let Some(baud_rate) = Config::BAUD_RATE else {
+            return Ok(());
+        };
+        if self.baud_rate == Some(baud_rate) {
+            return Ok(());
+        }
+
+        debug!("Switching to {:?}", baud_rate);
+        self.network
+            .send_internal(&SetDataRate { rate: baud_rate }, false)?;
+
+        // The module switches rate after sending the final result code
+        BlockingTimer::after(baud_rate_switch_time()).wait();
+        if !self.config.set_baud_rate(baud_rate) {
+            return Err(Error::BaudDetection);
+        }
+        self.is_alive(2).map_err(|_| Error::BaudDetection)?;
+
+        self.baud_rate = Some(baud_rate);
+        Ok(())
+    }
+
+    /// Apply [`CellularConfig::POWER_SAVING`] with `AT+UPSV`. Fails with
+    /// [`Error::InvalidPowerSaving`], without sending anything, when the mode
+    /// can't be used with this configuration.
+    pub(crate) fn set_power_saving(&mut self) -> Result<(), Error> {
+        let mode = Config::POWER_SAVING;
+        self.network
+            .at_tx
+            .client
+            .check_mode(mode, Config::FLOW_CONTROL)?;
+
+        // Drive the DTR/RTS line from now on, so it is ON by the time the
+        // module starts observing it
+        self.network.at_tx.client.set_mode(mode);
+
+        // The UART idle timeout, in GSM frames, only applies to cyclic power
+        // saving
+        let timeout = (mode == PowerSavingMode::Enabled).then(|| {
+            let frames = Config::POWER_SAVING_IDLE.as_micros() / GSM_FRAME.as_micros();
+            Seconds(frames.clamp(40, 65000) as u32)
+        });
+        self.network
+            .send_internal(&SetPowerSavingControl { mode, timeout }, false)?;
+        Ok(())
+    }
+
     /// Perform at full factory reset of the module, clearing all NVM sectors in the process
     pub fn factory_reset(&mut self) -> Result<(), Error> {
         self.network.send_internal(
@@ -83,18 +148,10 @@ where
         } else {
             Functionality::SilentReset
         };
+        let rst = self.network.module.cfun_reset_mode(&fun);
 
-        self.network.send_internal(
-            &SetModuleFunctionality {
-                fun,
-                // SARA-R5 This parameter can be used only when <fun> is 1, 4 or 19
-                #[cfg(feature = "sara-r5")]
-                rst: None,
-                #[cfg(not(feature = "sara-r5"))]
-                rst: Some(ResetMode::DontReset),
-            },
-            false,
-        )?;
+        self.network
+            .send_internal(&SetModuleFunctionality { fun, rst }, false)?;
 
         self.wait_power_state(PowerState::On, Duration::from_secs(30))
             .map_err(|_| Error::Generic(GenericError::Timeout))?;
@@ -110,7 +167,7 @@ where
         if let Some(rst) = self.config.reset_pin() {
             rst.set_low().ok();
 
-            BlockingTimer::after(reset_time()).wait();
+            BlockingTimer::after(reset_time(self.network.module)).wait();
 
             rst.set_high().ok();
 
@@ -137,7 +194,7 @@ where
                 // Apply Low pulse on PWR_ON for 50 microseconds to power on
                 Some(pwr) => {
                     pwr.set_low().ok();
-                    BlockingTimer::after(pwr_on_time()).wait();
+                    BlockingTimer::after(pwr_on_time(self.network.module)).wait();
 
                     pwr.set_high().ok();
 
@@ -184,7 +241,7 @@ where
                 Some(pwr) => {
                     // Apply Low pulse on PWR_ON >= 1 second to power off
                     pwr.set_low().ok();
-                    BlockingTimer::after(pwr_off_time()).wait();
+                    BlockingTimer::after(pwr_off_time(self.network.module)).wait();
 
                     pwr.set_high().ok();
                     self.power_state = PowerState::Off;
@@ -246,3 +303,134 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command::control::types::BaudRate,
+        test_support::{new_device, MockConfig, MockModem, MockSettings, RecordingPin},
+    };
+
+    /// Board able to reconfigure its UART
+    struct SwitchedBaudRate;
+
+    impl MockSettings for SwitchedBaudRate {
+        const BAUD_RATES: &'static [BaudRate] = &[BaudRate::B115200, BaudRate::B921600];
+        const BAUD_RATE: Option<BaudRate> = Some(BaudRate::B921600);
+        const SET_BAUD_RATE: bool = true;
+    }
+
+    #[test]
+    fn baud_rate_is_detected_from_candidates() {
+        let modem = MockModem::new();
+        modem
+            .expect_err("AT", atat::Error::Timeout)
+            .expect_err("AT", atat::Error::Timeout)
+            .expect("AT", "");
+
+        let mut device = new_device(&modem, MockConfig::<SwitchedBaudRate>::default());
+
+        assert_eq!(device.detect_baud_rate(), Ok(()));
+        assert_eq!(device.baud_rate, Some(BaudRate::B921600));
+        assert_eq!(
+            device.config.baud_rates,
+            [BaudRate::B115200, BaudRate::B921600]
+        );
+
+        // Already running at the target rate
+        assert_eq!(device.switch_baud_rate(), Ok(()));
+        modem.assert_done();
+    }
+
+    #[test]
+    fn baud_rate_is_switched_to_target() {
+        let modem = MockModem::new();
+        modem.expect("AT+IPR=921600", "").expect("AT", "");
+
+        let mut device = new_device(&modem, MockConfig::<SwitchedBaudRate>::default());
+
+        assert_eq!(device.switch_baud_rate(), Ok(()));
+        assert_eq!(device.baud_rate, Some(BaudRate::B921600));
+        assert_eq!(device.config.baud_rates, [BaudRate::B921600]);
+        modem.assert_done();
+    }
+
+    #[test]
+    fn baud_rate_detection_needs_transport_hook() {
+        let modem = MockModem::new();
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        assert_eq!(device.detect_baud_rate(), Err(Error::BaudDetection));
+        assert_eq!(device.switch_baud_rate(), Ok(()));
+        modem.assert_done();
+    }
+
+    /// Board controlling the UART power saving through DTR
+    struct DtrPowerSaving;
+
+    impl MockSettings for DtrPowerSaving {
+        const POWER_SAVING: PowerSavingMode = PowerSavingMode::CtrlByDtr;
+        const POWER_SAVING_IDLE: Duration = Duration::from_millis(100);
+    }
+
+    #[test]
+    fn dtr_is_asserted_while_sending_commands() {
+        let modem = MockModem::new();
+        modem
+            .expect("AT+UPSV=3", "")
+            .expect("AT", "")
+            .expect("AT", "");
+
+        let dtr = RecordingPin::default();
+        let mut device = new_device(
+            &modem,
+            MockConfig::<DtrPowerSaving>::default().with_dtr_pin(dtr.clone()),
+        );
+
+        // DTR is ON (low) before the module starts observing it
+        device.set_power_saving().unwrap();
+        assert_eq!(dtr.levels(), [false]);
+
+        device.network.send_internal(&AT, false).unwrap();
+        device.network.at_tx.client.sleep_if_idle();
+        assert_eq!(dtr.levels(), [false]);
+
+        // Released once idle, and asserted again for the next command
+        std::thread::sleep(std::time::Duration::from_millis(150));
+        device.network.at_tx.client.sleep_if_idle();
+        assert_eq!(dtr.levels(), [false, true]);
+
+        device.network.send_internal(&AT, false).unwrap();
+        assert_eq!(dtr.levels(), [false, true, false]);
+        modem.assert_done();
+    }
+
+    /// Board controlling the UART power saving through RTS, also used for the
+    /// hardware flow control
+    struct RtsPowerSaving;
+
+    impl MockSettings for RtsPowerSaving {
+        const FLOW_CONTROL: bool = true;
+        const POWER_SAVING: PowerSavingMode = PowerSavingMode::CtrlByRts;
+    }
+
+    #[test]
+    fn power_saving_without_usable_line_is_refused() {
+        let modem = MockModem::new();
+
+        // No DTR pin is given
+        let mut device = new_device(&modem, MockConfig::<DtrPowerSaving>::default());
+        assert_eq!(device.set_power_saving(), Err(Error::InvalidPowerSaving));
+        modem.assert_done();
+
+        let modem = MockModem::new();
+        let mut device = new_device(
+            &modem,
+            MockConfig::<RtsPowerSaving>::default().with_dtr_pin(RecordingPin::default()),
+        );
+        assert_eq!(device.set_power_saving(), Err(Error::InvalidPowerSaving));
+        modem.assert_done();
+    }
+}
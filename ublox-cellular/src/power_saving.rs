@@ -0,0 +1,165 @@
+//! UART power saving (`AT+UPSV`)
+//!
+//! With power saving enabled, the module's UART only accepts commands while
+//! it is awake. [`PowerSavingClient`] wraps the AT client of the driver,
+//! waking the UART before every command: by asserting the DTR (or RTS) line
+//! for [`PowerSavingMode::CtrlByDtr`] ([`PowerSavingMode::CtrlByRts`]), or by
+//! sending a wake-up `AT` for the cyclic [`PowerSavingMode::Enabled`]. The
+//! line is released again once the AT interface has been idle for
+//! [`CellularConfig::POWER_SAVING_IDLE`].
+//!
+//! [`CellularConfig::POWER_SAVING_IDLE`]: crate::config::CellularConfig::POWER_SAVING_IDLE
+
+use atat::AtatCmd;
+use embassy_time::{Duration, Instant};
+use embedded_hal::digital::OutputPin;
+
+use crate::{
+    command::{system_features::types::PowerSavingMode, AT},
+    error::Error,
+};
+
+/// Attempts at waking the UART with [`PowerSavingMode::Enabled`], as the
+/// characters received while it is asleep are lost
+const WAKE_ATTEMPTS: u8 = 3;
+
+/// AT client waking the module's UART before every command
+pub struct PowerSavingClient<AtCl, P> {
+    client: AtCl,
+    /// DTR or RTS line, driven ON (low) to keep the UART awake
+    pin: Option<P>,
+    mode: PowerSavingMode,
+    /// Time the module's UART stays awake after the last command
+    idle: Duration,
+    /// The pin is driven ON
+    awake: bool,
+    last_activity: Option<Instant>,
+}
+
+impl<AtCl, P: OutputPin> PowerSavingClient<AtCl, P> {
+    pub(crate) fn new(client: AtCl, pin: Option<P>, idle: Duration) -> Self {
+        Self {
+            client,
+            pin,
+            mode: PowerSavingMode::Disabled,
+            idle,
+            awake: false,
+            last_activity: None,
+        }
+    }
+
+    /// Power saving mode in use by the module. Switch to
+    /// [`PowerSavingMode::CtrlByDtr`] and [`PowerSavingMode::CtrlByRts`]
+    /// before sending `AT+UPSV`, so the line is ON by the time the module
+    /// starts observing it.
+    pub(crate) fn set_mode(&mut self, mode: PowerSavingMode) {
+        self.mode = mode;
+    }
+
+    pub(crate) fn mode(&self) -> PowerSavingMode {
+        self.mode
+    }
+
+    /// Check that `mode` can be applied: the line controlling the UART is
+    /// driven by the host, and RTS is not taken by the hardware flow control
+    pub(crate) fn check_mode(
+        &self,
+        mode: PowerSavingMode,
+        flow_control: bool,
+    ) -> Result<(), Error> {
+        match mode {
+            PowerSavingMode::CtrlByDtr | PowerSavingMode::CtrlByRts if self.pin.is_none() => {
+                Err(Error::InvalidPowerSaving)
+            }
+            PowerSavingMode::CtrlByRts if flow_control => Err(Error::InvalidPowerSaving),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.last_activity
+            .and_then(|t| Instant::now().checked_duration_since(t))
+            .map_or(true, |idle| idle >= self.idle)
+    }
+
+    fn controlled_by_line(&self) -> bool {
+        matches!(
+            self.mode,
+            PowerSavingMode::CtrlByDtr | PowerSavingMode::CtrlByRts
+        )
+    }
+
+    /// Drive the line ON, if the UART is controlled by it. Returns whether
+    /// a wake-up `AT` is needed instead.
+    fn wake_line(&mut self) -> bool {
+        if self.controlled_by_line() {
+            if !self.awake {
+                if let Some(pin) = self.pin.as_mut() {
+                    pin.set_low().ok();
+                }
+                self.awake = true;
+            }
+            false
+        } else {
+            self.mode == PowerSavingMode::Enabled && self.is_idle()
+        }
+    }
+
+    /// Release the line, letting the module's UART sleep, once it has been
+    /// idle long enough
+    pub(crate) fn sleep_if_idle(&mut self) {
+        if self.controlled_by_line() && self.awake && self.is_idle() {
+            if let Some(pin) = self.pin.as_mut() {
+                pin.set_high().ok();
+            }
+            self.awake = false;
+        }
+    }
+}
+
+impl<AtCl, P> atat::blocking::AtatClient for PowerSavingClient<AtCl, P>
+where
+    AtCl: atat::blocking::AtatClient,
+    P: OutputPin,
+{
+    fn send<A: AtatCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> Result<A::Response, atat::Error> {
+        if self.wake_line() {
+            for _ in 0..WAKE_ATTEMPTS {
+                if self.client.send(&AT).is_ok() {
+                    break;
+                }
+            }
+        }
+
+        let res = self.client.send(cmd);
+        self.last_activity = Some(Instant::now());
+        res
+    }
+}
+
+#[cfg(feature = "async")]
+impl<AtCl, P> atat::asynch::AtatClient for PowerSavingClient<AtCl, P>
+where
+    AtCl: atat::asynch::AtatClient,
+    P: OutputPin,
+{
+    async fn send<A: AtatCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> Result<A::Response, atat::Error> {
+        if self.wake_line() {
+            for _ in 0..WAKE_ATTEMPTS {
+                if self.client.send(&AT).await.is_ok() {
+                    break;
+                }
+            }
+        }
+
+        let res = self.client.send(cmd).await;
+        self.last_activity = Some(Instant::now());
+        res
+    }
+}
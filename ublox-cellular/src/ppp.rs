@@ -0,0 +1,198 @@
+//! PPP data mode
+//!
+//! Instead of the module's internal IP stack (`AT+USOCR` and friends, with
+//! their socket limit and AT framing overhead), the module can carry IP
+//! packets over PPP to an IP stack running on the host, e.g. `smoltcp` or
+//! `embassy-net` (through `embassy-net-ppp`).
+//!
+//! The AT client keeps owning its serial port, so PPP runs on a second one:
+//! a [`cmux`](crate::cmux) channel, or a second UART/USB interface of the
+//! module. Bringing up the link takes two steps:
+//!
+//! 1. [`Device::ppp_context`] registers to the network and defines the PDP
+//!    context (`AT+CGDCONT`) through the AT client.
+//! 2. [`dial`] (or [`dial_async`]) sends `ATD*99***<cid>#` on the data port,
+//!    which is in PPP mode once it returns.
+//!
+//! ```ignore
+//! let cid = nb::block!(modem.ppp_context(&APNInfo::new("em")))?;
+//! ppp::dial(&mut data_port, cid)?;
+//!
+//! // `data_port` now carries the raw PPP byte stream
+//! let mut runner = embassy_net_ppp::Runner::new(&mut ppp_state);
+//! runner.run(data_port, config, |ipv4| { /* configure the stack */ }).await;
+//! ```
+//!
+//! The link is torn down by the host stack (LCP terminate), after which the
+//! module answers `NO CARRIER` and returns the port to command mode.
+
+use crate::{
+    client::Device,
+    config::CellularConfig,
+    error::Error as DeviceError,
+    network::ContextId,
+    services::data::{apn::APNInfo, context::PdpContext, dialect, ContextState},
+};
+use atat::blocking::AtatClient;
+use core::fmt::Write as _;
+use embassy_time::{Duration, Instant};
+use embedded_io::{Read, ReadReady, Write};
+use heapless::{String, Vec};
+
+/// Time allowed for the module to answer the dial command
+const DIAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DialError<E> {
+    /// Error of the underlying serial port
+    Transport(E),
+    /// No answer from the module within the dial timeout
+    Timeout,
+    /// The connection could not be set up (`NO CARRIER`, `BUSY`, ...)
+    NoCarrier,
+    /// The module rejected the dial command (`ERROR`, `+CME ERROR`)
+    Rejected,
+}
+
+/// Final result codes of the dial command, matched line by line
+struct ResultCode {
+    line: Vec<u8, 64>,
+}
+
+impl ResultCode {
+    const fn new() -> Self {
+        Self { line: Vec::new() }
+    }
+
+    /// Feed a received byte, returning the outcome of the dial command once
+    /// a final result code has been received. The echo and unknown lines
+    /// are skipped.
+    ///
+    /// Lines end on the LF of the `\r\n` terminator, so that no part of it is
+    /// left in front of the PPP stream following `CONNECT`.
+    fn feed<E>(&mut self, byte: u8) -> Option<Result<(), DialError<E>>> {
+        match byte {
+            b'\r' => return None,
+            b'\n' => {}
+            _ => {
+                // Overlong lines can't be result codes, keep their start only
+                self.line.push(byte).ok();
+                return None;
+            }
+        }
+
+        let res = match self.line.as_slice() {
+            l if l.starts_with(b"CONNECT") => Some(Ok(())),
+            b"NO CARRIER" | b"BUSY" | b"NO ANSWER" | b"NO DIALTONE" => {
+                Some(Err(DialError::NoCarrier))
+            }
+            l if l == b"ERROR" || l.starts_with(b"+CME ERROR") => Some(Err(DialError::Rejected)),
+            _ => None,
+        };
+        self.line.clear();
+        res
+    }
+}
+
+fn dial_command(cid: ContextId) -> String<16> {
+    let mut cmd = String::new();
+    write!(cmd, "ATD*99***{}#\r", cid.0).ok();
+    cmd
+}
+
+/// Dial the packet data service on `port`, a serial port of the module not
+/// used by the AT client. On success, `port` carries the PPP byte stream,
+/// the `CONNECT` result code and its line terminator having been consumed.
+///
+/// The PDP context `cid` must have been defined first, see
+/// [`Device::ppp_context`].
+pub fn dial<P: Read + Write + ReadReady>(
+    port: &mut P,
+    cid: ContextId,
+) -> Result<(), DialError<P::Error>> {
+    port.write_all(dial_command(cid).as_bytes()).map_err(DialError::Transport)?;
+    port.flush().map_err(DialError::Transport)?;
+
+    let deadline = Instant::now() + DIAL_TIMEOUT;
+    let mut result = ResultCode::new();
+    loop {
+        if !port.read_ready().map_err(DialError::Transport)? {
+            if Instant::now() > deadline {
+                return Err(DialError::Timeout);
+            }
+            continue;
+        }
+
+        // Read a byte at a time, so no PPP data following the result code
+        // is consumed
+        let mut byte = [0u8];
+        if port.read(&mut byte).map_err(DialError::Transport)? == 0 {
+            continue;
+        }
+        if let Some(res) = result.feed(byte[0]) {
+            return res;
+        }
+    }
+}
+
+/// Async version of [`dial`]
+#[cfg(feature = "async")]
+pub async fn dial_async<P>(port: &mut P, cid: ContextId) -> Result<(), DialError<P::Error>>
+where
+    P: embedded_io_async::Read + embedded_io_async::Write,
+{
+    port
+        .write_all(dial_command(cid).as_bytes())
+        .await
+        .map_err(DialError::Transport)?;
+    port.flush().await.map_err(DialError::Transport)?;
+
+    let deadline = Instant::now() + DIAL_TIMEOUT;
+    let mut result = ResultCode::new();
+    loop {
+        let mut byte = [0u8];
+        let remaining = deadline
+            .checked_duration_since(Instant::now())
+            .ok_or(DialError::Timeout)?;
+        let read = embassy_time::with_timeout(remaining, port.read(&mut byte))
+            .await
+            .map_err(|_| DialError::Timeout)?
+            .map_err(DialError::Transport)?;
+        if read == 0 {
+            continue;
+        }
+        if let Some(res) = result.feed(byte[0]) {
+            return res;
+        }
+    }
+}
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Prepare the PPP data mode: turn on and register the module, and define
+    /// the PDP context to [`dial`].
+    ///
+    /// This must be called periodically in a loop, until it returns the
+    /// context id.
+    pub fn ppp_context(&mut self, apn_info: &APNInfo) -> nb::Result<ContextId, DeviceError> {
+        let res = self.spin();
+
+        // Define the context while registering, as data_service does. The
+        // module is taken to minimum functionality meanwhile.
+        let cid = PdpContext::DEFAULT.cid;
+        if matches!(res, Ok(()) | Err(nb::Error::WouldBlock))
+            && self.network.contexts.state(cid) == ContextState::Setup
+        {
+            dialect::define_pdp_context(&mut self.network, cid, apn_info)
+                .map_err(DeviceError::from)?;
+        }
+
+        res.map(|()| cid)
+    }
+}
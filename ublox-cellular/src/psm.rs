@@ -0,0 +1,311 @@
+//! Power Saving Mode (PSM) of LTE-M/NB-IoT networks
+//!
+//! With PSM, the module stays registered to the network while being
+//! unreachable, for up to the periodic TAU timer (T3412 extended). After each
+//! tracking area update or data transfer, it remains reachable for the active
+//! time (T3324), before falling asleep again.
+//!
+//! The timers are requested with [`Device::request_psm`], but the network
+//! decides on the values actually used, see [`Device::psm_timers`]. While the
+//! module sleeps in PSM, the driver does not poll the registration status,
+//! and does not consider the registration lost.
+
+use crate::{
+    client::Device,
+    command::{
+        psn::{types::PSMMode, SetPSMSettings},
+        system_features::{types::PSMReporting, SetPSMReporting},
+    },
+    config::CellularConfig,
+    error::Error,
+    module::{ModuleKind, Vendor},
+    registration::eps_registration_urcs,
+};
+use atat::blocking::AtatClient;
+use core::fmt::Write as _;
+use embassy_time::Duration;
+use heapless::String;
+
+/// Timer units of the GPRS Timer 3 information element (periodic TAU), in
+/// seconds. The `0b111` unit deactivates the timer.
+const TIMER3_UNITS: [(u8, u64); 7] = [
+    (0b011, 2),
+    (0b100, 30),
+    (0b101, 60),
+    (0b000, 10 * 60),
+    (0b001, 60 * 60),
+    (0b010, 10 * 60 * 60),
+    (0b110, 320 * 60 * 60),
+];
+
+/// Timer units of the GPRS Timer 2 information element (active time), in
+/// seconds. The `0b111` unit deactivates the timer.
+const TIMER2_UNITS: [(u8, u64); 3] = [(0b000, 2), (0b001, 60), (0b010, 6 * 60)];
+
+const TIMER_DEACTIVATED: u8 = 0b111;
+const TIMER_MAX_VALUE: u64 = 0b11111;
+
+/// PSM timers, as requested to or granted by the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PsmTimers {
+    /// Periodic tracking area update timer (T3412 extended): the longest
+    /// time the module may sleep in PSM
+    pub periodic_tau: Duration,
+    /// Active time (T3324): the time the module stays reachable before
+    /// entering PSM
+    pub active_time: Duration,
+}
+
+/// Encode `timer` in the smallest unit fitting it, rounding the value up.
+/// Durations beyond the largest unit are clamped.
+fn encode_timer(timer: Duration, units: &[(u8, u64)]) -> String<8> {
+    let secs = timer.as_secs();
+    let (unit, value) = units
+        .iter()
+        .map(|&(unit, step)| (unit, (secs + step - 1) / step))
+        .find(|&(_, value)| value <= TIMER_MAX_VALUE)
+        .unwrap_or((units[units.len() - 1].0, TIMER_MAX_VALUE));
+
+    let mut bits = String::new();
+    write!(bits, "{unit:03b}{value:05b}").ok();
+    bits
+}
+
+/// Decode a one byte octet string, e.g. `"00100110"`. Returns `None` if the
+/// timer is deactivated or malformed.
+fn decode_timer(bits: &str, units: &[(u8, u64)]) -> Option<Duration> {
+    if bits.len() != 8 {
+        return None;
+    }
+    let byte = u8::from_str_radix(bits, 2).ok()?;
+    let (unit, value) = (byte >> 5, u64::from(byte & 0b11111));
+    if unit == TIMER_DEACTIVATED {
+        return None;
+    }
+
+    // Unknown units are interpreted as the second one (3GPP TS 24.008)
+    let step = units
+        .iter()
+        .find(|&&(u, _)| u == unit)
+        .map_or(units[1].1, |&(_, step)| step);
+    Some(Duration::from_secs(value * step))
+}
+
+/// Encode the requested periodic TAU as a GPRS Timer 3 octet string
+pub(crate) fn encode_periodic_tau(timer: Duration) -> String<8> {
+    encode_timer(timer, &TIMER3_UNITS)
+}
+
+/// Encode the requested active time as a GPRS Timer 2 octet string
+pub(crate) fn encode_active_time(timer: Duration) -> String<8> {
+    encode_timer(timer, &TIMER2_UNITS)
+}
+
+/// Decode the periodic TAU reported by `+CEREG`
+pub(crate) fn decode_periodic_tau(bits: &str) -> Option<Duration> {
+    decode_timer(bits, &TIMER3_UNITS)
+}
+
+/// Decode the active time reported by `+CEREG`
+pub(crate) fn decode_active_time(bits: &str) -> Option<Duration> {
+    decode_timer(bits, &TIMER2_UNITS)
+}
+
+/// PSM request of `request_psm`, the timers being encoded
+pub(crate) struct PsmRequest {
+    periodic_tau: String<8>,
+    active_time: String<8>,
+}
+
+impl PsmRequest {
+    pub(crate) fn new(timers: PsmTimers) -> Self {
+        Self {
+            periodic_tau: encode_periodic_tau(timers.periodic_tau),
+            active_time: encode_active_time(timers.active_time),
+        }
+    }
+
+    pub(crate) fn settings(&self) -> SetPSMSettings<'_> {
+        SetPSMSettings {
+            mode: PSMMode::Enabled,
+            requested_periodic_rau: None,
+            requested_gprs_ready_timer: None,
+            requested_periodic_tau: Some(&self.periodic_tau),
+            requested_active_time: Some(&self.active_time),
+        }
+    }
+}
+
+/// u-blox specific: report entering and leaving PSM, to tell a PSM sleep
+/// from a lost registration. Not supported by every module.
+pub(crate) fn psm_reporting(module: ModuleKind) -> Option<SetPSMReporting> {
+    (module.vendor() == Vendor::Ublox).then_some(SetPSMReporting {
+        mode: PSMReporting::Enabled,
+    })
+}
+
+pub(crate) const PSM_DISABLED: SetPSMSettings<'static> = SetPSMSettings {
+    mode: PSMMode::Disabled,
+    requested_periodic_rau: None,
+    requested_gprs_ready_timer: None,
+    requested_periodic_tau: None,
+    requested_active_time: None,
+};
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Request PSM with the given timers, which are rounded up to the
+    /// closest values that can be encoded. The network may grant different
+    /// values, available from [`psm_timers`](Device::psm_timers) once
+    /// registered.
+    ///
+    /// The setting is stored by the module, and takes effect at the next
+    /// registration or tracking area update.
+    pub fn request_psm(&mut self, timers: PsmTimers) -> Result<(), Error> {
+        self.send_at(&PsmRequest::new(timers).settings())?;
+
+        if let Some(reporting) = psm_reporting(self.network.module) {
+            if self.send_at(&reporting).is_err() {
+                warn!("PSM state reporting not supported");
+            }
+        }
+
+        // Report the granted timers along with the EPS registration
+        self.send_at(&eps_registration_urcs(true))?;
+
+        self.network.status.psm_requested = true;
+        Ok(())
+    }
+
+    /// Disable PSM
+    pub fn disable_psm(&mut self) -> Result<(), Error> {
+        self.send_at(&PSM_DISABLED)?;
+        self.send_at(&eps_registration_urcs(false))?;
+
+        self.network.status.psm_requested = false;
+        self.network.status.psm = None;
+        Ok(())
+    }
+
+    /// PSM timers granted by the network at the last EPS registration
+    /// update, if PSM has been requested and granted.
+    pub fn psm_timers(&self) -> Option<PsmTimers> {
+        self.network.status.psm
+    }
+
+    /// Whether the module is currently sleeping in PSM
+    pub fn is_psm_sleeping(&self) -> bool {
+        self.network.status.psm_sleep
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "async")]
+    use crate::test_support::{async_registered_device, block_on};
+    use crate::{
+        registration::ConnectionState,
+        test_support::{registered_device, MockConfig, MockModem},
+    };
+
+    const REQUESTED: PsmTimers = PsmTimers {
+        periodic_tau: Duration::from_secs(60 * 60),
+        active_time: Duration::from_secs(60),
+    };
+
+    /// Timers granted by [`expect_granted_timers`]
+    const GRANTED: PsmTimers = PsmTimers {
+        periodic_tau: Duration::from_secs(2 * 60 * 60),
+        active_time: Duration::from_secs(16),
+    };
+
+    /// Script the request of [`REQUESTED`]
+    fn expect_psm_request(modem: &MockModem) {
+        modem
+            .expect("AT+CPSMS=1,,,\"00000110\",\"00011110\"", "")
+            .expect("AT+UPSMR=1", "")
+            .expect("AT+CEREG=4", "");
+    }
+
+    /// Script a registration check reporting the granted timers, with
+    /// `<n>`=4, as a 2 hours TAU and a 16 seconds active time
+    fn expect_granted_timers(modem: &MockModem) {
+        modem
+            .expect("AT+CEER", "")
+            .expect("AT+CREG?", "+CREG: 2,1")
+            .expect("AT+CGREG?", "+CGREG: 2,1")
+            .expect(
+                "AT+CEREG?",
+                "+CEREG: 4,1,\"0001\",\"01A2B3C4\",7,,,\"00001000\",\"00100010\"",
+            );
+    }
+
+    #[test]
+    fn psm_timers_are_requested_and_read_back() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "LARA-R211", MockConfig::new());
+
+        expect_psm_request(&modem);
+        device.request_psm(REQUESTED).unwrap();
+
+        device.network.status.reg_check_time = None;
+        expect_granted_timers(&modem);
+        assert!(device.spin().is_ok());
+        modem.assert_done();
+
+        assert_eq!(device.psm_timers(), Some(GRANTED));
+    }
+
+    #[test]
+    fn psm_sleep_is_not_a_lost_registration() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "LARA-R211", MockConfig::new());
+
+        // Nothing is sent to the sleeping module, even once the registration
+        // check is due
+        modem.urc("+UUPSMR: 1");
+        modem.flush_urcs();
+        device.network.status.reg_check_time = None;
+        assert!(device.spin().is_ok());
+        assert!(device.spin().is_ok());
+        assert!(device.is_psm_sleeping());
+        assert_eq!(device.network.status.conn_state, ConnectionState::Connected);
+
+        // The registration is checked right away after waking up
+        modem.urc("+UUPSMR: 0");
+        modem.flush_urcs();
+        modem
+            .expect("AT+CEER", "")
+            .expect("AT+CREG?", "+CREG: 0,1")
+            .expect("AT+CGREG?", "+CGREG: 0,1")
+            .expect("AT+CEREG?", "+CEREG: 0,1");
+        assert!(device.spin().is_ok());
+        assert!(!device.is_psm_sleeping());
+        assert_eq!(device.network.status.conn_state, ConnectionState::Connected);
+        modem.assert_done();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_psm_timers_are_requested_and_read_back() {
+        let modem = MockModem::new();
+        let mut device = async_registered_device(&modem, "LARA-R211", MockConfig::new());
+
+        expect_psm_request(&modem);
+        assert_eq!(block_on(device.request_psm(REQUESTED)), Ok(()));
+
+        device.network.status.reg_check_time = None;
+        expect_granted_timers(&modem);
+        assert!(block_on(device.spin()).is_ok());
+        modem.assert_done();
+
+        assert_eq!(device.psm_timers(), Some(GRANTED));
+    }
+}
@@ -0,0 +1,153 @@
+//! Radio Access Technology and band configuration
+//!
+//! Applied by `initialize` on SARA-R4 / SARA-R5, from
+//! [`CellularConfig::RADIO_ACCESS_TECHNOLOGIES`] and
+//! [`CellularConfig::BAND_MASKS`]. Both settings are stored in NVM by the
+//! module, so they are only written, with the module deregistered, when they
+//! differ from the current ones.
+//!
+//! [`CellularConfig::RADIO_ACCESS_TECHNOLOGIES`]: crate::CellularConfig::RADIO_ACCESS_TECHNOLOGIES
+//! [`CellularConfig::BAND_MASKS`]: crate::CellularConfig::BAND_MASKS
+
+use crate::{
+    command::network_service::{
+        responses::{BandMasks, LpwaRadioAccessTechnologies},
+        types::{BandMaskRat, LpwaRadioAccessTechnology},
+        SetBandMask, SetLpwaRadioAccessTechnology,
+    },
+    config::CellularConfig,
+    module::ModuleKind,
+};
+
+/// LTE bands enabled for a Radio Access Technology
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BandMask {
+    pub rat: BandMaskRat,
+    /// Bands 1 to 64, bit 0 being band 1
+    pub bands: u64,
+    /// Bands 65 to 128, on the modules supporting them
+    pub bands_ext: u64,
+}
+
+/// Whether `initialize` has a RAT or band configuration to apply on `module`
+pub(crate) fn configured<Config: CellularConfig>(module: ModuleKind) -> bool {
+    if Config::RADIO_ACCESS_TECHNOLOGIES.is_empty() && Config::BAND_MASKS.is_empty() {
+        return false;
+    }
+    if !matches!(module, ModuleKind::SaraR4 | ModuleKind::SaraR5) {
+        warn!("RAT and band configuration not supported by the module");
+        return false;
+    }
+    true
+}
+
+/// `+URAT` list of `rats`, at most three of them being used
+fn rat_list(rats: &[LpwaRadioAccessTechnology]) -> [Option<LpwaRadioAccessTechnology>; 3] {
+    let mut list = [None; 3];
+    for (slot, &rat) in list.iter_mut().zip(rats) {
+        *slot = Some(rat);
+    }
+    list
+}
+
+/// The command selecting `rats`, if they differ from the `current` ones
+pub(crate) fn rat_update(
+    rats: &[LpwaRadioAccessTechnology],
+    current: &LpwaRadioAccessTechnologies,
+) -> Option<SetLpwaRadioAccessTechnology> {
+    let [first_act, second_act, third_act] = rat_list(rats);
+    let current = [
+        Some(current.first_act),
+        current.second_act,
+        current.third_act,
+    ];
+    if [first_act, second_act, third_act] == current {
+        return None;
+    }
+
+    Some(SetLpwaRadioAccessTechnology {
+        first_act: first_act?,
+        second_act,
+        third_act,
+    })
+}
+
+/// The command enabling the bands of `mask`, if they differ from the
+/// `current` ones
+pub(crate) fn band_update(mask: &BandMask, current: &BandMasks) -> Option<SetBandMask> {
+    let (bands, bands_ext) = current.masks(mask.rat);
+    if (bands, bands_ext) == (mask.bands, mask.bands_ext) {
+        return None;
+    }
+
+    Some(SetBandMask {
+        rat: mask.rat,
+        bitmask1: mask.bands,
+        // Only given to the modules supporting bands above 64, which report it
+        bitmask2: (mask.bands_ext != 0 || bands_ext != 0).then_some(mask.bands_ext),
+    })
+}
+
+/// The commands enabling the bands of `masks` which differ from the
+/// `current` ones, none if they are unknown
+pub(crate) fn band_updates<'a>(
+    masks: &'a [BandMask],
+    current: Option<&'a BandMasks>,
+) -> impl Iterator<Item = SetBandMask> + 'a {
+    masks
+        .iter()
+        .filter_map(move |mask| current.and_then(|current| band_update(mask, current)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{
+        expect_initialize_configured, new_device, MockConfig, MockModem, MockSettings,
+    };
+
+    /// SARA-R5 board preferring LTE Cat M1, restricted to bands 3 and 20
+    struct LteM;
+
+    impl MockSettings for LteM {
+        const RADIO_ACCESS_TECHNOLOGIES: &'static [LpwaRadioAccessTechnology] = &[
+            LpwaRadioAccessTechnology::LteCatM1,
+            LpwaRadioAccessTechnology::LteCatNb1,
+        ];
+        const BAND_MASKS: &'static [BandMask] = &[BandMask {
+            rat: BandMaskRat::LteCatM1,
+            bands: 1 << 2 | 1 << 19,
+            bands_ext: 0,
+        }];
+    }
+
+    #[test]
+    fn radio_configuration_is_only_written_when_different() {
+        let modem = MockModem::new();
+        expect_initialize_configured(&modem, "SARA-R510M8S", |modem| {
+            modem
+                .expect("AT+URAT?", "+URAT: 7")
+                .expect("AT+UBANDMASK?", "+UBANDMASK: 0,524420,1,524420")
+                .expect("AT+CFUN=0", "")
+                .expect("AT+URAT=7,8", "")
+                .expect("AT+UBANDMASK=0,524292", "");
+        });
+
+        let mut device = new_device(&modem, MockConfig::<LteM>::default());
+        assert!(device.spin().is_ok());
+        modem.assert_done();
+
+        // Already applied, e.g. after a module reset
+        let modem = MockModem::new();
+        expect_initialize_configured(&modem, "SARA-R510M8S", |modem| {
+            modem
+                .expect("AT+URAT?", "+URAT: 7,8")
+                .expect("AT+UBANDMASK?", "+UBANDMASK: 0,524292,0,1,524420,0");
+        });
+
+        let mut device = new_device(&modem, MockConfig::<LteM>::default());
+        assert!(device.spin().is_ok());
+        modem.assert_done();
+    }
+}
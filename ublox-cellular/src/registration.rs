@@ -1,17 +1,30 @@
 use crate::command::{
     network_service::{
         responses::NetworkRegistrationStatus,
-        types::{NetworkRegistrationStat, RatAct},
-        // urc::NetworkRegistration,
+        types::{
+            NetworkRegistrationStat, NetworkRegistrationUrcConfig, OperatorSelectionMode, RatAct,
+        },
+        urc::NetworkRegistration,
+        SetNetworkRegistrationStatus,
     },
     psn::{
         responses::{EPSNetworkRegistrationStatus, GPRSNetworkRegistrationStatus},
-        types::{EPSNetworkRegistrationStat, GPRSNetworkRegistrationStat},
-        // urc::{EPSNetworkRegistration, GPRSNetworkRegistration},
+        types::{
+            EPSNetworkRegistrationStat, EPSNetworkRegistrationUrcConfig,
+            GPRSNetworkRegistrationStat, GPRSNetworkRegistrationUrcConfig, PSEventReportingMode,
+        },
+        urc::{EPSNetworkRegistration, GPRSNetworkRegistration},
+        SetEPSNetworkRegistrationStatus, SetGPRSNetworkRegistrationStatus,
+        SetPacketSwitchedEventReporting,
     },
 };
+use crate::{
+    edrx::EdrxParameters,
+    psm::{self, PsmTimers},
+};
 use embassy_time::{Duration, Instant};
 use heapless::String;
+use serde::Serialize;
 
 #[derive(Debug, Clone, Default)]
 pub struct CellularRegistrationStatus {
@@ -85,7 +98,8 @@ impl From<u8> for Status {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// 3GPP registration status of a domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Status {
     None,
@@ -132,8 +146,8 @@ pub struct RegistrationParams {
 
     cell_id: Option<String<8>>,
     lac: Option<String<4>>,
-    // active_time: Option<u16>,
-    // periodic_tau: Option<u16>,
+    active_time: Option<Duration>,
+    periodic_tau: Option<Duration>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -176,9 +190,9 @@ impl From<RegType> for RadioAccessNetwork {
 #[derive(Debug, Clone, Default)]
 pub struct CellularGlobalIdentity {
     /// Registered network operator cell Id.
-    cell_id: Option<String<8>>,
+    pub(crate) cell_id: Option<String<8>>,
     /// Registered network operator Location Area Code.
-    lac: Option<String<4>>,
+    pub(crate) lac: Option<String<4>>,
     // Registered network operator Routing Area Code.
     // rac: u8,
     // Registered network operator Tracking Area Code.
@@ -190,6 +204,8 @@ pub struct RegistrationState {
     pub(crate) reg_check_time: Option<Instant>,
     pub(crate) reg_start_time: Option<Instant>,
     pub(crate) imsi_check_time: Option<Instant>,
+    /// IMSI of the SIM, as last read with `+CIMI`
+    pub(crate) imsi: Option<u64>,
 
     pub(crate) conn_state: ConnectionState,
     /// CSD (Circuit Switched Data) registration status (registered/searching/roaming etc.).
@@ -203,11 +219,26 @@ pub struct RegistrationState {
     check_imsi: bool,
 
     pub(crate) cgi: CellularGlobalIdentity,
-    // Radio Access Technology (RAT)
-    // pub(crate) act: RatAct,
+    /// Radio Access Technology (RAT) of the last packet switched or EPS
+    /// registration
+    pub(crate) act: RatAct,
+    /// PSM timers granted by the network, as reported by `+CEREG`
+    pub(crate) psm: Option<PsmTimers>,
+    /// PSM has been requested with `+CPSMS`
+    pub(crate) psm_requested: bool,
+    /// The module is sleeping in PSM, as reported by `+UUPSMR`
+    pub(crate) psm_sleep: bool,
+    /// eDRX parameters provided by the network, as reported by `+CEDRXP`
+    pub(crate) edrx: Option<EdrxParameters>,
+    /// Preferred operators, see [`CellularConfig::OPERATORS`]
+    ///
+    /// [`CellularConfig::OPERATORS`]: crate::CellularConfig::OPERATORS
+    pub(crate) operators: &'static [&'static str],
+    /// Index in `operators` of the next operator to select
+    operator_index: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ConnectionState {
     Disconnected,
@@ -227,6 +258,7 @@ impl RegistrationState {
             reg_check_time: None,
             reg_start_time: None,
             imsi_check_time: None,
+            imsi: None,
 
             conn_state: ConnectionState::Disconnected,
             csd: CellularRegistrationStatus::new(),
@@ -236,7 +268,13 @@ impl RegistrationState {
             check_imsi: false,
 
             cgi: CellularGlobalIdentity::default(),
-            // act: RatAct::default(),
+            act: RatAct::default(),
+            psm: None,
+            psm_requested: false,
+            psm_sleep: false,
+            edrx: None,
+            operators: &[],
+            operator_index: 0,
         }
     }
 
@@ -248,6 +286,33 @@ impl RegistrationState {
         self.reg_check_time = Some(Instant::now());
         self.imsi_check_time = None;
         self.registration_interventions = 1;
+        self.psm_sleep = false;
+        self.operator_index = 0;
+    }
+
+    /// Next operator to select manually, cycling through the preferred
+    /// operators, then automatic selection (`None`).
+    pub fn next_operator(&mut self) -> Option<&'static str> {
+        let operator = self.operators.get(self.operator_index).copied();
+        self.operator_index = (self.operator_index + 1) % (self.operators.len() + 1);
+        operator
+    }
+
+    /// Whether the operator selection `mode` read back by `initialize` is
+    /// already the one to register with. `AT+COPS=0` is only sent when it is
+    /// not, to avoid a PLMN reselection.
+    pub(crate) fn operator_selected(&self, mode: OperatorSelectionMode) -> bool {
+        if self.operators.is_empty() {
+            matches!(
+                mode,
+                OperatorSelectionMode::Automatic | OperatorSelectionMode::Manual
+            )
+        } else {
+            matches!(
+                mode,
+                OperatorSelectionMode::Manual | OperatorSelectionMode::ManualAutomatic
+            )
+        }
     }
 
     pub fn set_connection_state(&mut self, state: ConnectionState) {
@@ -259,6 +324,26 @@ impl RegistrationState {
         self.conn_state = state;
     }
 
+    /// Derive the overall [`ConnectionState`] from the per-domain registration
+    /// statuses.
+    pub fn update_connection_state(&mut self) {
+        // Don't do anything if we are actually disconnected by choice, or
+        // sleeping in PSM, where the module stays registered while unreachable
+        if self.conn_state == ConnectionState::Disconnected || self.psm_sleep {
+            return;
+        }
+
+        // If both (CSD + PSD) is registered, or EPS is registered, we are connected!
+        if (self.csd.registered() && self.psd.registered()) || self.eps.registered() {
+            self.set_connection_state(ConnectionState::Connected);
+        } else if self.conn_state == ConnectionState::Connected {
+            // FIXME: potentially go back into connecting state only when getting into
+            // a 'sticky' non-registered state
+            self.reset();
+            self.set_connection_state(ConnectionState::Connecting);
+        }
+    }
+
     pub fn compare_and_set(&mut self, new_params: RegistrationParams) {
         match new_params.reg_type {
             RegType::Creg => {
@@ -281,6 +366,14 @@ impl RegistrationState {
                 if !prev_reg_status && self.eps.registered() {
                     self.check_imsi = true
                 }
+
+                self.psm = match (new_params.periodic_tau, new_params.active_time) {
+                    (Some(periodic_tau), Some(active_time)) => Some(PsmTimers {
+                        periodic_tau,
+                        active_time,
+                    }),
+                    _ => None,
+                };
             }
             RegType::Unknown => {
                 error!("unknown reg type");
@@ -288,6 +381,11 @@ impl RegistrationState {
             }
         }
 
+        // `+CREG` does not report the access technology
+        if new_params.act != RatAct::Unknown && !matches!(new_params.reg_type, RegType::Creg) {
+            self.act = new_params.act;
+        }
+
         // Update Cellular Global Identity
         if new_params.cell_id.is_some() && self.cgi.cell_id != new_params.cell_id {
             self.cgi.cell_id = new_params.cell_id.clone();
@@ -296,19 +394,19 @@ impl RegistrationState {
     }
 }
 
-// impl From<NetworkRegistration> for RegistrationParams {
-//     fn from(v: NetworkRegistration) -> Self {
-//         Self {
-//             act: RatAct::Gsm,
-//             reg_type: RegType::Creg,
-//             status: v.stat.into(),
-//             cell_id: None,
-//             lac: None,
-//             // active_time: None,
-//             // periodic_tau: None,
-//         }
-//     }
-// }
+impl From<NetworkRegistration> for RegistrationParams {
+    fn from(v: NetworkRegistration) -> Self {
+        Self {
+            act: RatAct::Gsm,
+            reg_type: RegType::Creg,
+            status: v.stat.into(),
+            cell_id: None,
+            lac: None,
+            active_time: None,
+            periodic_tau: None,
+        }
+    }
+}
 
 impl From<NetworkRegistrationStatus> for RegistrationParams {
     fn from(v: NetworkRegistrationStatus) -> Self {
@@ -318,25 +416,25 @@ impl From<NetworkRegistrationStatus> for RegistrationParams {
             status: v.stat.into(),
             cell_id: None,
             lac: None,
-            // active_time: None,
-            // periodic_tau: None,
+            active_time: None,
+            periodic_tau: None,
         }
     }
 }
 
-// impl From<GPRSNetworkRegistration> for RegistrationParams {
-//     fn from(v: GPRSNetworkRegistration) -> Self {
-//         Self {
-//             act: v.act.unwrap_or(RatAct::Unknown),
-//             reg_type: RegType::Cgreg,
-//             status: v.stat.into(),
-//             cell_id: v.ci,
-//             lac: v.lac,
-//             // active_time: None,
-//             // periodic_tau: None,
-//         }
-//     }
-// }
+impl From<GPRSNetworkRegistration> for RegistrationParams {
+    fn from(v: GPRSNetworkRegistration) -> Self {
+        Self {
+            act: v.act.unwrap_or(RatAct::Unknown),
+            reg_type: RegType::Cgreg,
+            status: v.stat.into(),
+            cell_id: v.ci,
+            lac: v.lac,
+            active_time: None,
+            periodic_tau: None,
+        }
+    }
+}
 
 impl From<GPRSNetworkRegistrationStatus> for RegistrationParams {
     fn from(v: GPRSNetworkRegistrationStatus) -> Self {
@@ -346,25 +444,25 @@ impl From<GPRSNetworkRegistrationStatus> for RegistrationParams {
             cell_id: v.ci,
             lac: v.lac,
             act: v.act.unwrap_or(RatAct::Unknown),
-            // active_time: None,
-            // periodic_tau: None,
+            active_time: None,
+            periodic_tau: None,
         }
     }
 }
 
-// impl From<EPSNetworkRegistration> for RegistrationParams {
-//     fn from(v: EPSNetworkRegistration) -> Self {
-//         Self {
-//             reg_type: RegType::Cereg,
-//             status: v.stat.into(),
-//             cell_id: v.ci,
-//             lac: v.tac,
-//             act: v.act.unwrap_or(RatAct::Unknown),
-//             // active_time: None,
-//             // periodic_tau: None,
-//         }
-//     }
-// }
+impl From<EPSNetworkRegistration> for RegistrationParams {
+    fn from(v: EPSNetworkRegistration) -> Self {
+        Self {
+            reg_type: RegType::Cereg,
+            status: v.stat.into(),
+            cell_id: v.ci,
+            lac: v.tac,
+            act: v.act.unwrap_or(RatAct::Unknown),
+            active_time: v.active_time.as_deref().and_then(psm::decode_active_time),
+            periodic_tau: v.periodic_tau.as_deref().and_then(psm::decode_periodic_tau),
+        }
+    }
+}
 
 impl From<EPSNetworkRegistrationStatus> for RegistrationParams {
     fn from(v: EPSNetworkRegistrationStatus) -> Self {
@@ -374,8 +472,8 @@ impl From<EPSNetworkRegistrationStatus> for RegistrationParams {
             cell_id: v.ci,
             lac: v.tac,
             act: v.act.unwrap_or(RatAct::Unknown),
-            // active_time: None,
-            // periodic_tau: None,
+            active_time: v.active_time.as_deref().and_then(psm::decode_active_time),
+            periodic_tau: v.periodic_tau.as_deref().and_then(psm::decode_periodic_tau),
         }
     }
 }
@@ -399,3 +497,111 @@ impl From<usize> for RadioAccessNetwork {
         }
     }
 }
+
+/// `+CEREG` URCs, along with the granted PSM timers if PSM is requested
+pub(crate) fn eps_registration_urcs(psm_requested: bool) -> SetEPSNetworkRegistrationStatus {
+    let n = if psm_requested {
+        EPSNetworkRegistrationUrcConfig::UrcPsm
+    } else {
+        EPSNetworkRegistrationUrcConfig::UrcVerbose
+    };
+    SetEPSNetworkRegistrationStatus { n }
+}
+
+/// URCs enabled by `initialize`, reporting the registration changes
+pub(crate) struct RegistrationUrcs {
+    /// Packet domain events. Not a stopper if not supported, only some
+    /// events being lacked when dropped from the network.
+    pub events: SetPacketSwitchedEventReporting,
+    pub creg: SetNetworkRegistrationStatus,
+    pub cgreg: SetGPRSNetworkRegistrationStatus,
+    pub cereg: SetEPSNetworkRegistrationStatus,
+}
+
+impl RegistrationUrcs {
+    pub(crate) fn new(status: &RegistrationState) -> Self {
+        Self {
+            events: SetPacketSwitchedEventReporting {
+                mode: PSEventReportingMode::CircularBufferUrcs,
+                bfr: None,
+            },
+            creg: SetNetworkRegistrationStatus {
+                n: NetworkRegistrationUrcConfig::UrcVerbose,
+            },
+            cgreg: SetGPRSNetworkRegistrationStatus {
+                n: GPRSNetworkRegistrationUrcConfig::UrcVerbose,
+            },
+            cereg: eps_registration_urcs(status.psm_requested),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        command::network_service::types::OperatorStatus,
+        test_support::{registered_device, MockConfig, MockModem},
+    };
+
+    #[test]
+    fn registration_urcs_update_connection_state() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "LARA-R211", MockConfig::new());
+
+        // Dropped from the packet switched domain, without polling
+        modem.urc("+CGREG: 0");
+        modem.flush_urcs();
+        modem.expect("AT+CIMI", "238010000000000");
+        assert_eq!(device.spin(), Err(nb::Error::WouldBlock));
+        assert_eq!(
+            device.network.status.conn_state,
+            ConnectionState::Connecting
+        );
+
+        modem.urc("+CEREG: 1,\"0001\",\"01A2B3C4\",7");
+        modem.flush_urcs();
+        assert!(device.spin().is_ok());
+        assert_eq!(device.network.status.conn_state, ConnectionState::Connected);
+        assert_eq!(
+            device.network.status.cgi.cell_id.as_deref(),
+            Some("01A2B3C4")
+        );
+        modem.assert_done();
+    }
+
+    #[test]
+    fn operators_are_scanned() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "LARA-R211", MockConfig::new());
+
+        modem.expect(
+            "AT+COPS=?",
+            "+COPS: (2,\"Telenor\",\"Telenor\",\"23801\",7),(3,\"TDC (DK)\",\"TDC\",\"23802\",0),,(0-4),(0-2)",
+        );
+        let operators = device.scan_operators().unwrap();
+        assert_eq!(operators.len(), 2);
+        assert_eq!(operators[0].status, OperatorStatus::Current);
+        assert_eq!(operators[0].numeric, "23801");
+        assert_eq!(operators[0].act, Some(RatAct::Lte));
+        assert_eq!(operators[1].status, OperatorStatus::Forbidden);
+        assert_eq!(operators[1].long_name, "TDC (DK)");
+        assert_eq!(operators[1].act, Some(RatAct::Gsm));
+        modem.assert_done();
+    }
+
+    #[test]
+    fn preferred_operators_are_tried_before_automatic_selection() {
+        let mut status = RegistrationState::new();
+        status.operators = &["23801", "23802"];
+
+        assert_eq!(status.next_operator(), Some("23801"));
+        assert_eq!(status.next_operator(), Some("23802"));
+        assert_eq!(status.next_operator(), None);
+        assert_eq!(status.next_operator(), Some("23801"));
+
+        // Losing the registration starts over from the first one
+        status.reset();
+        assert_eq!(status.next_operator(), Some("23801"));
+    }
+}
@@ -1,3 +1,6 @@
+use crate::command::psn::types::{AuthenticationType, ProtocolType};
+use embedded_nal::IpAddr;
+
 #[derive(Debug, Clone)]
 pub enum Apn<'a> {
     Given(&'a str),
@@ -10,11 +13,66 @@ impl<'a> Default for Apn<'a> {
     }
 }
 
+/// IP version of a PDP context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PdpType {
+    /// IPv4
+    #[default]
+    Ip,
+    /// IPv6 only
+    Ipv6,
+    /// Dual-stack, IPv4 being preferred when both addresses are available
+    Ipv4v6,
+}
+
+impl PdpType {
+    /// `<PDP_type>` of `+CGDCONT`
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Ip => "IP",
+            Self::Ipv6 => "IPV6",
+            Self::Ipv4v6 => "IPV4V6",
+        }
+    }
+
+    /// `+UPSD` protocol type of the PSD profile
+    pub(crate) fn protocol_type(self) -> ProtocolType {
+        match self {
+            Self::Ip => ProtocolType::IPv4,
+            Self::Ipv6 => ProtocolType::IPv6,
+            Self::Ipv4v6 => ProtocolType::IPv4v6PreferV4Internal,
+        }
+    }
+
+    /// Address of the context among the ones reported by the module, a
+    /// dual-stack context reporting one of each
+    pub(crate) fn select_addr(self, addrs: impl IntoIterator<Item = IpAddr>) -> Option<IpAddr> {
+        let mut ipv4 = None;
+        let mut ipv6 = None;
+        for addr in addrs {
+            match addr {
+                IpAddr::V4(_) => ipv4 = ipv4.or(Some(addr)),
+                IpAddr::V6(_) => ipv6 = ipv6.or(Some(addr)),
+            }
+        }
+
+        match self {
+            Self::Ip => ipv4,
+            Self::Ipv6 => ipv6,
+            Self::Ipv4v6 => ipv4.or(ipv6),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct APNInfo<'a> {
     pub apn: Apn<'a>,
     pub user_name: Option<&'a str>,
     pub password: Option<&'a str>,
+    /// Authentication protocol of `user_name` and `password`
+    pub auth_type: AuthenticationType,
+    pub pdp_type: PdpType,
 }
 
 impl<'a> APNInfo<'a> {
@@ -24,6 +82,181 @@ impl<'a> APNInfo<'a> {
             apn: Apn::Given(apn),
             user_name: None,
             password: None,
+            auth_type: AuthenticationType::Auto,
+            pdp_type: PdpType::Ip,
+        }
+    }
+
+    /// APN `apn`, authenticating with `user_name` and `password` through
+    /// `auth_type`
+    #[must_use]
+    pub fn with_credentials(
+        apn: &'a str,
+        user_name: &'a str,
+        password: &'a str,
+        auth_type: AuthenticationType,
+    ) -> Self {
+        Self {
+            user_name: Some(user_name),
+            password: Some(password),
+            auth_type,
+            ..Self::new(apn)
+        }
+    }
+
+    pub(crate) fn has_credentials(&self) -> bool {
+        self.user_name.is_some() || self.password.is_some()
+    }
+
+    /// APN to use at the `attempt`th activation of the context, with the SIM
+    /// of `imsi`. An automatic APN is looked up in the operator database,
+    /// with the `apn-db` feature.
+    #[cfg_attr(not(feature = "apn-db"), allow(unused_variables))]
+    pub(crate) fn resolve(&self, imsi: Option<u64>, attempt: usize) -> APNInfo<'a> {
+        #[cfg(feature = "apn-db")]
+        if let (Apn::Automatic, Some(imsi)) = (&self.apn, imsi) {
+            if let Some(candidate) = super::apn_db::candidate(imsi, attempt) {
+                return APNInfo {
+                    pdp_type: self.pdp_type,
+                    ..candidate
+                };
+            }
         }
+
+        self.clone()
+    }
+
+    /// APN `apn`, of IP version `pdp_type`
+    #[must_use]
+    pub fn with_pdp_type(apn: &'a str, pdp_type: PdpType) -> Self {
+        Self {
+            pdp_type,
+            ..Self::new(apn)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{expect_initialize, new_device, MockConfig, MockModem};
+    use embedded_nal::{AddrType, Dns, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn apn_credentials_are_set_before_activation() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        modem
+            .expect("AT+CFUN=0,0", "")
+            .expect("AT+CGDCONT=1,\"IP\",\"em\"", "")
+            .expect("AT+UAUTHREQ=1,1,\"user\",\"secret\"", "")
+            .expect("AT+CFUN=1,0", "")
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+CGACT?", "+CGACT: 1,1")
+            .expect("AT+UPSD=1,100", "+UPSD: 1,100,1")
+            .expect("AT+UPSND=1,8", "+UPSND: 1,8,1")
+            .expect("AT+CGPADDR=1", "+CGPADDR: 1,\"10.0.0.2\"");
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let apn = APNInfo::with_credentials("em", "user", "secret", AuthenticationType::PAP);
+        assert!(device.data_service(&apn).is_ok());
+        modem.assert_done();
+    }
+
+    #[test]
+    fn apn_credentials_are_set_with_automatic_apn() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        modem
+            .expect("AT+CFUN=0,0", "")
+            .expect("AT+UAUTHREQ=1,1,\"user\",\"secret\"", "")
+            .expect("AT+CFUN=1,0", "")
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+CGACT?", "+CGACT: 1,1")
+            .expect("AT+UPSD=1,100", "+UPSD: 1,100,1")
+            .expect("AT+UPSND=1,8", "+UPSND: 1,8,1")
+            .expect("AT+CGPADDR=1", "+CGPADDR: 1,\"10.0.0.2\"");
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let apn = APNInfo {
+            user_name: Some("user"),
+            password: Some("secret"),
+            auth_type: AuthenticationType::PAP,
+            ..APNInfo::default()
+        };
+        assert!(device.data_service(&apn).is_ok());
+        modem.assert_done();
+    }
+
+    #[test]
+    fn dual_stack_context_is_defined_and_addressed() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        modem
+            .expect("AT+CFUN=0,0", "")
+            .expect("AT+CGDCONT=1,\"IPV4V6\",\"em\"", "")
+            .expect("AT+CFUN=1,0", "")
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+CGACT?", "+CGACT: 1,1")
+            .expect("AT+UPSD=1,100", "+UPSD: 1,100,1")
+            .expect("AT+UPSND=1,8", "+UPSND: 1,8,1")
+            // IPv6 addresses are reported in the dotted decimal notation by default
+            .expect(
+                "AT+CGPADDR=1",
+                "+CGPADDR: 1,\"10.0.0.2\",\"32.1.13.184.0.0.0.0.0.0.0.0.0.0.0.2\"",
+            )
+            .expect("AT+UDNSRN=0,\"example.com\"", "+UDNSRN: \"2001:db8::1\"");
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let apn = APNInfo::with_pdp_type("em", PdpType::Ipv4v6);
+        let mut data_service = device.data_service(&apn).unwrap();
+        assert_eq!(
+            data_service.ip_addr(),
+            Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+        );
+
+        let ipv6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        assert_eq!(
+            data_service
+                .get_host_by_name("example.com", AddrType::IPv6)
+                .ok(),
+            Some(ipv6)
+        );
+        drop(data_service);
+        modem.assert_done();
+    }
+
+    #[cfg(feature = "apn-db")]
+    #[test]
+    fn automatic_apn_falls_back_through_operator_candidates() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        // The IMSI of the SIM is the one of TDC, Denmark
+        modem
+            .expect("AT+CFUN=0,0", "")
+            .expect("AT+CGDCONT=1,\"IP\",\"internet\"", "")
+            .expect("AT+CFUN=1,0", "")
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+CGACT?", "+CGACT: 1,0")
+            .expect_err("AT+CGACT=1,1", atat::Error::Error)
+            // Then the APN is left to the network
+            .expect("AT+CFUN=0,0", "")
+            .expect("AT+CGDCONT=1,\"IP\",\"\"", "")
+            .expect("AT+CFUN=1,0", "")
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+CGACT?", "+CGACT: 1,1")
+            .expect("AT+UPSD=1,100", "+UPSD: 1,100,1")
+            .expect("AT+UPSND=1,8", "+UPSND: 1,8,1")
+            .expect("AT+CGPADDR=1", "+CGPADDR: 1,\"10.0.0.2\"");
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let apn = APNInfo::default();
+        assert!(device.data_service(&apn).is_err());
+        assert!(device.data_service(&apn).is_ok());
+        modem.assert_done();
     }
 }
@@ -0,0 +1,95 @@
+//! Operator APN database
+//!
+//! With the `apn-db` feature, an [`Apn::Automatic`] is looked up from the
+//! MCC-MNC of the IMSI read with `+CIMI`. The candidates of the operator are
+//! tried in turn, moving to the next one each time the context fails to
+//! activate, then an empty APN leaves it to the network, before starting over.
+//!
+//! [`Apn::Automatic`]: super::apn::Apn::Automatic
+
+use super::apn::{APNInfo, Apn};
+use core::fmt::Write;
+use heapless::String;
+
+/// APN of an operator
+struct Entry {
+    /// MCC and MNC, the IMSI starts with
+    plmn: &'static str,
+    apn: &'static str,
+    user_name: Option<&'static str>,
+    password: Option<&'static str>,
+}
+
+const fn entry(plmn: &'static str, apn: &'static str) -> Entry {
+    Entry {
+        plmn,
+        apn,
+        user_name: None,
+        password: None,
+    }
+}
+
+const fn entry_with_credentials(
+    plmn: &'static str,
+    apn: &'static str,
+    user_name: &'static str,
+    password: &'static str,
+) -> Entry {
+    Entry {
+        plmn,
+        apn,
+        user_name: Some(user_name),
+        password: Some(password),
+    }
+}
+
+/// Candidates of an operator are listed in the order they are tried
+static DATABASE: &[Entry] = &[
+    // Denmark
+    entry("23801", "internet"),
+    entry("23802", "internet"),
+    // United Kingdom
+    entry_with_credentials("23410", "mobile.o2.co.uk", "o2web", "password"),
+    entry_with_credentials("23415", "internet", "web", "web"),
+    entry_with_credentials("23415", "pp.vodafone.co.uk", "web", "web"),
+    entry_with_credentials("23430", "everywhere", "eesecure", "secure"),
+    // Sweden
+    entry("24001", "online.telia.se"),
+    // Germany
+    entry("26201", "internet.telekom"),
+    entry("26202", "web.vodafone.de"),
+    entry("26203", "internet"),
+    entry("26207", "internet"),
+    // United States
+    entry("310260", "fast.t-mobile.com"),
+    entry("310410", "m2m.com.attz"),
+    entry("310410", "broadband"),
+    entry("311480", "vzwinternet"),
+];
+
+/// APN to try at the `attempt`th activation of a context of the SIM of
+/// `imsi`, `None` if its operator is unknown
+pub(crate) fn candidate(imsi: u64, attempt: usize) -> Option<APNInfo<'static>> {
+    let mut digits = String::<20>::new();
+    write!(&mut digits, "{imsi}").ok()?;
+
+    let mut candidates = DATABASE
+        .iter()
+        .filter(|entry| digits.starts_with(entry.plmn));
+    let count = candidates.clone().count();
+    if count == 0 {
+        return None;
+    }
+
+    // After the known APNs, leave it to the network
+    let candidate = match candidates.nth(attempt % (count + 1)) {
+        Some(entry) => APNInfo {
+            apn: Apn::Given(entry.apn),
+            user_name: entry.user_name,
+            password: entry.password,
+            ..APNInfo::default()
+        },
+        None => APNInfo::new(""),
+    };
+    Some(candidate)
+}
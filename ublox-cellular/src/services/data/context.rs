@@ -0,0 +1,324 @@
+//! PDP contexts of the data connections
+//!
+//! Each data connection runs over a PDP context, defined with `AT+CGDCONT`,
+//! and mapped to a PSD profile on the modules activating contexts through
+//! `AT+UPSD`. Several of them can be active at the same time, e.g. a private
+//! APN for device management next to a public one. The driver tracks the
+//! state of each context from `+UUPSDA`, `+UUPSDD` and `+CGEV`.
+
+use super::{usage::DataBudget, ContextState, Error};
+use crate::network::{ContextId, ProfileId};
+use embedded_nal::IpAddr;
+use heapless::Vec;
+
+/// Maximum number of PDP contexts in use at the same time
+pub const MAX_CONTEXTS: usize = 3;
+
+/// PDP context, and the PSD profile it is mapped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PdpContext {
+    pub cid: ContextId,
+    pub profile_id: ProfileId,
+}
+
+impl PdpContext {
+    /// Context of [`data_service`](crate::GsmClient::data_service)
+    pub const DEFAULT: Self = Self::new(ContextId(1), ProfileId(1));
+
+    pub const fn new(cid: ContextId, profile_id: ProfileId) -> Self {
+        Self { cid, profile_id }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    context: PdpContext,
+    state: ContextState,
+    /// IP address of the context, valid while it is active
+    ip_addr: Option<IpAddr>,
+    /// Number of failed activations, selecting the APN candidate to try
+    failed_activations: usize,
+}
+
+/// State of the PDP contexts in use
+#[derive(Debug, Clone)]
+pub(crate) struct Contexts {
+    entries: Vec<Entry, MAX_CONTEXTS>,
+    /// Profile of the last `AT+UPSDA` activation, whose result is reported
+    /// by `+UUPSDA` without the profile id
+    pub(crate) activating_profile: Option<ProfileId>,
+    /// Cap on the bytes sent and received, see
+    /// [`CellularConfig::DATA_BUDGET`](crate::CellularConfig::DATA_BUDGET)
+    pub(crate) data_budget: Option<DataBudget>,
+}
+
+impl Contexts {
+    pub(crate) fn new() -> Self {
+        let mut contexts = Self {
+            entries: Vec::new(),
+            activating_profile: None,
+            data_budget: None,
+        };
+        contexts.register(PdpContext::DEFAULT).ok();
+        contexts
+    }
+
+    /// Start tracking `context`. Fails if [`MAX_CONTEXTS`] are already
+    /// tracked, or if its profile is mapped to another context.
+    pub(crate) fn register(&mut self, context: PdpContext) -> Result<(), Error> {
+        match self
+            .entries
+            .iter()
+            .find(|e| e.context.cid == context.cid || e.context.profile_id == context.profile_id)
+        {
+            Some(entry) if entry.context == context => Ok(()),
+            Some(_) => Err(Error::InvalidContext),
+            None => self
+                .entries
+                .push(Entry {
+                    context,
+                    state: ContextState::Setup,
+                    ip_addr: None,
+                    failed_activations: 0,
+                })
+                .map_err(|_| Error::InvalidContext),
+        }
+    }
+
+    fn get(&self, cid: ContextId) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.context.cid == cid)
+    }
+
+    fn get_mut(&mut self, cid: ContextId) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|e| e.context.cid == cid)
+    }
+
+    /// State of `cid`, [`ContextState::Setup`] if not tracked
+    pub(crate) fn state(&self, cid: ContextId) -> ContextState {
+        self.get(cid).map_or(ContextState::Setup, |e| e.state)
+    }
+
+    /// IP address of `cid`, while it is active
+    pub(crate) fn ip_addr(&self, cid: ContextId) -> Option<IpAddr> {
+        self.get(cid)
+            .filter(|e| e.state == ContextState::Active)
+            .and_then(|e| e.ip_addr)
+    }
+
+    /// States of the tracked contexts
+    pub(crate) fn states(&self) -> impl Iterator<Item = (ContextId, ContextState)> + '_ {
+        self.entries.iter().map(|e| (e.context.cid, e.state))
+    }
+
+    pub(crate) fn set_state(&mut self, cid: ContextId, state: ContextState) {
+        if let Some(entry) = self.get_mut(cid) {
+            entry.state = state;
+        }
+    }
+
+    pub(crate) fn set_active(&mut self, cid: ContextId, ip_addr: Option<IpAddr>) {
+        if let Some(entry) = self.get_mut(cid) {
+            entry.state = ContextState::Active;
+            entry.ip_addr = ip_addr;
+        }
+    }
+
+    /// Number of failed activations of `cid`
+    pub(crate) fn failed_activations(&self, cid: ContextId) -> usize {
+        self.get(cid).map_or(0, |e| e.failed_activations)
+    }
+
+    /// Define `cid` again before the next activation attempt, possibly with
+    /// another APN
+    pub(crate) fn activation_failed(&mut self, cid: ContextId) {
+        if let Some(entry) = self.get_mut(cid) {
+            entry.state = ContextState::Setup;
+            entry.failed_activations = entry.failed_activations.wrapping_add(1);
+        }
+    }
+
+    /// Whether a context other than `cid` is activating or active
+    pub(crate) fn others_in_use(&self, cid: ContextId) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.context.cid != cid && e.state != ContextState::Setup)
+    }
+
+    /// Context mapped to `profile_id`
+    pub(crate) fn by_profile(&self, profile_id: ProfileId) -> Option<ContextId> {
+        self.entries
+            .iter()
+            .find(|e| e.context.profile_id == profile_id)
+            .map(|e| e.context.cid)
+    }
+
+    /// Check `cid` again before using it, if active, e.g. after a `+CGEV`
+    /// deactivation
+    pub(crate) fn deactivate(&mut self, cid: ContextId) {
+        if let Some(entry) = self.get_mut(cid) {
+            if entry.state == ContextState::Active {
+                entry.state = ContextState::Activating;
+            }
+        }
+    }
+
+    /// Check the active contexts again before using them, e.g. after a
+    /// `+CGEV` detach, or a deactivation not telling which context it
+    /// concerns
+    pub(crate) fn deactivate_all(&mut self) {
+        for entry in self.entries.iter_mut() {
+            if entry.state == ContextState::Active {
+                entry.state = ContextState::Activating;
+            }
+        }
+    }
+
+    /// Count `bytes` sent or received through the sockets against the data
+    /// budget
+    pub(crate) fn record_data(&mut self, bytes: usize) {
+        if let Some(budget) = self.data_budget.as_mut() {
+            budget.record(bytes);
+        }
+    }
+
+    /// Define every context again, e.g. once the module is reconfigured
+    pub(crate) fn reset(&mut self) {
+        for entry in self.entries.iter_mut() {
+            entry.state = ContextState::Setup;
+            entry.ip_addr = None;
+        }
+        self.activating_profile = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        test_support::{
+            expect_context_activation, expect_initialize, expect_initialize_model, new_device,
+            MockConfig, MockModem, L, N,
+        },
+        APNInfo, Event,
+    };
+    use embedded_nal::{Ipv4Addr, TcpClientStack};
+    use ublox_sockets::SocketSet;
+
+    #[test]
+    fn contexts_are_activated_and_lost_independently() {
+        let modem = MockModem::new();
+        expect_initialize_model(&modem, "SARA-R510M8S");
+        modem
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+UPSND=1,8", "+UPSND: 1,8,0")
+            .expect("AT+UPSD=1,1,\"em\"", "")
+            .expect("AT+UPSD=1,0,0", "")
+            .expect("AT+UPSD=1,100,1", "")
+            .expect("AT+UPSDA=1,3", "")
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+UPSND=2,8", "+UPSND: 2,8,0")
+            .expect("AT+UPSD=2,1,\"mgmt\"", "")
+            .expect("AT+UPSD=2,0,0", "")
+            .expect("AT+UPSD=2,100,2", "")
+            .expect("AT+UPSDA=2,3", "");
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let management = PdpContext::new(ContextId(2), ProfileId(2));
+        assert!(device.data_service(&APNInfo::new("em")).is_ok());
+        assert!(device
+            .data_service_on(management, &APNInfo::new("mgmt"))
+            .is_ok());
+        assert_eq!(
+            device.network.contexts.state(ContextId(2)),
+            ContextState::Active
+        );
+
+        // A profile is mapped to a single context
+        assert!(device
+            .data_service_on(
+                PdpContext::new(ContextId(3), ProfileId(2)),
+                &APNInfo::new("mgmt")
+            )
+            .is_err());
+
+        modem.urc("+CGEV: NW PDN DEACT 2").flush_urcs();
+        device.spin().ok();
+
+        assert_eq!(
+            device.network.contexts.state(ContextId(1)),
+            ContextState::Active
+        );
+        assert_eq!(
+            device.network.contexts.state(ContextId(2)),
+            ContextState::Activating
+        );
+        assert!(device
+            .config
+            .events
+            .contains(&Event::ContextDeactivated(ContextId(2))));
+
+        modem.urc("+UUPSDD: 1").flush_urcs();
+        device.spin().ok();
+
+        assert_eq!(
+            device.network.contexts.state(ContextId(1)),
+            ContextState::Activating
+        );
+        modem.assert_done();
+    }
+
+    #[test]
+    fn context_is_defined_without_tearing_down_active_ones() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        expect_context_activation(&modem);
+        // Context 1 is in use, so context 2 is defined at full functionality
+        modem
+            .expect("AT+CGDCONT=2,\"IP\",\"mgmt\"", "")
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+CGACT?", "+CGACT: 1,1")
+            .expect("AT+CGACT=1,2", "");
+
+        let mut device = new_device(&modem, MockConfig::new());
+        device.set_socket_storage(Box::leak(Box::new(SocketSet::<N, L>::new())));
+
+        let management = PdpContext::new(ContextId(2), ProfileId(2));
+        let mgmt = APNInfo::new("mgmt");
+        assert!(device.data_service(&APNInfo::new("em")).is_ok());
+        assert!(matches!(
+            device.data_service_on(management, &mgmt),
+            Err(nb::Error::WouldBlock)
+        ));
+        assert_eq!(
+            device.network.contexts.state(ContextId(1)),
+            ContextState::Active
+        );
+
+        modem
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+CGACT?", "+CGACT: 1,1\r\n+CGACT: 2,1")
+            .expect("AT+UPSD=2,100", "+UPSD: 2,100,2")
+            .expect("AT+UPSND=2,8", "+UPSND: 2,8,1")
+            .expect("AT+CGPADDR=2", "+CGPADDR: 2,\"10.0.0.3\"");
+
+        let mut data_service = device.data_service_on(management, &mgmt).unwrap();
+        // Sockets can not be bound to another context than the default one
+        assert_eq!(
+            TcpClientStack::socket(&mut data_service),
+            Err(ublox_sockets::Error::Unaddressable)
+        );
+        drop(data_service);
+
+        assert_eq!(
+            device.network.contexts.state(ContextId(1)),
+            ContextState::Active
+        );
+        assert_eq!(
+            device.network.contexts.ip_addr(ContextId(2)),
+            Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)))
+        );
+        modem.assert_done();
+    }
+}
@@ -0,0 +1,422 @@
+//! Fibocom dialect: `+MIPxxx` commands
+use super::{check_security_data, string_arg, Dialect, SecurityDataKind};
+use crate::{
+    command::{
+        fibocom::{
+            responses::{InternetConnection, OpenSocketResponse},
+            types::{
+                CertificateType, ConnectionOperation, ConnectionStatus, MipProtocol, SslParam,
+            },
+            CloseSocket, GetInternetConnection, OpenSocket, PrepareCertificateImport,
+            PushSocketData, ReadSocketData, ResolveHost, SendCertificateData, SendSocketData,
+            SetInternetConnection, SetSslConfig,
+        },
+        ip_transport_layer::responses::SocketData,
+        psn::responses::DataCounters,
+    },
+    error::GenericError,
+    network::{ContextId, Network},
+    services::data::{
+        apn::{APNInfo, Apn, PdpType},
+        context::PdpContext,
+        hex,
+        ssl::SecurityProfileId,
+        usage::DataUsage,
+        ContextState, Error,
+    },
+};
+use atat::blocking::AtatClient;
+use embedded_nal::{IpAddr, SocketAddr};
+use heapless::{String, Vec};
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketType};
+
+/// Maximum number of bytes sent with a single `+MIPSEND`, as the data is hex
+/// encoded.
+pub(crate) const SEND_CHUNK_SIZE: usize = 512;
+
+/// Dialect of the Fibocom modules
+#[derive(Debug, Clone, Copy)]
+pub struct Fibocom;
+
+impl Fibocom {
+    fn open_socket<AtCl: AtatClient>(
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+        protocol: MipProtocol,
+    ) -> Result<(), Error> {
+        let OpenSocketResponse { state, .. } = network.send_internal(
+            &OpenSocket {
+                socket,
+                local_port: None,
+                remote_addr: remote.ip(),
+                remote_port: remote.port(),
+                protocol,
+            },
+            false,
+        )?;
+
+        if state != 1 {
+            return Err(Error::Socket(SocketError::Unaddressable));
+        }
+
+        Ok(())
+    }
+
+    fn send<AtCl: AtatClient>(
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        for chunk in data.chunks(SEND_CHUNK_SIZE) {
+            trace!("Sending: {} bytes", chunk.len());
+            let hex =
+                hex::to_hex::<{ SEND_CHUNK_SIZE * 2 }>(chunk).map_err(|_| Error::BadLength)?;
+
+            let response = network.send_internal(&SendSocketData { socket, data: &hex }, false)?;
+            if response.socket != socket {
+                return Err(Error::Socket(SocketError::InvalidSocket));
+            }
+
+            network.send_internal(&PushSocketData { socket }, false)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<AtCl: AtatClient> Dialect<AtCl> for Fibocom {
+    fn define_context(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _cid: ContextId,
+        _apn_info: &APNInfo,
+    ) -> Result<(), Error> {
+        // The APN is given along `+MIPCALL` when activating
+        Ok(())
+    }
+
+    fn activate_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error> {
+        // `+MIPCALL` brings up a single IPv4 connection
+        if context != PdpContext::DEFAULT || apn_info.pdp_type != PdpType::Ip {
+            return Err(nb::Error::Other(Error::Generic(GenericError::Unsupported)));
+        }
+
+        let InternetConnection { status, ip_addr } = network
+            .send_internal(&GetInternetConnection, true)
+            .map_err(Error::from)?;
+
+        if status == ConnectionStatus::Connected {
+            network.contexts.set_active(context.cid, ip_addr);
+            return Ok(());
+        }
+
+        let apn = match apn_info.apn {
+            Apn::Given(apn) => Some(apn),
+            Apn::Automatic => None,
+        };
+
+        network
+            .send_internal(
+                &SetInternetConnection {
+                    operation: ConnectionOperation::Connect,
+                    apn,
+                    username: apn_info.user_name,
+                    password: apn_info.password,
+                },
+                true,
+            )
+            .map_err(Error::from)?;
+
+        network
+            .contexts
+            .set_state(context.cid, ContextState::Activating);
+        Err(nb::Error::WouldBlock)
+    }
+
+    fn create_socket(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        socket_type: SocketType,
+        candidate: SocketHandle,
+    ) -> Result<SocketHandle, Error> {
+        // `+MIPCALL` only brings up the default context
+        if context != PdpContext::DEFAULT {
+            return Err(Error::Generic(GenericError::Unsupported));
+        }
+
+        // Sockets are only created on the module by `+MIPOPEN`, with an id
+        // chosen by the host
+        match socket_type {
+            SocketType::Tcp | SocketType::Udp => Ok(candidate),
+            _ => Err(Error::WrongSocketType),
+        }
+    }
+
+    fn connect_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        Self::open_socket(network, socket, remote, MipProtocol::Tls)
+    }
+
+    fn connect_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        Self::open_socket(network, socket, remote, MipProtocol::Udp)
+    }
+
+    fn send_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        Self::send(network, socket, data)
+    }
+
+    fn send_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        _remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        // The remote endpoint is fixed by `+MIPOPEN`
+        Self::send(network, socket, data)
+    }
+
+    fn read_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        _socket_type: SocketType,
+        length: usize,
+    ) -> Result<SocketData, Error> {
+        let data = network.send_internal(&ReadSocketData { socket, length }, false)?;
+
+        Ok(SocketData {
+            socket: data.socket,
+            length: data.length,
+            data: data.data,
+        })
+    }
+
+    fn close_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<(), Error> {
+        network.send_internal(&CloseSocket { socket }, false)?;
+        Ok(())
+    }
+
+    fn socket_usage(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _socket: SocketHandle,
+    ) -> Result<DataUsage, Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    fn data_counters(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+    ) -> Result<Vec<DataCounters, 8>, Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    fn reset_data_counters(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _cid: ContextId,
+    ) -> Result<(), Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    fn resolve_host(&self, network: &mut Network<'_, AtCl>, host: &str) -> Result<IpAddr, Error> {
+        let resp = network.send_internal(&ResolveHost { host }, true)?;
+        resp.ip_addr.parse().map_err(|_| Error::Dns)
+    }
+
+    fn resolve_address(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _ip_addr: IpAddr,
+    ) -> Result<String<256>, Error> {
+        Err(Error::Generic(GenericError::Unsupported))
+    }
+
+    fn import_security_data(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &str,
+        data: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error> {
+        let commands = SecurityDataImport::new(profile_id, kind, name, data, password)?;
+        network.send_internal(&commands.prepare, true)?;
+        network.send_internal(&commands.data, true)?;
+        network.send_internal(&commands.assign, true)?;
+
+        Ok(())
+    }
+
+    fn enable_ssl(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error> {
+        for cmd in &ssl_config(profile_id, server_hostname, use_sni)? {
+            network.send_internal(cmd, true)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `+MIPCERT` commands importing security data under `name`, and the
+/// `+MIPSSLCFG` command assigning it to `profile_id`, sent in the order of the
+/// fields
+pub(crate) struct SecurityDataImport<'a> {
+    pub prepare: PrepareCertificateImport<'a>,
+    pub data: SendCertificateData<'a>,
+    pub assign: SetSslConfig,
+}
+
+impl<'a> SecurityDataImport<'a> {
+    pub(crate) fn new(
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &'a str,
+        data: &'a [u8],
+        password: Option<&'a str>,
+    ) -> Result<Self, Error> {
+        check_security_data(data, password)?;
+        let cert_name = string_arg(name)?;
+
+        let (cert_type, param) = match kind {
+            SecurityDataKind::TrustedRootCa => (
+                CertificateType::TrustedRootCa,
+                SslParam::TrustedRootCa(cert_name),
+            ),
+            SecurityDataKind::ClientCertificate => (
+                CertificateType::ClientCertificate,
+                SslParam::ClientCertificate(cert_name),
+            ),
+            SecurityDataKind::ClientPrivateKey => (
+                CertificateType::ClientPrivateKey,
+                SslParam::ClientPrivateKey(cert_name),
+            ),
+        };
+
+        Ok(Self {
+            prepare: PrepareCertificateImport {
+                cert_type,
+                name,
+                data_size: data.len(),
+                password,
+            },
+            data: SendCertificateData {
+                data: atat::serde_bytes::Bytes::new(data),
+            },
+            assign: SetSslConfig { profile_id, param },
+        })
+    }
+}
+
+/// `+MIPSSLCFG` settings of `profile_id` for TLS connections to
+/// `server_hostname`
+pub(crate) fn ssl_config(
+    profile_id: SecurityProfileId,
+    server_hostname: &str,
+    use_sni: bool,
+) -> Result<[SetSslConfig; 2], Error> {
+    // Fibocom uses the same parameter for the expected host name and SNI,
+    // so host name verification is skipped without SNI
+    let server_name = if use_sni {
+        string_arg(server_hostname)?
+    } else {
+        String::new()
+    };
+
+    Ok(
+        [SslParam::VerifyLevel(2), SslParam::ServerName(server_name)]
+            .map(|param| SetSslConfig { profile_id, param }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        module::ModuleKind,
+        services::data::ContextState,
+        test_support::{
+            connected_tcp_socket, expect_initialize_model, new_device, MockConfig, MockModem, L, N,
+        },
+        APNInfo, ContextId,
+    };
+    use embedded_nal::TcpClientStack;
+    use ublox_sockets::SocketSet;
+
+    #[test]
+    fn fibocom_activates_context_and_sends_through_mip_commands() {
+        let modem = MockModem::new();
+        expect_initialize_model(&modem, "L610");
+        modem
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+MIPCALL?", "+MIPCALL: 0")
+            .expect("AT+MIPCALL=1,\"em\"", "");
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let sockets = Box::leak(Box::new(SocketSet::<N, L>::new()));
+        let mut handle = connected_tcp_socket(sockets);
+        device.set_socket_storage(sockets);
+
+        let apn = APNInfo::new("em");
+        assert!(matches!(
+            device.data_service(&apn),
+            Err(nb::Error::WouldBlock)
+        ));
+        assert_eq!(device.network.module, ModuleKind::Fibocom);
+        assert_eq!(
+            device.network.contexts.state(ContextId(1)),
+            ContextState::Activating
+        );
+
+        modem
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+MIPCALL?", "+MIPCALL: 1,\"10.0.0.2\"")
+            .expect("AT+MIPSEND=0,\"DEADBEEF\"", "+MIPSEND: 0,1020")
+            .expect("AT+MIPPUSH=0", "");
+
+        let mut data_service = device.data_service(&apn).unwrap();
+        assert_eq!(
+            data_service.send(&mut handle, &[0xDE, 0xAD, 0xBE, 0xEF]),
+            Ok(4)
+        );
+        drop(data_service);
+
+        assert_eq!(
+            device.network.contexts.state(ContextId(1)),
+            ContextState::Active
+        );
+        modem.assert_done();
+    }
+}
@@ -0,0 +1,257 @@
+//! AT command dialects of the data service
+//!
+//! PDP context activation, sockets, DNS and TLS are driven through vendor
+//! proprietary commands. Everything above this module ([`DataService`] and
+//! the `embedded-nal` stacks) only talks to a [`Dialect`], selected from the
+//! detected [`ModuleKind`].
+//!
+//! [`DataService`]: super::DataService
+
+pub(crate) mod fibocom;
+pub(crate) mod ublox;
+
+pub use fibocom::Fibocom;
+pub use ublox::Ublox;
+
+use super::{apn::APNInfo, context::PdpContext, ssl::SecurityProfileId, usage::DataUsage, Error};
+use super::{apn::Apn, context::Contexts, ContextState};
+use crate::{
+    command::{
+        ip_transport_layer::responses::SocketData,
+        mobile_control::SetModuleFunctionality,
+        psn::{responses::DataCounters, SetAuthParameters, SetPDPContextDefinition},
+    },
+    module::{ModuleKind, Vendor},
+    network::{ContextId, Network},
+    setup::{minimum_functionality, FULL_FUNCTIONALITY},
+};
+use atat::blocking::AtatClient;
+use embedded_nal::{IpAddr, SocketAddr};
+use heapless::{String, Vec};
+use ublox_sockets::{SocketHandle, SocketType};
+
+/// Kind of security data imported into the module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SecurityDataKind {
+    TrustedRootCa,
+    ClientCertificate,
+    ClientPrivateKey,
+}
+
+/// `value` as a string argument of at most `N` bytes, failing with
+/// [`Error::BadLength`] instead of overflowing it
+pub(crate) fn string_arg<const N: usize>(value: &str) -> Result<String<N>, Error> {
+    value.parse().map_err(|_| Error::BadLength)
+}
+
+/// Refuse security data or a password not fitting in the import commands
+pub(crate) fn check_security_data(data: &[u8], password: Option<&str>) -> Result<(), Error> {
+    match password {
+        _ if data.len() > 2048 => Err(Error::BadLength),
+        Some(password) if password.len() > 128 => Err(Error::BadLength),
+        _ => Ok(()),
+    }
+}
+
+/// Vendor specific implementation of the data service commands
+pub trait Dialect<AtCl: AtatClient> {
+    /// Define the PDP context `cid` while the module is registering. Called
+    /// on every `data_service` call until the context is activating.
+    fn define_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+        apn_info: &APNInfo,
+    ) -> Result<(), Error>;
+
+    /// Activate the data connection over `context`, once attached to the
+    /// network. Returns `WouldBlock` while the activation is still pending.
+    fn activate_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error>;
+
+    /// Create a socket of `socket_type`, bound to the connection of
+    /// `context`. Modules letting the host choose the socket id use
+    /// `candidate`, the lowest id not in use.
+    fn create_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        socket_type: SocketType,
+        candidate: SocketHandle,
+    ) -> Result<SocketHandle, Error>;
+
+    /// Connect a TCP socket, using TLS with security profile 0.
+    fn connect_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error>;
+
+    /// Bind a UDP socket to its remote endpoint.
+    fn connect_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error>;
+
+    fn send_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error>;
+
+    fn send_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error>;
+
+    /// Read up to `length` hex encoded bytes from `socket`. With `length` 0
+    /// only the number of available bytes is returned.
+    fn read_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        socket_type: SocketType,
+        length: usize,
+    ) -> Result<SocketData, Error>;
+
+    fn close_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<(), Error>;
+
+    /// Bytes sent and received over `socket` since it was created
+    fn socket_usage(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<DataUsage, Error>;
+
+    /// Data counters of the active contexts
+    fn data_counters(&self, network: &mut Network<'_, AtCl>)
+        -> Result<Vec<DataCounters, 8>, Error>;
+
+    /// Reset the total data counters of `cid`
+    fn reset_data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+    ) -> Result<(), Error>;
+
+    fn resolve_host(&self, network: &mut Network<'_, AtCl>, host: &str) -> Result<IpAddr, Error>;
+
+    fn resolve_address(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        ip_addr: IpAddr,
+    ) -> Result<String<256>, Error>;
+
+    /// Import security data under `name`, and assign it to `profile_id`.
+    /// Fails with [`Error::BadLength`] when `name`, `data` or `password` do
+    /// not fit in the import commands.
+    fn import_security_data(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &str,
+        data: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error>;
+
+    fn enable_ssl(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error>;
+}
+
+/// Select the dialect spoken by `module`
+pub fn for_module<'a, AtCl: AtatClient + 'a>(module: ModuleKind) -> &'a dyn Dialect<AtCl> {
+    match module.vendor() {
+        Vendor::Ublox => &Ublox,
+        Vendor::Fibocom => &Fibocom,
+    }
+}
+
+/// Commands defining a context through the standard 3GPP commands, sent in
+/// the order of the fields. The first context is set while the module is at
+/// minimum functionality, which some modules require. Once another context is
+/// in use, going through minimum functionality would tear it down, so
+/// `AT+CGDCONT` is set right away.
+pub(crate) struct ContextDefinition<'a> {
+    pub minimum_functionality: Option<SetModuleFunctionality>,
+    pub definition: Option<SetPDPContextDefinition<'a>>,
+    /// Sent along the activation request, the context not being active yet.
+    /// An automatic APN may still need credentials.
+    pub credentials: Option<SetAuthParameters<'a>>,
+    pub full_functionality: Option<SetModuleFunctionality>,
+}
+
+impl<'a> ContextDefinition<'a> {
+    pub(crate) fn new(
+        contexts: &Contexts,
+        module: ModuleKind,
+        cid: ContextId,
+        apn_info: &APNInfo<'a>,
+    ) -> Self {
+        let cycle_functionality = !contexts.others_in_use(cid);
+
+        Self {
+            minimum_functionality: cycle_functionality.then(|| minimum_functionality(module)),
+            definition: match apn_info.apn {
+                Apn::Given(apn) => Some(SetPDPContextDefinition {
+                    cid,
+                    pdp_type: apn_info.pdp_type.as_str(),
+                    apn,
+                }),
+                Apn::Automatic => None,
+            },
+            credentials: apn_info.has_credentials().then(|| SetAuthParameters {
+                cid,
+                auth_type: apn_info.auth_type,
+                username: apn_info.user_name.unwrap_or_default(),
+                password: apn_info.password.unwrap_or_default(),
+            }),
+            full_functionality: cycle_functionality.then_some(FULL_FUNCTIONALITY),
+        }
+    }
+}
+
+/// Define `cid` with the [`ContextDefinition`] commands
+pub(crate) fn define_pdp_context<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    cid: ContextId,
+    apn_info: &APNInfo,
+) -> Result<(), Error> {
+    let commands = ContextDefinition::new(&network.contexts, network.module, cid, apn_info);
+    if let Some(cmd) = &commands.minimum_functionality {
+        network.send_internal(cmd, true)?;
+    }
+    if let Some(cmd) = &commands.definition {
+        network.send_internal(cmd, true)?;
+    }
+    if let Some(cmd) = &commands.credentials {
+        network.send_internal(cmd, true)?;
+    }
+    if let Some(cmd) = &commands.full_functionality {
+        network.send_internal(cmd, true)?;
+    }
+
+    network.contexts.set_state(cid, ContextState::Activating);
+    Ok(())
+}
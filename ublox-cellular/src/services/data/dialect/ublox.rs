@@ -0,0 +1,693 @@
+//! u-blox dialect: `+UPSD`, `+USOxx`, `+UDNSRN` and `+USECxxx` commands
+use super::{check_security_data, define_pdp_context, string_arg, Dialect, SecurityDataKind};
+use crate::{
+    command::{
+        device_data_security::{
+            types::{CertificateValidationLevel, SecurityDataType, SecurityProfileOperation},
+            PrepareSecurityDataImport, SecurityProfileManager, SendSecurityDataImport,
+        },
+        dns::{types::ResolutionType, ResolveNameIp},
+        ip_transport_layer::{
+            responses::{SocketData, UDPSocketData},
+            types::{SocketControlParam, SocketProtocol, SslTlsStatus},
+            CloseSocket, ConnectSocket, CreateSocket, PrepareUDPSendToDataBinary,
+            PrepareWriteSocketDataBinary, ReadSocketData, ReadUDPSocketData, SetSocketSslState,
+            SocketControl, UDPSendToDataBinary, WriteSocketDataBinary,
+        },
+        psn::{
+            self,
+            responses::{DataCounters, PDPContextState, PacketSwitchedNetworkData},
+            types::{
+                PDPContextStatus, PacketSwitchedAction, PacketSwitchedNetworkDataParam,
+                PacketSwitchedParam,
+            },
+            GetDataCounters, GetPDPContextState, GetPacketSwitchedNetworkData, SetDataCounters,
+            SetPDPContextState, SetPacketSwitchedAction, SetPacketSwitchedConfig,
+        },
+    },
+    error::GenericError,
+    module::ModuleKind,
+    network::{ContextId, Network},
+    services::data::{
+        apn::{APNInfo, Apn, PdpType},
+        context::PdpContext,
+        ssl::SecurityProfileId,
+        usage::DataUsage,
+        ContextState, Error, EGRESS_CHUNK_SIZE,
+    },
+};
+use atat::blocking::AtatClient;
+use core::fmt::Write;
+use embedded_nal::{IpAddr, Ipv4Addr, SocketAddr};
+use heapless::{String, Vec};
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketType};
+
+/// Dialect of the u-blox modules
+#[derive(Debug, Clone, Copy)]
+pub struct Ublox;
+
+impl<AtCl: AtatClient> Dialect<AtCl> for Ublox {
+    fn define_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+        apn_info: &APNInfo,
+    ) -> Result<(), Error> {
+        // With AT+UPSD-based context activation, everything is set up when
+        // activating
+        if network.module.upsd_context_activation()
+            || network.contexts.state(cid) != ContextState::Setup
+        {
+            return Ok(());
+        }
+
+        define_pdp_context(network, cid, apn_info)
+    }
+
+    fn activate_context(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<(), Error> {
+        if network.module.upsd_context_activation() {
+            activate_context_upsd(network, context, apn_info)
+        } else {
+            activate_context_3gpp(network, context, apn_info)
+        }
+    }
+
+    fn create_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        context: PdpContext,
+        socket_type: SocketType,
+        _candidate: SocketHandle,
+    ) -> Result<SocketHandle, Error> {
+        // `+USOCR` sockets go over the default context, with no way of
+        // selecting another one
+        if context != PdpContext::DEFAULT {
+            return Err(Error::Generic(GenericError::Unsupported));
+        }
+
+        let (protocol, check_urc) = match socket_type {
+            SocketType::Tcp => (SocketProtocol::TCP, true),
+            SocketType::Udp => (SocketProtocol::UDP, false),
+            _ => return Err(Error::WrongSocketType),
+        };
+
+        let socket_resp = network.send_internal(
+            &CreateSocket {
+                protocol,
+                local_port: None,
+            },
+            check_urc,
+        )?;
+
+        Ok(socket_resp.socket)
+    }
+
+    fn connect_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+    ) -> Result<(), Error> {
+        network.send_internal(
+            &SetSocketSslState {
+                socket,
+                ssl_tls_status: SslTlsStatus::Enabled(SecurityProfileId(0)),
+            },
+            true,
+        )?;
+
+        network.send_internal(
+            &ConnectSocket {
+                socket,
+                remote_addr: remote.ip(),
+                remote_port: remote.port(),
+            },
+            false,
+        )?;
+
+        Ok(())
+    }
+
+    fn connect_udp(
+        &self,
+        _network: &mut Network<'_, AtCl>,
+        _socket: SocketHandle,
+        _remote: SocketAddr,
+    ) -> Result<(), Error> {
+        // The remote endpoint is given along every `+USOST`
+        Ok(())
+    }
+
+    fn send_tcp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        for chunk in data.chunks(EGRESS_CHUNK_SIZE) {
+            trace!("Sending: {} bytes", chunk.len());
+            network.send_internal(
+                &PrepareWriteSocketDataBinary {
+                    socket,
+                    length: chunk.len(),
+                },
+                false,
+            )?;
+
+            let response = network.send_internal(
+                &WriteSocketDataBinary {
+                    data: atat::serde_bytes::Bytes::new(chunk),
+                },
+                false,
+            )?;
+
+            if response.length != chunk.len() {
+                return Err(Error::BadLength);
+            }
+            if response.socket != socket {
+                return Err(Error::Socket(SocketError::InvalidSocket));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send_udp(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        remote: SocketAddr,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        for chunk in data.chunks(EGRESS_CHUNK_SIZE) {
+            trace!("Sending: {} bytes", chunk.len());
+            network.send_internal(
+                &PrepareUDPSendToDataBinary {
+                    socket,
+                    remote_addr: remote.ip(),
+                    remote_port: remote.port(),
+                    length: chunk.len(),
+                },
+                false,
+            )?;
+
+            let response = network.send_internal(
+                &UDPSendToDataBinary {
+                    data: atat::serde_bytes::Bytes::new(chunk),
+                },
+                false,
+            )?;
+
+            if response.length != chunk.len() {
+                return Err(Error::BadLength);
+            }
+            if response.socket != socket {
+                return Err(Error::Socket(SocketError::InvalidSocket));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+        socket_type: SocketType,
+        length: usize,
+    ) -> Result<SocketData, Error> {
+        match socket_type {
+            SocketType::Tcp => {
+                Ok(network.send_internal(&ReadSocketData { socket, length }, false)?)
+            }
+            SocketType::Udp => {
+                let UDPSocketData {
+                    socket,
+                    data,
+                    length,
+                    ..
+                } = network.send_internal(&ReadUDPSocketData { socket, length }, false)?;
+
+                Ok(SocketData {
+                    socket,
+                    length,
+                    data,
+                })
+            }
+            _ => Err(Error::WrongSocketType),
+        }
+    }
+
+    fn close_socket(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<(), Error> {
+        network.send_internal(&CloseSocket { socket }, false)?;
+        Ok(())
+    }
+
+    fn socket_usage(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        socket: SocketHandle,
+    ) -> Result<DataUsage, Error> {
+        let sent = network.send_internal(
+            &SocketControl {
+                socket,
+                param_id: SocketControlParam::BytesSent,
+            },
+            false,
+        )?;
+        let received = network.send_internal(
+            &SocketControl {
+                socket,
+                param_id: SocketControlParam::BytesReceived,
+            },
+            false,
+        )?;
+
+        Ok(DataUsage {
+            sent: sent.param_val.into(),
+            received: received.param_val.into(),
+        })
+    }
+
+    fn data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+    ) -> Result<Vec<DataCounters, 8>, Error> {
+        Ok(network.send_internal(&GetDataCounters, true)?)
+    }
+
+    fn reset_data_counters(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        cid: ContextId,
+    ) -> Result<(), Error> {
+        network.send_internal(
+            &SetDataCounters {
+                cid,
+                total_bytes_sent: 0,
+                total_bytes_received: 0,
+            },
+            true,
+        )?;
+        Ok(())
+    }
+
+    fn resolve_host(&self, network: &mut Network<'_, AtCl>, host: &str) -> Result<IpAddr, Error> {
+        let resp = network.send_internal(
+            &ResolveNameIp {
+                resolution_type: ResolutionType::DomainNameToIp,
+                ip_domain_string: host,
+            },
+            true,
+        )?;
+
+        resp.ip_domain_string.parse().map_err(|_| Error::Dns)
+    }
+
+    fn resolve_address(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        ip_addr: IpAddr,
+    ) -> Result<String<256>, Error> {
+        let mut ip_str = String::<256>::new();
+        write!(&mut ip_str, "{ip_addr}").map_err(|_| Error::BadLength)?;
+
+        let resp = network.send_internal(
+            &ResolveNameIp {
+                resolution_type: ResolutionType::IpToDomainName,
+                ip_domain_string: &ip_str,
+            },
+            true,
+        )?;
+
+        Ok(String::from(resp.ip_domain_string.as_str()))
+    }
+
+    fn import_security_data(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &str,
+        data: &[u8],
+        password: Option<&str>,
+    ) -> Result<(), Error> {
+        let commands = SecurityDataImport::new(profile_id, kind, name, data, password)?;
+        network.send_internal(&commands.prepare, true)?;
+        network.send_internal(&commands.data, true)?;
+        network.send_internal(&commands.assign, true)?;
+
+        Ok(())
+    }
+
+    fn enable_ssl(
+        &self,
+        network: &mut Network<'_, AtCl>,
+        profile_id: SecurityProfileId,
+        server_hostname: &str,
+        use_sni: bool,
+    ) -> Result<(), Error> {
+        for cmd in &security_profile_config(profile_id, server_hostname, use_sni)? {
+            network.send_internal(cmd, true)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `+USECMNG` commands importing security data under `name`, and the
+/// `+USECPRF` command assigning it to `profile_id`, sent in the order of the
+/// fields
+pub(crate) struct SecurityDataImport<'a> {
+    pub prepare: PrepareSecurityDataImport<'a>,
+    pub data: SendSecurityDataImport<'a>,
+    pub assign: SecurityProfileManager,
+}
+
+impl<'a> SecurityDataImport<'a> {
+    pub(crate) fn new(
+        profile_id: SecurityProfileId,
+        kind: SecurityDataKind,
+        name: &'a str,
+        data: &'a [u8],
+        password: Option<&'a str>,
+    ) -> Result<Self, Error> {
+        check_security_data(data, password)?;
+        let internal_name = string_arg(name)?;
+
+        let (data_type, operation) = match kind {
+            SecurityDataKind::TrustedRootCa => (
+                SecurityDataType::TrustedRootCA,
+                SecurityProfileOperation::TrustedRootCertificateInternalName(internal_name),
+            ),
+            SecurityDataKind::ClientCertificate => (
+                SecurityDataType::ClientCertificate,
+                SecurityProfileOperation::ClientCertificateInternalName(internal_name),
+            ),
+            SecurityDataKind::ClientPrivateKey => (
+                SecurityDataType::ClientPrivateKey,
+                SecurityProfileOperation::ClientPrivateKeyInternalName(internal_name),
+            ),
+        };
+
+        Ok(Self {
+            prepare: PrepareSecurityDataImport {
+                data_type,
+                data_size: data.len(),
+                internal_name: name,
+                password,
+            },
+            data: SendSecurityDataImport {
+                data: atat::serde_bytes::Bytes::new(data),
+            },
+            assign: SecurityProfileManager {
+                profile_id,
+                operation: Some(operation),
+            },
+        })
+    }
+}
+
+/// `+USECPRF` settings of `profile_id` for TLS connections to
+/// `server_hostname`
+pub(crate) fn security_profile_config(
+    profile_id: SecurityProfileId,
+    server_hostname: &str,
+    use_sni: bool,
+) -> Result<[SecurityProfileManager; 4], Error> {
+    let sni = if use_sni {
+        string_arg(server_hostname)?
+    } else {
+        String::new()
+    };
+
+    Ok([
+        SecurityProfileOperation::CertificateValidationLevel(
+            CertificateValidationLevel::RootCertValidationWithValidityDate,
+        ),
+        SecurityProfileOperation::CipherSuite(0),
+        SecurityProfileOperation::ExpectedServerHostname(string_arg(server_hostname)?),
+        SecurityProfileOperation::ServerNameIndication(sni),
+    ]
+    .map(|operation| SecurityProfileManager {
+        profile_id,
+        operation: Some(operation),
+    }))
+}
+
+/// `+UPSD` parameters of the PSD profile of `context`, set before
+/// activating it through `AT+UPSDA`
+pub(crate) fn upsd_profile_config(
+    module: ModuleKind,
+    context: PdpContext,
+    apn_info: &APNInfo,
+) -> Vec<SetPacketSwitchedConfig, 6> {
+    let PdpContext { cid, profile_id } = context;
+    let mut config = Vec::new();
+    let mut set = |param| {
+        config
+            .push(SetPacketSwitchedConfig { profile_id, param })
+            .ok();
+    };
+
+    // SARA-U2 pattern: everything is done through AT+UPSD
+    if let Apn::Given(apn) = apn_info.apn {
+        set(PacketSwitchedParam::APN(String::from(apn)));
+    }
+    if let Some(user_name) = apn_info.user_name {
+        set(PacketSwitchedParam::Username(String::from(user_name)));
+    }
+    if let Some(password) = apn_info.password {
+        set(PacketSwitchedParam::Password(String::from(password)));
+    }
+
+    if module == ModuleKind::SaraR5 {
+        if apn_info.has_credentials() {
+            set(PacketSwitchedParam::Authentication(apn_info.auth_type));
+        }
+        set(PacketSwitchedParam::ProtocolType(
+            apn_info.pdp_type.protocol_type(),
+        ));
+        set(PacketSwitchedParam::MapProfile(cid));
+    } else {
+        // Dynamic IP address assignment, and authentication protocol of the
+        // credentials
+        set(PacketSwitchedParam::IPAddress(
+            Ipv4Addr::unspecified().into(),
+        ));
+        set(PacketSwitchedParam::Authentication(apn_info.auth_type));
+    }
+
+    config
+}
+
+/// Whether `cid` is activated, according to `AT+CGACT?`
+pub(crate) fn context_activated(states: &[PDPContextState], cid: ContextId) -> bool {
+    states
+        .iter()
+        .find_map(|state| {
+            if state.cid == cid {
+                Some(state.status == PDPContextStatus::Activated)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(false)
+}
+
+/// Activate context using AT+UPSD commands
+/// Required for SARA-G3, SARA-U2 SARA-R5 modules.
+fn activate_context_upsd<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    context: PdpContext,
+    apn_info: &APNInfo,
+) -> nb::Result<(), Error> {
+    let PdpContext { cid, profile_id } = context;
+    if network.contexts.state(cid) == ContextState::Active {
+        return Ok(());
+    }
+
+    // Only the SARA-R5 PSD profiles have a protocol type, the others being
+    // IPv4 only
+    if apn_info.pdp_type != PdpType::Ip && network.module != ModuleKind::SaraR5 {
+        return Err(nb::Error::Other(Error::Generic(GenericError::Unsupported)));
+    }
+
+    // Check if the PSD profile is activated (param_tag = 1)
+    let PacketSwitchedNetworkData { param_tag, .. } = network
+        .send_internal(
+            &GetPacketSwitchedNetworkData {
+                profile_id,
+                param: PacketSwitchedNetworkDataParam::PsdProfileStatus,
+            },
+            true,
+        )
+        .map_err(Error::from)?;
+
+    if param_tag == 0 {
+        network.contexts.set_state(cid, ContextState::Activating);
+
+        for cmd in upsd_profile_config(network.module, context, apn_info) {
+            network.send_internal(&cmd, true).map_err(Error::from)?;
+        }
+
+        network.contexts.activating_profile = Some(profile_id);
+        network
+            .send_internal(
+                &SetPacketSwitchedAction {
+                    profile_id,
+                    action: PacketSwitchedAction::Activate,
+                },
+                true,
+            )
+            .map_err(Error::from)?;
+    }
+
+    network.contexts.set_state(cid, ContextState::Active);
+    Ok(())
+}
+
+/// Activate context using 3GPP commands
+/// Required for SARA-R4 and TOBY modules.
+fn activate_context_3gpp<AtCl: AtatClient>(
+    network: &mut Network<'_, AtCl>,
+    context: PdpContext,
+    apn_info: &APNInfo,
+) -> nb::Result<(), Error> {
+    let PdpContext { cid, profile_id } = context;
+    if network.contexts.state(cid) == ContextState::Active {
+        return Ok(());
+    }
+
+    let context_states = network
+        .send_internal(&GetPDPContextState, true)
+        .map_err(Error::from)?;
+
+    if context_activated(&context_states, cid) {
+        // Note: SARA-R4 only supports a single context at any one time and
+        // so doesn't require/support AT+UPSD.
+        if network.module.maps_psd_profile() {
+            if let psn::responses::PacketSwitchedConfig {
+                param: psn::types::PacketSwitchedParam::MapProfile(context),
+                ..
+            } = network
+                .send_internal(
+                    &psn::GetPacketSwitchedConfig {
+                        profile_id,
+                        param: psn::types::PacketSwitchedParamReq::MapProfile,
+                    },
+                    true,
+                )
+                .map_err(Error::from)?
+            {
+                if context != cid {
+                    network
+                        .send_internal(
+                            &psn::SetPacketSwitchedConfig {
+                                profile_id,
+                                param: psn::types::PacketSwitchedParam::MapProfile(cid),
+                            },
+                            true,
+                        )
+                        .map_err(Error::from)?;
+
+                    network
+                        .send_internal(
+                            &psn::GetPacketSwitchedNetworkData {
+                                profile_id,
+                                param: psn::types::PacketSwitchedNetworkDataParam::PsdProfileStatus,
+                            },
+                            true,
+                        )
+                        .map_err(Error::from)?;
+                }
+            }
+
+            let psn::responses::PacketSwitchedNetworkData { param_tag, .. } = network
+                .send_internal(
+                    &psn::GetPacketSwitchedNetworkData {
+                        profile_id,
+                        param: psn::types::PacketSwitchedNetworkDataParam::PsdProfileStatus,
+                    },
+                    true,
+                )
+                .map_err(Error::from)?;
+
+            if param_tag == 0 {
+                network
+                    .send_internal(
+                        &psn::SetPacketSwitchedAction {
+                            profile_id,
+                            action: psn::types::PacketSwitchedAction::Activate,
+                        },
+                        true,
+                    )
+                    .map_err(Error::from)?;
+            }
+        }
+
+        // Only `+UPSD` activations report the IP address, in `+UUPSDA`
+        let ip_addr = network
+            .send_internal(&psn::GetPDPAddress { cid }, true)
+            .ok()
+            .and_then(|addr| apn_info.pdp_type.select_addr(addr.ip_addrs()));
+
+        network.contexts.set_active(cid, ip_addr);
+        Ok(())
+    } else {
+        network
+            .send_internal(
+                &SetPDPContextState {
+                    status: PDPContextStatus::Activated,
+                    cid: Some(cid),
+                },
+                true,
+            )
+            .map_err(Error::from)?;
+
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        module::ModuleKind,
+        services::data::ContextState,
+        test_support::{expect_initialize_model, new_device, MockConfig, MockModem},
+        APNInfo, ContextId,
+    };
+
+    #[test]
+    fn sara_r5_activates_context_through_upsd() {
+        let modem = MockModem::new();
+        expect_initialize_model(&modem, "SARA-R510M8S");
+        modem
+            .expect("AT+CGATT?", "+CGATT: 1")
+            .expect("AT+UPSND=1,8", "+UPSND: 1,8,0")
+            .expect("AT+UPSD=1,1,\"em\"", "")
+            .expect("AT+UPSD=1,0,0", "")
+            .expect("AT+UPSD=1,100,1", "")
+            .expect("AT+UPSDA=1,3", "");
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let apn = APNInfo::new("em");
+        assert!(device.data_service(&apn).is_ok());
+        assert_eq!(device.network.module, ModuleKind::SaraR5);
+        assert_eq!(
+            device.network.contexts.state(ContextId(1)),
+            ContextState::Active
+        );
+        modem.assert_done();
+    }
+}
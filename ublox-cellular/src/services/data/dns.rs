@@ -1,11 +1,9 @@
 use atat::blocking::AtatClient;
-use core::fmt::Write;
 use embedded_nal::IpAddr;
 use embedded_nal::{AddrType, Dns};
 use heapless::String;
 
 use super::DataService;
-use crate::command::dns::{self, types::ResolutionType};
 use ublox_sockets::Error;
 
 impl<'a, 'sub, AtCl, const N: usize, const L: usize> Dns for DataService<'a, 'sub, AtCl, N, L>
@@ -15,17 +13,8 @@ where
     type Error = Error;
 
     fn get_host_by_address(&mut self, ip_addr: IpAddr) -> nb::Result<String<256>, Self::Error> {
-        let mut ip_str = String::<256>::new();
-        write!(&mut ip_str, "{ip_addr}").map_err(|_| Error::BadLength)?;
-
-        match self.network.send_internal(
-            &dns::ResolveNameIp {
-                resolution_type: ResolutionType::IpToDomainName,
-                ip_domain_string: &ip_str,
-            },
-            true,
-        ) {
-            Ok(resp) => Ok(String::from(resp.ip_domain_string.as_str())),
+        match self.dialect().resolve_address(self.network, ip_addr) {
+            Ok(host) => Ok(host),
             Err(e) => {
                 error!("get_host_by_address failed: {:?}", e);
                 Err(nb::Error::Other(Error::Unaddressable))
@@ -38,21 +27,16 @@ where
         hostname: &str,
         addr_type: AddrType,
     ) -> nb::Result<IpAddr, Self::Error> {
-        if addr_type == AddrType::IPv6 {
-            return Err(nb::Error::Other(Error::Illegal));
-        }
-
-        match self.network.send_internal(
-            &dns::ResolveNameIp {
-                resolution_type: ResolutionType::DomainNameToIp,
-                ip_domain_string: hostname,
-            },
-            true,
-        ) {
-            Ok(resp) => resp
-                .ip_domain_string
-                .parse()
-                .map_err(|_e| nb::Error::Other(Error::Illegal)),
+        // The module resolves to the address type of the PDP context
+        match self.dialect().resolve_host(self.network, hostname) {
+            Ok(IpAddr::V4(_)) if addr_type == AddrType::IPv6 => {
+                Err(nb::Error::Other(Error::Illegal))
+            }
+            Ok(IpAddr::V6(_)) if addr_type == AddrType::IPv4 => {
+                Err(nb::Error::Other(Error::Illegal))
+            }
+            Ok(ip_addr) => Ok(ip_addr),
+            Err(super::Error::Dns) => Err(nb::Error::Other(Error::Illegal)),
             Err(e) => {
                 error!("get_host_by_name failed: {:?}", e);
                 Err(nb::Error::Other(Error::Unaddressable))
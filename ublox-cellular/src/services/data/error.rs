@@ -12,6 +12,12 @@ pub enum Error {
     Dns,
     BufferFull,
     InvalidHex,
+    /// The PDP context or its profile is already mapped otherwise, or too
+    /// many contexts are in use
+    InvalidContext,
+    /// The data sent and received has reached
+    /// [`CellularConfig::DATA_BUDGET`](crate::CellularConfig::DATA_BUDGET)
+    DataBudgetExceeded,
 
     Socket(SocketError),
 
@@ -36,3 +42,30 @@ impl From<SocketError> for Error {
         Self::Socket(e)
     }
 }
+
+impl From<Error> for SocketError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Socket(e) => e,
+            Error::BadLength => Self::BadLength,
+            Error::DataBudgetExceeded => Self::Exhausted,
+            _ => Self::Unaddressable,
+        }
+    }
+}
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Self::Socket(SocketError::SocketClosed) => embedded_io::ErrorKind::NotConnected,
+            Self::Socket(SocketError::SocketSetFull) | Self::SocketMemory | Self::BufferFull => {
+                embedded_io::ErrorKind::OutOfMemory
+            }
+            Self::Generic(GenericError::Timeout) => embedded_io::ErrorKind::TimedOut,
+            Self::Generic(GenericError::Unsupported) => embedded_io::ErrorKind::Unsupported,
+            Self::DataBudgetExceeded => embedded_io::ErrorKind::PermissionDenied,
+            Self::BadLength | Self::InvalidHex => embedded_io::ErrorKind::InvalidData,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
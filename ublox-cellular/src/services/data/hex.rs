@@ -1,3 +1,6 @@
+use core::fmt::Write;
+use heapless::String;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FromHexError {
     /// An invalid character was found. Valid ones are: `0...9`, `a...f`
@@ -32,3 +35,13 @@ pub fn from_hex(hex: &mut [u8]) -> Result<&[u8], FromHexError> {
     }
     Ok(&hex[..len])
 }
+
+/// Encode `data` as upper case hex into a string of capacity `N`, which has to
+/// be at least twice the length of `data`.
+pub fn to_hex<const N: usize>(data: &[u8]) -> Result<String<N>, core::fmt::Error> {
+    let mut hex = String::new();
+    for byte in data {
+        write!(hex, "{:02X}", byte)?;
+    }
+    Ok(hex)
+}
@@ -1,7 +1,12 @@
 pub mod apn;
+#[cfg(feature = "apn-db")]
+mod apn_db;
+pub mod context;
+pub mod dialect;
 pub mod dns;
 pub mod error;
 pub mod ssl;
+pub mod usage;
 
 #[cfg(feature = "socket-tcp")]
 mod tcp_stack;
@@ -9,58 +14,35 @@ mod tcp_stack;
 #[cfg(feature = "socket-udp")]
 mod udp_stack;
 
-mod hex;
+pub(crate) mod hex;
 
 use crate::{
     blocking_timer::BlockingTimer,
     client::Device,
-    command::mobile_control::types::{Functionality, ResetMode},
-    command::mobile_control::SetModuleFunctionality,
-    command::psn::types::PDPContextStatus,
-    command::psn::SetPDPContextDefinition,
-    command::psn::SetPDPContextState,
-    command::Urc,
-    command::{
-        ip_transport_layer::{
-            responses::{SocketData, UDPSocketData},
-            ReadSocketData, ReadUDPSocketData,
-        },
-        psn::{self, responses::GPRSAttached, GetPDPContextState},
-    },
+    command::psn::{responses::GPRSAttached, types::GPRSAttachedState, GetGPRSAttached},
+    command::{ip_transport_layer::responses::SocketData, Urc},
     config::CellularConfig,
-    error::Error as DeviceError,
+    error::{Error as DeviceError, GenericError},
     network::{ContextId, Network},
-    ProfileId,
+    power_saving::PowerSavingClient,
 };
-use apn::{APNInfo, Apn};
+use apn::APNInfo;
 use atat::blocking::AtatClient;
+use context::PdpContext;
+use dialect::Dialect;
 use embassy_time::Duration;
+use embedded_nal::IpAddr;
+use serde::Serialize;
+use usage::{ContextUsage, DataBudget, DataUsage};
 
 pub use error::Error;
-use psn::{types::GPRSAttachedState, GetGPRSAttached};
-use ublox_sockets::{Error as SocketError, SocketSet, SocketType};
-
-use crate::command::psn::responses::PacketSwitchedNetworkData;
-use crate::command::psn::types::{
-    PacketSwitchedAction, PacketSwitchedNetworkDataParam, PacketSwitchedParam, ProtocolType,
-};
-use crate::command::psn::{
-    GetPacketSwitchedNetworkData, SetPacketSwitchedAction, SetPacketSwitchedConfig,
-};
-#[cfg(feature = "upsd-context-activation")]
-use embedded_nal::Ipv4Addr;
-use heapless::String;
+use ublox_sockets::{Error as SocketError, SocketHandle, SocketSet};
 
 // NOTE: If these are changed, remember to change the corresponding `Bytes` len
 // in commands for now.
 pub const INGRESS_CHUNK_SIZE: usize = 256;
 pub const EGRESS_CHUNK_SIZE: usize = 1024;
 
-pub const PROFILE_ID: ProfileId = ProfileId(1);
-
-#[cfg(not(feature = "upsd-context-activation"))]
-const CONTEXT_ID: ContextId = ContextId(1);
-
 impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
     Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
 where
@@ -69,55 +51,8 @@ where
     Config: CellularConfig,
 {
     /// Define a PDP context
-    #[cfg(not(feature = "upsd-context-activation"))]
     fn define_context(&mut self, cid: ContextId, apn_info: &APNInfo) -> Result<(), Error> {
-        if self.network.context_state != ContextState::Setup {
-            return Ok(());
-        }
-
-        self.network.send_internal(
-            &SetModuleFunctionality {
-                fun: Functionality::Minimum,
-                // SARA-R5: this parameter can be used only when <fun> is 1, 4 or 19
-                #[cfg(feature = "sara-r5")]
-                rst: None,
-                #[cfg(not(feature = "sara-r5"))]
-                rst: Some(ResetMode::DontReset),
-            },
-            true,
-        )?;
-
-        if let Apn::Given(apn) = apn_info.apn {
-            self.network.send_internal(
-                &SetPDPContextDefinition {
-                    cid,
-                    pdp_type: "IP",
-                    apn,
-                },
-                true,
-            )?;
-        }
-
-        // self.network.send_internal(
-        //     &SetAuthParameters {
-        //         cid,
-        //         auth_type: AuthenticationType::Auto,
-        //         username: &apn_info.clone().user_name.unwrap_or_default(),
-        //         password: &apn_info.clone().password.unwrap_or_default(),
-        //     },
-        //     true,
-        // )?;
-
-        self.network.send_internal(
-            &SetModuleFunctionality {
-                fun: Functionality::Full,
-                rst: Some(ResetMode::DontReset),
-            },
-            true,
-        )?;
-
-        self.network.context_state = ContextState::Activating;
-        Ok(())
+        dialect::for_module(self.network.module).define_context(&mut self.network, cid, apn_info)
     }
 
     /// Handle modem data connection
@@ -141,28 +76,70 @@ where
     pub fn data_service<'a>(
         &'a mut self,
         apn_info: &APNInfo,
-    ) -> nb::Result<DataService<'a, 'sub, AtCl, N, L>, DeviceError> {
+    ) -> nb::Result<DataService<'a, 'sub, PowerSavingClient<AtCl, Config::DtrPin>, N, L>, DeviceError>
+    {
+        self.data_service_on(PdpContext::DEFAULT, apn_info)
+    }
+
+    /// Handle the data connection over `context`, as
+    /// [`data_service`](Device::data_service) does over the default one.
+    /// Each context has its own APN. Sockets only go over the default
+    /// context, as the modules can not select the context of a socket:
+    /// opening one through the [`DataService`] of another context fails.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// const MANAGEMENT: PdpContext = PdpContext::new(ContextId(2), ProfileId(2));
+    ///
+    /// loop {
+    ///     modem.data_service_on(MANAGEMENT, &APNInfo::new("private.apn"))?;
+    ///     modem.data_service(&APNInfo::new("public.apn"))?;
+    /// }
+    /// ```
+    pub fn data_service_on<'a>(
+        &'a mut self,
+        context: PdpContext,
+        apn_info: &APNInfo,
+    ) -> nb::Result<DataService<'a, 'sub, PowerSavingClient<AtCl, Config::DtrPin>, N, L>, DeviceError>
+    {
+        self.network
+            .contexts
+            .register(context)
+            .map_err(DeviceError::from)?;
+
         // Spin [`Device`], handling [`Network`] related URC changes and
         // propagting the FSM
-        match self.spin() {
-            // If we're not using AT+UPSD-based context activation, set the
-            // context using AT+CGDCONT and the authentication mode
+        let spin = self.spin();
+
+        // Resolved once the IMSI has been read
+        let apn_info = &apn_info.resolve(
+            self.network.status.imsi,
+            self.network.contexts.failed_activations(context.cid),
+        );
+
+        match spin {
+            // Define the context ahead of activation, if the dialect needs
+            // to, e.g. through AT+CGDCONT
             Err(nb::Error::WouldBlock) => {
-                #[cfg(not(feature = "upsd-context-activation"))]
-                self.define_context(CONTEXT_ID, apn_info)
+                self.define_context(context.cid, apn_info)
                     .map_err(DeviceError::from)?;
                 return Err(nb::Error::WouldBlock);
             }
             Ok(()) => {
-                #[cfg(not(feature = "upsd-context-activation"))]
-                self.define_context(CONTEXT_ID, apn_info)
+                self.define_context(context.cid, apn_info)
                     .map_err(DeviceError::from)?;
             }
             Err(e) => return Err(e),
         }
 
         // At this point we WILL be registered on the network!
-        match DataService::try_new(apn_info, &mut self.network, self.sockets.as_deref_mut()) {
+        match DataService::try_new(
+            context,
+            apn_info,
+            &mut self.network,
+            self.sockets.as_deref_mut(),
+        ) {
             Ok(service) => Ok(service),
             Err(nb::Error::Other(e)) => Err(nb::Error::Other(e.into())),
             Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
@@ -170,7 +147,23 @@ where
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Lowest socket id from 1 not in use, for dialects letting the host choose
+/// the socket id.
+pub(crate) fn free_socket_id<const N: usize, const L: usize>(
+    sockets: &mut SocketSet<N, L>,
+) -> SocketHandle {
+    let mut id = 1;
+    while sockets
+        .iter_mut()
+        .any(|(handle, _)| handle == SocketHandle(id))
+    {
+        id += 1;
+    }
+    SocketHandle(id)
+}
+
+/// State of the PDP context of a data connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ContextState {
     Setup,
@@ -178,11 +171,19 @@ pub enum ContextState {
     Active,
 }
 
+/// Data connection over a PDP context, with the `embedded-nal` stacks.
+///
+/// Sockets are only opened over the default context,
+/// [`PdpContext::DEFAULT`]: neither `+USOCR` nor `+MIPOPEN` select the
+/// context of a socket, so opening one through the [`DataService`] of another
+/// context fails with [`GenericError::Unsupported`].
 pub struct DataService<'a, 'sub, AtCl, const N: usize, const L: usize>
 where
     AtCl: AtatClient,
 {
     network: &'a mut Network<'sub, AtCl>,
+    /// Context of the data connection
+    context: PdpContext,
     pub(crate) sockets: Option<&'a mut SocketSet<N, L>>,
 }
 
@@ -191,11 +192,16 @@ where
     AtCl: AtatClient,
 {
     pub fn try_new(
+        context: PdpContext,
         apn_info: &APNInfo,
         network: &'a mut Network<'sub, AtCl>,
         sockets: Option<&'a mut SocketSet<N, L>>,
     ) -> nb::Result<Self, Error> {
-        let mut data_service = Self { network, sockets };
+        let mut data_service = Self {
+            network,
+            context,
+            sockets,
+        };
 
         // Check if context is active, and create if not
         data_service.connect(apn_info)?;
@@ -212,9 +218,8 @@ where
         Ok(data_service)
     }
 
-    #[allow(unused_variables)]
     fn connect(&mut self, apn_info: &APNInfo) -> nb::Result<(), Error> {
-        match self.network.context_state {
+        match self.network.contexts.state(self.context.cid) {
             ContextState::Active => return Ok(()),
             ContextState::Setup | ContextState::Activating => {}
         }
@@ -228,15 +233,82 @@ where
         // RANs, it is best to be consistent.
         self.attach_network()?;
 
-        // Activate the context
-        #[cfg(feature = "upsd-context-activation")]
-        self.activate_context_upsd(PROFILE_ID, apn_info)?;
-        #[cfg(not(feature = "upsd-context-activation"))]
-        self.activate_context(CONTEXT_ID, PROFILE_ID)?;
+        // Activate the context, going through the next APN candidate on
+        // failure
+        let result = self
+            .dialect()
+            .activate_context(self.network, self.context, apn_info);
+        if let Err(nb::Error::Other(_)) = result {
+            self.network.contexts.activation_failed(self.context.cid);
+        }
+        result
+    }
+
+    /// Context of the data connection
+    pub fn context(&self) -> PdpContext {
+        self.context
+    }
+
+    /// IP address of the data connection
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.network.contexts.ip_addr(self.context.cid)
+    }
+
+    /// Bytes sent and received over `socket` since it was created
+    pub fn socket_usage(&mut self, socket: SocketHandle) -> Result<DataUsage, Error> {
+        self.dialect().socket_usage(self.network, socket)
+    }
 
+    /// Bytes sent and received over the context of the data connection
+    pub fn context_usage(&mut self) -> Result<ContextUsage, Error> {
+        let counters = self.dialect().data_counters(self.network)?;
+        Ok(usage::context_usage(&counters, self.context.cid))
+    }
+
+    /// Reset the total data usage of the context of the data connection,
+    /// persisted by the module. The data budget is reset as well, including
+    /// on the modules not counting the data.
+    pub fn reset_data_usage(&mut self) -> Result<(), Error> {
+        match self
+            .dialect()
+            .reset_data_counters(self.network, self.context.cid)
+        {
+            Ok(()) => {}
+            Err(Error::Generic(GenericError::Unsupported))
+                if self.network.contexts.data_budget.is_some() => {}
+            Err(e) => return Err(e),
+        }
+        if let Some(budget) = self.network.contexts.data_budget.as_mut() {
+            budget.reset();
+        }
         Ok(())
     }
 
+    /// Refuse to send once the data budget is used up, reading the data
+    /// counters again if they are due to.
+    pub(crate) fn check_data_budget(&mut self) -> Result<(), Error> {
+        let Some(budget) = self.network.contexts.data_budget.as_ref() else {
+            return Ok(());
+        };
+
+        if budget.needs_refresh() {
+            let counters = match self.dialect().data_counters(self.network) {
+                Ok(counters) => Some(counters),
+                Err(Error::Generic(GenericError::Unsupported)) => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(budget) = self.network.contexts.data_budget.as_mut() {
+                budget.refresh(counters.as_deref());
+            }
+        }
+
+        self.network
+            .contexts
+            .data_budget
+            .as_ref()
+            .map_or(Ok(()), DataBudget::check)
+    }
+
     // Make sure we are attached to the cellular network.
     fn attach_network(&mut self) -> nb::Result<(), Error> {
         // Wait for AT+CGATT to return 1
@@ -259,250 +331,9 @@ where
         Err(nb::Error::WouldBlock)
     }
 
-    /// Activate context using AT+UPSD commands
-    /// Required for SARA-G3, SARA-U2 SARA-R5 modules.
-    #[cfg(feature = "upsd-context-activation")]
-    fn activate_context_upsd(
-        &mut self,
-        profile_id: ProfileId,
-        apn_info: &APNInfo,
-    ) -> nb::Result<(), Error> {
-        if self.network.context_state == ContextState::Active {
-            return Ok(());
-        }
-
-        // Check if the PSD profile is activated (param_tag = 1)
-        let PacketSwitchedNetworkData { param_tag, .. } = self
-            .network
-            .send_internal(
-                &GetPacketSwitchedNetworkData {
-                    profile_id,
-                    param: PacketSwitchedNetworkDataParam::PsdProfileStatus,
-                },
-                true,
-            )
-            .map_err(Error::from)?;
-
-        if param_tag == 0 {
-            self.network.context_state = ContextState::Activating;
-
-            // SARA-U2 pattern: everything is done through AT+UPSD
-            // Set up the APN
-            if let Apn::Given(apn) = apn_info.clone().apn {
-                self.network
-                    .send_internal(
-                        &SetPacketSwitchedConfig {
-                            profile_id,
-                            param: PacketSwitchedParam::APN(String::from(apn)),
-                        },
-                        true,
-                    )
-                    .map_err(Error::from)?;
-            }
-
-            // Set up the user name
-            if let Some(user_name) = apn_info.clone().user_name {
-                self.network
-                    .send_internal(
-                        &SetPacketSwitchedConfig {
-                            profile_id,
-                            param: PacketSwitchedParam::Username(String::from(user_name)),
-                        },
-                        true,
-                    )
-                    .map_err(Error::from)?;
-            }
-
-            // Set up the password
-            if let Some(password) = apn_info.clone().password {
-                self.network
-                    .send_internal(
-                        &SetPacketSwitchedConfig {
-                            profile_id,
-                            param: PacketSwitchedParam::Password(String::from(password)),
-                        },
-                        true,
-                    )
-                    .map_err(Error::from)?;
-            }
-
-            // Set up the dynamic IP address assignment.
-            #[cfg(not(feature = "sara-r5"))]
-            self.network
-                .send_internal(
-                    &SetPacketSwitchedConfig {
-                        profile_id,
-                        param: PacketSwitchedParam::IPAddress(Ipv4Addr::unspecified().into()),
-                    },
-                    true,
-                )
-                .map_err(Error::from)?;
-
-            // Automatic authentication protocol selection
-            #[cfg(not(feature = "sara-r5"))]
-            self.network
-                .send_internal(
-                    &SetPacketSwitchedConfig {
-                        profile_id,
-                        param: PacketSwitchedParam::Authentication(AuthenticationType::Auto),
-                    },
-                    true,
-                )
-                .map_err(Error::from)?;
-
-            #[cfg(not(feature = "sara-r5"))]
-            self.network
-                .send_internal(
-                    &SetPacketSwitchedConfig {
-                        profile_id,
-                        param: PacketSwitchedParam::IPAddress(Ipv4Addr::unspecified().into()),
-                    },
-                    true,
-                )
-                .map_err(Error::from)?;
-
-            #[cfg(feature = "sara-r5")]
-            self.network
-                .send_internal(
-                    &SetPacketSwitchedConfig {
-                        profile_id,
-                        param: PacketSwitchedParam::ProtocolType(ProtocolType::IPv4),
-                    },
-                    true,
-                )
-                .map_err(Error::from)?;
-
-            #[cfg(feature = "sara-r5")]
-            self.network
-                .send_internal(
-                    &SetPacketSwitchedConfig {
-                        profile_id,
-                        param: PacketSwitchedParam::MapProfile(ContextId(1)),
-                    },
-                    true,
-                )
-                .map_err(Error::from)?;
-
-            self.network
-                .send_internal(
-                    &SetPacketSwitchedAction {
-                        profile_id,
-                        action: PacketSwitchedAction::Activate,
-                    },
-                    true,
-                )
-                .map_err(Error::from)?;
-        }
-
-        self.network.context_state = ContextState::Active;
-        Ok(())
-    }
-
-    /// Activate context using 3GPP commands
-    /// Required for SARA-R4 and TOBY modules.
-    #[cfg(not(feature = "upsd-context-activation"))]
-    fn activate_context(&mut self, cid: ContextId, profile_id: ProfileId) -> nb::Result<(), Error> {
-        if self.network.context_state == ContextState::Active {
-            return Ok(());
-        }
-
-        let context_states = self
-            .network
-            .send_internal(&GetPDPContextState, true)
-            .map_err(Error::from)?;
-
-        let activated = context_states
-            .iter()
-            .find_map(|state| {
-                if state.cid == cid {
-                    Some(state.status == PDPContextStatus::Activated)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or(false);
-
-        if activated {
-            // Note: SARA-R4 only supports a single context at any one time and
-            // so doesn't require/support AT+UPSD.
-            #[cfg(not(any(feature = "sara-r4", feature = "lara-r6")))]
-            {
-                if let psn::responses::PacketSwitchedConfig {
-                    param: psn::types::PacketSwitchedParam::MapProfile(context),
-                    ..
-                } = self
-                    .network
-                    .send_internal(
-                        &psn::GetPacketSwitchedConfig {
-                            profile_id,
-                            param: psn::types::PacketSwitchedParamReq::MapProfile,
-                        },
-                        true,
-                    )
-                    .map_err(Error::from)?
-                {
-                    if context != cid {
-                        self.network
-                            .send_internal(
-                                &psn::SetPacketSwitchedConfig {
-                                    profile_id,
-                                    param: psn::types::PacketSwitchedParam::MapProfile(cid),
-                                },
-                                true,
-                            )
-                            .map_err(Error::from)?;
-
-                        self.network
-                            .send_internal(
-                                &psn::GetPacketSwitchedNetworkData {
-                                    profile_id,
-                                    param: psn::types::PacketSwitchedNetworkDataParam::PsdProfileStatus,
-                                },
-                                true,
-                            )
-                            .map_err(Error::from)?;
-                    }
-                }
-
-                let psn::responses::PacketSwitchedNetworkData { param_tag, .. } = self
-                    .network
-                    .send_internal(
-                        &psn::GetPacketSwitchedNetworkData {
-                            profile_id,
-                            param: psn::types::PacketSwitchedNetworkDataParam::PsdProfileStatus,
-                        },
-                        true,
-                    )
-                    .map_err(Error::from)?;
-
-                if param_tag == 0 {
-                    self.network
-                        .send_internal(
-                            &psn::SetPacketSwitchedAction {
-                                profile_id,
-                                action: psn::types::PacketSwitchedAction::Activate,
-                            },
-                            true,
-                        )
-                        .map_err(Error::from)?;
-                }
-            }
-
-            self.network.context_state = ContextState::Active;
-            Ok(())
-        } else {
-            self.network
-                .send_internal(
-                    &SetPDPContextState {
-                        status: PDPContextStatus::Activated,
-                        cid: Some(cid),
-                    },
-                    true,
-                )
-                .map_err(Error::from)?;
-
-            Err(nb::Error::WouldBlock)
-        }
+    /// Dialect spoken by the attached module
+    pub(crate) fn dialect(&self) -> &'a dyn Dialect<AtCl> {
+        dialect::for_module(self.network.module)
     }
 
     pub fn send_at<A, const LEN: usize>(&mut self, cmd: &A) -> Result<A::Response, Error>
@@ -517,6 +348,7 @@ where
     }
 
     fn socket_ingress_all(&mut self) -> Result<(), Error> {
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
             let network = &mut self.network;
             sockets
@@ -528,13 +360,7 @@ where
                         // Check for new socket data available at regular
                         // intervals, just in case a URC is missed
                         if socket.should_update_available_data() {
-                            match network.send_internal(
-                                &ReadSocketData {
-                                    socket: handle,
-                                    length: 0,
-                                },
-                                false,
-                            ) {
+                            match dialect.read_socket(network, handle, socket.get_type(), 0) {
                                 Ok(SocketData { length, .. }) => socket.set_available_data(length),
                                 Err(_) => socket.closed_by_remote(),
                             }
@@ -554,44 +380,11 @@ where
                     // ingress the smallest of the two
                     let requested_len = core::cmp::min(wanted_len, socket.rx_window());
 
-                    let (socket_handle, mut data, len) = match socket.get_type() {
-                        SocketType::Tcp => {
-                            // Allow room for 2x length (Hex), and command
-                            // overhead
-                            let SocketData {
-                                socket,
-                                data,
-                                length,
-                            } = network.send_internal(
-                                &ReadSocketData {
-                                    socket: handle,
-                                    length: requested_len,
-                                },
-                                false,
-                            )?;
-
-                            (socket, data, length)
-                        }
-                        SocketType::Udp => {
-                            // Allow room for 2x length (Hex), and command
-                            // overhead
-                            let UDPSocketData {
-                                socket,
-                                data,
-                                length,
-                                ..
-                            } = network.send_internal(
-                                &ReadUDPSocketData {
-                                    socket: handle,
-                                    length: requested_len,
-                                },
-                                false,
-                            )?;
-
-                            (socket, data, length)
-                        }
-                        _ => return Err(Error::WrongSocketType),
-                    };
+                    let SocketData {
+                        socket: socket_handle,
+                        mut data,
+                        length: len,
+                    } = dialect.read_socket(network, handle, socket.get_type(), requested_len)?;
 
                     if socket_handle != handle {
                         error!("WrongSocketType {:?} != {:?}", socket_handle, handle);
@@ -618,6 +411,7 @@ where
                             data.as_bytes()
                         };
 
+                        network.contexts.record_data(demangled.len());
                         let enqueued = socket.rx_enqueue_slice(demangled);
                         if enqueued != demangled.len() {
                             // This should never happen, due to the
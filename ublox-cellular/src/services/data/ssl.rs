@@ -1,10 +1,5 @@
-use super::{DataService, Error};
-use crate::command::device_data_security::{
-    types::{CertificateValidationLevel, SecurityDataType, SecurityProfileOperation},
-    PrepareSecurityDataImport, SecurityProfileManager, SendSecurityDataImport,
-};
+use super::{dialect::SecurityDataKind, DataService, Error};
 use atat::{atat_derive::AtatLen, blocking::AtatClient};
-use heapless::String;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, AtatLen)]
@@ -48,36 +43,14 @@ where
         name: &str,
         certificate: &[u8],
     ) -> Result<(), Error> {
-        assert!(name.len() < 200);
-
-        self.network.send_internal(
-            &PrepareSecurityDataImport {
-                data_type: SecurityDataType::ClientCertificate,
-                data_size: certificate.len(),
-                internal_name: name,
-                password: None,
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SendSecurityDataImport {
-                data: atat::serde_bytes::Bytes::new(certificate),
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SecurityProfileManager {
-                profile_id,
-                operation: Some(SecurityProfileOperation::ClientCertificateInternalName(
-                    String::from(name),
-                )),
-            },
-            true,
-        )?;
-
-        Ok(())
+        self.dialect().import_security_data(
+            self.network,
+            profile_id,
+            SecurityDataKind::ClientCertificate,
+            name,
+            certificate,
+            None,
+        )
     }
 
     fn import_root_ca(
@@ -86,38 +59,14 @@ where
         name: &str,
         root_ca: &[u8],
     ) -> Result<(), Error> {
-        assert!(name.len() < 200);
-
-        self.network.send_internal(
-            &PrepareSecurityDataImport {
-                data_type: SecurityDataType::TrustedRootCA,
-                data_size: root_ca.len(),
-                internal_name: name,
-                password: None,
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SendSecurityDataImport {
-                data: atat::serde_bytes::Bytes::new(root_ca),
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SecurityProfileManager {
-                profile_id,
-                operation: Some(
-                    SecurityProfileOperation::TrustedRootCertificateInternalName(String::from(
-                        name,
-                    )),
-                ),
-            },
-            true,
-        )?;
-
-        Ok(())
+        self.dialect().import_security_data(
+            self.network,
+            profile_id,
+            SecurityDataKind::TrustedRootCa,
+            name,
+            root_ca,
+            None,
+        )
     }
 
     fn import_private_key(
@@ -127,36 +76,14 @@ where
         private_key: &[u8],
         password: Option<&str>,
     ) -> Result<(), Error> {
-        assert!(name.len() < 200);
-
-        self.network.send_internal(
-            &PrepareSecurityDataImport {
-                data_type: SecurityDataType::ClientPrivateKey,
-                data_size: private_key.len(),
-                internal_name: name,
-                password,
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SendSecurityDataImport {
-                data: atat::serde_bytes::Bytes::new(private_key),
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SecurityProfileManager {
-                profile_id,
-                operation: Some(SecurityProfileOperation::ClientPrivateKeyInternalName(
-                    String::from(name),
-                )),
-            },
-            true,
-        )?;
-
-        Ok(())
+        self.dialect().import_security_data(
+            self.network,
+            profile_id,
+            SecurityDataKind::ClientPrivateKey,
+            name,
+            private_key,
+            password,
+        )
     }
 
     fn enable_ssl(
@@ -165,54 +92,7 @@ where
         server_hostname: &str,
         use_sni: bool,
     ) -> Result<(), Error> {
-        self.network.send_internal(
-            &SecurityProfileManager {
-                profile_id,
-                operation: Some(SecurityProfileOperation::CertificateValidationLevel(
-                    CertificateValidationLevel::RootCertValidationWithValidityDate,
-                )),
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SecurityProfileManager {
-                profile_id,
-                operation: Some(SecurityProfileOperation::CipherSuite(0)),
-            },
-            true,
-        )?;
-
-        self.network.send_internal(
-            &SecurityProfileManager {
-                profile_id,
-                operation: Some(SecurityProfileOperation::ExpectedServerHostname(
-                    String::from(server_hostname),
-                )),
-            },
-            true,
-        )?;
-
-        if use_sni {
-            self.network.send_internal(
-                &SecurityProfileManager {
-                    profile_id,
-                    operation: Some(SecurityProfileOperation::ServerNameIndication(
-                        String::from(server_hostname),
-                    )),
-                },
-                true,
-            )?;
-        } else {
-            self.network.send_internal(
-                &SecurityProfileManager {
-                    profile_id,
-                    operation: Some(SecurityProfileOperation::ServerNameIndication(String::new())),
-                },
-                true,
-            )?;
-        }
-
-        Ok(())
+        self.dialect()
+            .enable_ssl(self.network, profile_id, server_hostname, use_sni)
     }
 }
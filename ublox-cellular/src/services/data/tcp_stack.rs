@@ -1,14 +1,7 @@
-use super::ssl::SecurityProfileId;
-use super::DataService;
-use super::EGRESS_CHUNK_SIZE;
-use crate::command::ip_transport_layer::{
-    types::{SocketProtocol, SslTlsStatus},
-    CloseSocket, ConnectSocket, CreateSocket, PrepareWriteSocketDataBinary, SetSocketSslState,
-    WriteSocketDataBinary,
-};
+use super::{free_socket_id, DataService};
 use atat::blocking::AtatClient;
 use embedded_nal::{SocketAddr, TcpClientStack};
-use ublox_sockets::{Error, SocketHandle, TcpSocket, TcpState};
+use ublox_sockets::{Error, SocketHandle, SocketType, TcpSocket, TcpState};
 
 impl<'a, 'sub, AtCl, const N: usize, const L: usize> TcpClientStack
     for DataService<'a, 'sub, AtCl, N, L>
@@ -23,6 +16,7 @@ where
 
     /// Open a new TCP socket to the given address and port. The socket starts in the unconnected state.
     fn socket(&mut self) -> Result<Self::TcpSocket, Self::Error> {
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
             // Check if there are any unused sockets available
             if sockets.len() >= sockets.capacity() {
@@ -33,18 +27,12 @@ where
                 }
             }
 
-            let socket_resp = self
-                .network
-                .send_internal(
-                    &CreateSocket {
-                        protocol: SocketProtocol::TCP,
-                        local_port: None,
-                    },
-                    true,
-                )
+            let candidate = free_socket_id(sockets);
+            let socket = dialect
+                .create_socket(self.network, self.context, SocketType::Tcp, candidate)
                 .map_err(|_| Error::Unaddressable)?;
 
-            Ok(sockets.add(TcpSocket::new(socket_resp.socket.0))?)
+            Ok(sockets.add(TcpSocket::new(socket.0))?)
         } else {
             Err(Error::Illegal)
         }
@@ -56,31 +44,15 @@ where
         socket: &mut Self::TcpSocket,
         remote: SocketAddr,
     ) -> nb::Result<(), Self::Error> {
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
             let mut tcp = sockets
                 .get::<TcpSocket<L>>(*socket)
                 .map_err(Self::Error::from)?;
 
             if matches!(tcp.state(), TcpState::Created) {
-                self.network
-                    .send_internal(
-                        &SetSocketSslState {
-                            socket: *socket,
-                            ssl_tls_status: SslTlsStatus::Enabled(SecurityProfileId(0)),
-                        },
-                        true,
-                    )
-                    .map_err(|_| nb::Error::Other(Error::Unaddressable))?;
-
-                self.network
-                    .send_internal(
-                        &ConnectSocket {
-                            socket: *socket,
-                            remote_addr: remote.ip(),
-                            remote_port: remote.port(),
-                        },
-                        false,
-                    )
+                dialect
+                    .connect_tcp(self.network, *socket, remote)
                     .map_err(|_| nb::Error::Other(Error::Unaddressable))?;
 
                 tcp.set_state(TcpState::Connected(remote));
@@ -117,36 +89,12 @@ where
         if !self.is_connected(socket)? {
             return Err(Error::SocketClosed.into());
         }
+        self.check_data_budget().map_err(Error::from)?;
 
-        for chunk in buffer.chunks(EGRESS_CHUNK_SIZE) {
-            trace!("Sending: {} bytes", chunk.len());
-            self.network
-                .send_internal(
-                    &PrepareWriteSocketDataBinary {
-                        socket: *socket,
-                        length: chunk.len(),
-                    },
-                    false,
-                )
-                .map_err(|_| nb::Error::Other(Error::Unaddressable))?;
-
-            let response = self
-                .network
-                .send_internal(
-                    &WriteSocketDataBinary {
-                        data: atat::serde_bytes::Bytes::new(chunk),
-                    },
-                    false,
-                )
-                .map_err(|_| nb::Error::Other(Error::Unaddressable))?;
-
-            if response.length != chunk.len() {
-                return Err(Error::BadLength.into());
-            }
-            if &response.socket != socket {
-                return Err(Error::InvalidSocket.into());
-            }
-        }
+        self.dialect()
+            .send_tcp(self.network, *socket, buffer)
+            .map_err(Error::from)?;
+        self.network.contexts.record_data(buffer.len());
 
         Ok(buffer.len())
     }
@@ -172,10 +120,9 @@ where
 
     /// Close an existing TCP socket.
     fn close(&mut self, socket: Self::TcpSocket) -> Result<(), Self::Error> {
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
-            self.network
-                .send_internal(&CloseSocket { socket }, false)
-                .ok();
+            dialect.close_socket(self.network, socket).ok();
             sockets.remove(socket)?;
             Ok(())
         } else {
@@ -1,12 +1,7 @@
-use super::DataService;
-use super::EGRESS_CHUNK_SIZE;
-use crate::command::ip_transport_layer::{
-    types::SocketProtocol, CloseSocket, CreateSocket, PrepareUDPSendToDataBinary,
-    UDPSendToDataBinary,
-};
+use super::{free_socket_id, DataService};
 use atat::blocking::AtatClient;
 use embedded_nal::{SocketAddr, UdpClientStack};
-use ublox_sockets::{Error, SocketHandle, UdpSocket};
+use ublox_sockets::{Error, SocketHandle, SocketType, UdpSocket};
 
 impl<'a, 'sub, AtCl, const N: usize, const L: usize> UdpClientStack
     for DataService<'a, 'sub, AtCl, N, L>
@@ -22,6 +17,7 @@ where
     /// Open a new UDP socket to the given address and port. UDP is connectionless,
     /// so unlike `TcpStack` no `connect()` is required.
     fn socket(&mut self) -> Result<Self::UdpSocket, Self::Error> {
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
             if sockets.len() >= sockets.capacity() {
                 // Check if there are any sockets closed by remote, and close it
@@ -31,18 +27,12 @@ where
                 }
             }
 
-            let socket_resp = self
-                .network
-                .send_internal(
-                    &CreateSocket {
-                        protocol: SocketProtocol::UDP,
-                        local_port: None,
-                    },
-                    false,
-                )
+            let candidate = free_socket_id(sockets);
+            let socket = dialect
+                .create_socket(self.network, self.context, SocketType::Udp, candidate)
                 .map_err(|_| Error::Unaddressable)?;
 
-            Ok(sockets.add(UdpSocket::new(socket_resp.socket.0))?)
+            Ok(sockets.add(UdpSocket::new(socket.0))?)
         } else {
             Err(Error::Illegal)
         }
@@ -53,10 +43,14 @@ where
         socket: &mut Self::UdpSocket,
         remote: SocketAddr,
     ) -> Result<(), Self::Error> {
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
             let mut udp = sockets
                 .get::<UdpSocket<L>>(*socket)
                 .map_err(Self::Error::from)?;
+            dialect
+                .connect_udp(self.network, *socket, remote)
+                .map_err(|_| Error::Unaddressable)?;
             udp.bind(remote).map_err(Self::Error::from)?;
             Ok(())
         } else {
@@ -66,6 +60,8 @@ where
 
     /// Send a datagram to the remote host.
     fn send(&mut self, socket: &mut Self::UdpSocket, buffer: &[u8]) -> nb::Result<(), Self::Error> {
+        self.check_data_budget().map_err(Error::from)?;
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
             let udp = sockets
                 .get::<UdpSocket<L>>(*socket)
@@ -75,38 +71,11 @@ where
                 return Err(Error::SocketClosed.into());
             }
 
-            for chunk in buffer.chunks(EGRESS_CHUNK_SIZE) {
-                trace!("Sending: {} bytes", chunk.len());
-                let endpoint = udp.endpoint().ok_or(Error::SocketClosed)?;
-                self.network
-                    .send_internal(
-                        &PrepareUDPSendToDataBinary {
-                            socket: *socket,
-                            remote_addr: endpoint.ip(),
-                            remote_port: endpoint.port(),
-                            length: chunk.len(),
-                        },
-                        false,
-                    )
-                    .map_err(|_| nb::Error::Other(Error::Unaddressable))?;
-
-                let response = self
-                    .network
-                    .send_internal(
-                        &UDPSendToDataBinary {
-                            data: atat::serde_bytes::Bytes::new(chunk),
-                        },
-                        false,
-                    )
-                    .map_err(|_| nb::Error::Other(Error::Unaddressable))?;
-
-                if response.length != chunk.len() {
-                    return Err(Error::BadLength.into());
-                }
-                if &response.socket != socket {
-                    return Err(Error::InvalidSocket.into());
-                }
-            }
+            let endpoint = udp.endpoint().ok_or(Error::SocketClosed)?;
+            dialect
+                .send_udp(self.network, *socket, endpoint, buffer)
+                .map_err(Error::from)?;
+            self.network.contexts.record_data(buffer.len());
 
             Ok(())
         } else {
@@ -138,10 +107,9 @@ where
 
     /// Close an existing UDP socket.
     fn close(&mut self, socket: Self::UdpSocket) -> Result<(), Self::Error> {
+        let dialect = self.dialect();
         if let Some(ref mut sockets) = self.sockets {
-            self.network
-                .send_internal(&CloseSocket { socket }, false)
-                .ok();
+            dialect.close_socket(self.network, socket).ok();
             sockets.remove(socket)?;
             Ok(())
         } else {
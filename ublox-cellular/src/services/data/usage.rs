@@ -0,0 +1,234 @@
+//! Data usage accounting
+//!
+//! The module counts the bytes sent and received per socket, and per PDP
+//! context, both in the current session and in total. The totals are kept in
+//! NVM across power cycles until reset, and are the ones
+//! [`CellularConfig::DATA_BUDGET`] is checked against.
+//!
+//! Rather than reading the counters before every send, the budget is checked
+//! against the totals read at most every [`BUDGET_REFRESH_INTERVAL`], plus
+//! the bytes sent and received through the sockets since then.
+//!
+//! [`CellularConfig::DATA_BUDGET`]: crate::CellularConfig::DATA_BUDGET
+
+use super::Error;
+use crate::{command::psn::responses::DataCounters, network::ContextId};
+use embassy_time::{Duration, Instant};
+
+/// Interval at which the data counters are read again while checking the
+/// data budget, catching up with the protocol overhead not seen by the
+/// sockets
+pub const BUDGET_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Bytes sent and received
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DataUsage {
+    pub sent: u64,
+    pub received: u64,
+}
+
+impl DataUsage {
+    /// Bytes sent and received
+    pub fn total(&self) -> u64 {
+        self.sent.saturating_add(self.received)
+    }
+}
+
+/// Data usage of a PDP context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ContextUsage {
+    /// Since the context was activated
+    pub session: DataUsage,
+    /// Since the counters were last reset
+    pub total: DataUsage,
+}
+
+/// Usage of `cid` in `counters`, nothing if the context is not active
+pub(crate) fn context_usage(counters: &[DataCounters], cid: ContextId) -> ContextUsage {
+    counters
+        .iter()
+        .find(|c| c.cid == cid)
+        .map_or_else(ContextUsage::default, |c| ContextUsage {
+            session: DataUsage {
+                sent: c.sent_session_bytes,
+                received: c.received_session_bytes,
+            },
+            total: DataUsage {
+                sent: c.sent_total_bytes,
+                received: c.received_total_bytes,
+            },
+        })
+}
+
+/// Total bytes sent and received over every active context
+pub(crate) fn total_bytes(counters: &[DataCounters]) -> u64 {
+    counters.iter().fold(0, |bytes, c| {
+        bytes
+            .saturating_add(c.sent_total_bytes)
+            .saturating_add(c.received_total_bytes)
+    })
+}
+
+/// Data budget, see [`CellularConfig::DATA_BUDGET`]
+///
+/// [`CellularConfig::DATA_BUDGET`]: crate::CellularConfig::DATA_BUDGET
+#[derive(Debug, Clone)]
+pub(crate) struct DataBudget {
+    limit: u64,
+    /// Bytes used as of the last counters read, plus the bytes sent and
+    /// received since then
+    used: u64,
+    refreshed_at: Option<Instant>,
+    /// The module does not count the data, so `used` only counts the bytes
+    /// going through the sockets
+    counted_by_host: bool,
+}
+
+impl DataBudget {
+    pub(crate) fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: 0,
+            refreshed_at: None,
+            counted_by_host: false,
+        }
+    }
+
+    /// Whether the counters are due to be read again
+    pub(crate) fn needs_refresh(&self) -> bool {
+        !self.counted_by_host
+            && self
+                .refreshed_at
+                .map_or(true, |at| at.elapsed() >= BUDGET_REFRESH_INTERVAL)
+    }
+
+    /// Catch up with the `counters` read from the module, `None` if it does
+    /// not count the data
+    pub(crate) fn refresh(&mut self, counters: Option<&[DataCounters]>) {
+        match counters {
+            Some(counters) => {
+                self.used = total_bytes(counters);
+                self.refreshed_at = Some(Instant::now());
+            }
+            None => self.counted_by_host = true,
+        }
+    }
+
+    /// Count `bytes` sent or received since the last refresh
+    pub(crate) fn record(&mut self, bytes: usize) {
+        self.used = self.used.saturating_add(bytes as u64);
+    }
+
+    /// Read the counters again on the next check, once they are reset, or
+    /// count the bytes from zero again
+    pub(crate) fn reset(&mut self) {
+        self.used = 0;
+        self.refreshed_at = None;
+    }
+
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if self.used >= self.limit {
+            return Err(Error::DataBudgetExceeded);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        test_support::{
+            connected_tcp_socket, expect_context_activation, expect_initialize, new_device,
+            MockConfig, MockModem, MockSettings, L, N,
+        },
+        APNInfo,
+    };
+    use embedded_nal::TcpClientStack;
+    use ublox_sockets::SocketSet;
+
+    /// Board allowed 1000 bytes of data
+    struct SmallBudget;
+
+    impl MockSettings for SmallBudget {
+        const DATA_BUDGET: Option<u64> = Some(1000);
+    }
+
+    #[test]
+    fn budget_counts_socket_bytes_when_module_does_not() {
+        let mut budget = DataBudget::new(1000);
+        budget.refresh(None);
+        assert!(!budget.needs_refresh());
+
+        budget.record(600);
+        assert_eq!(budget.check(), Ok(()));
+        budget.record(400);
+        assert_eq!(budget.check(), Err(Error::DataBudgetExceeded));
+
+        budget.reset();
+        assert_eq!(budget.check(), Ok(()));
+        assert!(!budget.needs_refresh());
+    }
+
+    #[test]
+    fn data_usage_is_reported_and_capped_by_the_budget() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        expect_context_activation(&modem);
+        modem
+            .expect("AT+USOCTL=0,2", "+USOCTL: 0,2,4")
+            .expect("AT+USOCTL=0,3", "+USOCTL: 0,3,8")
+            .expect("AT+UGCNTRD", "+UGCNTRD: 1,100,200,600,500")
+            // The totals are over budget, and only read again after the refresh
+            // interval
+            .expect("AT+UGCNTRD", "+UGCNTRD: 1,100,200,600,500")
+            .expect("AT+UGCNTSET=1,0,0", "");
+
+        let mut device = new_device(&modem, MockConfig::<SmallBudget>::default());
+
+        let sockets = Box::leak(Box::new(SocketSet::<N, L>::new()));
+        let mut handle = connected_tcp_socket(sockets);
+        device.set_socket_storage(sockets);
+
+        let apn = APNInfo::new("em");
+        let mut data_service = device.data_service(&apn).unwrap();
+
+        assert_eq!(
+            data_service.socket_usage(handle),
+            Ok(DataUsage {
+                sent: 4,
+                received: 8
+            })
+        );
+        assert_eq!(
+            data_service.context_usage(),
+            Ok(ContextUsage {
+                session: DataUsage {
+                    sent: 100,
+                    received: 200
+                },
+                total: DataUsage {
+                    sent: 600,
+                    received: 500
+                },
+            })
+        );
+
+        for _ in 0..2 {
+            assert_eq!(
+                data_service.send(&mut handle, &[0xDE, 0xAD, 0xBE, 0xEF]),
+                Err(nb::Error::Other(ublox_sockets::Error::Exhausted))
+            );
+        }
+        assert_eq!(data_service.reset_data_usage(), Ok(()));
+        drop(data_service);
+
+        // The counters are read again on the next send
+        let budget = device.network.contexts.data_budget.as_ref().unwrap();
+        assert_eq!(budget.check(), Ok(()));
+        assert!(budget.needs_refresh());
+        modem.assert_done();
+    }
+}
@@ -0,0 +1,115 @@
+//! Module setup shared by the blocking and async devices
+//!
+//! `setup_at_commands` and `initialize` send the same commands whether the
+//! module is driven by a blocking or an async `AtatClient`. They are decided
+//! and built here, the devices only sending them in the order of the fields.
+//! The SIM, registration, PSM and context definition commands are built the
+//! same way in their own modules.
+
+use crate::{
+    command::{
+        control::{
+            types::{Circuit108Behaviour, Circuit109Behaviour, FlowControl},
+            SetCircuit108Behaviour, SetCircuit109Behaviour, SetFlowControl,
+        },
+        ip_transport_layer::{types::HexMode, SetHexMode},
+        mobile_control::{
+            types::{AutomaticTimezone, Functionality, ResetMode, TerminationErrorMode},
+            SetAutomaticTimezoneUpdate, SetModuleFunctionality, SetReportMobileTerminationError,
+        },
+        sms::{types::MessageWaitingMode, SetMessageWaitingIndication},
+    },
+    config::CellularConfig,
+    module::{ModuleKind, Vendor},
+};
+
+/// Extended errors on
+pub(crate) const EXTENDED_ERRORS: SetReportMobileTerminationError =
+    SetReportMobileTerminationError {
+        n: TerminationErrorMode::Enabled,
+    };
+
+/// Minimum functionality on `module`, leaving the network
+pub(crate) fn minimum_functionality(module: ModuleKind) -> SetModuleFunctionality {
+    SetModuleFunctionality {
+        fun: Functionality::Minimum,
+        rst: module.cfun_reset_mode(&Functionality::Minimum),
+    }
+}
+
+/// Full functionality again, after [`minimum_functionality`]
+pub(crate) const FULL_FUNCTIONALITY: SetModuleFunctionality = SetModuleFunctionality {
+    fun: Functionality::Full,
+    rst: Some(ResetMode::DontReset),
+};
+
+/// Serial interface settings of `setup_at_commands`, once the module is
+/// identified
+pub(crate) struct InterfaceSetup {
+    /// DCD circuit (109) changes in accordance with the carrier
+    pub dcd: SetCircuit109Behaviour,
+    /// Ignore changes to DTR
+    pub dtr: SetCircuit108Behaviour,
+    /// u-blox specific: the hex mode of the socket commands
+    pub hex_mode: Option<SetHexMode>,
+    /// Tell module whether we support flow control
+    // FIXME: Use AT+IFC=2,2 instead of AT&K here
+    pub flow_control: SetFlowControl,
+}
+
+impl InterfaceSetup {
+    pub(crate) fn new<Config: CellularConfig>(module: ModuleKind) -> Self {
+        let hex_mode_disable = if Config::HEX_MODE {
+            HexMode::Enabled
+        } else {
+            HexMode::Disabled
+        };
+        let flow_control = if Config::FLOW_CONTROL {
+            FlowControl::RtsCts
+        } else {
+            FlowControl::Disabled
+        };
+
+        Self {
+            dcd: SetCircuit109Behaviour {
+                value: Circuit109Behaviour::ChangesWithCarrier,
+            },
+            dtr: SetCircuit108Behaviour {
+                value: Circuit108Behaviour::Ignore,
+            },
+            hex_mode: (module.vendor() == Vendor::Ublox).then_some(SetHexMode { hex_mode_disable }),
+            flow_control: SetFlowControl {
+                value: flow_control,
+            },
+        }
+    }
+}
+
+/// Settings of `initialize`, before the radio configuration and the
+/// registration
+pub(crate) struct ModuleSetup {
+    /// Disable Message Waiting URCs (UMWI)
+    pub message_waiting: Option<SetMessageWaitingIndication>,
+    pub timezone: SetAutomaticTimezoneUpdate,
+    /// Full functionality, registering to the network
+    pub functionality: SetModuleFunctionality,
+}
+
+impl ModuleSetup {
+    pub(crate) fn new(module: ModuleKind) -> Self {
+        Self {
+            message_waiting: (module == ModuleKind::TobyR2).then_some(
+                SetMessageWaitingIndication {
+                    mode: MessageWaitingMode::Disabled,
+                },
+            ),
+            timezone: SetAutomaticTimezoneUpdate {
+                on_off: AutomaticTimezone::EnabledLocal,
+            },
+            functionality: SetModuleFunctionality {
+                fun: Functionality::Full,
+                rst: None,
+            },
+        }
+    }
+}
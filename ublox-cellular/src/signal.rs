@@ -0,0 +1,196 @@
+//! Signal quality in physical units
+//!
+//! Decodes the indices reported by `+CESQ` (3GPP TS 27.007, 8.69), or by
+//! `+CSQ` on the modules lacking it, for the Radio Access Technology of the
+//! serving cell.
+
+use crate::command::network_service::responses::{SignalQuality, SignalStrength};
+
+/// Index reported by `+CSQ`, and for `<rxlev>` and `<ber>` by `+CESQ`, when
+/// the value is not known or not detectable
+const NOT_KNOWN: u8 = 99;
+/// Index reported by `+CESQ` for `<rscp>`, `<ecn0>`, `<rsrq>` and `<rsrp>`
+/// when the value is not known or not detectable
+const NOT_KNOWN_EXT: u8 = 255;
+
+/// Signal quality of the serving cell. Values are the lower bound of the
+/// reported range, `None` when not known or not detectable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignalMetrics {
+    /// GERAN serving cell
+    Gsm {
+        /// Received signal strength, in dBm
+        rssi: Option<i16>,
+        /// Channel bit error rate, as RXQUAL (0 to 7)
+        ber: Option<u8>,
+    },
+    /// UTRA FDD serving cell
+    Utran {
+        /// Received signal code power, in dBm
+        rscp: Option<i16>,
+        /// Ratio of the received energy per PN chip to the total received
+        /// power spectral density, in dB
+        ecn0: Option<f32>,
+    },
+    /// E-UTRA serving cell
+    Eutran {
+        /// Reference signal received power, in dBm
+        rsrp: Option<i16>,
+        /// Reference signal received quality, in dB
+        rsrq: Option<f32>,
+    },
+    /// Reported by `+CSQ`, regardless of the Radio Access Technology
+    Rssi {
+        /// Received signal strength, in dBm
+        rssi: Option<i16>,
+        /// Channel bit error rate, as RXQUAL (0 to 7)
+        qual: Option<u8>,
+    },
+    /// No serving cell, or no value known
+    Unknown,
+}
+
+fn known(index: u8, max: u8) -> Option<u8> {
+    (index <= max).then_some(index)
+}
+
+/// Linear conversion of `index`, if known
+fn dbm(index: u8, max: u8, offset: i16) -> Option<i16> {
+    known(index, max).map(|index| i16::from(index) + offset)
+}
+
+/// Half dB steps conversion of `index`, if known
+fn half_db(index: u8, max: u8, offset: f32) -> Option<f32> {
+    known(index, max).map(|index| f32::from(index) / 2.0 + offset)
+}
+
+/// Position of `value` in `[min, max]`, in percent
+fn percent(value: i16, min: i16, max: i16) -> u8 {
+    let value = value.clamp(min, max);
+    ((i32::from(value - min) * 100) / i32::from(max - min)) as u8
+}
+
+/// Bars for `value`, given the lower bound of bars 1 to 5
+fn bars(value: i16, thresholds: [i16; 5]) -> u8 {
+    thresholds.iter().filter(|&&min| value >= min).count() as u8
+}
+
+impl SignalMetrics {
+    /// Signal strength, normalized to 0 (no signal, or not known) to 5 bars
+    pub fn bars(&self) -> u8 {
+        match *self {
+            Self::Gsm {
+                rssi: Some(rssi), ..
+            }
+            | Self::Rssi {
+                rssi: Some(rssi), ..
+            } => bars(rssi, [-105, -95, -85, -75, -65]),
+            Self::Utran {
+                rscp: Some(rscp), ..
+            } => bars(rscp, [-115, -105, -95, -85, -75]),
+            Self::Eutran {
+                rsrp: Some(rsrp), ..
+            } => bars(rsrp, [-120, -110, -100, -90, -80]),
+            _ => 0,
+        }
+    }
+
+    /// Signal strength within the range reported for the Radio Access
+    /// Technology, from 0 to 100 percent. 0 if not known.
+    pub fn percent(&self) -> u8 {
+        match *self {
+            Self::Gsm {
+                rssi: Some(rssi), ..
+            } => percent(rssi, -111, -48),
+            Self::Rssi {
+                rssi: Some(rssi), ..
+            } => percent(rssi, -113, -51),
+            Self::Utran {
+                rscp: Some(rscp), ..
+            } => percent(rscp, -121, -25),
+            Self::Eutran {
+                rsrp: Some(rsrp), ..
+            } => percent(rsrp, -141, -44),
+            _ => 0,
+        }
+    }
+}
+
+impl From<&SignalQuality> for SignalMetrics {
+    fn from(q: &SignalQuality) -> Self {
+        // Only the parameters of the serving cell's RAT are known
+        if q.rsrp != NOT_KNOWN_EXT || q.rsrq != NOT_KNOWN_EXT {
+            Self::Eutran {
+                rsrp: dbm(q.rsrp, 97, -141),
+                rsrq: half_db(q.rsrq, 34, -20.0),
+            }
+        } else if q.rscp != NOT_KNOWN_EXT || q.ecn0 != NOT_KNOWN_EXT {
+            Self::Utran {
+                rscp: dbm(q.rscp, 96, -121),
+                ecn0: half_db(q.ecn0, 49, -24.5),
+            }
+        } else if q.rxlev != NOT_KNOWN || q.ber != NOT_KNOWN {
+            Self::Gsm {
+                rssi: dbm(q.rxlev, 63, -111),
+                ber: known(q.ber, 7),
+            }
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+impl From<&SignalStrength> for SignalMetrics {
+    fn from(s: &SignalStrength) -> Self {
+        if s.rssi == NOT_KNOWN && s.qual == NOT_KNOWN {
+            return Self::Unknown;
+        }
+
+        Self::Rssi {
+            rssi: known(s.rssi, 31).map(|rssi| i16::from(rssi) * 2 - 113),
+            qual: known(s.qual, 7),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{registered_device, MockConfig, MockModem};
+
+    #[test]
+    fn signal_metrics_are_decoded_for_the_serving_cell() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "LARA-R211", MockConfig::new());
+
+        modem.expect("AT+CESQ", "+CESQ: 99,99,255,255,20,51");
+        let metrics = device.signal_metrics().unwrap();
+        assert_eq!(
+            metrics,
+            SignalMetrics::Eutran {
+                rsrp: Some(-90),
+                rsrq: Some(-10.0),
+            }
+        );
+        assert_eq!(metrics.bars(), 4);
+        assert_eq!(metrics.percent(), 52);
+
+        modem.expect("AT+CESQ", "+CESQ: 99,99,255,255,255,255");
+        assert_eq!(device.signal_metrics(), Ok(SignalMetrics::Unknown));
+
+        // Falls back to `+CSQ` on modules without `+CESQ`
+        modem
+            .expect_err("AT+CESQ", atat::Error::Error)
+            .expect("AT+CSQ", "+CSQ: 15,99");
+        let metrics = device.signal_metrics().unwrap();
+        assert_eq!(
+            metrics,
+            SignalMetrics::Rssi {
+                rssi: Some(-83),
+                qual: None,
+            }
+        );
+        assert_eq!(metrics.bars(), 3);
+        modem.assert_done();
+    }
+}
@@ -0,0 +1,438 @@
+//! SIM slot selection and PIN management
+//!
+//! The SIM slot is selected by `initialize` as configured with
+//! [`CellularConfig::SIM_SELECT`]. On boards with two slots switched by a GPIO,
+//! [`CellularConfig::SIM_FAILOVER_TIMEOUT`] switches to the other slot when
+//! the active SIM is denied registration, or does not register in time.
+//!
+//! `initialize` unlocks the SIM with [`CellularConfig::SIM_PIN`], giving it
+//! only once. If the SIM is still locked, it fails with [`Error::SimPin`],
+//! [`Error::SimPuk`] or [`Error::SimLocked`], and the application may unlock
+//! it with the methods below before initializing again. They are usable as
+//! soon as the module is powered, the failed `initialize` leaving it so, and
+//! the async `Device` has the same methods.
+//!
+//! [`CellularConfig::SIM_SELECT`]: crate::CellularConfig::SIM_SELECT
+//! [`CellularConfig::SIM_FAILOVER_TIMEOUT`]: crate::CellularConfig::SIM_FAILOVER_TIMEOUT
+//! [`CellularConfig::SIM_PIN`]: crate::CellularConfig::SIM_PIN
+
+use crate::{
+    client::{Device, State},
+    command::{
+        device_lock::{
+            responses::PinStatus,
+            types::{Facility, FacilityLockMode, PinStatusCode},
+            ChangePassword, ChangePin, SetFacilityLock, SetPin,
+        },
+        gpio::{
+            types::{GpioInPull, GpioMode, GpioOutValue},
+            SetGpioConfiguration,
+        },
+    },
+    config::CellularConfig,
+    error::Error,
+    module::{ModuleKind, Vendor},
+    network,
+    registration::{self, ConnectionState, RegistrationState},
+};
+use atat::blocking::AtatClient;
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+
+/// How the SIM slot is selected, see [`CellularConfig::SIM_SELECT`]
+///
+/// [`CellularConfig::SIM_SELECT`]: crate::CellularConfig::SIM_SELECT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SimSelect {
+    /// GPIO 25 driven high on u-blox modules, and GPIO 42 set as input on
+    /// LARA-R6, as on the u-blox evaluation boards
+    Default,
+    /// The SIM is not selected by the driver
+    None,
+    /// The SIM slot is switched by a GPIO of a u-blox module
+    Gpio {
+        gpio_id: u8,
+        /// Level of the GPIO selecting the primary slot, the other level
+        /// selecting the secondary slot
+        primary_high: bool,
+    },
+}
+
+/// SIM slot, on boards switching between two of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SimSlot {
+    Primary,
+    Secondary,
+}
+
+impl SimSlot {
+    pub(crate) fn other(self) -> Self {
+        match self {
+            Self::Primary => Self::Secondary,
+            Self::Secondary => Self::Primary,
+        }
+    }
+}
+
+impl SimSelect {
+    /// GPIO configuration selecting `slot` on `module`
+    pub(crate) fn gpio_configuration(
+        &self,
+        module: ModuleKind,
+        slot: SimSlot,
+    ) -> Vec<SetGpioConfiguration, 2> {
+        let mut configuration = Vec::new();
+        if module.vendor() != Vendor::Ublox {
+            if let Self::Gpio { .. } = self {
+                warn!("SIM selection by GPIO not supported by the module");
+            }
+            return configuration;
+        }
+
+        match *self {
+            Self::Default => {
+                configuration
+                    .push(SetGpioConfiguration {
+                        gpio_id: 25,
+                        gpio_mode: GpioMode::Output(GpioOutValue::High),
+                    })
+                    .ok();
+                if module == ModuleKind::LaraR6 {
+                    configuration
+                        .push(SetGpioConfiguration {
+                            gpio_id: 42,
+                            gpio_mode: GpioMode::Input(GpioInPull::NoPull),
+                        })
+                        .ok();
+                }
+            }
+            Self::None => {}
+            Self::Gpio {
+                gpio_id,
+                primary_high,
+            } => {
+                let value = if primary_high == (slot == SimSlot::Primary) {
+                    GpioOutValue::High
+                } else {
+                    GpioOutValue::Low
+                };
+                configuration
+                    .push(SetGpioConfiguration {
+                        gpio_id,
+                        gpio_mode: GpioMode::Output(value),
+                    })
+                    .ok();
+            }
+        }
+        configuration
+    }
+
+    /// Whether another SIM slot can be switched to
+    pub(crate) fn dual_sim(&self) -> bool {
+        matches!(self, Self::Gpio { .. })
+    }
+}
+
+/// Whether the active SIM should be given up: while registering, it is
+/// denied registration, or it has not registered within `timeout`
+pub(crate) fn failover_due(status: &RegistrationState, timeout: Duration) -> bool {
+    if status.conn_state != ConnectionState::Connecting {
+        return false;
+    }
+
+    let denied = [&status.csd, &status.psd, &status.eps]
+        .iter()
+        .any(|domain| domain.get_status() == registration::Status::Denied);
+    let timed_out = status
+        .reg_start_time
+        .and_then(|start| Instant::now().checked_duration_since(start))
+        .map_or(false, |elapsed| elapsed >= timeout);
+    denied || timed_out
+}
+
+/// Whether the device should switch to the other SIM slot, see
+/// [`CellularConfig::SIM_FAILOVER_TIMEOUT`]
+///
+/// [`CellularConfig::SIM_FAILOVER_TIMEOUT`]: crate::CellularConfig::SIM_FAILOVER_TIMEOUT
+pub(crate) fn failover_needed<Config: CellularConfig>(
+    state: State,
+    status: &RegistrationState,
+) -> bool {
+    let Some(timeout) = Config::SIM_FAILOVER_TIMEOUT else {
+        return false;
+    };
+    Config::SIM_SELECT.dual_sim()
+        && state == State::FullyInitialized
+        && failover_due(status, timeout)
+}
+
+/// Next step of `select_sim_card`
+pub(crate) enum PinStep {
+    /// The SIM is unlocked
+    Ready,
+    /// Give the configured PIN
+    EnterPin(&'static str),
+    /// The SIM may not be ready yet, read its status again after a while
+    Wait,
+}
+
+/// Unlocking of the SIM by `select_sim_card`, with the configured PIN
+#[derive(Default)]
+pub(crate) struct PinUnlock {
+    pin_entered: bool,
+}
+
+impl PinUnlock {
+    /// The step following the PIN `status` read from the module
+    pub(crate) fn step<Config: CellularConfig>(
+        &mut self,
+        status: Result<PinStatus, network::Error>,
+        pin_rejected: bool,
+    ) -> Result<PinStep, Error> {
+        let Ok(PinStatus { code }) = status else {
+            return Ok(PinStep::Wait);
+        };
+        match code {
+            PinStatusCode::Ready => Ok(PinStep::Ready),
+            PinStatusCode::SimPin => {
+                // The configured PIN is only given once, a wrong one would
+                // use up the attempts left before the PUK
+                let pin = match Config::SIM_PIN {
+                    Some(pin) if !self.pin_entered && !pin_rejected => pin,
+                    _ => return Err(Error::SimPin),
+                };
+                self.pin_entered = true;
+                Ok(PinStep::EnterPin(pin))
+            }
+            PinStatusCode::SimPuk => Err(Error::SimPuk),
+            _ => Err(Error::SimLocked),
+        }
+    }
+}
+
+/// Outcome of giving the configured PIN, noting in `pin_rejected` that it
+/// must not be given again
+pub(crate) fn check_pin<T>(
+    res: Result<T, network::Error>,
+    pin_rejected: &mut bool,
+) -> Result<(), Error> {
+    match res {
+        Ok(_) => Ok(()),
+        Err(network::Error::AT(atat::Error::Error)) => {
+            error!("SIM PIN rejected");
+            *pin_rejected = true;
+            Err(Error::SimPin)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl<'buf, 'sub, AtCl, AtUrcCh, Config, const N: usize, const L: usize>
+    Device<'buf, 'sub, AtCl, AtUrcCh, Config, N, L>
+where
+    'buf: 'sub,
+    AtCl: AtatClient,
+    Config: CellularConfig,
+{
+    /// Unlock the SIM with `pin`, when it is waiting for the SIM PIN
+    pub fn enter_pin(&mut self, pin: &str) -> Result<(), Error> {
+        self.network.send_internal(&SetPin { pin }, true)?;
+        self.sim_pin_rejected = false;
+        Ok(())
+    }
+
+    /// Unblock the SIM with `puk`, when it is waiting for the SIM PUK, and
+    /// set `new_pin` as its PIN
+    pub fn unblock_pin(&mut self, puk: &str, new_pin: &str) -> Result<(), Error> {
+        self.network.send_internal(
+            &ChangePin {
+                puk,
+                newpin: new_pin,
+            },
+            true,
+        )?;
+        self.sim_pin_rejected = false;
+        Ok(())
+    }
+
+    /// Change the SIM PIN from `old_pin` to `new_pin`. The PIN request must
+    /// be enabled.
+    pub fn change_pin(&mut self, old_pin: &str, new_pin: &str) -> Result<(), Error> {
+        self.network.send_internal(
+            &ChangePassword {
+                fac: Facility::SimPin,
+                oldpwd: old_pin,
+                newpwd: new_pin,
+            },
+            true,
+        )?;
+        Ok(())
+    }
+
+    /// Enable the SIM PIN request at power on
+    pub fn enable_pin(&mut self, pin: &str) -> Result<(), Error> {
+        self.set_pin_lock(FacilityLockMode::Lock, pin)
+    }
+
+    /// Disable the SIM PIN request at power on
+    pub fn disable_pin(&mut self, pin: &str) -> Result<(), Error> {
+        self.set_pin_lock(FacilityLockMode::Unlock, pin)
+    }
+
+    fn set_pin_lock(&mut self, mode: FacilityLockMode, pin: &str) -> Result<(), Error> {
+        self.network.send_internal(
+            &SetFacilityLock {
+                fac: Facility::SimPin,
+                mode,
+                passwd: Some(pin),
+            },
+            true,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "async")]
+    use crate::test_support::{block_on, new_async_device};
+    use crate::{
+        client::State,
+        test_support::{new_device, registered_device, MockConfig, MockModem, MockSettings},
+        Event,
+    };
+
+    /// Board whose SIM requests the PIN at power on
+    struct SimPin;
+
+    impl MockSettings for SimPin {
+        const SIM_PIN: Option<&'static str> = Some("1234");
+    }
+
+    #[test]
+    fn sim_is_unlocked_with_configured_pin() {
+        let modem = MockModem::new();
+        modem
+            .expect("AT+CPIN?", "+CPIN: SIM PIN")
+            .expect("AT+CPIN=\"1234\"", "")
+            .expect("AT+CPIN?", "+CPIN: READY");
+
+        let mut device = new_device(&modem, MockConfig::<SimPin>::default());
+        assert_eq!(device.select_sim_card(), Ok(()));
+        modem.assert_done();
+
+        // A rejected PIN is not given again
+        let modem = MockModem::new();
+        modem
+            .expect("AT+CPIN?", "+CPIN: SIM PIN")
+            .expect_err("AT+CPIN=\"1234\"", atat::Error::Error)
+            .expect("AT+CPIN?", "+CPIN: SIM PIN");
+
+        let mut device = new_device(&modem, MockConfig::<SimPin>::default());
+        assert_eq!(device.select_sim_card(), Err(Error::SimPin));
+        assert_eq!(device.select_sim_card(), Err(Error::SimPin));
+        modem.assert_done();
+    }
+
+    /// Script a SIM blocked at power on, unblocked with the PUK
+    fn expect_puk_unblock(modem: &MockModem) {
+        modem
+            .expect("AT+CPIN?", "+CPIN: SIM PUK")
+            .expect("AT+CPIN=\"12345678\",\"4321\"", "")
+            .expect("AT+CPIN?", "+CPIN: READY");
+    }
+
+    #[test]
+    fn blocked_sim_is_reported_and_unblocked() {
+        let modem = MockModem::new();
+        expect_puk_unblock(&modem);
+
+        let mut device = new_device(&modem, MockConfig::<SimPin>::default());
+        assert_eq!(device.select_sim_card(), Err(Error::SimPuk));
+        assert_eq!(device.unblock_pin("12345678", "4321"), Ok(()));
+        assert_eq!(device.select_sim_card(), Ok(()));
+        modem.assert_done();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_blocked_sim_is_reported_and_unblocked() {
+        let modem = MockModem::new();
+        expect_puk_unblock(&modem);
+
+        let mut device = new_async_device(&modem, MockConfig::new());
+        block_on(async {
+            assert_eq!(device.select_sim_card().await, Err(Error::SimPuk));
+            assert_eq!(device.unblock_pin("12345678", "4321").await, Ok(()));
+            assert_eq!(device.select_sim_card().await, Ok(()));
+        });
+        modem.assert_done();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_sim_pin_is_entered_changed_and_toggled() {
+        let modem = MockModem::new();
+        modem
+            .expect("AT+CPIN=\"1234\"", "")
+            .expect("AT+CPWD=\"SC\",\"1234\",\"4321\"", "")
+            .expect("AT+CLCK=\"SC\",0,\"4321\"", "")
+            .expect("AT+CLCK=\"SC\",1,\"4321\"", "");
+
+        let mut device = new_async_device(&modem, MockConfig::new());
+        device.sim_pin_rejected = true;
+        block_on(async {
+            assert_eq!(device.enter_pin("1234").await, Ok(()));
+            assert!(!device.sim_pin_rejected);
+            assert_eq!(device.change_pin("1234", "4321").await, Ok(()));
+            assert_eq!(device.disable_pin("4321").await, Ok(()));
+            assert_eq!(device.enable_pin("4321").await, Ok(()));
+        });
+        modem.assert_done();
+    }
+
+    /// Board with two SIM slots, switched by GPIO 25
+    struct DualSim;
+
+    impl MockSettings for DualSim {
+        const SIM_SELECT: SimSelect = SimSelect::Gpio {
+            gpio_id: 25,
+            primary_high: true,
+        };
+        const SIM_FAILOVER_TIMEOUT: Option<Duration> = Some(Duration::from_secs(120));
+    }
+
+    #[test]
+    fn denied_sim_fails_over_to_the_other_slot() {
+        let modem = MockModem::new();
+        let mut device = registered_device(&modem, "LARA-R211", MockConfig::<DualSim>::default());
+        assert_eq!(device.sim_slot(), SimSlot::Primary);
+
+        modem.urc("+CREG: 3");
+        modem.flush_urcs();
+        modem.expect("AT+CIMI", "238010000000000");
+        assert_eq!(device.spin(), Err(nb::Error::WouldBlock));
+        assert_eq!(device.sim_slot(), SimSlot::Primary);
+
+        modem.urc("+CGREG: 3");
+        modem.flush_urcs();
+        modem
+            .expect("AT+CFUN=0,0", "")
+            .expect("AT+UGPIOC=25,0,0", "");
+        assert_eq!(device.spin(), Err(nb::Error::WouldBlock));
+        assert_eq!(device.sim_slot(), SimSlot::Secondary);
+        assert_eq!(device.state, State::Off);
+        assert_eq!(
+            device.config.events,
+            [
+                Event::Registered,
+                Event::Deregistered,
+                Event::SimSlotSwitched(SimSlot::Secondary),
+            ]
+        );
+        modem.assert_done();
+    }
+}
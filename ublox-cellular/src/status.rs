@@ -0,0 +1,131 @@
+//! Public snapshot of the network state tracked by the driver
+
+use crate::{
+    command::network_service::types::RatAct,
+    registration::{CellularRegistrationStatus, ConnectionState, RegistrationState, Status},
+    services::data::{
+        context::{Contexts, PdpContext},
+        ContextState,
+    },
+};
+use embassy_time::Instant;
+use embedded_nal::IpAddr;
+use heapless::String;
+use serde::Serialize;
+
+/// Registration of a single domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DomainStatus {
+    pub status: Status,
+    /// Time spent in `status`, in milliseconds
+    pub duration_ms: u64,
+}
+
+impl DomainStatus {
+    fn new(status: &CellularRegistrationStatus, now: Instant) -> Self {
+        Self {
+            status: status.get_status(),
+            duration_ms: status.duration(now).as_millis(),
+        }
+    }
+}
+
+/// Snapshot of the network state, see
+/// [`Device::network_status`](crate::GsmClient::network_status)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkStatus {
+    pub connection: ConnectionState,
+    /// CS (Circuit Switched) registration, from `+CREG`
+    pub csd: DomainStatus,
+    /// PS (Packet Switched) registration, from `+CGREG`
+    pub psd: DomainStatus,
+    /// EPS (Evolved Packet Switched) registration, from `+CEREG`
+    pub eps: DomainStatus,
+    /// Radio Access Technology of the last PS or EPS registration
+    pub act: RatAct,
+    pub cell_id: Option<String<8>>,
+    /// Location Area Code, or Tracking Area Code on E-UTRAN
+    pub lac: Option<String<4>>,
+    /// Time spent registering since the last (re)start, in milliseconds
+    pub registration_ms: Option<u64>,
+    /// Interventions (RF resets, operator reselection) taken while
+    /// registering
+    pub registration_interventions: u8,
+    /// State of the default context, see [`PdpContext::DEFAULT`]
+    pub context: ContextState,
+    /// IP address of the data connection over the default context, while
+    /// it is active
+    pub ip_addr: Option<IpAddr>,
+}
+
+impl NetworkStatus {
+    pub(crate) fn new(status: &RegistrationState, contexts: &Contexts) -> Self {
+        let now = Instant::now();
+        let cid = PdpContext::DEFAULT.cid;
+
+        Self {
+            connection: status.conn_state,
+            csd: DomainStatus::new(&status.csd, now),
+            psd: DomainStatus::new(&status.psd, now),
+            eps: DomainStatus::new(&status.eps, now),
+            act: status.act,
+            cell_id: status.cgi.cell_id.clone(),
+            lac: status.cgi.lac.clone(),
+            registration_ms: status
+                .reg_start_time
+                .and_then(|start| now.checked_duration_since(start))
+                .map(|dur| dur.as_millis()),
+            // The counter starts from 1, as it also scales the time before
+            // the next intervention
+            registration_interventions: status.registration_interventions.saturating_sub(1),
+            context: contexts.state(cid),
+            ip_addr: contexts.ip_addr(cid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        test_support::{
+            expect_context_activation, expect_initialize, new_device, MockConfig, MockModem,
+        },
+        APNInfo, RegistrationStatus,
+    };
+    use embedded_nal::Ipv4Addr;
+
+    #[test]
+    fn network_status_reports_registration_and_context() {
+        let modem = MockModem::new();
+        expect_initialize(&modem);
+        expect_context_activation(&modem);
+
+        let mut device = new_device(&modem, MockConfig::new());
+
+        let apn = APNInfo::new("em");
+        assert!(device.data_service(&apn).is_ok());
+
+        modem.urc("+CEREG: 1,\"0001\",\"01A2B3C4\",7");
+        modem.flush_urcs();
+        assert!(device.spin().is_ok());
+
+        let status = device.network_status();
+        assert_eq!(status.connection, ConnectionState::Connected);
+        assert_eq!(status.csd.status, RegistrationStatus::Home);
+        assert_eq!(status.psd.status, RegistrationStatus::Home);
+        assert_eq!(status.eps.status, RegistrationStatus::Home);
+        assert_eq!(status.act, RatAct::Lte);
+        assert_eq!(status.cell_id.as_deref(), Some("01A2B3C4"));
+        assert_eq!(status.lac.as_deref(), Some("0001"));
+        assert_eq!(status.context, ContextState::Active);
+        assert_eq!(status.ip_addr, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))));
+
+        // The address is only reported while the context is active
+        modem.urc("+UUPSDD: 1").flush_urcs();
+        device.spin().ok();
+        assert_eq!(device.network_status().ip_addr, None);
+        modem.assert_done();
+    }
+}
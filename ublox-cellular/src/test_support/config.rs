@@ -0,0 +1,191 @@
+//! [`MockConfig`], with its associated constants overridden per test through
+//! [`MockSettings`]
+
+use std::{rc::Rc, vec::Vec};
+
+use core::{cell::RefCell, convert::Infallible, marker::PhantomData};
+use embassy_time::Duration;
+use embedded_hal::digital::{ErrorType, OutputPin};
+
+use crate::{
+    command::{
+        control::types::BaudRate, network_service::types::LpwaRadioAccessTechnology,
+        system_features::types::PowerSavingMode,
+    },
+    config::{CellularConfig, NoPin},
+    BandMask, Event, SimSelect,
+};
+
+/// [`CellularConfig`] keeping every default, backing those of
+/// [`MockSettings`]
+struct Defaults;
+
+impl CellularConfig for Defaults {
+    type ResetPin = NoPin;
+    type PowerPin = NoPin;
+    type VintPin = NoPin;
+    type DtrPin = NoPin;
+
+    fn reset_pin(&mut self) -> Option<&mut Self::ResetPin> {
+        None
+    }
+
+    fn power_pin(&mut self) -> Option<&mut Self::PowerPin> {
+        None
+    }
+
+    fn vint_pin(&mut self) -> Option<&mut Self::VintPin> {
+        None
+    }
+}
+
+/// Associated constants of a [`MockConfig`], defaulting to the ones of
+/// [`CellularConfig`]. Tests override the ones they exercise:
+///
+/// ```ignore
+/// struct SimPin;
+///
+/// impl MockSettings for SimPin {
+///     const SIM_PIN: Option<&'static str> = Some("1234");
+/// }
+///
+/// let config = MockConfig::<SimPin>::default();
+/// ```
+pub trait MockSettings {
+    const FLOW_CONTROL: bool = Defaults::FLOW_CONTROL;
+    const HEX_MODE: bool = Defaults::HEX_MODE;
+    const BAUD_RATES: &'static [BaudRate] = Defaults::BAUD_RATES;
+    const BAUD_RATE: Option<BaudRate> = Defaults::BAUD_RATE;
+    const OPERATORS: &'static [&'static str] = Defaults::OPERATORS;
+    const SIM_PIN: Option<&'static str> = Defaults::SIM_PIN;
+    const SIM_SELECT: SimSelect = Defaults::SIM_SELECT;
+    const SIM_FAILOVER_TIMEOUT: Option<Duration> = Defaults::SIM_FAILOVER_TIMEOUT;
+    const RADIO_ACCESS_TECHNOLOGIES: &'static [LpwaRadioAccessTechnology] =
+        Defaults::RADIO_ACCESS_TECHNOLOGIES;
+    const BAND_MASKS: &'static [BandMask] = Defaults::BAND_MASKS;
+    const DATA_BUDGET: Option<u64> = Defaults::DATA_BUDGET;
+    const POWER_SAVING: PowerSavingMode = Defaults::POWER_SAVING;
+    const POWER_SAVING_IDLE: Duration = Defaults::POWER_SAVING_IDLE;
+
+    /// Whether the host side of the serial port can be reconfigured, see
+    /// [`CellularConfig::set_baud_rate`]
+    const SET_BAUD_RATE: bool = false;
+}
+
+/// [`MockSettings`] keeping every default
+pub struct DefaultSettings;
+
+impl MockSettings for DefaultSettings {}
+
+/// [`CellularConfig`] without any pins, so power state is detected purely
+/// through AT responses. Its associated constants are taken from `S`, and it
+/// records what the driver hands over to the board.
+pub struct MockConfig<S: MockSettings = DefaultSettings> {
+    /// Events reported through [`CellularConfig::on_event`]
+    pub events: Vec<Event>,
+    /// Rates the serial port was set to, with [`MockSettings::SET_BAUD_RATE`]
+    pub baud_rates: Vec<BaudRate>,
+    dtr_pin: Option<RecordingPin>,
+    settings: PhantomData<S>,
+}
+
+impl MockConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: MockSettings> Default for MockConfig<S> {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            baud_rates: Vec::new(),
+            dtr_pin: None,
+            settings: PhantomData,
+        }
+    }
+}
+
+impl<S: MockSettings> MockConfig<S> {
+    /// Hand `pin` over to the driver as the DTR line
+    pub fn with_dtr_pin(mut self, pin: RecordingPin) -> Self {
+        self.dtr_pin = Some(pin);
+        self
+    }
+}
+
+impl<S: MockSettings> CellularConfig for MockConfig<S> {
+    type ResetPin = NoPin;
+    type PowerPin = NoPin;
+    type VintPin = NoPin;
+    type DtrPin = RecordingPin;
+
+    const FLOW_CONTROL: bool = S::FLOW_CONTROL;
+    const HEX_MODE: bool = S::HEX_MODE;
+    const BAUD_RATES: &'static [BaudRate] = S::BAUD_RATES;
+    const BAUD_RATE: Option<BaudRate> = S::BAUD_RATE;
+    const OPERATORS: &'static [&'static str] = S::OPERATORS;
+    const SIM_PIN: Option<&'static str> = S::SIM_PIN;
+    const SIM_SELECT: SimSelect = S::SIM_SELECT;
+    const SIM_FAILOVER_TIMEOUT: Option<Duration> = S::SIM_FAILOVER_TIMEOUT;
+    const RADIO_ACCESS_TECHNOLOGIES: &'static [LpwaRadioAccessTechnology] =
+        S::RADIO_ACCESS_TECHNOLOGIES;
+    const BAND_MASKS: &'static [BandMask] = S::BAND_MASKS;
+    const DATA_BUDGET: Option<u64> = S::DATA_BUDGET;
+    const POWER_SAVING: PowerSavingMode = S::POWER_SAVING;
+    const POWER_SAVING_IDLE: Duration = S::POWER_SAVING_IDLE;
+
+    fn reset_pin(&mut self) -> Option<&mut Self::ResetPin> {
+        None
+    }
+
+    fn power_pin(&mut self) -> Option<&mut Self::PowerPin> {
+        None
+    }
+
+    fn vint_pin(&mut self) -> Option<&mut Self::VintPin> {
+        None
+    }
+
+    fn take_dtr_pin(&mut self) -> Option<Self::DtrPin> {
+        self.dtr_pin.take()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: BaudRate) -> bool {
+        if S::SET_BAUD_RATE {
+            self.baud_rates.push(baud_rate);
+        }
+        S::SET_BAUD_RATE
+    }
+
+    fn on_event(&mut self, event: Event) {
+        self.events.push(event);
+    }
+}
+
+/// Output pin recording the levels it is driven to, `true` being high
+#[derive(Clone, Default)]
+pub struct RecordingPin(Rc<RefCell<Vec<bool>>>);
+
+impl RecordingPin {
+    /// Levels driven so far, through any clone of the pin
+    pub fn levels(&self) -> Vec<bool> {
+        self.0.borrow().clone()
+    }
+}
+
+impl ErrorType for RecordingPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for RecordingPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().push(true);
+        Ok(())
+    }
+}
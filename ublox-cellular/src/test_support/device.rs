@@ -0,0 +1,62 @@
+//! Devices on a [`MockModem`], set up the way most tests need them
+
+use super::{expect_initialize_model, MockAtClient, MockConfig, MockModem, MockSettings};
+use crate::client::Device;
+
+/// Number of sockets of the mock devices
+pub const N: usize = 2;
+/// Size of the socket buffers of the mock devices
+pub const L: usize = 1024;
+
+/// Blocking [`Device`] on a [`MockModem`], configured by `S`
+pub type MockDevice<'a, S> = Device<'a, 'a, MockAtClient<'a>, MockModem, MockConfig<S>, N, L>;
+
+/// Async [`Device`](crate::asynch::Device) on a [`MockModem`], configured by
+/// `S`
+#[cfg(feature = "async")]
+pub type MockAsyncDevice<'a, S> =
+    crate::asynch::Device<'a, 'a, MockAtClient<'a>, MockModem, MockConfig<S>, N, L>;
+
+/// Create a blocking device on `modem`
+pub fn new_device<S: MockSettings>(modem: &MockModem, config: MockConfig<S>) -> MockDevice<'_, S> {
+    Device::new(modem.client(), modem, config)
+}
+
+/// Create a blocking device on `modem`, and spin it through the
+/// initialization of `model`, scripted by [`expect_initialize_model`].
+pub fn registered_device<'a, S: MockSettings>(
+    modem: &'a MockModem,
+    model: &str,
+    config: MockConfig<S>,
+) -> MockDevice<'a, S> {
+    expect_initialize_model(modem, model);
+
+    let mut device = new_device(modem, config);
+    assert!(device.spin().is_ok());
+    modem.assert_done();
+    device
+}
+
+/// Async version of [`new_device`]
+#[cfg(feature = "async")]
+pub fn new_async_device<S: MockSettings>(
+    modem: &MockModem,
+    config: MockConfig<S>,
+) -> MockAsyncDevice<'_, S> {
+    crate::asynch::Device::new(modem.client(), modem, config)
+}
+
+/// Async version of [`registered_device`]
+#[cfg(feature = "async")]
+pub fn async_registered_device<'a, S: MockSettings>(
+    modem: &'a MockModem,
+    model: &str,
+    config: MockConfig<S>,
+) -> MockAsyncDevice<'a, S> {
+    expect_initialize_model(modem, model);
+
+    let mut device = new_async_device(modem, config);
+    assert!(super::block_on(device.spin()).is_ok());
+    modem.assert_done();
+    device
+}
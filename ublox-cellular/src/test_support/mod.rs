@@ -0,0 +1,305 @@
+//! Scripted mock modem for host-side tests
+//!
+//! [`MockModem`] replays a script of expected AT commands with canned
+//! responses, and URCs injected in between them. It hands out
+//! [`MockAtClient`]s implementing [`atat::blocking::AtatClient`], and
+//! implements [`AtatUrcChannel`] itself, so it can be plugged straight into
+//! [`GsmClient::new`](crate::GsmClient::new), or into the async `Device`
+//! driven by `block_on` with the `async` feature:
+//!
+//! ```ignore
+//! let modem = MockModem::new();
+//! modem
+//!     .expect("AT", "")
+//!     .expect("AT+CPIN?", "+CPIN: READY")
+//!     .urc("+UUPSDD: 1");
+//!
+//! let mut device = new_device(&modem, MockConfig::new());
+//! // ... drive `device` ...
+//! modem.assert_done();
+//! ```
+//!
+//! Any command that does not match the next step of the script panics, with
+//! both the sent and the expected command in the message. Once the script is
+//! exhausted, commands are answered by [`MockModem::answer`], which the AT
+//! trace [`replay`] relies on.
+//!
+//! The usual initialization and context activation are scripted by the
+//! `expect_*` functions, and [`new_device`] and [`registered_device`] (or their
+//! async versions) create the device under test. [`MockConfig`] takes the
+//! configuration under test from [`MockSettings`].
+
+mod config;
+mod device;
+mod replay;
+mod script;
+mod serial;
+
+pub use config::{DefaultSettings, MockConfig, MockSettings, RecordingPin};
+#[cfg(feature = "async")]
+pub use device::{async_registered_device, new_async_device, MockAsyncDevice};
+pub use device::{new_device, registered_device, MockDevice, L, N};
+pub use replay::{parse_log, replay, LogEntry};
+pub use script::{
+    connected_tcp_socket, expect_context_activation, expect_initialize,
+    expect_initialize_configured, expect_initialize_model,
+};
+pub use serial::{FakeSerial, Wire};
+
+use std::{collections::VecDeque, fmt::Write as _, string::String, vec::Vec};
+
+use atat::{AtatCmd, AtatUrc, AtatUrcChannel, UrcSubscription};
+use core::cell::RefCell;
+use embassy_sync::{
+    blocking_mutex::raw::CriticalSectionRawMutex,
+    pubsub::{self, PubSubChannel},
+};
+
+use crate::{
+    client::{URC_CAPACITY, URC_SUBSCRIBERS},
+    command::Urc,
+};
+
+#[derive(Debug)]
+enum Reply {
+    Ok(Vec<u8>),
+    Err(atat::Error),
+}
+
+#[derive(Debug)]
+enum Step {
+    Command { cmd: String, reply: Reply },
+    Urc(String),
+}
+
+/// Scripted fake modem
+pub struct MockModem {
+    steps: RefCell<VecDeque<Step>>,
+    /// Exchanges of the AT trace being replayed, each answering its command
+    /// once
+    traced: RefCell<VecDeque<(String, Reply)>>,
+    /// Answers to the commands sent once the script is exhausted
+    answers: RefCell<Vec<(String, String)>>,
+    channel: PubSubChannel<CriticalSectionRawMutex, Urc, URC_CAPACITY, URC_SUBSCRIBERS, 1>,
+}
+
+impl Default for MockModem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockModem {
+    pub fn new() -> Self {
+        Self {
+            steps: RefCell::new(VecDeque::new()),
+            traced: RefCell::new(VecDeque::new()),
+            answers: RefCell::new(Vec::new()),
+            channel: PubSubChannel::new(),
+        }
+    }
+
+    /// Expect `cmd` (without the trailing `\r\n`) to be sent next, and answer
+    /// it with `response`, as it would appear between the echo and the final
+    /// `OK`. Use an empty `response` for commands without a response.
+    pub fn expect(&self, cmd: &str, response: &str) -> &Self {
+        self.steps.borrow_mut().push_back(Step::Command {
+            cmd: cmd.into(),
+            reply: Reply::Ok(response.as_bytes().to_vec()),
+        });
+        self
+    }
+
+    /// Expect `cmd` to be sent next, and fail it with `error`
+    pub fn expect_err(&self, cmd: &str, error: atat::Error) -> &Self {
+        self.steps.borrow_mut().push_back(Step::Command {
+            cmd: cmd.into(),
+            reply: Reply::Err(error),
+        });
+        self
+    }
+
+    /// Inject `urc` (e.g. `+UUPSDD: 1`) once every step before it has been
+    /// consumed.
+    pub fn urc(&self, urc: &str) -> &Self {
+        self.steps.borrow_mut().push_back(Step::Urc(urc.into()));
+        self
+    }
+
+    /// Answer `cmd` with `response` whenever it is sent once the script is
+    /// exhausted, and it is not in the replayed trace either.
+    pub fn answer(&self, cmd: &str, response: &str) -> &Self {
+        self.answers
+            .borrow_mut()
+            .push((cmd.into(), response.into()));
+        self
+    }
+
+    /// Create a client talking to this modem
+    pub fn client(&self) -> MockAtClient<'_> {
+        MockAtClient { modem: self }
+    }
+
+    /// Publish every URC at the head of the script, without waiting for the
+    /// next command to be sent.
+    pub fn flush_urcs(&self) {
+        loop {
+            let step = self.steps.borrow_mut().pop_front();
+            let urc = match step {
+                Some(Step::Urc(urc)) => urc,
+                Some(step) => {
+                    self.steps.borrow_mut().push_front(step);
+                    return;
+                }
+                None => return,
+            };
+
+            if !self.publish_urc(&urc) {
+                panic!("Scripted URC {urc:?} does not parse");
+            }
+        }
+    }
+
+    /// Publish `urc` right away. Returns `false` if it is not a known URC.
+    pub fn publish_urc(&self, urc: &str) -> bool {
+        match <Urc as AtatUrc>::parse(urc.as_bytes()) {
+            Some(parsed) => {
+                self.channel.immediate_publisher().publish_immediate(parsed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of steps left in the script
+    pub fn remaining(&self) -> usize {
+        self.steps.borrow().len()
+    }
+
+    /// Panic if the script has not been fully consumed
+    pub fn assert_done(&self) {
+        let steps = self.steps.borrow();
+        if !steps.is_empty() {
+            let mut msg = String::new();
+            for step in steps.iter() {
+                writeln!(msg, "  {step:?}").ok();
+            }
+            panic!("{} scripted steps were never reached:\n{msg}", steps.len());
+        }
+    }
+
+    fn exchange<A, const LEN: usize>(&self, cmd: &A) -> Result<A::Response, atat::Error>
+    where
+        A: AtatCmd<LEN>,
+    {
+        self.flush_urcs();
+
+        let bytes = cmd.as_bytes();
+        let sent = core::str::from_utf8(&bytes)
+            .expect("AT command is not valid UTF-8")
+            .trim_end();
+
+        let step = self.steps.borrow_mut().pop_front();
+        let reply = match step {
+            Some(Step::Command {
+                cmd: expected,
+                reply,
+            }) => {
+                assert_eq!(sent, expected, "Sent command does not match the script");
+                reply
+            }
+            Some(Step::Urc(_)) => unreachable!(),
+            None => self
+                .unscripted(sent)
+                .unwrap_or_else(|| panic!("Unexpected command {sent:?}, the script is exhausted")),
+        };
+
+        let res = match reply {
+            Reply::Ok(response) => cmd.parse(Ok(&response)),
+            Reply::Err(e) => Err(e),
+        };
+
+        self.flush_urcs();
+
+        res
+    }
+
+    /// Answer to `sent` outside of the script: the first exchange of the
+    /// replayed trace with the same command, else the [`answer`](Self::answer)
+    /// given for it.
+    fn unscripted(&self, sent: &str) -> Option<Reply> {
+        let mut traced = self.traced.borrow_mut();
+        if let Some(i) = traced.iter().position(|(cmd, _)| cmd == sent) {
+            return traced.remove(i).map(|(_, reply)| reply);
+        }
+
+        self.answers
+            .borrow()
+            .iter()
+            .find(|(cmd, _)| cmd == sent)
+            .map(|(_, response)| Reply::Ok(response.as_bytes().to_vec()))
+    }
+}
+
+impl AtatUrcChannel<Urc, URC_CAPACITY, URC_SUBSCRIBERS> for MockModem {
+    fn subscribe(
+        &self,
+    ) -> Result<UrcSubscription<'_, Urc, URC_CAPACITY, URC_SUBSCRIBERS>, pubsub::Error> {
+        self.channel.subscriber()
+    }
+}
+
+/// [`atat`] client answering from the script of a [`MockModem`]
+pub struct MockAtClient<'a> {
+    modem: &'a MockModem,
+}
+
+impl atat::blocking::AtatClient for MockAtClient<'_> {
+    fn send<A: AtatCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> Result<A::Response, atat::Error> {
+        self.modem.exchange(cmd)
+    }
+}
+
+#[cfg(feature = "async")]
+impl atat::asynch::AtatClient for MockAtClient<'_> {
+    async fn send<A: AtatCmd<LEN>, const LEN: usize>(
+        &mut self,
+        cmd: &A,
+    ) -> Result<A::Response, atat::Error> {
+        self.modem.exchange(cmd)
+    }
+}
+
+/// Drive `future` to completion on the current thread, parking it while the
+/// future is pending. Enough for the async driver, whose only wakers are the
+/// mock client and the `embassy-time` std driver.
+#[cfg(feature = "async")]
+pub fn block_on<F: core::future::Future>(future: F) -> F::Output {
+    use core::task::{Context, Poll};
+    use std::{
+        sync::Arc,
+        task::Wake,
+        thread::{self, Thread},
+    };
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let mut future = core::pin::pin!(future);
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Parser and replay harness for the AT traces in `documentation/at_logs`
+//!
+//! The traces use the following line format:
+//!
+//! ```text
+//! >> "AT+CREG?"                        command sent to the module
+//! << "+CREG: 2,5,"9E9A","019607C0",2"  information response, followed by OK
+//! << response: "+CPIN: READY"          same as above
+//! << OK                                command without information response
+//! << ERROR                             failed command
+//! <<< +UUSOCL: 0                       URC
+//! <!-- free text -->                   comment
+//! ```
+//!
+//! Anything after the closing quote (usually a parenthesized explanation) is
+//! ignored, as are blank lines and `-----` section markers.
+//!
+//! The traces were captured from earlier revisions of the driver, so the
+//! commands in them do not line up exactly with what the driver sends today.
+//! [`replay`] runs the driver against the module's side of the trace: the
+//! traced responses answer the commands the driver sends, and the URCs are
+//! published in between, the test answering the commands the trace misses
+//! with [`MockModem::answer`]. The resulting state is checked against the
+//! `Connection state changed to "..."` comments in the trace.
+
+use std::{format, string::String, vec::Vec};
+
+use crate::{client::Device, registration::ConnectionState, PdpContext};
+
+use super::{MockAtClient, MockConfig, MockModem, MockSettings, Reply};
+
+/// A single entry of an AT trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEntry<'a> {
+    /// A command and the module's answer to it. `Ok` holds the information
+    /// response, which is empty for a plain `OK`.
+    Exchange {
+        command: &'a str,
+        response: Result<&'a str, &'a str>,
+    },
+    /// An unsolicited result code
+    Urc(&'a str),
+    /// A `<!-- ... -->` comment
+    Comment(&'a str),
+}
+
+/// Text between the first and the last `"` of `line`, or the trimmed line
+/// if it is not quoted.
+fn unquote(line: &str) -> &str {
+    match (line.find('"'), line.rfind('"')) {
+        (Some(start), Some(end)) if start < end => &line[start + 1..end],
+        _ => line.trim(),
+    }
+}
+
+/// Parse an AT trace into its entries
+///
+/// # Panics
+///
+/// Panics on lines that do not follow the trace format, or on a response
+/// that is not preceded by a command.
+pub fn parse_log(log: &str) -> impl Iterator<Item = LogEntry<'_>> {
+    let mut lines = log.lines().map(str::trim).peekable();
+
+    core::iter::from_fn(move || loop {
+        let line = lines.next()?;
+
+        if line.is_empty() || line.starts_with("---") {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix("<!--") {
+            let comment = comment.strip_suffix("-->").unwrap_or(comment);
+            return Some(LogEntry::Comment(comment.trim()));
+        }
+
+        if let Some(urc) = line.strip_prefix("<<<") {
+            return Some(LogEntry::Urc(urc.trim()));
+        }
+
+        if let Some(command) = line.strip_prefix(">>") {
+            let command = unquote(command);
+
+            let has_response = matches!(
+                lines.peek(),
+                Some(next) if next.starts_with("<<") && !next.starts_with("<<<")
+            );
+
+            // Commands are not always followed by their response in the
+            // traces, treat those as a plain `OK`
+            let response = if has_response {
+                let response = lines.next().unwrap()[2..].trim();
+                let response = response.strip_prefix("response:").unwrap_or(response);
+                match response.trim() {
+                    "OK" => Ok(""),
+                    err if err.starts_with("ERROR") || err.starts_with("+CME ERROR") => Err(err),
+                    _ => Ok(unquote(response)),
+                }
+            } else {
+                Ok("")
+            };
+
+            return Some(LogEntry::Exchange { command, response });
+        }
+
+        panic!("Unexpected line in AT log: {line:?}");
+    })
+}
+
+fn expected_connection_state(comment: &str) -> Option<ConnectionState> {
+    let state = comment.strip_prefix("Connection state changed to")?;
+    match unquote(state) {
+        "Disconnected" => Some(ConnectionState::Disconnected),
+        "Connecting" => Some(ConnectionState::Connecting),
+        "Connected" => Some(ConnectionState::Connected),
+        other => panic!("Unknown connection state {other:?} in AT log"),
+    }
+}
+
+/// Profile the traced driver mapped the default context to, with
+/// `AT+UPSD=0,100,1`
+const TRACED_PROFILE: &str = "0";
+
+/// Upper bound on the `drive` calls in a row, in case the driver keeps
+/// answering the trace without ever running out of it
+const MAX_DRIVES: usize = 16;
+
+/// Replace the PSD profile of the traced driver in `line` with the one of
+/// [`PdpContext::DEFAULT`], for the `+UPSD`, `+UPSDA` and `+UPSND` commands and
+/// the `+UUPSDD` URC.
+fn map_profile(line: &str) -> String {
+    for prefix in ["AT+UPSD=", "AT+UPSDA=", "AT+UPSND=", "+UUPSDD: "] {
+        let Some(rest) = line
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix(TRACED_PROFILE))
+        else {
+            continue;
+        };
+
+        if rest.is_empty() || rest.starts_with(',') {
+            return format!("{prefix}{}{rest}", PdpContext::DEFAULT.profile_id.0);
+        }
+    }
+    line.into()
+}
+
+type ReplayDevice<'buf, 'sub, 'm, S, const N: usize, const L: usize> =
+    Device<'buf, 'sub, MockAtClient<'m>, MockModem, MockConfig<S>, N, L>;
+
+/// Replay an AT trace through `modem`, with `drive` running the driver
+///
+/// `device` must be created on `modem`, whose script must be exhausted. The
+/// trace is split into parts at its `Connection state changed to "..."`
+/// comments. Within a part, each exchange answers the first command the
+/// driver sends that matches it, the commands missing from the trace being
+/// answered by [`MockModem::answer`]. `drive` is called until the driver
+/// stops going through the exchanges of the part, then after each URC of the
+/// part, published one at a time. URCs the driver does not know are skipped,
+/// like the digester would. The connection state is then checked against the
+/// comment, and the exchanges the driver did not go through are dropped.
+///
+/// The traces have no timestamps: the registration check interval is taken
+/// to have elapsed whenever an `AT+CREG?` of the part is still unanswered, as
+/// the traced driver checked the registration there.
+///
+/// The traced driver mapped the default context to PSD profile
+/// [`TRACED_PROFILE`], which is replaced with the profile of
+/// [`PdpContext::DEFAULT`].
+///
+/// # Panics
+///
+/// Panics on a command that is neither in the part nor answered by `modem`,
+/// or if the connection state does not match a comment.
+pub fn replay<'buf, 'sub, 'm, S, const N: usize, const L: usize>(
+    device: &mut ReplayDevice<'buf, 'sub, 'm, S, N, L>,
+    modem: &MockModem,
+    log: &str,
+    mut drive: impl FnMut(&mut ReplayDevice<'buf, 'sub, 'm, S, N, L>),
+) where
+    'buf: 'sub,
+    S: MockSettings,
+{
+    let mut urcs = Vec::new();
+
+    for entry in parse_log(log) {
+        match entry {
+            LogEntry::Exchange { command, response } => {
+                let reply = match response {
+                    Ok(response) => Reply::Ok(response.as_bytes().to_vec()),
+                    Err(_) => Reply::Err(atat::Error::Error),
+                };
+                modem
+                    .traced
+                    .borrow_mut()
+                    .push_back((map_profile(command), reply));
+            }
+            LogEntry::Urc(urc) => urcs.push(map_profile(urc)),
+            LogEntry::Comment(comment) => {
+                let Some(expected) = expected_connection_state(comment) else {
+                    continue;
+                };
+
+                play_part(device, modem, &urcs, &mut drive);
+                urcs.clear();
+
+                assert_eq!(
+                    device.network.status.conn_state, expected,
+                    "Connection state mismatch at {comment:?}"
+                );
+            }
+        }
+    }
+
+    play_part(device, modem, &urcs, &mut drive);
+}
+
+/// Run the driver through the exchanges queued in `modem` and `urcs`, then
+/// drop the exchanges it did not go through
+fn play_part<'buf, 'sub, 'm, S, const N: usize, const L: usize>(
+    device: &mut ReplayDevice<'buf, 'sub, 'm, S, N, L>,
+    modem: &MockModem,
+    urcs: &[String],
+    drive: &mut impl FnMut(&mut ReplayDevice<'buf, 'sub, 'm, S, N, L>),
+) where
+    'buf: 'sub,
+    S: MockSettings,
+{
+    settle(device, modem, drive);
+
+    for urc in urcs {
+        if modem.publish_urc(urc) {
+            settle(device, modem, drive);
+        }
+    }
+
+    modem.traced.borrow_mut().clear();
+}
+
+/// Call `drive` until it no longer consumes any traced exchange
+fn settle<'buf, 'sub, 'm, S, const N: usize, const L: usize>(
+    device: &mut ReplayDevice<'buf, 'sub, 'm, S, N, L>,
+    modem: &MockModem,
+    drive: &mut impl FnMut(&mut ReplayDevice<'buf, 'sub, 'm, S, N, L>),
+) where
+    'buf: 'sub,
+    S: MockSettings,
+{
+    for _ in 0..MAX_DRIVES {
+        let traced = modem.traced.borrow();
+        if traced.iter().any(|(cmd, _)| cmd == "AT+CREG?") {
+            device.network.status.reg_check_time = None;
+        }
+        let left = traced.len();
+        drop(traced);
+
+        drive(device);
+
+        if modem.traced.borrow().len() == left {
+            return;
+        }
+    }
+}
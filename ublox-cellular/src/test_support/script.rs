@@ -0,0 +1,103 @@
+//! Scripts of the exchanges shared by most tests
+
+use embedded_nal::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use ublox_sockets::{SocketHandle, SocketSet, TcpSocket, TcpState};
+
+use super::MockModem;
+use crate::module::{ModuleKind, Vendor};
+
+/// Script the commands sent by the first `spin` of a powered, but
+/// unconfigured `model`, ending up registered on its home network.
+pub fn expect_initialize_model(modem: &MockModem, model: &str) {
+    expect_initialize_configured(modem, model, |_| {});
+}
+
+/// Same as [`expect_initialize_model`], with `configure` scripting the radio
+/// configuration, before the module is set to full functionality.
+pub fn expect_initialize_configured(
+    modem: &MockModem,
+    model: &str,
+    configure: impl Fn(&MockModem),
+) {
+    let ublox = ModuleKind::from_model_id(model.as_bytes())
+        .map_or(true, |module| module.vendor() == Vendor::Ublox);
+
+    modem
+        // Power state, `power_on` & `is_alive`
+        .expect("AT", "")
+        .expect("AT", "")
+        .expect("AT", "")
+        // `setup_at_commands`
+        .expect("AT+CMEE=1", "")
+        .expect("AT+CGMM", model)
+        .expect("AT+CGMR", "03.15");
+    if ublox {
+        modem.expect("AT+UGPIOC=25,0,1", "");
+    }
+    modem
+        .expect("AT+CPIN?", "+CPIN: READY")
+        .expect("AT+CCID", "+CCID: 89450000000000000000")
+        .expect("AT&C1", "")
+        .expect("AT&D0", "");
+    if ublox {
+        modem.expect("AT+UDCONF=1,1", "");
+    }
+    modem.expect("AT&K0", "");
+    if ublox {
+        modem.expect("AT+UPSV=0", "");
+    }
+    modem
+        // `initialize`
+        .expect("AT+CPIN?", "+CPIN: READY")
+        .expect("AT+CTZU=1", "");
+    configure(modem);
+    modem
+        .expect("AT+CFUN=1", "")
+        .expect("AT+CGEREP=0", "")
+        .expect("AT+CREG=2", "")
+        .expect("AT+CGREG=2", "")
+        .expect("AT+CEREG=2", "")
+        .expect("AT+COPS?", "+COPS: 0")
+        // `update_registration`
+        .expect("AT+CEER", "")
+        .expect("AT+CREG?", "+CREG: 0,1")
+        .expect("AT+CGREG?", "+CGREG: 0,1")
+        .expect("AT+CEREG?", "+CEREG: 0,0")
+        // `process_events`
+        .expect("AT+CIMI", "238010000000000");
+}
+
+/// Script the first `spin` of a module activating contexts through 3GPP
+/// commands.
+pub fn expect_initialize(modem: &MockModem) {
+    expect_initialize_model(modem, "LARA-R211");
+}
+
+/// Script the PDP context definition and activation done by the first
+/// `data_service` call after registration.
+pub fn expect_context_activation(modem: &MockModem) {
+    modem
+        .expect("AT+CFUN=0,0", "")
+        .expect("AT+CGDCONT=1,\"IP\",\"em\"", "")
+        .expect("AT+CFUN=1,0", "")
+        .expect("AT+CGATT?", "+CGATT: 1")
+        .expect("AT+CGACT?", "+CGACT: 1,1")
+        .expect("AT+UPSD=1,100", "+UPSD: 1,100,1")
+        .expect("AT+UPSND=1,8", "+UPSND: 1,8,1")
+        .expect("AT+CGPADDR=1", "+CGPADDR: 1,\"10.0.0.2\"");
+}
+
+/// Add a TCP socket with id 0 to `sockets`, connected to 10.0.0.1:443
+pub fn connected_tcp_socket<const N: usize, const L: usize>(
+    sockets: &mut SocketSet<N, L>,
+) -> SocketHandle {
+    let handle = sockets.add(TcpSocket::new(0)).unwrap();
+    sockets
+        .get::<TcpSocket<L>>(handle)
+        .unwrap()
+        .set_state(TcpState::Connected(SocketAddr::V4(SocketAddrV4::new(
+            Ipv4Addr::new(10, 0, 0, 1),
+            443,
+        ))));
+    handle
+}
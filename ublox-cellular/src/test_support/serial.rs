@@ -0,0 +1,59 @@
+//! In-memory serial port, for code driving the raw byte stream rather than
+//! the AT client (CMUX, PPP dialing).
+
+use core::{cell::RefCell, convert::Infallible};
+use std::{collections::VecDeque, vec::Vec};
+
+use embedded_io::{ErrorType, Read, ReadReady, Write};
+
+/// Both directions of an in-memory serial line
+#[derive(Default)]
+pub struct Wire {
+    /// Bytes sent by the module, waiting to be read
+    pub rx: VecDeque<u8>,
+    /// Bytes written to the module
+    pub tx: Vec<u8>,
+}
+
+impl Wire {
+    /// Queue `bytes` as sent by the module
+    pub fn push_rx(&mut self, bytes: &[u8]) {
+        self.rx.extend(bytes);
+    }
+}
+
+/// Serial port end of a [`Wire`]. Reads never block: they return 0 bytes
+/// once the wire is drained.
+pub struct FakeSerial<'a>(pub &'a RefCell<Wire>);
+
+impl ErrorType for FakeSerial<'_> {
+    type Error = Infallible;
+}
+
+impl Read for FakeSerial<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let mut wire = self.0.borrow_mut();
+        let len = buf.len().min(wire.rx.len());
+        for b in &mut buf[..len] {
+            *b = wire.rx.pop_front().unwrap();
+        }
+        Ok(len)
+    }
+}
+
+impl ReadReady for FakeSerial<'_> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.0.borrow().rx.is_empty())
+    }
+}
+
+impl Write for FakeSerial<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.0.borrow_mut().tx.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
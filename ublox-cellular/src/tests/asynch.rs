@@ -0,0 +1,180 @@
+use embedded_io_async::{Read, Write};
+use embedded_nal::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use embedded_nal_async::{AddrType, Dns, TcpConnect};
+use ublox_sockets::{SocketSet, TcpSocket};
+
+use crate::{
+    asynch::DataStack,
+    client::State,
+    module::ModuleKind,
+    registration::ConnectionState,
+    services::data::{ContextState, Error},
+    test_support::{
+        block_on, connected_tcp_socket, expect_context_activation, expect_initialize,
+        expect_initialize_model, new_async_device, MockConfig, MockModem, L, N,
+    },
+    APNInfo, ContextId, SecurityProfileId,
+};
+
+#[test]
+fn initialize_and_register() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+
+    let mut device = new_async_device(&modem, MockConfig::new());
+
+    assert!(block_on(device.spin()).is_ok());
+    assert_eq!(device.state, State::FullyInitialized);
+    assert_eq!(device.network.module, ModuleKind::LaraR2);
+    assert_eq!(device.network.status.conn_state, ConnectionState::Connected);
+    modem.assert_done();
+
+    // Nothing left to do until the next registration check
+    assert!(block_on(device.spin()).is_ok());
+}
+
+#[test]
+fn data_service_activates_context_and_ingresses_socket_data() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+    expect_context_activation(&modem);
+    modem.expect("AT+USORD=0,4", "+USORD: 0,4,\"DEADBEEF\"");
+
+    let mut device = new_async_device(&modem, MockConfig::new());
+
+    let sockets = Box::leak(Box::new(SocketSet::<N, L>::new()));
+    let handle = connected_tcp_socket(sockets);
+    sockets
+        .get::<TcpSocket<L>>(handle)
+        .unwrap()
+        .set_available_data(4);
+    device.set_socket_storage(sockets);
+
+    let apn = APNInfo::new("em");
+    let data_service = block_on(device.data_service(&apn));
+    assert!(data_service.is_ok());
+    drop(data_service);
+
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Active
+    );
+    modem.assert_done();
+
+    let sockets = device.take_socket_storage().unwrap();
+    let mut buf = [0u8; 8];
+    let len = sockets
+        .get::<TcpSocket<L>>(handle)
+        .unwrap()
+        .recv_slice(&mut buf)
+        .unwrap();
+    assert_eq!(&buf[..len], &[0xDE, 0xAD, 0xBE, 0xEF]);
+}
+
+#[test]
+fn tcp_connection_sends_and_reads_through_the_stack() {
+    let modem = MockModem::new();
+    expect_initialize_model(&modem, "L610");
+    modem
+        .expect("AT+CGATT?", "+CGATT: 1")
+        .expect("AT+MIPCALL?", "+MIPCALL: 1,\"10.0.0.2\"")
+        .expect("AT+MIPOPEN=1,,\"10.0.0.1\",443,2", "+MIPOPEN: 1,1")
+        .expect("AT+MIPSEND=1,\"DEADBEEF\"", "+MIPSEND: 1,1020")
+        .expect("AT+MIPPUSH=1", "")
+        .expect("AT+MIPREAD=1,4", "+MIPREAD: 1,4,\"CAFEF00D\"")
+        .expect("AT+MIPCLOSE=1", "");
+
+    let mut device = new_async_device(&modem, MockConfig::new());
+    device.set_socket_storage(Box::leak(Box::new(SocketSet::<N, L>::new())));
+
+    let apn = APNInfo::new("em");
+    let stack = DataStack::new(block_on(device.data_service(&apn)).unwrap());
+    let remote = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 443));
+
+    block_on(async {
+        let mut conn = (&stack).connect(remote).await.unwrap();
+        assert_eq!(conn.write(&[0xDE, 0xAD, 0xBE, 0xEF]).await, Ok(4));
+
+        // As reported by `+MIPRTCP`
+        stack
+            .lock()
+            .await
+            .sockets
+            .as_deref_mut()
+            .unwrap()
+            .get::<TcpSocket<L>>(conn.handle())
+            .unwrap()
+            .set_available_data(4);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(conn.read(&mut buf).await, Ok(4));
+        assert_eq!(&buf[..4], &[0xCA, 0xFE, 0xF0, 0x0D]);
+
+        assert_eq!(conn.close().await, Ok(()));
+    });
+    modem.assert_done();
+}
+
+#[test]
+fn host_names_are_resolved_through_the_stack() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+    expect_context_activation(&modem);
+    modem
+        .expect("AT+UDNSRN=0,\"example.com\"", "+UDNSRN: \"93.184.216.34\"")
+        .expect("AT+UDNSRN=1,\"93.184.216.34\"", "+UDNSRN: \"example.com\"");
+
+    let mut device = new_async_device(&modem, MockConfig::new());
+
+    let apn = APNInfo::new("em");
+    let stack = DataStack::new(block_on(device.data_service(&apn)).unwrap());
+    let addr = IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34));
+
+    block_on(async {
+        assert_eq!(
+            stack.get_host_by_name("example.com", AddrType::IPv4).await,
+            Ok(addr)
+        );
+
+        let mut name = [0u8; 64];
+        let len = stack.get_host_by_address(addr, &mut name).await.unwrap();
+        assert_eq!(&name[..len], b"example.com");
+    });
+    modem.assert_done();
+}
+
+#[test]
+fn security_profile_is_set_up_through_the_data_service() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+    expect_context_activation(&modem);
+    modem
+        .expect("AT+USECPRF=0,0,3", "")
+        .expect("AT+USECPRF=0,2,0", "")
+        .expect("AT+USECPRF=0,4,\"example.com\"", "")
+        .expect("AT+USECPRF=0,10,\"example.com\"", "");
+
+    let mut device = new_async_device(&modem, MockConfig::new());
+
+    let apn = APNInfo::new("em");
+    let mut data_service = block_on(device.data_service(&apn)).unwrap();
+    let name = "a".repeat(201);
+
+    block_on(async {
+        // Refused before anything is sent
+        assert_eq!(
+            data_service
+                .import_root_ca(SecurityProfileId(0), &name, b"")
+                .await,
+            Err(Error::BadLength)
+        );
+        assert_eq!(
+            data_service
+                .enable_ssl(SecurityProfileId(0), "example.com", true)
+                .await,
+            Ok(())
+        );
+    });
+    drop(data_service);
+    modem.assert_done();
+}
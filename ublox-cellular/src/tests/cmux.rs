@@ -0,0 +1,144 @@
+use core::cell::RefCell;
+use std::vec::Vec;
+
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_io::{Read, ReadReady, Write};
+
+use crate::{
+    cmux::{
+        frame::{self, Decoder, Frame, FrameType},
+        Error, Mux,
+    },
+    test_support::{FakeSerial, Wire},
+};
+
+/// Encode a frame, as sent by the module
+fn encode(dlci: u8, frame_type: FrameType, payload: &[u8]) -> Vec<u8> {
+    let wire = RefCell::new(Wire::default());
+    frame::write_frame(&mut FakeSerial(&wire), dlci, frame_type, true, payload).unwrap();
+    wire.into_inner().tx
+}
+
+fn decode_all(bytes: &[u8]) -> Vec<(u8, FrameType, Vec<u8>)> {
+    let mut decoder = Decoder::new();
+    let mut frames = Vec::new();
+    for &b in bytes {
+        if let Some(Frame {
+            dlci,
+            frame_type,
+            payload,
+            ..
+        }) = decoder.feed(b)
+        {
+            frames.push((dlci, frame_type, payload.to_vec()));
+        }
+    }
+    frames
+}
+
+fn push_rx(wire: &RefCell<Wire>, bytes: &[u8]) {
+    wire.borrow_mut().push_rx(bytes);
+}
+
+#[test]
+fn sabm_on_control_channel_matches_reference_frame() {
+    assert_eq!(
+        encode(0, FrameType::Sabm, &[]),
+        [0xF9, 0x03, 0x3F, 0x01, 0x1C, 0xF9]
+    );
+}
+
+#[test]
+fn decoder_drops_frames_with_bad_fcs() {
+    let mut bytes = encode(1, FrameType::Uih, b"AT\r\n");
+    bytes.extend(encode(2, FrameType::Uih, &[0x55; 200]));
+
+    let mut corrupted = encode(1, FrameType::Uih, b"OK");
+    let fcs = corrupted.len() - 2;
+    corrupted[fcs] ^= 0xFF;
+    bytes.extend(corrupted);
+
+    assert_eq!(
+        decode_all(&bytes),
+        [
+            (1, FrameType::Uih, b"AT\r\n".to_vec()),
+            (2, FrameType::Uih, [0x55; 200].to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn mux_opens_channels_and_routes_data() {
+    let wire = RefCell::new(Wire::default());
+    let mux = Mux::<NoopRawMutex, _, 2, 256>::new(FakeSerial(&wire));
+
+    // Before starting, DLCI 1 is a plain passthrough
+    let mut at = mux.channel(1);
+    at.write_all(b"AT+CMUX=0,0,,127\r\n").unwrap();
+    assert_eq!(wire.borrow_mut().tx.split_off(0), b"AT+CMUX=0,0,,127\r\n");
+    assert_eq!(mux.channel(2).write(b"x"), Err(Error::Closed));
+    assert_eq!(at.read(&mut [0u8; 4]), Err(Error::WouldBlock));
+
+    for dlci in 0..=2 {
+        push_rx(&wire, &encode(dlci, FrameType::Ua, &[]));
+    }
+    mux.start().unwrap();
+    assert!(mux.is_started());
+    assert_eq!(
+        decode_all(&wire.borrow_mut().tx.split_off(0)),
+        [
+            (0, FrameType::Sabm, Vec::new()),
+            (1, FrameType::Sabm, Vec::new()),
+            (2, FrameType::Sabm, Vec::new()),
+        ]
+    );
+
+    push_rx(&wire, &encode(2, FrameType::Uih, b"$GPGGA"));
+    push_rx(&wire, &encode(1, FrameType::Uih, b"\r\nOK\r\n"));
+
+    let mut buf = [0u8; 16];
+    let len = mux.channel(2).read(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"$GPGGA");
+    let len = at.read(&mut buf).unwrap();
+    assert_eq!(&buf[..len], b"\r\nOK\r\n");
+    // Nothing left, the read does not wait for more
+    assert_eq!(at.read(&mut buf), Err(Error::WouldBlock));
+
+    at.write_all(b"AT\r\n").unwrap();
+    assert_eq!(
+        decode_all(&wire.borrow().tx),
+        [(1, FrameType::Uih, b"AT\r\n".to_vec())]
+    );
+}
+
+#[test]
+fn mux_acknowledges_control_commands() {
+    let wire = RefCell::new(Wire::default());
+    let mux = Mux::<NoopRawMutex, _, 1, 64>::new(FakeSerial(&wire));
+
+    push_rx(&wire, &encode(0, FrameType::Ua, &[]));
+    push_rx(&wire, &encode(1, FrameType::Ua, &[]));
+    mux.start().unwrap();
+    wire.borrow_mut().tx.clear();
+
+    // Modem status command for DLCI 1
+    push_rx(&wire, &encode(0, FrameType::Uih, &[0xE3, 0x05, 0x07, 0x0D]));
+    assert_eq!(mux.channel(1).read_ready(), Ok(false));
+
+    assert_eq!(
+        decode_all(&wire.borrow().tx),
+        [(0, FrameType::Uih, [0xE1, 0x05, 0x07, 0x0D].to_vec())]
+    );
+}
+
+#[test]
+fn start_fails_when_module_refuses_channel() {
+    let wire = RefCell::new(Wire::default());
+    let mux = Mux::<NoopRawMutex, _, 2, 64>::new(FakeSerial(&wire));
+
+    push_rx(&wire, &encode(0, FrameType::Ua, &[]));
+    push_rx(&wire, &encode(1, FrameType::Dm, &[]));
+
+    assert_eq!(mux.start(), Err(Error::Rejected(1)));
+    assert_eq!(mux.channel(1).write(b"AT"), Err(Error::Closed));
+}
@@ -0,0 +1,76 @@
+use atat::{DigestResult, Digester};
+
+use crate::UbloxDigester;
+
+#[test]
+fn registration_read_responses_are_not_urcs() {
+    let mut digester = UbloxDigester::default();
+
+    for response in [
+        &b"\r\n+CREG: 2,1,\"4E54\",\"0141FD35\",7\r\n\r\nOK\r\n"[..],
+        b"\r\n+CGREG: 0,1\r\n\r\nOK\r\n",
+        b"\r\n+CEREG: 4,1,\"0001\",\"01A2B3C4\",7,,,\"00001000\",\"00100010\"\r\n\r\nOK\r\n",
+    ] {
+        let (result, _) = digester.digest(response);
+        assert!(
+            matches!(result, DigestResult::Response(Ok(_))),
+            "{:?} digested as {result:?}",
+            core::str::from_utf8(response)
+        );
+    }
+}
+
+#[test]
+fn echoed_registration_read_responses_are_not_urcs() {
+    let mut digester = UbloxDigester::default();
+
+    for response in [
+        &b"AT+CREG?\r\r\n+CREG: 2,1\r\n\r\nOK\r\n"[..],
+        b"AT+CGREG?\r\r\n+CGREG: 0,1\r\n\r\nOK\r\n",
+        b"\r\nAT+CEREG?\r\r\n+CEREG: 2,1,\"0001\",\"01A2B3C4\",7\r\n\r\nOK\r\n",
+    ] {
+        let (result, _) = digester.digest(response);
+        assert!(
+            matches!(result, DigestResult::Response(Ok(_))),
+            "{:?} digested as {result:?}",
+            core::str::from_utf8(response)
+        );
+    }
+}
+
+#[test]
+fn registration_urcs_are_digested_as_urcs() {
+    let mut digester = UbloxDigester::default();
+
+    for urc in [
+        &b"\r\n+CREG: 5\r\n"[..],
+        b"\r\n+CGREG: 1,\"4E54\",\"0141FD35\",2\r\n",
+        b"\r\n+CEREG: 1,\"0001\",\"01A2B3C4\",7\r\n",
+        b"\r\n+CEREG: 3,,,,0,15\r\n",
+    ] {
+        let (result, _) = digester.digest(urc);
+        assert!(
+            matches!(result, DigestResult::Urc(_)),
+            "{:?} digested as {result:?}",
+            core::str::from_utf8(urc)
+        );
+    }
+}
+
+#[test]
+fn partial_registration_line_is_left_in_buffer() {
+    let mut digester = UbloxDigester::default();
+
+    let (result, used) = digester.digest(b"\r\n+CREG: 2,");
+    assert!(matches!(result, DigestResult::None));
+    assert_eq!(used, 0);
+}
+
+#[test]
+fn partial_echoed_registration_line_is_left_in_buffer() {
+    let mut digester = UbloxDigester::default();
+
+    let (result, used) = digester.digest(b"AT+CREG?\r\r\n+CREG: 2,");
+    assert!(matches!(result, DigestResult::None));
+    assert_eq!(used, 0);
+}
@@ -0,0 +1,12 @@
+//! Host-side integration tests, driving the whole driver against the
+//! scripted [`MockModem`](crate::test_support::MockModem). Tests of a single
+//! feature live in the `test` module next to it.
+
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "cmux")]
+mod cmux;
+mod digester;
+mod ppp;
+mod replay;
+mod scripted;
@@ -0,0 +1,54 @@
+use core::cell::RefCell;
+
+use crate::{
+    ppp::{dial, DialError},
+    test_support::{expect_initialize, new_device, FakeSerial, MockConfig, MockModem, Wire},
+    APNInfo, ContextId,
+};
+
+fn dial_with(rx: &[u8]) -> (Result<(), DialError<core::convert::Infallible>>, Wire) {
+    let wire = RefCell::new(Wire::default());
+    wire.borrow_mut().push_rx(rx);
+    let res = dial(&mut FakeSerial(&wire), ContextId(1));
+    (res, wire.into_inner())
+}
+
+#[test]
+fn dial_leaves_ppp_stream_on_port() {
+    // Echo, result code and the first bytes of an LCP configure-request
+    let (res, wire) = dial_with(b"ATD*99***1#\r\r\nCONNECT 150000000\r\n\x7E\xFF\x7D\x23");
+
+    assert_eq!(res, Ok(()));
+    assert_eq!(wire.tx, b"ATD*99***1#\r");
+    // Everything following the result code is left for the PPP stack
+    assert_eq!(wire.rx, b"\x7E\xFF\x7D\x23");
+}
+
+#[test]
+fn dial_fails_on_error_result_codes() {
+    assert_eq!(
+        dial_with(b"\r\nNO CARRIER\r\n").0,
+        Err(DialError::NoCarrier)
+    );
+    assert_eq!(
+        dial_with(b"\r\n+CME ERROR: 148\r\n").0,
+        Err(DialError::Rejected)
+    );
+}
+
+#[test]
+fn ppp_context_defines_context_once() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+    modem
+        .expect("AT+CFUN=0,0", "")
+        .expect("AT+CGDCONT=1,\"IP\",\"em\"", "")
+        .expect("AT+CFUN=1,0", "");
+
+    let mut device = new_device(&modem, MockConfig::new());
+
+    let apn = APNInfo::new("em");
+    assert_eq!(device.ppp_context(&apn), Ok(ContextId(1)));
+    assert_eq!(device.ppp_context(&apn), Ok(ContextId(1)));
+    modem.assert_done();
+}
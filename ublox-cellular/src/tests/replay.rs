@@ -0,0 +1,190 @@
+use embedded_nal::{IpAddr, Ipv4Addr};
+use ublox_sockets::{SocketSet, TcpSocket};
+
+use crate::{
+    network::ContextId,
+    registration::ConnectionState,
+    services::data::ContextState,
+    test_support::{
+        connected_tcp_socket, new_device, parse_log, replay, LogEntry, MockConfig, MockModem, L, N,
+    },
+    APNInfo,
+};
+
+const STARTUP: &str = include_str!("../../../documentation/at_logs/startup.md");
+const EMNIFY_RESET_CONNECTIVITY: &str =
+    include_str!("../../../documentation/at_logs/emnify_reset_connectivity.md");
+
+/// Answers to the commands the driver sends to a LARA-R211, where the traces
+/// do not have them
+const UNTRACED: &[(&str, &str)] = &[
+    ("AT", ""),
+    ("AT+CMEE=1", ""),
+    ("AT+CGMM", "LARA-R211"),
+    ("AT+CGMR", "03.15"),
+    ("AT+UGPIOC=25,0,1", ""),
+    ("AT+CPIN?", "+CPIN: READY"),
+    ("AT+CCID", "+CCID: 89450000000000000000"),
+    ("AT&K0", ""),
+    ("AT+CTZU=1", ""),
+    ("AT+CGEREP=0", ""),
+    ("AT+CEER", ""),
+    ("AT+CIMI", "238010000000000"),
+    ("AT+CFUN=0,0", ""),
+    ("AT+CFUN=1,0", ""),
+    ("AT+CGATT?", "+CGATT: 1"),
+    ("AT+CGACT?", "+CGACT: 1,1"),
+    ("AT+CGACT=1,1", ""),
+    ("AT+UPSD=1,100", "+UPSD: 1,100,0"),
+    ("AT+UPSD=1,100,1", ""),
+    ("AT+UPSND=1,8", "+UPSND: 1,8,0"),
+    ("AT+UPSDA=1,3", ""),
+    ("AT+CGPADDR=1", "+CGPADDR: 1,\"100.92.188.77\""),
+    ("AT+USORD=0,0", "+USORD: 0,0"),
+];
+
+fn answer_untraced(modem: &MockModem) {
+    for (cmd, response) in UNTRACED {
+        modem.answer(cmd, response);
+    }
+}
+
+#[test]
+fn parse_trace_format() {
+    let log = r#"
+--------------- Initialization start ---------------
+
+<!-- Check SIM Pin code -->
+>> "AT+CPIN?"
+<< response: "+CPIN: READY"
+
+>> "AT+CGDCONT=1,"IP","em""
+<< OK
+
+>> "AT+USOCR=17"                            (17 = UDP)
+<< "+USOCR: 0"
+
+<<< +UUPSDA: 0,"100.92.188.77"
+
+>> "AT+CGACT=1"
+<< ERROR
+"#;
+
+    let entries: Vec<_> = parse_log(log).collect();
+    assert_eq!(
+        entries,
+        [
+            LogEntry::Comment("Check SIM Pin code"),
+            LogEntry::Exchange {
+                command: "AT+CPIN?",
+                response: Ok("+CPIN: READY"),
+            },
+            LogEntry::Exchange {
+                command: r#"AT+CGDCONT=1,"IP","em""#,
+                response: Ok(""),
+            },
+            LogEntry::Exchange {
+                command: "AT+USOCR=17",
+                response: Ok("+USOCR: 0"),
+            },
+            LogEntry::Urc(r#"+UUPSDA: 0,"100.92.188.77""#),
+            LogEntry::Exchange {
+                command: "AT+CGACT=1",
+                response: Err("ERROR"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn replay_startup() {
+    let modem = MockModem::new();
+    answer_untraced(&modem);
+    let mut device = new_device(&modem, MockConfig::new());
+
+    let apn = APNInfo::new("em");
+    replay(&mut device, &modem, STARTUP, |device| {
+        device.data_service(&apn).ok();
+    });
+
+    assert_eq!(device.network.status.conn_state, ConnectionState::Connected);
+    assert_eq!(
+        device.network.contexts.ip_addr(ContextId(1)),
+        Some(IpAddr::V4(Ipv4Addr::new(100, 92, 188, 77)))
+    );
+    modem.assert_done();
+}
+
+#[test]
+fn replay_emnify_reset_connectivity() {
+    let modem = MockModem::new();
+    answer_untraced(&modem);
+    let mut device = new_device(&modem, MockConfig::new());
+
+    let apn = APNInfo::new("em");
+    replay(&mut device, &modem, STARTUP, |device| {
+        device.data_service(&apn).ok();
+    });
+
+    let sockets = Box::leak(Box::new(SocketSet::<N, L>::new()));
+    let handle = connected_tcp_socket(sockets);
+    device.set_socket_storage(sockets);
+
+    // Asserts the "Connecting" and "Connected" transitions noted in the trace
+    replay(&mut device, &modem, EMNIFY_RESET_CONNECTIVITY, |device| {
+        device.data_service(&apn).ok();
+    });
+
+    assert_eq!(device.network.status.conn_state, ConnectionState::Connected);
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Active
+    );
+
+    // `+UUSOCL: 0` closed the socket that was open before the reset
+    let sockets = device.take_socket_storage().unwrap();
+    assert!(!sockets.get::<TcpSocket<L>>(handle).unwrap().is_connected());
+    modem.assert_done();
+}
+
+#[test]
+fn replay_context_deactivation() {
+    let modem = MockModem::new();
+    answer_untraced(&modem);
+    let mut device = new_device(&modem, MockConfig::new());
+
+    let apn = APNInfo::new("em");
+    replay(&mut device, &modem, STARTUP, |device| {
+        device.data_service(&apn).ok();
+    });
+
+    // Profile 0 of the traced driver is the profile of the default context
+    replay(&mut device, &modem, "<<< +UUPSDD: 0\n", |device| {
+        device.spin().ok();
+    });
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Activating
+    );
+
+    replay(&mut device, &modem, "<<< +UUPSDA: 1\n", |device| {
+        device.spin().ok();
+    });
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Setup
+    );
+
+    replay(
+        &mut device,
+        &modem,
+        "<<< +UUPSDA: 0,\"100.92.188.77\"\n",
+        |device| {
+            device.spin().ok();
+        },
+    );
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Active
+    );
+}
@@ -0,0 +1,126 @@
+use ublox_sockets::{SocketSet, TcpSocket};
+
+use crate::{
+    client::State,
+    module::ModuleKind,
+    registration::ConnectionState,
+    services::data::ContextState,
+    test_support::{
+        connected_tcp_socket, expect_context_activation, expect_initialize, new_device,
+        registered_device, MockConfig, MockModem, L, N,
+    },
+    APNInfo, ContextId,
+};
+
+#[test]
+fn initialize_and_register() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+
+    let mut device = new_device(&modem, MockConfig::new());
+
+    assert!(device.spin().is_ok());
+    assert_eq!(device.state, State::FullyInitialized);
+    assert_eq!(device.network.module, ModuleKind::LaraR2);
+    assert_eq!(device.network.status.conn_state, ConnectionState::Connected);
+    modem.assert_done();
+
+    // Nothing left to do until the next registration check
+    assert!(device.spin().is_ok());
+}
+
+#[test]
+fn registration_is_lost_without_home_network() {
+    let modem = MockModem::new();
+    let mut device = registered_device(&modem, "LARA-R211", MockConfig::new());
+
+    device.network.status.reg_check_time = None;
+    modem
+        .expect("AT+CEER", "")
+        .expect("AT+CREG?", "+CREG: 0,2")
+        .expect("AT+CGREG?", "+CGREG: 0,2")
+        .expect("AT+CEREG?", "+CEREG: 0,2")
+        .expect("AT+CIMI", "238010000000000");
+    assert!(device.spin().is_ok());
+
+    // The lost registration is only acted upon on the following spin, which
+    // also restarts the IMSI check
+    assert_eq!(device.spin(), Err(nb::Error::WouldBlock));
+    assert_eq!(
+        device.network.status.conn_state,
+        ConnectionState::Connecting
+    );
+    modem.assert_done();
+}
+
+#[test]
+fn data_service_activates_context_and_ingresses_socket_data() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+    expect_context_activation(&modem);
+    modem.expect("AT+USORD=0,4", "+USORD: 0,4,\"DEADBEEF\"");
+
+    let mut device = new_device(&modem, MockConfig::new());
+
+    let sockets = Box::leak(Box::new(SocketSet::<N, L>::new()));
+    let handle = connected_tcp_socket(sockets);
+    sockets
+        .get::<TcpSocket<L>>(handle)
+        .unwrap()
+        .set_available_data(4);
+    device.set_socket_storage(sockets);
+
+    let apn = APNInfo::new("em");
+    let data_service = device.data_service(&apn);
+    assert!(data_service.is_ok());
+    drop(data_service);
+
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Active
+    );
+    modem.assert_done();
+
+    let sockets = device.take_socket_storage().unwrap();
+    let mut buf = [0u8; 8];
+    let len = sockets
+        .get::<TcpSocket<L>>(handle)
+        .unwrap()
+        .recv_slice(&mut buf)
+        .unwrap();
+    assert_eq!(&buf[..len], &[0xDE, 0xAD, 0xBE, 0xEF]);
+}
+
+#[test]
+fn urcs_update_context_and_socket_state() {
+    let modem = MockModem::new();
+    expect_initialize(&modem);
+    expect_context_activation(&modem);
+
+    let mut device = new_device(&modem, MockConfig::new());
+
+    let sockets = Box::leak(Box::new(SocketSet::<N, L>::new()));
+    let handle = connected_tcp_socket(sockets);
+    device.set_socket_storage(sockets);
+
+    let apn = APNInfo::new("em");
+    assert!(device.data_service(&apn).is_ok());
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Active
+    );
+
+    modem.urc("+UUSOCL: 0").urc("+UUPSDD: 1").flush_urcs();
+
+    // One URC is handled per subscriber per spin
+    device.spin().ok();
+    device.spin().ok();
+
+    assert_eq!(
+        device.network.contexts.state(ContextId(1)),
+        ContextState::Activating
+    );
+    let sockets = device.take_socket_storage().unwrap();
+    assert!(!sockets.get::<TcpSocket<L>>(handle).unwrap().is_connected());
+    modem.assert_done();
+}